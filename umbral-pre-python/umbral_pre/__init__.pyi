@@ -1,4 +1,8 @@
-from typing import Optional, Tuple, List, Sequence
+from typing import Optional, Tuple, List, Sequence, Union
+
+
+# Any object implementing the buffer protocol is accepted.
+BytesLike = Union[bytes, bytearray, memoryview]
 
 
 class SecretKey:
@@ -10,6 +14,9 @@ class SecretKey:
     def public_key(self) -> PublicKey:
         ...
 
+    def derive_policy_key(self, label: BytesLike) -> SecretKey:
+        ...
+
     def to_secret_bytes(self) -> bytes:
         ...
 
@@ -33,13 +40,19 @@ class SecretKeyFactory:
         ...
 
     @staticmethod
-    def from_secure_randomness(seed: bytes) -> SecretKeyFactory:
+    def from_secure_randomness(seed: BytesLike) -> SecretKeyFactory:
+        ...
+
+    def make_key(self, label: BytesLike) -> SecretKey:
+        ...
+
+    def policy_master_public_key(self) -> PublicKey:
         ...
 
-    def make_key(self, label: bytes) -> SecretKey:
+    def make_policy_key(self, label: BytesLike) -> SecretKey:
         ...
 
-    def make_factory(self, label: bytes) -> SecretKeyFactory:
+    def make_factory(self, label: BytesLike) -> SecretKeyFactory:
         ...
 
     def to_secret_bytes(self) -> bytes:
@@ -64,13 +77,16 @@ class PublicKey:
     def serialized_size() -> int:
         ...
 
+    def derive_policy_key(self, label: BytesLike) -> PublicKey:
+        ...
+
 
 class Signer:
 
     def __init__(secret_key: SecretKey):
         ...
 
-    def sign(message: bytes) -> Signature:
+    def sign(message: BytesLike) -> Signature:
         ...
 
     def verifying_key() -> PublicKey:
@@ -79,7 +95,7 @@ class Signer:
 
 class Signature:
 
-    def verify(verifying_pk: PublicKey, message: bytes) -> bool:
+    def verify(verifying_pk: PublicKey, message: BytesLike) -> bool:
         ...
 
     @staticmethod
@@ -98,11 +114,11 @@ class Capsule:
         ...
 
 
-def encrypt(delegating_pk: PublicKey, plaintext: bytes) -> Tuple[Capsule, bytes]:
+def encrypt(delegating_pk: PublicKey, plaintext: BytesLike) -> Tuple[Capsule, bytes]:
     ...
 
 
-def decrypt_original(delegating_sk: SecretKey, capsule: Capsule, ciphertext: bytes) -> bytes:
+def decrypt_original(delegating_sk: SecretKey, capsule: Capsule, ciphertext: BytesLike) -> bytes:
     ...
 
 
@@ -130,7 +146,7 @@ class KeyFrag:
 
 class VerifiedKeyFrag:
 
-    def from_verified_bytes(data: bytes) -> VerifiedKeyFrag:
+    def from_verified_bytes(data: BytesLike) -> VerifiedKeyFrag:
         ...
 
     def unverify(self) -> KeyFrag:
@@ -178,7 +194,7 @@ class CapsuleFrag:
 
 class VerifiedCapsuleFrag:
 
-    def from_verified_bytes(data: bytes) -> VerifiedCapsuleFrag:
+    def from_verified_bytes(data: BytesLike) -> VerifiedCapsuleFrag:
         ...
 
     def unverify(self) -> CapsuleFrag:
@@ -198,6 +214,6 @@ def decrypt_reencrypted(
         delegating_pk: PublicKey,
         capsule: Capsule,
         cfrags: Sequence[VerifiedCapsuleFrag],
-        ciphertext: bytes,
+        ciphertext: BytesLike,
         ) -> Optional[bytes]:
     ...
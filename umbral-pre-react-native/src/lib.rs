@@ -0,0 +1,488 @@
+//! UniFFI-based bindings for `umbral-pre`, intended to be wrapped into
+//! a React Native module with `uniffi-bindgen-react-native`.
+//!
+//! The resulting module calls the native library directly through JSI,
+//! bypassing the WASM interpreter (which is slow in Hermes).
+
+use core::fmt;
+use std::sync::Arc;
+
+use umbral_pre::{DeserializableFromArray, SerializableToArray, SerializableToSecretArray};
+use zeroize::Zeroize;
+
+uniffi::setup_scaffolding!();
+
+/// An error returned by the bindings.
+///
+/// The error is flattened on the JS side, and only has the error message attached.
+#[derive(Debug, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum UmbralError {
+    /// Invalid arguments, or a failed deserialization.
+    Value(String),
+    /// A fragment failed to verify.
+    Verification(String),
+    /// A ciphertext failed to decrypt.
+    Decryption(String),
+}
+
+impl fmt::Display for UmbralError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value(message) => write!(f, "Invalid value: {}", message),
+            Self::Verification(message) => write!(f, "Verification failed: {}", message),
+            Self::Decryption(message) => write!(f, "Decryption failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for UmbralError {}
+
+fn value_err(err: impl fmt::Display) -> UmbralError {
+    UmbralError::Value(err.to_string())
+}
+
+#[derive(uniffi::Object)]
+#[uniffi::export(Display)]
+pub struct SecretKey {
+    backend: umbral_pre::SecretKey,
+}
+
+#[uniffi::export]
+impl SecretKey {
+    #[uniffi::constructor]
+    pub fn random() -> Self {
+        Self {
+            backend: umbral_pre::SecretKey::random(),
+        }
+    }
+
+    #[uniffi::constructor]
+    pub fn from_bytes(mut data: Vec<u8>) -> Result<Self, UmbralError> {
+        let result = umbral_pre::SecretKey::from_bytes(&data)
+            .map(|backend| Self { backend })
+            .map_err(value_err);
+        // The buffer was lifted from the JS side and is owned by us now.
+        data.zeroize();
+        result
+    }
+
+    pub fn public_key(&self) -> Arc<PublicKey> {
+        Arc::new(PublicKey {
+            backend: self.backend.public_key(),
+        })
+    }
+
+    /// Returns the serialized secret key.
+    ///
+    /// The returned buffer is moved to the JS side as is
+    /// (the temporary array it is copied from is zeroized on drop),
+    /// so it is the caller's responsibility to erase it after use.
+    pub fn to_secret_bytes(&self) -> Vec<u8> {
+        // Dereferencing a secret.
+        self.backend.to_secret_array().as_secret().to_vec()
+    }
+}
+
+impl fmt::Display for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.backend, f)
+    }
+}
+
+#[derive(uniffi::Object)]
+#[uniffi::export(Display)]
+pub struct PublicKey {
+    backend: umbral_pre::PublicKey,
+}
+
+#[uniffi::export]
+impl PublicKey {
+    #[uniffi::constructor]
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, UmbralError> {
+        umbral_pre::PublicKey::from_bytes(data)
+            .map(|backend| Self { backend })
+            .map_err(value_err)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.backend.to_array().to_vec()
+    }
+
+    pub fn equals(&self, other: &PublicKey) -> bool {
+        self.backend == other.backend
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.backend, f)
+    }
+}
+
+#[derive(uniffi::Object)]
+#[uniffi::export(Display)]
+pub struct Signer {
+    backend: umbral_pre::Signer,
+}
+
+#[uniffi::export]
+impl Signer {
+    #[uniffi::constructor]
+    pub fn new(sk: &SecretKey) -> Self {
+        Self {
+            backend: umbral_pre::Signer::new(sk.backend.clone()),
+        }
+    }
+
+    pub fn verifying_key(&self) -> Arc<PublicKey> {
+        Arc::new(PublicKey {
+            backend: self.backend.verifying_key(),
+        })
+    }
+}
+
+impl fmt::Display for Signer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.backend, f)
+    }
+}
+
+#[derive(uniffi::Object)]
+#[uniffi::export(Display)]
+pub struct Capsule {
+    backend: umbral_pre::Capsule,
+}
+
+#[uniffi::export]
+impl Capsule {
+    #[uniffi::constructor]
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, UmbralError> {
+        umbral_pre::Capsule::from_bytes(data)
+            .map(|backend| Self { backend })
+            .map_err(value_err)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.backend.to_array().to_vec()
+    }
+}
+
+impl fmt::Display for Capsule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.backend, f)
+    }
+}
+
+#[derive(uniffi::Object)]
+#[uniffi::export(Display)]
+pub struct KeyFrag {
+    backend: umbral_pre::KeyFrag,
+}
+
+#[uniffi::export]
+impl KeyFrag {
+    #[uniffi::constructor]
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, UmbralError> {
+        umbral_pre::KeyFrag::from_bytes(data)
+            .map(|backend| Self { backend })
+            .map_err(value_err)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.backend.to_array().to_vec()
+    }
+
+    pub fn verify(
+        &self,
+        verifying_pk: &PublicKey,
+        delegating_pk: Option<Arc<PublicKey>>,
+        receiving_pk: Option<Arc<PublicKey>>,
+    ) -> Result<Arc<VerifiedKeyFrag>, UmbralError> {
+        self.backend
+            .clone()
+            .verify(
+                &verifying_pk.backend,
+                delegating_pk.as_ref().map(|pk| &pk.backend),
+                receiving_pk.as_ref().map(|pk| &pk.backend),
+            )
+            .map(|backend| Arc::new(VerifiedKeyFrag { backend }))
+            .map_err(|(err, _kfrag)| UmbralError::Verification(err.to_string()))
+    }
+}
+
+impl fmt::Display for KeyFrag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.backend, f)
+    }
+}
+
+#[derive(uniffi::Object)]
+#[uniffi::export(Display)]
+pub struct VerifiedKeyFrag {
+    backend: umbral_pre::VerifiedKeyFrag,
+}
+
+#[uniffi::export]
+impl VerifiedKeyFrag {
+    #[uniffi::constructor]
+    pub fn from_verified_bytes(data: Vec<u8>) -> Result<Self, UmbralError> {
+        umbral_pre::VerifiedKeyFrag::from_verified_bytes(data)
+            .map(|backend| Self { backend })
+            .map_err(value_err)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.backend.to_array().to_vec()
+    }
+
+    pub fn unverify(&self) -> Arc<KeyFrag> {
+        Arc::new(KeyFrag {
+            backend: self.backend.clone().unverify(),
+        })
+    }
+}
+
+impl fmt::Display for VerifiedKeyFrag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.backend, f)
+    }
+}
+
+#[derive(uniffi::Object)]
+#[uniffi::export(Display)]
+pub struct CapsuleFrag {
+    backend: umbral_pre::CapsuleFrag,
+}
+
+#[uniffi::export]
+impl CapsuleFrag {
+    #[uniffi::constructor]
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, UmbralError> {
+        umbral_pre::CapsuleFrag::from_bytes(data)
+            .map(|backend| Self { backend })
+            .map_err(value_err)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.backend.to_array().to_vec()
+    }
+
+    pub fn verify(
+        &self,
+        capsule: &Capsule,
+        verifying_pk: &PublicKey,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+    ) -> Result<Arc<VerifiedCapsuleFrag>, UmbralError> {
+        self.backend
+            .clone()
+            .verify(
+                &capsule.backend,
+                &verifying_pk.backend,
+                &delegating_pk.backend,
+                &receiving_pk.backend,
+            )
+            .map(|backend| Arc::new(VerifiedCapsuleFrag { backend }))
+            .map_err(|(err, _cfrag)| UmbralError::Verification(err.to_string()))
+    }
+}
+
+impl fmt::Display for CapsuleFrag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.backend, f)
+    }
+}
+
+#[derive(uniffi::Object)]
+#[uniffi::export(Display)]
+pub struct VerifiedCapsuleFrag {
+    backend: umbral_pre::VerifiedCapsuleFrag,
+}
+
+#[uniffi::export]
+impl VerifiedCapsuleFrag {
+    #[uniffi::constructor]
+    pub fn from_verified_bytes(data: Vec<u8>) -> Result<Self, UmbralError> {
+        umbral_pre::VerifiedCapsuleFrag::from_verified_bytes(data)
+            .map(|backend| Self { backend })
+            .map_err(value_err)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.backend.to_array().to_vec()
+    }
+
+    pub fn unverify(&self) -> Arc<CapsuleFrag> {
+        Arc::new(CapsuleFrag {
+            backend: self.backend.clone().unverify(),
+        })
+    }
+}
+
+impl fmt::Display for VerifiedCapsuleFrag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.backend, f)
+    }
+}
+
+/// The result of [`encrypt`].
+///
+/// (UniFFI does not support returning tuples, so we return a record instead)
+#[derive(uniffi::Record)]
+pub struct EncryptionResult {
+    pub capsule: Arc<Capsule>,
+    pub ciphertext: Vec<u8>,
+}
+
+#[uniffi::export]
+pub fn encrypt(
+    delegating_pk: &PublicKey,
+    plaintext: Vec<u8>,
+) -> Result<EncryptionResult, UmbralError> {
+    umbral_pre::encrypt(&delegating_pk.backend, &plaintext)
+        .map(|(backend_capsule, ciphertext)| EncryptionResult {
+            capsule: Arc::new(Capsule {
+                backend: backend_capsule,
+            }),
+            ciphertext: ciphertext.into_vec(),
+        })
+        .map_err(value_err)
+}
+
+#[uniffi::export]
+pub fn decrypt_original(
+    delegating_sk: &SecretKey,
+    capsule: &Capsule,
+    ciphertext: Vec<u8>,
+) -> Result<Vec<u8>, UmbralError> {
+    umbral_pre::decrypt_original(&delegating_sk.backend, &capsule.backend, ciphertext)
+        .map(|plaintext| plaintext.into_vec())
+        .map_err(|err| UmbralError::Decryption(err.to_string()))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[uniffi::export]
+pub fn generate_kfrags(
+    delegating_sk: &SecretKey,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: u32,
+    shares: u32,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Vec<Arc<VerifiedKeyFrag>>, UmbralError> {
+    let backend_kfrags = umbral_pre::generate_kfrags(
+        &delegating_sk.backend,
+        &receiving_pk.backend,
+        &signer.backend,
+        threshold as usize,
+        shares as usize,
+        sign_delegating_key,
+        sign_receiving_key,
+    )
+    .map_err(value_err)?;
+
+    Ok(backend_kfrags
+        .into_vec()
+        .into_iter()
+        .map(|backend| Arc::new(VerifiedKeyFrag { backend }))
+        .collect())
+}
+
+#[uniffi::export]
+pub fn reencrypt(capsule: &Capsule, kfrag: &VerifiedKeyFrag) -> Arc<VerifiedCapsuleFrag> {
+    Arc::new(VerifiedCapsuleFrag {
+        backend: umbral_pre::reencrypt(&capsule.backend, kfrag.backend.clone()),
+    })
+}
+
+#[uniffi::export]
+pub fn decrypt_reencrypted(
+    receiving_sk: &SecretKey,
+    delegating_pk: &PublicKey,
+    capsule: &Capsule,
+    verified_cfrags: Vec<Arc<VerifiedCapsuleFrag>>,
+    ciphertext: Vec<u8>,
+) -> Result<Vec<u8>, UmbralError> {
+    let backend_cfrags = verified_cfrags
+        .iter()
+        .map(|vcfrag| vcfrag.backend.clone())
+        .collect::<Vec<_>>();
+    umbral_pre::decrypt_reencrypted(
+        &receiving_sk.backend,
+        &delegating_pk.backend,
+        &capsule.backend,
+        backend_cfrags,
+        ciphertext,
+    )
+    .map(|plaintext| plaintext.into_vec())
+    .map_err(|err| UmbralError::Decryption(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{
+        decrypt_original, decrypt_reencrypted, encrypt, generate_kfrags, reencrypt, Capsule,
+        KeyFrag, PublicKey, SecretKey, Signer,
+    };
+
+    #[test]
+    fn test_roundtrip() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(&SecretKey::random());
+        let verifying_pk = signer.verifying_key();
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        // Serialization roundtrips
+        let sk_back = SecretKey::from_bytes(delegating_sk.to_secret_bytes()).unwrap();
+        assert!(sk_back.public_key().equals(&delegating_pk));
+        let pk_back = PublicKey::from_bytes(delegating_pk.to_bytes()).unwrap();
+        assert!(pk_back.equals(&delegating_pk));
+        assert!(SecretKey::from_bytes(vec![0u8; 3]).is_err());
+
+        let plaintext = b"peace at dawn".to_vec();
+        let result = encrypt(&delegating_pk, plaintext.clone()).unwrap();
+        let capsule = Capsule::from_bytes(result.capsule.to_bytes()).unwrap();
+
+        let plaintext_alice =
+            decrypt_original(&delegating_sk, &capsule, result.ciphertext.clone()).unwrap();
+        assert_eq!(plaintext_alice, plaintext);
+
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+
+        let vcfrags = vkfrags[..2]
+            .iter()
+            .map(|vkfrag| {
+                // Simulate network transfer to the proxy and back
+                let kfrag = KeyFrag::from_bytes(vkfrag.to_bytes()).unwrap();
+                let vkfrag = kfrag
+                    .verify(
+                        &verifying_pk,
+                        Some(delegating_pk.clone()),
+                        Some(receiving_pk.clone()),
+                    )
+                    .unwrap();
+                let vcfrag = reencrypt(&capsule, &vkfrag);
+                vcfrag
+                    .unverify()
+                    .verify(&capsule, &verifying_pk, &delegating_pk, &receiving_pk)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            vcfrags,
+            result.ciphertext,
+        )
+        .unwrap();
+        assert_eq!(plaintext_bob, plaintext);
+    }
+}
@@ -103,13 +103,13 @@ function App() {
     // Finally, Bob opens the capsule by using at least `threshold` cfrags,
     // and then decrypts the re-encrypted ciphertext.
 
-    // Another deviation from the Rust API.
-    // wasm-pack does not support taking arrays as arguments,
-    // so we build a capsule+cfrags object before decryption.
-    let plaintext_bob = capsule
-      .withCFrag(cfrag0)
-      .withCFrag(cfrag1)
-      .decryptReencrypted(bob_sk, alice_pk, ciphertext);
+    let plaintext_bob = umbral.decryptReencrypted(
+      bob_sk,
+      alice_pk,
+      capsule,
+      [cfrag0, cfrag1],
+      ciphertext
+    );
 
     console.assert(
       dec.decode(plaintext_bob) === plaintext,
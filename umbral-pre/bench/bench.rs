@@ -52,7 +52,8 @@ fn bench_capsule_open_reencrypted<'a, M: Measurement>(group: &mut BenchmarkGroup
         num_frags,
         true,
         true,
-    );
+    )
+    .unwrap();
 
     let vcfrags: Vec<_> = kfrags
         .iter()
@@ -122,7 +123,8 @@ fn bench_pre<'a, M: Measurement>(group: &mut BenchmarkGroup<'a, M>) {
         num_frags,
         true,
         true,
-    );
+    )
+    .unwrap();
 
     let vkfrag = &verified_kfrags[0];
 
@@ -0,0 +1,58 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use umbral_pre::{decrypt_original, decrypt_with, encrypt_for_with_rng};
+use umbral_pre_fuzz::{apply_mutations, Delegation};
+
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    seed: [u8; 32],
+    plaintext: Vec<u8>,
+    // `(position, mask)` pairs applied to the ciphertext.
+    mutations: Vec<(u16, u8)>,
+    truncate_to: Option<u16>,
+}
+
+fuzz_target!(|input: Input| {
+    let delegation = Delegation::new(input.seed, 1, 1, &input.plaintext);
+
+    let mut ciphertext = delegation.ciphertext.to_vec();
+    apply_mutations(&mut ciphertext, &input.mutations);
+    if let Some(length) = input.truncate_to {
+        ciphertext.truncate(length as usize);
+    }
+    let modified = ciphertext != delegation.ciphertext.as_ref();
+
+    // The DEM must reject any modification, and never panic.
+    match decrypt_original(&delegation.delegating_sk, &delegation.capsule, &ciphertext) {
+        Ok(plaintext) => {
+            assert!(!modified);
+            assert_eq!(plaintext.as_ref(), input.plaintext.as_slice());
+        }
+        Err(_) => assert!(modified),
+    }
+
+    // Same for the single-recipient encryption, where the capsule is a part of the ciphertext.
+    let mut rng = ChaCha20Rng::from_seed(input.seed);
+    let mut ciphertext =
+        encrypt_for_with_rng(&mut rng, &delegation.delegating_pk, &input.plaintext)
+            .unwrap()
+            .to_vec();
+    let original = ciphertext.clone();
+    apply_mutations(&mut ciphertext, &input.mutations);
+    if let Some(length) = input.truncate_to {
+        ciphertext.truncate(length as usize);
+    }
+    match decrypt_with(&delegation.delegating_sk, &ciphertext) {
+        Ok(plaintext) => {
+            assert_eq!(ciphertext, original);
+            assert_eq!(plaintext.as_ref(), input.plaintext.as_slice());
+        }
+        Err(_) => assert_ne!(ciphertext, original),
+    }
+});
@@ -0,0 +1,62 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use umbral_pre::{
+    AuditEntry, Capsule, CapsuleFrag, DelegationCertificate, DeserializableFromArray, KeyFrag,
+    PartialDecryption, PolicyId, PublicKey, ReceivingKeyShare, SecretKey, SecretKeyFactory,
+    SerializableToArray, SerializableToSecretArray, Signature, UnprovenCapsuleFrag,
+};
+
+// Deserialization must never panic, and a successfully deserialized object
+// must serialize back to the same bytes (since the encoding is canonical).
+macro_rules! check_public {
+    ($data:expr, $($tp:ty),*) => {
+        $(
+            if let Ok(obj) = <$tp>::from_bytes($data) {
+                assert_eq!(obj.to_array().as_slice(), $data);
+            }
+        )*
+    };
+}
+
+macro_rules! check_secret {
+    ($data:expr, $($tp:ty),*) => {
+        $(
+            if let Ok(obj) = <$tp>::from_bytes($data) {
+                assert_eq!(obj.to_secret_array().as_secret().as_slice(), $data);
+            }
+        )*
+    };
+}
+
+fuzz_target!(|data: &[u8]| {
+    check_public!(
+        data,
+        PublicKey,
+        Signature,
+        Capsule,
+        KeyFrag,
+        CapsuleFrag,
+        UnprovenCapsuleFrag,
+        PolicyId,
+        DelegationCertificate,
+        AuditEntry,
+        PartialDecryption
+    );
+    check_secret!(data, SecretKey, SecretKeyFactory, ReceivingKeyShare);
+
+    // The diagnostic deserialization must agree with the regular one.
+    assert_eq!(
+        Capsule::from_bytes(data).is_ok(),
+        Capsule::from_bytes_diagnostic(data).is_ok()
+    );
+    assert_eq!(
+        KeyFrag::from_bytes(data).is_ok(),
+        KeyFrag::from_bytes_diagnostic(data).is_ok()
+    );
+    assert_eq!(
+        CapsuleFrag::from_bytes(data).is_ok(),
+        CapsuleFrag::from_bytes_diagnostic(data).is_ok()
+    );
+});
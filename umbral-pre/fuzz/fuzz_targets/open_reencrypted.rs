@@ -0,0 +1,78 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use umbral_pre::{
+    decrypt_reencrypted, decrypt_reencrypted_unproven, CapsuleFrag, DeserializableFromArray,
+    SerializableToArray, UnprovenCapsuleFrag,
+};
+use umbral_pre_fuzz::{apply_mutations, Delegation};
+
+const PLAINTEXT: &[u8] = b"peace at dawn";
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    seed: [u8; 32],
+    threshold: u8,
+    shares: u8,
+    // Indices (modulo `shares`) of the capsule fragments to combine, possibly repeating.
+    selection: Vec<u8>,
+    // `(position, mask)` pairs applied to the concatenation of the selected fragments.
+    mutations: Vec<(u16, u8)>,
+}
+
+fuzz_target!(|input: Input| {
+    let shares = (input.shares % 5) as usize + 1;
+    let threshold = (input.threshold as usize % shares) + 1;
+    let delegation = Delegation::new(input.seed, threshold, shares, PLAINTEXT);
+
+    let mut bytes = Vec::new();
+    for index in input.selection.iter().take(8) {
+        let vcfrag = &delegation.verified_cfrags[*index as usize % shares];
+        bytes.extend_from_slice(&vcfrag.to_array());
+    }
+    apply_mutations(&mut bytes, &input.mutations);
+
+    let cfrag_size = CapsuleFrag::serialized_size();
+    let cfrags: Vec<_> = bytes
+        .chunks(cfrag_size)
+        .filter_map(|chunk| CapsuleFrag::from_bytes(chunk).ok())
+        .collect();
+
+    // The unproven path must never panic, and never return a wrong plaintext.
+    let unproven_cfrags: Vec<_> = cfrags.iter().map(UnprovenCapsuleFrag::from).collect();
+    if let Ok(plaintext) = decrypt_reencrypted_unproven(
+        &delegation.receiving_sk,
+        &delegation.delegating_pk,
+        &delegation.capsule,
+        unproven_cfrags,
+        &delegation.ciphertext,
+    ) {
+        assert_eq!(&plaintext as &[u8], PLAINTEXT);
+    }
+
+    // Same for the verified path, with the fragments that pass verification.
+    let verified_cfrags: Vec<_> = cfrags
+        .into_iter()
+        .filter_map(|cfrag| {
+            cfrag
+                .verify(
+                    &delegation.capsule,
+                    &delegation.verifying_pk,
+                    &delegation.delegating_pk,
+                    &delegation.receiving_pk,
+                )
+                .ok()
+        })
+        .collect();
+    if let Ok(plaintext) = decrypt_reencrypted(
+        &delegation.receiving_sk,
+        &delegation.delegating_pk,
+        &delegation.capsule,
+        verified_cfrags,
+        &delegation.ciphertext,
+    ) {
+        assert_eq!(&plaintext as &[u8], PLAINTEXT);
+    }
+});
@@ -0,0 +1,84 @@
+//! Writes valid serialized objects into `corpus/deserialize`,
+//! to be used as seeds for the `deserialize` fuzz target.
+//!
+//! The structured targets create valid objects from the fuzzed seed themselves,
+//! so they do not need a pre-generated corpus.
+
+use std::fs;
+use std::path::Path;
+
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use umbral_pre::{
+    split_receiving_key_with_rng, AuditLog, DelegationCertificate, PolicyId, SecretKey,
+    SerializableToArray, SerializableToSecretArray, Signer, UnprovenCapsuleFrag,
+};
+use umbral_pre_fuzz::Delegation;
+
+fn main() {
+    let dir = Path::new("corpus").join("deserialize");
+    fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, data: &[u8]| fs::write(dir.join(name), data).unwrap();
+
+    let delegation = Delegation::new([0u8; 32], 2, 3, b"peace at dawn");
+    let mut rng = ChaCha20Rng::from_seed([1u8; 32]);
+
+    write("public_key", &delegation.delegating_pk.to_array());
+    write(
+        "secret_key",
+        delegation.delegating_sk.to_secret_array().as_secret(),
+    );
+    write("capsule", &delegation.capsule.to_array());
+    write("kfrag", &delegation.verified_kfrags[0].to_array());
+    write("cfrag", &delegation.verified_cfrags[0].to_array());
+
+    let cfrag = delegation.verified_cfrags[0].clone().unverify();
+    write(
+        "unproven_cfrag",
+        &UnprovenCapsuleFrag::from(&cfrag).to_array(),
+    );
+
+    let signer = Signer::new(SecretKey::random_with_rng(&mut rng));
+    write(
+        "signature",
+        &signer.sign_with_rng(&mut rng, b"message").to_array(),
+    );
+
+    let policy_id = PolicyId::new(
+        &delegation.delegating_pk,
+        &delegation.receiving_pk,
+        b"label",
+    );
+    write("policy_id", &policy_id.to_array());
+
+    let certificate = DelegationCertificate::new_with_rng(
+        &mut rng,
+        &signer,
+        &delegation.delegating_pk,
+        &delegation.receiving_pk,
+        b"label",
+        2,
+        0,
+        u64::MAX,
+    );
+    write("delegation_certificate", &certificate.to_array());
+
+    let mut log = AuditLog::new(signer, Vec::new());
+    log.reencrypt_with_rng(
+        &mut rng,
+        &delegation.capsule,
+        delegation.verified_kfrags[0].clone(),
+        0,
+    );
+    write("audit_entry", &log.into_sink()[0].to_array());
+
+    let key_shares =
+        split_receiving_key_with_rng(&mut rng, &delegation.receiving_sk, 2, 3).unwrap();
+    write(
+        "receiving_key_share",
+        key_shares[0].to_secret_array().as_secret(),
+    );
+    let partial_decryption = key_shares[0].partial_decryption(&delegation.verified_cfrags[0]);
+    write("partial_decryption", &partial_decryption.to_array());
+}
@@ -0,0 +1,81 @@
+//! Helpers shared between the fuzz targets.
+
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use umbral_pre::{
+    encrypt_with_rng, generate_kfrags_with_rng, reencrypt_with_rng, Capsule, PublicKey, SecretKey,
+    Signer, VerifiedCapsuleFrag, VerifiedKeyFrag,
+};
+
+/// A complete and valid delegation, created deterministically from a seed.
+pub struct Delegation {
+    pub delegating_sk: SecretKey,
+    pub delegating_pk: PublicKey,
+    pub verifying_pk: PublicKey,
+    pub receiving_sk: SecretKey,
+    pub receiving_pk: PublicKey,
+    pub capsule: Capsule,
+    pub ciphertext: Box<[u8]>,
+    pub verified_kfrags: Box<[VerifiedKeyFrag]>,
+    pub verified_cfrags: Box<[VerifiedCapsuleFrag]>,
+}
+
+impl Delegation {
+    /// Creates a delegation with `shares` key fragments (`threshold` of which are required),
+    /// encrypts `plaintext`, and reencrypts the capsule with every key fragment.
+    pub fn new(seed: [u8; 32], threshold: usize, shares: usize, plaintext: &[u8]) -> Self {
+        let mut rng = ChaCha20Rng::from_seed(seed);
+
+        let delegating_sk = SecretKey::random_with_rng(&mut rng);
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random_with_rng(&mut rng));
+        let verifying_pk = signer.verifying_key();
+        let receiving_sk = SecretKey::random_with_rng(&mut rng);
+        let receiving_pk = receiving_sk.public_key();
+
+        let (capsule, ciphertext) = encrypt_with_rng(&mut rng, &delegating_pk, plaintext).unwrap();
+
+        let verified_kfrags = generate_kfrags_with_rng(
+            &mut rng,
+            &delegating_sk,
+            &receiving_pk,
+            &signer,
+            threshold,
+            shares,
+            true,
+            true,
+        )
+        .unwrap();
+
+        let verified_cfrags = verified_kfrags
+            .iter()
+            .map(|vkfrag| reencrypt_with_rng(&mut rng, &capsule, vkfrag.clone()))
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        Self {
+            delegating_sk,
+            delegating_pk,
+            verifying_pk,
+            receiving_sk,
+            receiving_pk,
+            capsule,
+            ciphertext,
+            verified_kfrags,
+            verified_cfrags,
+        }
+    }
+}
+
+/// Flips the bytes of `data` according to the given list of `(position, mask)` pairs.
+/// Positions are taken modulo the length of `data`.
+pub fn apply_mutations(data: &mut [u8], mutations: &[(u16, u8)]) {
+    if data.is_empty() {
+        return;
+    }
+    for (position, mask) in mutations {
+        let position = *position as usize % data.len();
+        data[position] ^= mask;
+    }
+}
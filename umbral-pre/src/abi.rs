@@ -0,0 +1,190 @@
+//! Ethereum ABI encoding of the capsules and capsule fragments,
+//! allowing smart contracts to verify the reencryption proofs.
+//!
+//! The objects are encoded as static tuples following the field order of their
+//! [`Layout`](`crate::format::Layout`), with every value taking a 32-byte word:
+//! curve points are `(uint256 x, uint256 y)` in the affine form,
+//! scalars are `uint256`, identifiers are `bytes32`,
+//! and signatures are `(uint256 r, uint256 s)`.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use elliptic_curve::sec1::ToEncodedPoint;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::curve::CurvePoint;
+use crate::format::{FieldEncoding, Layout, CAPSULE, CAPSULE_FRAG};
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, SerializableToArray,
+};
+
+const WORD_SIZE: usize = 32;
+
+fn words(encoding: FieldEncoding) -> usize {
+    match encoding {
+        FieldEncoding::Point | FieldEncoding::Signature => 2,
+        FieldEncoding::Scalar | FieldEncoding::Identifier | FieldEncoding::Bool => 1,
+    }
+}
+
+fn abi_size(layout: &Layout) -> usize {
+    layout
+        .fields
+        .iter()
+        .map(|field| words(field.encoding) * WORD_SIZE)
+        .sum()
+}
+
+/// Converts the canonical serialization of an object into the ABI encoding.
+fn to_abi(layout: &Layout, bytes: &[u8]) -> Box<[u8]> {
+    let mut result = Vec::with_capacity(abi_size(layout));
+    let mut offset = 0;
+    for field in layout.fields {
+        let size = field.encoding.size();
+        let field_bytes = &bytes[offset..offset + size];
+        offset += size;
+        match field.encoding {
+            FieldEncoding::Point => {
+                // The canonical serialization only contains valid points.
+                let point = CurvePoint::from_sec1_bytes(field_bytes)
+                    .unwrap_or_default()
+                    .to_affine_point()
+                    .to_encoded_point(false);
+                // Skip the SEC1 tag
+                result.extend_from_slice(&point.as_bytes()[1..]);
+            }
+            FieldEncoding::Scalar | FieldEncoding::Identifier | FieldEncoding::Signature => {
+                result.extend_from_slice(field_bytes)
+            }
+            FieldEncoding::Bool => {
+                result.extend_from_slice(&[0u8; WORD_SIZE - 1]);
+                result.extend_from_slice(field_bytes);
+            }
+        }
+    }
+    result.into_boxed_slice()
+}
+
+/// Converts the ABI encoding back into the canonical serialization.
+fn from_abi(layout: &Layout, data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    if data.len() != abi_size(layout) {
+        return Err("Invalid ABI encoding length");
+    }
+    let mut result = Vec::with_capacity(layout.size());
+    let mut offset = 0;
+    for field in layout.fields {
+        let size = words(field.encoding) * WORD_SIZE;
+        let field_data = &data[offset..offset + size];
+        offset += size;
+        match field.encoding {
+            FieldEncoding::Point => {
+                let mut uncompressed = [0u8; 1 + 2 * WORD_SIZE];
+                uncompressed[0] = 0x04;
+                uncompressed[1..].copy_from_slice(field_data);
+                let point = CurvePoint::from_sec1_bytes(&uncompressed)
+                    .ok_or("Point coordinates are not on the curve")?;
+                result.extend_from_slice(&point.to_array());
+            }
+            FieldEncoding::Scalar | FieldEncoding::Identifier | FieldEncoding::Signature => {
+                result.extend_from_slice(field_data)
+            }
+            FieldEncoding::Bool => {
+                if field_data[..WORD_SIZE - 1].iter().any(|byte| *byte != 0) {
+                    return Err("Invalid boolean value");
+                }
+                result.push(field_data[WORD_SIZE - 1]);
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn from_abi_object<T: DeserializableFromArray>(
+    layout: &Layout,
+    data: &[u8],
+) -> Result<T, DeserializationError> {
+    let bytes = from_abi(layout, data).map_err(|msg| {
+        DeserializationError::ConstructionFailure(ConstructionError::new(layout.type_name, msg))
+    })?;
+    T::from_bytes(bytes)
+}
+
+impl Capsule {
+    /// Returns the ABI encoding of the capsule as the static tuple
+    /// `((uint256,uint256),(uint256,uint256),uint256)` (160 bytes).
+    pub fn to_abi_encoded(&self) -> Box<[u8]> {
+        to_abi(&CAPSULE, &self.to_array())
+    }
+
+    /// Restores the capsule from the ABI encoding produced by
+    /// [`to_abi_encoded`](`Self::to_abi_encoded`).
+    pub fn from_abi_encoded(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        from_abi_object(&CAPSULE, data.as_ref())
+    }
+}
+
+impl CapsuleFrag {
+    /// Returns the ABI encoding of the capsule fragment as the static tuple
+    /// `((uint256,uint256),(uint256,uint256),bytes32,(uint256,uint256),`
+    /// `((uint256,uint256),(uint256,uint256),(uint256,uint256),(uint256,uint256),`
+    /// `uint256,(uint256,uint256)))` (576 bytes), where the last element is the proof.
+    pub fn to_abi_encoded(&self) -> Box<[u8]> {
+        to_abi(&CAPSULE_FRAG, &self.to_array())
+    }
+
+    /// Restores the capsule fragment from the ABI encoding produced by
+    /// [`to_abi_encoded`](`Self::to_abi_encoded`).
+    /// The result has to be verified before use, as with any received capsule fragment.
+    pub fn from_abi_encoded(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        from_abi_object(&CAPSULE_FRAG, data.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use elliptic_curve::sec1::ToEncodedPoint;
+
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{encrypt, Capsule, CapsuleFrag, SecretKey, SerializableToArray};
+
+    #[test]
+    fn test_roundtrip() {
+        let Delegation {
+            capsule,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+
+        let capsule_abi = capsule.to_abi_encoded();
+        assert_eq!(capsule_abi.len(), 160);
+        assert_eq!(Capsule::from_abi_encoded(&capsule_abi).unwrap(), capsule);
+
+        // The coordinates of `point_e` and the signature
+        let point_e = capsule.point_e.to_affine_point().to_encoded_point(false);
+        assert_eq!(capsule_abi[..64], point_e.as_bytes()[1..]);
+        assert_eq!(capsule_abi[128..], capsule.to_array()[66..]);
+
+        let cfrag = vcfrags[0].clone().unverify();
+        let cfrag_abi = cfrag.to_abi_encoded();
+        assert_eq!(cfrag_abi.len(), 576);
+        assert_eq!(CapsuleFrag::from_abi_encoded(&cfrag_abi).unwrap(), cfrag);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+        let capsule_abi = capsule.to_abi_encoded();
+
+        // A point not on the curve
+        let mut wrong_y = capsule_abi.to_vec();
+        wrong_y[63] ^= 1;
+        assert!(Capsule::from_abi_encoded(&wrong_y).is_err());
+
+        // Wrong length
+        assert!(Capsule::from_abi_encoded(&capsule_abi[..128]).is_err());
+    }
+}
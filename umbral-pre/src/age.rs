@@ -0,0 +1,502 @@
+//! Encryption into the [age](https://age-encryption.org/v1) file format,
+//! allowing Umbral delegation to be used in age-based pipelines.
+//!
+//! The age file key is encrypted with the DEM under a fresh capsule,
+//! and stored in a recipient stanza of type [`AGE_STANZA_TYPE`]:
+//!
+//! ```text
+//! -> umbral <base64 capsule>
+//! <base64 encrypted file key>
+//! ```
+//!
+//! The header MAC and the payload follow the age specification
+//! (HKDF-SHA256 key derivation and the ChaCha20-Poly1305 STREAM construction
+//! with 64 KiB chunks), so other tools can process the file if they have
+//! a plugin for the `umbral` recipient type.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use aead::AeadInPlace;
+use chacha20poly1305::aead::NewAead;
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+use hmac::{Hmac, Mac, NewMac};
+use rand_core::{CryptoRng, RngCore};
+use sha2::Sha256;
+use typenum::U32;
+use zeroize::Zeroize;
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::VerifiedCapsuleFrag;
+use crate::dem::{kdf, DecryptionError, EncryptionError};
+use crate::keys::PublicKey;
+use crate::pre::{self, ReencryptionError};
+use crate::roles::{AsDelegatingKey, AsReceivingKey};
+use crate::secret_box::{CanBeZeroizedOnDrop, SecretBox};
+use crate::traits::{
+    fmt_public, ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
+    SerializableToArray,
+};
+
+/// The type of the recipient stanza carrying the capsule and the encrypted file key.
+pub const AGE_STANZA_TYPE: &str = "umbral";
+
+const VERSION_LINE: &str = "age-encryption.org/v1";
+const STANZA_PREFIX: &str = "-> ";
+const MAC_PREFIX: &str = "---";
+
+// Base64 lines in stanza bodies are wrapped at this width.
+const COLUMNS: usize = 64;
+
+const FILE_KEY_SIZE: usize = 16;
+const MAC_SIZE: usize = 32;
+const PAYLOAD_NONCE_SIZE: usize = 16;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+
+fn encode_base64(data: &[u8]) -> String {
+    base64::encode_config(data, base64::STANDARD_NO_PAD)
+}
+
+fn decode_base64(encoded: &[u8]) -> Option<Vec<u8>> {
+    let decoded = base64::decode_config(encoded, base64::STANDARD_NO_PAD).ok()?;
+    // age requires the canonical encoding (no padding, no extra bits).
+    if encode_base64(&decoded).as_bytes() == encoded {
+        Some(decoded)
+    } else {
+        None
+    }
+}
+
+fn header_mac(file_key: &SecretBox<[u8; FILE_KEY_SIZE]>, header: &[u8]) -> Hmac<Sha256> {
+    let mac_key = kdf::<_, U32>(file_key, None, Some(b"header"));
+    // HMAC accepts keys of any length, so this cannot fail.
+    let mut mac = Hmac::<Sha256>::new_from_slice(mac_key.as_secret()).unwrap();
+    mac.update(header);
+    mac
+}
+
+impl CanBeZeroizedOnDrop for ChaCha20Poly1305 {
+    fn ensure_zeroized_on_drop(&mut self) {
+        // `ChaCha20Poly1305` is zeroized on drop in `chacha20poly1305-0.9`
+    }
+}
+
+fn payload_cipher(
+    file_key: &SecretBox<[u8; FILE_KEY_SIZE]>,
+    nonce: &[u8],
+) -> SecretBox<ChaCha20Poly1305> {
+    let key = kdf::<_, U32>(file_key, Some(nonce), Some(b"payload"));
+    SecretBox::new(ChaCha20Poly1305::new(Key::from_slice(key.as_secret())))
+}
+
+/// The nonce of a STREAM chunk: an 11-byte big-endian counter and the last chunk flag.
+fn chunk_nonce(counter: u64, last: bool) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[3..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = u8::from(last);
+    nonce
+}
+
+fn take_line<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let end = data.iter().position(|byte| *byte == b'\n')?;
+    let line = &data[..end];
+    *data = &data[end + 1..];
+    Some(line)
+}
+
+/// A file in the age format, with the file key encapsulated for an Umbral public key.
+///
+/// Can be decrypted by the owner of the public key it was created for
+/// with [`decrypt_original`](`Self::decrypt_original`), or, if the access was delegated,
+/// by the receiver using the fragments of its [`capsule`](`Self::capsule`)
+/// with [`decrypt_reencrypted`](`Self::decrypt_reencrypted`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgeFile {
+    capsule: Capsule,
+    encrypted_file_key: Box<[u8]>,
+    // The header up to and including the `---` of the MAC line.
+    header: Box<[u8]>,
+    mac: [u8; MAC_SIZE],
+    // The payload nonce followed by the STREAM chunks.
+    payload: Box<[u8]>,
+}
+
+impl AgeFile {
+    /// Encrypts `plaintext` into an age file for the owner of `delegating_pk`
+    /// using the given RNG.
+    pub fn encrypt_with_rng(
+        rng: &mut (impl CryptoRng + RngCore),
+        delegating_pk: &PublicKey,
+        plaintext: &[u8],
+    ) -> Result<Self, EncryptionError> {
+        let file_key =
+            SecretBox::new_with(|file_key: &mut [u8; FILE_KEY_SIZE]| rng.fill_bytes(file_key));
+        let (capsule, encrypted_file_key) =
+            pre::encrypt_with_rng(rng, delegating_pk, file_key.as_secret())?;
+
+        let mut header = String::from(VERSION_LINE);
+        header.push('\n');
+        header.push_str(STANZA_PREFIX);
+        header.push_str(AGE_STANZA_TYPE);
+        header.push(' ');
+        header.push_str(&encode_base64(&capsule.to_array()));
+        header.push('\n');
+        let body = encode_base64(&encrypted_file_key);
+        for line in body.as_bytes().chunks(COLUMNS) {
+            // The lines are slices of a base64 string, so they are valid ASCII.
+            header.push_str(core::str::from_utf8(line).unwrap_or_default());
+            header.push('\n');
+        }
+        // The last line of the body must be shorter than the full width.
+        if body.len() % COLUMNS == 0 {
+            header.push('\n');
+        }
+        header.push_str(MAC_PREFIX);
+
+        let mut mac = [0u8; MAC_SIZE];
+        mac.copy_from_slice(
+            &header_mac(&file_key, header.as_bytes())
+                .finalize()
+                .into_bytes(),
+        );
+
+        let mut nonce = [0u8; PAYLOAD_NONCE_SIZE];
+        rng.fill_bytes(&mut nonce);
+        let cipher = payload_cipher(&file_key, &nonce);
+
+        let chunks = core::cmp::max(1, (plaintext.len() + CHUNK_SIZE - 1) / CHUNK_SIZE);
+        let mut payload =
+            Vec::with_capacity(PAYLOAD_NONCE_SIZE + plaintext.len() + chunks * TAG_SIZE);
+        payload.extend_from_slice(&nonce);
+        for counter in 0..chunks {
+            let start = counter * CHUNK_SIZE;
+            let end = core::cmp::min(start + CHUNK_SIZE, plaintext.len());
+            let chunk_start = payload.len();
+            payload.extend_from_slice(&plaintext[start..end]);
+            let tag = cipher
+                .as_secret()
+                .encrypt_in_place_detached(
+                    &chunk_nonce(counter as u64, counter == chunks - 1),
+                    &[],
+                    &mut payload[chunk_start..],
+                )
+                .map_err(|_| EncryptionError::PlaintextTooLarge)?;
+            payload.extend_from_slice(&tag);
+        }
+
+        Ok(Self {
+            capsule,
+            encrypted_file_key,
+            header: header.into_bytes().into_boxed_slice(),
+            mac,
+            payload: payload.into_boxed_slice(),
+        })
+    }
+
+    /// A synonym for [`encrypt_with_rng`](`Self::encrypt_with_rng`) with the default RNG.
+    #[cfg(feature = "default-rng")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+    pub fn encrypt(delegating_pk: &PublicKey, plaintext: &[u8]) -> Result<Self, EncryptionError> {
+        Self::encrypt_with_rng(&mut OsRng, delegating_pk, plaintext)
+    }
+
+    /// Returns the capsule (to be reencrypted by proxies if the access was delegated).
+    pub fn capsule(&self) -> &Capsule {
+        &self.capsule
+    }
+
+    /// Decrypts the file using the secret key it was encrypted for.
+    pub fn decrypt_original(
+        &self,
+        delegating_sk: &impl AsDelegatingKey,
+    ) -> Result<Box<[u8]>, DecryptionError> {
+        let file_key =
+            pre::decrypt_original(delegating_sk, &self.capsule, &self.encrypted_file_key)?;
+        self.decrypt_payload(file_key)
+    }
+
+    /// Decrypts the file using the capsule fragments obtained by reencrypting
+    /// [`capsule`](`Self::capsule`)
+    /// (see [`decrypt_reencrypted`](`crate::decrypt_reencrypted`)).
+    pub fn decrypt_reencrypted(
+        &self,
+        receiving_sk: &impl AsReceivingKey,
+        delegating_pk: &PublicKey,
+        verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+    ) -> Result<Box<[u8]>, ReencryptionError> {
+        let file_key = pre::decrypt_reencrypted(
+            receiving_sk,
+            delegating_pk,
+            &self.capsule,
+            verified_cfrags,
+            &self.encrypted_file_key,
+        )?;
+        self.decrypt_payload(file_key)
+            .map_err(ReencryptionError::OnDecryption)
+    }
+
+    fn decrypt_payload(&self, mut file_key_bytes: Box<[u8]>) -> Result<Box<[u8]>, DecryptionError> {
+        let file_key = if file_key_bytes.len() == FILE_KEY_SIZE {
+            Some(SecretBox::new_with(|file_key: &mut [u8; FILE_KEY_SIZE]| {
+                file_key.copy_from_slice(&file_key_bytes)
+            }))
+        } else {
+            None
+        };
+        file_key_bytes.zeroize();
+        let file_key = file_key.ok_or(DecryptionError::AuthenticationFailed)?;
+
+        header_mac(&file_key, &self.header)
+            .verify(&self.mac)
+            .map_err(|_| DecryptionError::AuthenticationFailed)?;
+
+        let (nonce, chunks) = self.payload.split_at(PAYLOAD_NONCE_SIZE);
+        let cipher = payload_cipher(&file_key, nonce);
+
+        let chunks_num = (chunks.len() + CHUNK_SIZE + TAG_SIZE - 1) / (CHUNK_SIZE + TAG_SIZE);
+        let mut plaintext = Vec::with_capacity(chunks.len());
+        for (counter, chunk) in chunks.chunks(CHUNK_SIZE + TAG_SIZE).enumerate() {
+            let last = counter == chunks_num - 1;
+            // Only an empty file can have an empty last chunk.
+            if chunk.len() < TAG_SIZE || (last && counter > 0 && chunk.len() == TAG_SIZE) {
+                return Err(DecryptionError::AuthenticationFailed);
+            }
+            let (data, tag) = chunk.split_at(chunk.len() - TAG_SIZE);
+            let chunk_start = plaintext.len();
+            plaintext.extend_from_slice(data);
+            let result = cipher.as_secret().decrypt_in_place_detached(
+                &chunk_nonce(counter as u64, last),
+                &[],
+                &mut plaintext[chunk_start..],
+                Tag::from_slice(tag),
+            );
+            if result.is_err() {
+                plaintext.zeroize();
+                return Err(DecryptionError::AuthenticationFailed);
+            }
+        }
+        Ok(plaintext.into_boxed_slice())
+    }
+
+    /// Serializes the file in the age format.
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        let mac = encode_base64(&self.mac);
+        let mut result = Vec::with_capacity(self.header.len() + mac.len() + 2 + self.payload.len());
+        result.extend_from_slice(&self.header);
+        result.push(b' ');
+        result.extend_from_slice(mac.as_bytes());
+        result.push(b'\n');
+        result.extend_from_slice(&self.payload);
+        result.into_boxed_slice()
+    }
+
+    /// Parses a file in the age format.
+    ///
+    /// The file must contain a recipient stanza of type [`AGE_STANZA_TYPE`];
+    /// stanzas for other recipients are skipped.
+    pub fn from_bytes(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let error = |message| {
+            DeserializationError::ConstructionFailure(ConstructionError::new("AgeFile", message))
+        };
+
+        let data = data.as_ref();
+        let mut rest = data;
+        if take_line(&mut rest) != Some(VERSION_LINE.as_bytes()) {
+            return Err(error("Unsupported age version"));
+        }
+
+        let mut recipient = None;
+        let (header_size, mac_line) = loop {
+            let line_start = data.len() - rest.len();
+            let line = take_line(&mut rest).ok_or_else(|| error("The header is truncated"))?;
+            if let Some(mac) = line.strip_prefix(MAC_PREFIX.as_bytes()) {
+                break (line_start + MAC_PREFIX.len(), mac);
+            }
+
+            let arguments = line
+                .strip_prefix(STANZA_PREFIX.as_bytes())
+                .ok_or_else(|| error("Malformed header line"))?;
+            let mut body = Vec::new();
+            loop {
+                let body_line =
+                    take_line(&mut rest).ok_or_else(|| error("The header is truncated"))?;
+                if body_line.len() > COLUMNS {
+                    return Err(error("Malformed stanza body"));
+                }
+                body.extend_from_slice(body_line);
+                if body_line.len() < COLUMNS {
+                    break;
+                }
+            }
+
+            let mut arguments = arguments.split(|byte| *byte == b' ');
+            if arguments.next() != Some(AGE_STANZA_TYPE.as_bytes()) || recipient.is_some() {
+                continue;
+            }
+            let capsule_bytes = arguments
+                .next()
+                .and_then(decode_base64)
+                .ok_or_else(|| error("Malformed Umbral stanza arguments"))?;
+            if arguments.next().is_some() {
+                return Err(error("Malformed Umbral stanza arguments"));
+            }
+            let capsule = Capsule::from_bytes(&capsule_bytes)?;
+            let encrypted_file_key =
+                decode_base64(&body).ok_or_else(|| error("Malformed Umbral stanza body"))?;
+            recipient = Some((capsule, encrypted_file_key));
+        };
+
+        let (capsule, encrypted_file_key) =
+            recipient.ok_or_else(|| error("No Umbral recipient stanza"))?;
+
+        let mac_bytes = mac_line
+            .strip_prefix(b" ")
+            .and_then(decode_base64)
+            .ok_or_else(|| error("Malformed header MAC"))?;
+        if mac_bytes.len() != MAC_SIZE {
+            return Err(error("Malformed header MAC"));
+        }
+        let mut mac = [0u8; MAC_SIZE];
+        mac.copy_from_slice(&mac_bytes);
+
+        if rest.len() < PAYLOAD_NONCE_SIZE + TAG_SIZE {
+            return Err(error("The payload is truncated"));
+        }
+
+        Ok(Self {
+            capsule,
+            encrypted_file_key: encrypted_file_key.into_boxed_slice(),
+            header: data[..header_size].into(),
+            mac,
+            payload: rest.into(),
+        })
+    }
+}
+
+impl HasTypeName for AgeFile {
+    fn type_name() -> &'static str {
+        "AgeFile"
+    }
+}
+
+impl fmt::Display for AgeFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(&self.capsule, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{AgeFile, AGE_STANZA_TYPE, CHUNK_SIZE, TAG_SIZE};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{reencrypt, DecryptionError, SecretKey};
+
+    #[test]
+    fn test_roundtrip() {
+        let Delegation {
+            delegating_sk,
+            delegating_pk,
+            receiving_sk,
+            verified_kfrags: vkfrags,
+            ..
+        } = prepare_delegation(true, true);
+        let plaintext = b"peace at dawn";
+
+        let file = AgeFile::encrypt(&delegating_pk, plaintext).unwrap();
+        let file_bytes = file.to_bytes();
+        assert!(file_bytes.starts_with(b"age-encryption.org/v1\n-> umbral "));
+
+        let file_back = AgeFile::from_bytes(&file_bytes).unwrap();
+        assert_eq!(file_back, file);
+        let plaintext_back = file_back.decrypt_original(&delegating_sk).unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        // Delegated decryption
+        let vcfrags = vkfrags[0..2]
+            .iter()
+            .map(|vkfrag| reencrypt(file.capsule(), vkfrag.clone()));
+        let plaintext_bob = file
+            .decrypt_reencrypted(&receiving_sk, &delegating_pk, vcfrags)
+            .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+
+        // Wrong key
+        assert_eq!(
+            file.decrypt_original(&receiving_sk),
+            Err(DecryptionError::AuthenticationFailed)
+        );
+    }
+
+    #[test]
+    fn test_chunks() {
+        let sk = SecretKey::random();
+        let pk = sk.public_key();
+
+        // An empty file
+        let file = AgeFile::encrypt(&pk, b"").unwrap();
+        assert!(file.decrypt_original(&sk).unwrap().is_empty());
+
+        // Several chunks, the last one being full
+        let plaintext: Vec<u8> = (0..2 * CHUNK_SIZE).map(|i| i as u8).collect();
+        let file = AgeFile::encrypt(&pk, &plaintext).unwrap();
+        let file_bytes = file.to_bytes();
+        assert_eq!(
+            &file.decrypt_original(&sk).unwrap() as &[u8],
+            &plaintext[..]
+        );
+
+        // Dropping the last chunk is detected
+        let truncated = &file_bytes[..file_bytes.len() - CHUNK_SIZE - TAG_SIZE];
+        let file = AgeFile::from_bytes(truncated).unwrap();
+        assert_eq!(
+            file.decrypt_original(&sk),
+            Err(DecryptionError::AuthenticationFailed)
+        );
+    }
+
+    #[test]
+    fn test_header() {
+        let sk = SecretKey::random();
+        let file = AgeFile::encrypt(&sk.public_key(), b"peace at dawn").unwrap();
+        let file_bytes = file.to_bytes();
+        let (header, payload) = file_bytes.split_at(file_bytes.len() - 16 - 13 - TAG_SIZE);
+
+        // Stanzas for other recipients are skipped
+        let other_stanza = b"-> X25519 CJM36AHmTbdHSuOQL+NESqyVQE75f2e610iRdLPEN20\nC3ZAeY64NXS4QFrksLm3EGz+uPRyI0eQsWw7LWbbYig\n";
+        let header_str = core::str::from_utf8(header).unwrap();
+        let position = header_str.find("-> ").unwrap();
+        let with_other = [
+            &header[..position],
+            other_stanza,
+            &header[position..],
+            payload,
+        ]
+        .concat();
+        let file_back = AgeFile::from_bytes(&with_other).unwrap();
+        assert_eq!(file_back.capsule(), file.capsule());
+        // But the header MAC covers them
+        assert_eq!(
+            file_back.decrypt_original(&sk),
+            Err(DecryptionError::AuthenticationFailed)
+        );
+
+        // No Umbral stanza
+        let without_umbral = [
+            header_str.replace(AGE_STANZA_TYPE, "X25519").as_bytes(),
+            payload,
+        ]
+        .concat();
+        assert!(AgeFile::from_bytes(&without_umbral).is_err());
+
+        assert!(AgeFile::from_bytes(&header[..40]).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! Armored text encoding of the public objects, suitable for copying between tools.
+//!
+//! The encoding is Bech32m (BIP 350): a human-readable prefix identifying the object type
+//! (see [`Armored::HRP`]), the separator `1`, the byte representation of the object
+//! in the base32 alphabet, and a 6-character checksum.
+//! Since the objects are longer than the 90 characters of BIP 173,
+//! the length limit is not enforced. The guaranteed detection of errors affecting
+//! up to 4 characters only holds for shorter strings, but for any length
+//! a random corruption goes undetected with a probability of about `2^-30`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::capsule::Capsule;
+use crate::key_frag::KeyFrag;
+use crate::keys::PublicKey;
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
+    SerializableToArray,
+};
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const SEPARATOR: char = '1';
+const CHECKSUM_SIZE: usize = 6;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+fn expanded_hrp(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|c| c >> 5)
+        .chain(core::iter::once(0))
+        .chain(hrp.bytes().map(|c| c & 0x1f))
+}
+
+fn checksum(hrp: &str, data: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let values = expanded_hrp(hrp)
+        .chain(data.iter().copied())
+        .chain([0u8; CHECKSUM_SIZE]);
+    let modulus = polymod(values) ^ BECH32M_CONST;
+    let mut result = [0u8; CHECKSUM_SIZE];
+    for (i, value) in result.iter_mut().enumerate() {
+        *value = ((modulus >> (5 * (CHECKSUM_SIZE - 1 - i))) & 0x1f) as u8;
+    }
+    result
+}
+
+fn verify_checksum(hrp: &str, data: &[u8]) -> bool {
+    polymod(expanded_hrp(hrp).chain(data.iter().copied())) == BECH32M_CONST
+}
+
+/// Regroups a bit stream from `from`-bit to `to`-bit values.
+/// If `pad` is `false`, the leftover bits must be zero and fewer than `from`,
+/// otherwise `None` is returned.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max_value = (1u32 << to) - 1;
+    let mut result =
+        Vec::with_capacity((data.len() * from as usize + to as usize - 1) / to as usize);
+    for value in data {
+        acc = (acc << from) | u32::from(*value);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push(((acc >> bits) & max_value) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            result.push(((acc << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & max_value != 0 {
+        return None;
+    }
+    Some(result)
+}
+
+fn encode(hrp: &str, bytes: &[u8]) -> String {
+    let mut data = convert_bits(bytes, 8, 5, true).unwrap_or_default();
+    let checksum = checksum(hrp, &data);
+    data.extend_from_slice(&checksum);
+
+    let mut result = String::with_capacity(hrp.len() + 1 + data.len());
+    result.push_str(hrp);
+    result.push(SEPARATOR);
+    result.extend(data.iter().map(|value| CHARSET[*value as usize] as char));
+    result
+}
+
+fn decode(expected_hrp: &str, armored: &str) -> Result<Vec<u8>, &'static str> {
+    let armored = armored.trim();
+    let has_lower = armored.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = armored.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err("Mixed-case armored string");
+    }
+
+    let (hrp, encoded) = armored
+        .rsplit_once(SEPARATOR)
+        .ok_or("Missing the armored string separator")?;
+    if !hrp.eq_ignore_ascii_case(expected_hrp) {
+        return Err("Unexpected armored string prefix");
+    }
+    if encoded.len() < CHECKSUM_SIZE {
+        return Err("Armored string is too short");
+    }
+
+    let data = encoded
+        .bytes()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|x| *x == c.to_ascii_lowercase())
+                .map(|position| position as u8)
+        })
+        .collect::<Option<Vec<u8>>>()
+        .ok_or("Invalid character in the armored string")?;
+    if !verify_checksum(expected_hrp, &data) {
+        return Err("Invalid armored string checksum");
+    }
+
+    convert_bits(&data[..data.len() - CHECKSUM_SIZE], 5, 8, false)
+        .ok_or("Invalid armored string padding")
+}
+
+/// A trait for objects with an armored text representation.
+pub trait Armored: SerializableToArray + DeserializableFromArray + HasTypeName {
+    /// The human-readable prefix identifying the object type.
+    const HRP: &'static str;
+
+    /// Encodes the object as an armored string.
+    fn to_armored(&self) -> String {
+        encode(Self::HRP, &self.to_array())
+    }
+
+    /// Decodes the object from an armored string,
+    /// checking the prefix and the checksum.
+    /// Both lowercase and uppercase strings are accepted.
+    fn from_armored(armored: &str) -> Result<Self, DeserializationError> {
+        let bytes = decode(Self::HRP, armored).map_err(|msg| {
+            DeserializationError::ConstructionFailure(ConstructionError::new(
+                Self::type_name(),
+                msg,
+            ))
+        })?;
+        Self::from_bytes(bytes)
+    }
+}
+
+impl Armored for PublicKey {
+    const HRP: &'static str = "umbralpk";
+}
+
+impl Armored for Capsule {
+    const HRP: &'static str = "umbralcapsule";
+}
+
+impl Armored for KeyFrag {
+    const HRP: &'static str = "umbralkfrag";
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::string::String;
+
+    use super::{decode, encode, Armored};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{Capsule, KeyFrag, PublicKey, SecretKey};
+
+    #[test]
+    fn test_bip350_vectors() {
+        // Valid Bech32m strings from BIP 350
+        let valid = [
+            ("a", "a1lqfn3a"),
+            ("abcdef", "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx"),
+            ("?", "?1v759aa"),
+        ];
+        for (hrp, armored) in valid {
+            let bytes = decode(hrp, armored).unwrap();
+            assert_eq!(encode(hrp, &bytes), armored);
+            assert!(decode(hrp, &armored.to_uppercase()).is_ok());
+        }
+
+        // Invalid: a Bech32 (not Bech32m) checksum, mixed case, wrong prefix
+        assert!(decode("a", "a12uel5l").is_err());
+        assert!(decode("abcdef", "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryX").is_err());
+        assert!(decode("b", "a1lqfn3a").is_err());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let Delegation {
+            delegating_pk,
+            capsule,
+            verified_kfrags: vkfrags,
+            ..
+        } = prepare_delegation(true, true);
+
+        let armored = delegating_pk.to_armored();
+        assert!(armored.starts_with("umbralpk1"));
+        assert_eq!(PublicKey::from_armored(&armored).unwrap(), delegating_pk);
+        assert_eq!(
+            PublicKey::from_armored(&armored.to_uppercase()).unwrap(),
+            delegating_pk
+        );
+
+        let armored = capsule.to_armored();
+        assert!(armored.starts_with("umbralcapsule1"));
+        assert_eq!(Capsule::from_armored(&armored).unwrap(), capsule);
+
+        // Objects of a different type are rejected
+        assert!(PublicKey::from_armored(&armored).is_err());
+
+        let kfrag = vkfrags[0].clone().unverify();
+        let armored = kfrag.to_armored();
+        assert!(armored.starts_with("umbralkfrag1"));
+        assert_eq!(KeyFrag::from_armored(&armored).unwrap(), kfrag);
+    }
+
+    #[test]
+    fn test_corruption() {
+        let pk = SecretKey::random().public_key();
+        let armored = pk.to_armored();
+
+        // A substituted character is detected
+        let position = armored.len() / 2;
+        let replacement = if &armored[position..position + 1] == "q" {
+            "p"
+        } else {
+            "q"
+        };
+        let mut corrupted = String::from(&armored[..position]);
+        corrupted.push_str(replacement);
+        corrupted.push_str(&armored[position + 1..]);
+        assert!(PublicKey::from_armored(&corrupted).is_err());
+
+        // Truncation is detected
+        assert!(PublicKey::from_armored(&armored[..armored.len() - 1]).is_err());
+
+        // Characters outside of the alphabet are rejected
+        let invalid = armored.replacen(&armored[position..position + 1], "b", 1);
+        assert!(PublicKey::from_armored(&invalid).is_err());
+    }
+}
@@ -0,0 +1,525 @@
+//! Signed, hash-chained records of reencryption and combination operations.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use digest::Digest;
+use generic_array::sequence::{Concat, Split};
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+use typenum::{op, U1, U32};
+
+#[cfg(feature = "serde-support")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::VerifiedCapsuleFrag;
+use crate::dst;
+use crate::hashing::{BackendDigest, Hash};
+use crate::key_frag::{KeyFragID, VerifiedKeyFrag};
+use crate::keys::{PublicKey, SecretKey, Signature, Signer};
+use crate::pre::{decrypt_reencrypted, reencrypt_with_rng, ReencryptionError};
+use crate::traits::{
+    fmt_public, from_str_public, ConstructionError, DeserializableFromArray, DeserializationError,
+    HasTypeName, RepresentableAsArray, SerializableToArray,
+};
+
+#[cfg(feature = "serde-support")]
+use crate::serde::{serde_deserialize, serde_serialize, Representation};
+
+type DigestSize = U32;
+
+/// The kind of an operation recorded in an [`AuditEntry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditOperation {
+    /// A reencryption of a capsule with a key fragment.
+    Reencryption,
+    /// A combination of capsule fragments to decrypt a ciphertext.
+    Combination,
+}
+
+impl RepresentableAsArray for AuditOperation {
+    type Size = U1;
+}
+
+impl SerializableToArray for AuditOperation {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        let tag = match self {
+            Self::Reencryption => 0u8,
+            Self::Combination => 1u8,
+        };
+        GenericArray::<u8, Self::Size>::from([tag])
+    }
+}
+
+impl DeserializableFromArray for AuditOperation {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        match arr[0] {
+            0u8 => Ok(Self::Reencryption),
+            1u8 => Ok(Self::Combination),
+            tag => Err(ConstructionError::new(
+                "AuditOperation",
+                &format!("Unknown operation tag 0x{:x?}", tag),
+            )),
+        }
+    }
+}
+
+/// A signed record of a single operation.
+///
+/// Each entry contains the digest of the previous entry in the log,
+/// so removing or reordering entries can be detected with [`verify_audit_log`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    operation: AuditOperation,
+    kfrag_id: Option<KeyFragID>,
+    previous: GenericArray<u8, DigestSize>,
+    digest: GenericArray<u8, DigestSize>,
+    timestamp: u64,
+    signature: Signature,
+}
+
+type OperationSize = <AuditOperation as RepresentableAsArray>::Size;
+type KeyFragIDSize = <KeyFragID as RepresentableAsArray>::Size;
+type U64Size = <u64 as RepresentableAsArray>::Size;
+type SignatureSize = <Signature as RepresentableAsArray>::Size;
+type AuditEntryContentsSize =
+    op!(OperationSize + KeyFragIDSize + DigestSize + DigestSize + U64Size);
+
+fn entry_contents(
+    operation: AuditOperation,
+    kfrag_id: Option<KeyFragID>,
+    previous: &GenericArray<u8, DigestSize>,
+    digest: &GenericArray<u8, DigestSize>,
+    timestamp: u64,
+) -> GenericArray<u8, AuditEntryContentsSize> {
+    // A zero identifier is used for the operations not involving a key fragment;
+    // the operation tag tells the two cases apart.
+    let kfrag_id = match kfrag_id {
+        Some(kfrag_id) => kfrag_id.to_array(),
+        None => GenericArray::<u8, KeyFragIDSize>::default(),
+    };
+    operation
+        .to_array()
+        .concat(kfrag_id)
+        .concat(*previous)
+        .concat(*digest)
+        .concat(timestamp.to_array())
+}
+
+fn signed_digest(contents: &GenericArray<u8, AuditEntryContentsSize>) -> BackendDigest {
+    Hash::new_with_dst(dst::AUDIT_ENTRY)
+        .chain_bytes(contents)
+        .digest()
+}
+
+impl AuditEntry {
+    fn contents(&self) -> GenericArray<u8, AuditEntryContentsSize> {
+        entry_contents(
+            self.operation,
+            self.kfrag_id,
+            &self.previous,
+            &self.digest,
+            self.timestamp,
+        )
+    }
+
+    /// Returns the kind of the recorded operation.
+    pub fn operation(&self) -> AuditOperation {
+        self.operation
+    }
+
+    /// Returns the identifier of the key fragment used in a reencryption
+    /// (`None` for combinations).
+    pub fn kfrag_id(&self) -> Option<KeyFragID> {
+        self.kfrag_id
+    }
+
+    /// Returns the digest of the inputs and outputs of the operation.
+    pub fn operation_digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Returns the digest of the previous entry in the log
+    /// (all zeros for the first entry).
+    pub fn previous_digest(&self) -> &[u8] {
+        &self.previous
+    }
+
+    /// Returns the timestamp supplied when the operation was performed.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Returns the digest of this entry, which the next entry in the log refers to.
+    pub fn entry_digest(&self) -> GenericArray<u8, DigestSize> {
+        Hash::new_with_dst(dst::AUDIT_ENTRY)
+            .chain_bytes(self.to_array())
+            .digest()
+            .finalize()
+    }
+
+    /// Checks the signature of the entry.
+    pub fn verify(&self, verifying_pk: &PublicKey) -> bool {
+        verifying_pk.verify_digest(signed_digest(&self.contents()), &self.signature)
+    }
+}
+
+impl RepresentableAsArray for AuditEntry {
+    type Size = op!(AuditEntryContentsSize + SignatureSize);
+}
+
+impl SerializableToArray for AuditEntry {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.contents().concat(self.signature.to_array())
+    }
+}
+
+impl DeserializableFromArray for AuditEntry {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let (operation, rest) = AuditOperation::take(*arr)?;
+        let (kfrag_id, rest) = KeyFragID::take(rest)?;
+        let (previous, rest): (GenericArray<u8, DigestSize>, GenericArray<u8, _>) = rest.split();
+        let (digest, rest): (GenericArray<u8, DigestSize>, GenericArray<u8, _>) = rest.split();
+        let (timestamp, rest) = u64::take(rest)?;
+        let signature = Signature::take_last(rest)?;
+
+        let kfrag_id = match operation {
+            AuditOperation::Reencryption => Some(kfrag_id),
+            AuditOperation::Combination => {
+                if kfrag_id.as_ref().iter().any(|byte| *byte != 0) {
+                    return Err(ConstructionError::new(
+                        "AuditEntry",
+                        "A combination entry cannot have a key fragment identifier",
+                    ));
+                }
+                None
+            }
+        };
+
+        Ok(Self {
+            operation,
+            kfrag_id,
+            previous,
+            digest,
+            timestamp,
+            signature,
+        })
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for AuditEntry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize(self, serializer, Representation::Base64)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for AuditEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_deserialize(deserializer, Representation::Base64)
+    }
+}
+
+impl HasTypeName for AuditEntry {
+    fn type_name() -> &'static str {
+        "AuditEntry"
+    }
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(self, f)
+    }
+}
+
+impl FromStr for AuditEntry {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
+/// A destination for the entries produced by an [`AuditLog`]
+/// (e.g. an append-only file or a remote log service).
+pub trait AuditSink {
+    /// Stores the entry.
+    fn record(&mut self, entry: AuditEntry);
+}
+
+impl AuditSink for Vec<AuditEntry> {
+    fn record(&mut self, entry: AuditEntry) {
+        self.push(entry);
+    }
+}
+
+/// A wrapper for the reencryption and combination operations
+/// that emits a signed [`AuditEntry`] for each operation performed.
+///
+/// Timestamps are opaque to the library (e.g. UNIX time in seconds)
+/// and must be supplied by the caller.
+pub struct AuditLog<S: AuditSink> {
+    signer: Signer,
+    sink: S,
+    last_digest: GenericArray<u8, DigestSize>,
+}
+
+impl<S: AuditSink> AuditLog<S> {
+    /// Creates a new log signing the entries with `signer` and storing them in `sink`.
+    pub fn new(signer: Signer, sink: S) -> Self {
+        Self::resume(signer, sink, GenericArray::<u8, DigestSize>::default())
+    }
+
+    /// Continues an existing log (e.g. after a restart),
+    /// chaining the new entries to the entry with the digest `head_digest`
+    /// (as returned by [`AuditEntry::entry_digest`] or [`head_digest`](`Self::head_digest`)).
+    pub fn resume(signer: Signer, sink: S, head_digest: GenericArray<u8, DigestSize>) -> Self {
+        Self {
+            signer,
+            sink,
+            last_digest: head_digest,
+        }
+    }
+
+    /// Returns the digest of the last recorded entry
+    /// (all zeros if nothing was recorded yet),
+    /// which can be persisted to [`resume`](`Self::resume`) the log later.
+    pub fn head_digest(&self) -> GenericArray<u8, DigestSize> {
+        self.last_digest
+    }
+
+    /// Returns the public key that can be used to verify the entries.
+    pub fn verifying_key(&self) -> PublicKey {
+        self.signer.verifying_key()
+    }
+
+    /// Returns a reference to the sink.
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Consumes the log and returns the sink.
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+
+    fn record(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        operation: AuditOperation,
+        kfrag_id: Option<KeyFragID>,
+        digest: GenericArray<u8, DigestSize>,
+        timestamp: u64,
+    ) {
+        let contents = entry_contents(operation, kfrag_id, &self.last_digest, &digest, timestamp);
+        let entry = AuditEntry {
+            operation,
+            kfrag_id,
+            previous: self.last_digest,
+            digest,
+            timestamp,
+            signature: self
+                .signer
+                .sign_digest_with_rng(rng, signed_digest(&contents)),
+        };
+        self.last_digest = entry.entry_digest();
+        self.sink.record(entry);
+    }
+
+    /// Performs [`reencrypt_with_rng`] and records the operation.
+    pub fn reencrypt_with_rng(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        capsule: &Capsule,
+        verified_kfrag: VerifiedKeyFrag,
+        timestamp: u64,
+    ) -> VerifiedCapsuleFrag {
+        let kfrag_id = verified_kfrag.id();
+        let vcfrag = reencrypt_with_rng(rng, capsule, verified_kfrag);
+        let digest = Hash::new_with_dst(dst::AUDIT_REENCRYPTION)
+            .chain_bytes(capsule.to_array())
+            .chain_bytes(vcfrag.to_array())
+            .digest()
+            .finalize();
+        self.record(
+            rng,
+            AuditOperation::Reencryption,
+            Some(kfrag_id),
+            digest,
+            timestamp,
+        );
+        vcfrag
+    }
+
+    /// A synonym for [`reencrypt_with_rng`](`Self::reencrypt_with_rng`) with the default RNG.
+    #[cfg(feature = "default-rng")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+    pub fn reencrypt(
+        &mut self,
+        capsule: &Capsule,
+        verified_kfrag: VerifiedKeyFrag,
+        timestamp: u64,
+    ) -> VerifiedCapsuleFrag {
+        self.reencrypt_with_rng(&mut OsRng, capsule, verified_kfrag, timestamp)
+    }
+
+    /// Performs [`decrypt_reencrypted`] and records the operation
+    /// (only if it succeeded).
+    #[allow(clippy::too_many_arguments)]
+    pub fn decrypt_reencrypted_with_rng(
+        &mut self,
+        rng: &mut (impl CryptoRng + RngCore),
+        receiving_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        capsule: &Capsule,
+        verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+        ciphertext: impl AsRef<[u8]>,
+        timestamp: u64,
+    ) -> Result<Box<[u8]>, ReencryptionError> {
+        let verified_cfrags: Vec<_> = verified_cfrags.into_iter().collect();
+        let mut hash = Hash::new_with_dst(dst::AUDIT_COMBINATION).chain_bytes(capsule.to_array());
+        for vcfrag in verified_cfrags.iter() {
+            hash = hash.chain_bytes(vcfrag.to_array());
+        }
+        let digest = hash.chain_bytes(ciphertext.as_ref()).digest().finalize();
+
+        let plaintext = decrypt_reencrypted(
+            receiving_sk,
+            delegating_pk,
+            capsule,
+            verified_cfrags,
+            ciphertext,
+        )?;
+        self.record(rng, AuditOperation::Combination, None, digest, timestamp);
+        Ok(plaintext)
+    }
+
+    /// A synonym for [`decrypt_reencrypted_with_rng`](`Self::decrypt_reencrypted_with_rng`)
+    /// with the default RNG.
+    #[cfg(feature = "default-rng")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+    pub fn decrypt_reencrypted(
+        &mut self,
+        receiving_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        capsule: &Capsule,
+        verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+        ciphertext: impl AsRef<[u8]>,
+        timestamp: u64,
+    ) -> Result<Box<[u8]>, ReencryptionError> {
+        self.decrypt_reencrypted_with_rng(
+            &mut OsRng,
+            receiving_sk,
+            delegating_pk,
+            capsule,
+            verified_cfrags,
+            ciphertext,
+            timestamp,
+        )
+    }
+}
+
+/// Checks the signatures of the given entries, and that they form an unbroken chain
+/// starting from the beginning of the log.
+pub fn verify_audit_log(entries: &[AuditEntry], verifying_pk: &PublicKey) -> bool {
+    let mut last_digest = GenericArray::<u8, DigestSize>::default();
+    for entry in entries {
+        if entry.previous != last_digest || !entry.verify(verifying_pk) {
+            return false;
+        }
+        last_digest = entry.entry_digest();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{verify_audit_log, AuditEntry, AuditLog, AuditOperation};
+    use crate::{
+        encrypt, generate_kfrags, DeserializableFromArray, SecretKey, SerializableToArray, Signer,
+    };
+
+    #[test]
+    fn test_audit_log() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+
+        let log_signer = Signer::new(SecretKey::random());
+        let mut log = AuditLog::new(log_signer.clone(), Vec::new());
+        let vcfrag = log.reencrypt(&capsule, vkfrags[0].clone(), 100);
+
+        // Continue the log after a restart
+        let head_digest = log.head_digest();
+        let mut entries = log.into_sink();
+        let mut log = AuditLog::resume(log_signer, Vec::new(), head_digest);
+        let vcfrags = [vcfrag, log.reencrypt(&capsule, vkfrags[1].clone(), 100)];
+        let plaintext_back = log
+            .decrypt_reencrypted(
+                &receiving_sk,
+                &delegating_pk,
+                &capsule,
+                vcfrags,
+                &ciphertext,
+                200,
+            )
+            .unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        let verifying_pk = log.verifying_key();
+        entries.extend(log.into_sink());
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].operation(), AuditOperation::Reencryption);
+        assert_eq!(entries[0].kfrag_id(), Some(vkfrags[0].id()));
+        assert_eq!(entries[2].operation(), AuditOperation::Combination);
+        assert_eq!(entries[2].kfrag_id(), None);
+        assert_eq!(entries[2].timestamp(), 200);
+        assert!(verify_audit_log(&entries, &verifying_pk));
+
+        for entry in entries.iter() {
+            let entry_back = AuditEntry::from_array(&entry.to_array()).unwrap();
+            assert_eq!(&entry_back, entry);
+        }
+
+        // Wrong key
+        assert!(!verify_audit_log(
+            &entries,
+            &SecretKey::random().public_key()
+        ));
+
+        // A removed entry
+        let truncated = [entries[0].clone(), entries[2].clone()];
+        assert!(!verify_audit_log(&truncated, &verifying_pk));
+
+        // The entries are not signed as plain messages
+        assert!(!entries[0]
+            .signature
+            .verify(&verifying_pk, &entries[0].contents()));
+    }
+}
@@ -0,0 +1,217 @@
+//! A container format for serializing collections of objects as a single bytestring.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[cfg(feature = "std")]
+use crate::pool::{BufferPool, PooledBuffer};
+use crate::traits::{DeserializableFromArray, DeserializationError, SerializableToArray};
+
+const LENGTH_SIZE: usize = 4;
+
+/// Errors that can happen when deserializing a batch.
+#[derive(Debug, PartialEq)]
+pub enum BatchError {
+    /// The bytestring ended before the declared number of elements.
+    Truncated,
+    /// There are bytes left after the declared number of elements.
+    TrailingBytes,
+    /// One of the elements failed to deserialize.
+    Element {
+        /// The position of the failed element in the batch.
+        index: usize,
+        /// The deserialization error.
+        error: DeserializationError,
+    },
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "The batch is truncated"),
+            Self::TrailingBytes => write!(f, "The batch has trailing bytes"),
+            Self::Element { index, error } => write!(f, "Batch element {}: {}", index, error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for BatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Element { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+fn push_length(result: &mut Vec<u8>, length: usize) {
+    // The number of elements and their sizes are limited by the available memory
+    // long before reaching `u32::MAX`.
+    result.extend_from_slice(&(length as u32).to_be_bytes());
+}
+
+fn take_length(data: &mut &[u8]) -> Result<usize, BatchError> {
+    if data.len() < LENGTH_SIZE {
+        return Err(BatchError::Truncated);
+    }
+    let (length, rest) = data.split_at(LENGTH_SIZE);
+    *data = rest;
+    let mut bytes = [0u8; LENGTH_SIZE];
+    bytes.copy_from_slice(length);
+    Ok(u32::from_be_bytes(bytes) as usize)
+}
+
+/// Serializes a collection of objects (e.g. [`VerifiedCapsuleFrag`](`crate::VerifiedCapsuleFrag`)
+/// or [`VerifiedKeyFrag`](`crate::VerifiedKeyFrag`)) as a single bytestring.
+///
+/// The format is the 4-byte big-endian number of elements,
+/// followed by each element's byte representation prefixed by its 4-byte big-endian length.
+pub fn batch_to_bytes<T: SerializableToArray>(items: &[T]) -> Box<[u8]> {
+    let mut result = Vec::new();
+    write_batch(&mut result, items);
+    result.into_boxed_slice()
+}
+
+/// Same as [`batch_to_bytes`], but serializes into a buffer from `pool`
+/// instead of allocating one.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub fn batch_to_bytes_with_pool<'a, T: SerializableToArray>(
+    pool: &'a BufferPool,
+    items: &[T],
+) -> PooledBuffer<'a> {
+    let mut result = pool.take();
+    write_batch(&mut result, items);
+    result
+}
+
+fn write_batch<T: SerializableToArray>(result: &mut Vec<u8>, items: &[T]) {
+    result.reserve(LENGTH_SIZE + items.len() * (LENGTH_SIZE + T::serialized_size()));
+    push_length(result, items.len());
+    for item in items {
+        push_length(result, T::serialized_size());
+        result.extend_from_slice(&item.to_array());
+    }
+}
+
+/// Deserializes a collection of objects serialized by [`batch_to_bytes`].
+///
+/// Verified objects are deserialized as their unverified counterparts
+/// (e.g. [`CapsuleFrag`](`crate::CapsuleFrag`) for
+/// [`VerifiedCapsuleFrag`](`crate::VerifiedCapsuleFrag`)),
+/// and have to be verified again by the receiving side.
+pub fn batch_from_bytes<T: DeserializableFromArray>(
+    data: impl AsRef<[u8]>,
+) -> Result<Vec<T>, BatchError> {
+    let mut data = data.as_ref();
+    let count = take_length(&mut data)?;
+
+    // Do not trust the declared count when allocating.
+    let mut result = Vec::with_capacity(core::cmp::min(count, data.len() / LENGTH_SIZE));
+    for index in 0..count {
+        let length = take_length(&mut data)?;
+        if data.len() < length {
+            return Err(BatchError::Truncated);
+        }
+        let (element, rest) = data.split_at(length);
+        data = rest;
+        let item = T::from_bytes(element).map_err(|error| BatchError::Element { index, error })?;
+        result.push(item);
+    }
+
+    if !data.is_empty() {
+        return Err(BatchError::TrailingBytes);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{batch_from_bytes, batch_to_bytes, BatchError};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{CapsuleFrag, DeserializationError, KeyFrag, SizeMismatchError};
+
+    #[test]
+    fn test_roundtrip() {
+        let Delegation {
+            verified_kfrags: vkfrags,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+
+        let kfrags_bytes = batch_to_bytes(&vkfrags);
+        let kfrags = batch_from_bytes::<KeyFrag>(&kfrags_bytes).unwrap();
+        let expected: Vec<_> = vkfrags
+            .iter()
+            .map(|vkfrag| vkfrag.clone().unverify())
+            .collect();
+        assert_eq!(kfrags, expected);
+
+        let cfrags_bytes = batch_to_bytes(&vcfrags);
+        let cfrags = batch_from_bytes::<CapsuleFrag>(&cfrags_bytes).unwrap();
+        let expected: Vec<_> = vcfrags
+            .iter()
+            .map(|vcfrag| vcfrag.clone().unverify())
+            .collect();
+        assert_eq!(cfrags, expected);
+
+        // An empty batch
+        let empty_bytes = batch_to_bytes::<CapsuleFrag>(&[]);
+        assert_eq!(&empty_bytes as &[u8], &[0u8; 4]);
+        assert!(batch_from_bytes::<CapsuleFrag>(&empty_bytes)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        let vkfrags = prepare_delegation(true, true).verified_kfrags;
+        let bytes = batch_to_bytes(&vkfrags);
+
+        assert_eq!(
+            batch_from_bytes::<KeyFrag>(&bytes[..bytes.len() - 1]),
+            Err(BatchError::Truncated)
+        );
+        assert_eq!(
+            batch_from_bytes::<KeyFrag>(&[&bytes as &[u8], &[0]].concat()),
+            Err(BatchError::TrailingBytes)
+        );
+
+        // A huge declared count does not lead to a huge allocation
+        assert_eq!(
+            batch_from_bytes::<KeyFrag>(&[0xffu8; 4]),
+            Err(BatchError::Truncated)
+        );
+
+        // The failing element is identified
+        let element_size = (bytes.len() - 4) / 3;
+        let mut corrupted = bytes.to_vec();
+        // The flags at the end of the second element
+        corrupted[4 + 2 * element_size - 1] = 2;
+        assert!(matches!(
+            batch_from_bytes::<KeyFrag>(&corrupted),
+            Err(BatchError::Element {
+                index: 1,
+                error: DeserializationError::ConstructionFailure(_)
+            })
+        ));
+
+        // An element of the wrong type
+        assert_eq!(
+            batch_from_bytes::<CapsuleFrag>(&bytes),
+            Err(BatchError::Element {
+                index: 0,
+                error: DeserializationError::SizeMismatch(SizeMismatchError::new(
+                    element_size - 4,
+                    359
+                ))
+            })
+        );
+    }
+}
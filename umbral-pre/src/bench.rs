@@ -2,12 +2,19 @@
 //! This module re-exports some internals for the purposes of benchmarking.
 //! Should not be used by regular users.
 
+use alloc::vec::Vec;
+
+use generic_array::GenericArray;
 use rand_core::OsRng;
 
 use crate::capsule::{Capsule, KeySeed, OpenReencryptedError};
 use crate::capsule_frag::CapsuleFrag;
+use crate::curve;
 use crate::keys::{PublicKey, SecretKey};
 use crate::secret_box::SecretBox;
+use crate::traits::RepresentableAsArray;
+
+pub use crate::curve::{CurvePoint, CurveScalar};
 
 /// Exported `Capsule::from_public_key()` for benchmark purposes.
 pub fn capsule_from_public_key(delegating_pk: &PublicKey) -> (Capsule, SecretBox<KeySeed>) {
@@ -28,3 +35,29 @@ pub fn capsule_open_reencrypted(
 ) -> Result<SecretBox<KeySeed>, OpenReencryptedError> {
     capsule.open_reencrypted(receiving_sk, delegating_pk, cfrags)
 }
+
+/// Exported batch scalar inversion (Montgomery's trick).
+/// Returns `None` if any of the scalars is zero.
+pub fn batch_invert(scalars: &[CurveScalar]) -> Option<Vec<CurveScalar>> {
+    curve::batch_invert(scalars)
+}
+
+/// Exported multi-scalar multiplication `sum(scalars[i] * points[i])`.
+/// Returns `None` if the lengths of the slices are different.
+///
+/// **Warning:** not constant-time, must only be used with public scalars.
+pub fn lincomb(points: &[CurvePoint], scalars: &[CurveScalar]) -> Option<CurvePoint> {
+    curve::lincomb(points, scalars)
+}
+
+/// Exported batch point decompression.
+/// Returns the index of the first point that failed to decode on error.
+pub fn decompress_points(
+    arrays: &[GenericArray<u8, <CurvePoint as RepresentableAsArray>::Size>],
+) -> Result<Vec<CurvePoint>, usize> {
+    arrays
+        .iter()
+        .enumerate()
+        .map(|(i, arr)| CurvePoint::from_compressed_array(arr).ok_or(i))
+        .collect()
+}
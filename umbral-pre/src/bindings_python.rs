@@ -8,6 +8,7 @@ use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
+use pyo3::buffer::PyBuffer;
 use pyo3::class::basic::CompareOp;
 use pyo3::create_exception;
 use pyo3::exceptions::{PyException, PyTypeError, PyValueError};
@@ -16,6 +17,7 @@ use pyo3::pyclass::PyClass;
 use pyo3::types::{PyBytes, PyUnicode};
 use pyo3::wrap_pyfunction;
 use pyo3::PyObjectProtocol;
+use zeroize::Zeroize;
 
 use crate as umbral_pre;
 use crate::{
@@ -57,12 +59,56 @@ where
     })
 }
 
-fn from_bytes<T, U>(data: &[u8]) -> PyResult<T>
+/// A bytestring argument accepting `bytes` or any other object
+/// implementing the buffer protocol (`bytearray`, `memoryview`, `numpy` arrays and so on).
+pub enum BytesLike<'a> {
+    /// The contents of a `bytes` object, borrowed without copying.
+    Borrowed(&'a [u8]),
+    /// The contents of any other buffer.
+    /// Since such buffers can be mutated while we are using them,
+    /// they cannot be safely borrowed, and are copied (once) instead.
+    /// The copy is zeroized on drop, since it can contain secret key bytes.
+    Copied(Vec<u8>),
+}
+
+impl<'a> FromPyObject<'a> for BytesLike<'a> {
+    fn extract(obj: &'a PyAny) -> PyResult<Self> {
+        if let Ok(bytes) = obj.downcast::<PyBytes>() {
+            return Ok(Self::Borrowed(bytes.as_bytes()));
+        }
+        let buffer = PyBuffer::<u8>::get(obj).map_err(|_| {
+            PyTypeError::new_err(format!(
+                "a bytes-like object is required, not '{}'",
+                obj.get_type().name().unwrap_or("unknown")
+            ))
+        })?;
+        buffer.to_vec(obj.py()).map(Self::Copied)
+    }
+}
+
+impl Drop for BytesLike<'_> {
+    fn drop(&mut self) {
+        if let Self::Copied(data) = self {
+            data.zeroize();
+        }
+    }
+}
+
+impl AsRef<[u8]> for BytesLike<'_> {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            Self::Borrowed(data) => data,
+            Self::Copied(data) => data,
+        }
+    }
+}
+
+fn from_bytes<T, U>(data: BytesLike<'_>) -> PyResult<T>
 where
     T: FromBackend<U>,
     U: DeserializableFromArray + HasTypeName,
 {
-    U::from_bytes(data)
+    U::from_bytes(data.as_ref())
         .map(T::from_backend)
         .map_err(|err| PyValueError::new_err(format!("{}", err)))
 }
@@ -132,12 +178,18 @@ impl SecretKey {
         }
     }
 
+    pub fn derive_policy_key(&self, label: BytesLike<'_>) -> Self {
+        Self {
+            backend: self.backend.derive_policy_key(label.as_ref()),
+        }
+    }
+
     pub fn to_secret_bytes(&self) -> PyResult<PyObject> {
         to_secret_bytes(self)
     }
 
     #[staticmethod]
-    pub fn from_bytes(data: &[u8]) -> PyResult<Self> {
+    pub fn from_bytes(data: BytesLike<'_>) -> PyResult<Self> {
         from_bytes(data)
     }
 
@@ -186,23 +238,35 @@ impl SecretKeyFactory {
     }
 
     #[staticmethod]
-    pub fn from_secure_randomness(seed: &[u8]) -> PyResult<SecretKeyFactory> {
-        umbral_pre::SecretKeyFactory::from_secure_randomness(seed)
+    pub fn from_secure_randomness(seed: BytesLike<'_>) -> PyResult<SecretKeyFactory> {
+        umbral_pre::SecretKeyFactory::from_secure_randomness(seed.as_ref())
             .map(|backend_sk| SecretKeyFactory {
                 backend: backend_sk,
             })
             .map_err(|err| PyValueError::new_err(format!("{}", err)))
     }
 
-    pub fn make_key(&self, label: &[u8]) -> SecretKey {
+    pub fn make_key(&self, label: BytesLike<'_>) -> SecretKey {
         SecretKey {
-            backend: self.backend.make_key(label),
+            backend: self.backend.make_key(label.as_ref()),
+        }
+    }
+
+    pub fn policy_master_public_key(&self) -> PublicKey {
+        PublicKey {
+            backend: self.backend.policy_master_public_key(),
         }
     }
 
-    pub fn make_factory(&self, label: &[u8]) -> Self {
+    pub fn make_policy_key(&self, label: BytesLike<'_>) -> SecretKey {
+        SecretKey {
+            backend: self.backend.make_policy_key(label.as_ref()),
+        }
+    }
+
+    pub fn make_factory(&self, label: BytesLike<'_>) -> Self {
         Self {
-            backend: self.backend.make_factory(label),
+            backend: self.backend.make_factory(label.as_ref()),
         }
     }
 
@@ -211,7 +275,7 @@ impl SecretKeyFactory {
     }
 
     #[staticmethod]
-    pub fn from_bytes(data: &[u8]) -> PyResult<Self> {
+    pub fn from_bytes(data: BytesLike<'_>) -> PyResult<Self> {
         from_bytes(data)
     }
 
@@ -249,7 +313,7 @@ impl FromBackend<umbral_pre::PublicKey> for PublicKey {
 #[pymethods]
 impl PublicKey {
     #[staticmethod]
-    pub fn from_bytes(data: &[u8]) -> PyResult<Self> {
+    pub fn from_bytes(data: BytesLike<'_>) -> PyResult<Self> {
         from_bytes(data)
     }
 
@@ -258,6 +322,12 @@ impl PublicKey {
         umbral_pre::PublicKey::serialized_size()
     }
 
+    pub fn derive_policy_key(&self, label: BytesLike<'_>) -> Self {
+        Self {
+            backend: self.backend.derive_policy_key(label.as_ref()),
+        }
+    }
+
     fn __bytes__(&self) -> PyResult<PyObject> {
         to_bytes(self)
     }
@@ -298,9 +368,9 @@ impl Signer {
         }
     }
 
-    pub fn sign(&self, message: &[u8]) -> Signature {
+    pub fn sign(&self, message: BytesLike<'_>) -> Signature {
         Signature {
-            backend: self.backend.sign(message),
+            backend: self.backend.sign(message.as_ref()),
         }
     }
 
@@ -339,12 +409,12 @@ impl FromBackend<umbral_pre::Signature> for Signature {
 #[pymethods]
 impl Signature {
     #[staticmethod]
-    pub fn from_bytes(data: &[u8]) -> PyResult<Self> {
+    pub fn from_bytes(data: BytesLike<'_>) -> PyResult<Self> {
         from_bytes(data)
     }
 
-    pub fn verify(&self, verifying_pk: &PublicKey, message: &[u8]) -> bool {
-        self.backend.verify(&verifying_pk.backend, message)
+    pub fn verify(&self, verifying_pk: &PublicKey, message: BytesLike<'_>) -> bool {
+        self.backend.verify(&verifying_pk.backend, message.as_ref())
     }
 
     #[staticmethod]
@@ -393,7 +463,7 @@ impl FromBackend<umbral_pre::Capsule> for Capsule {
 #[pymethods]
 impl Capsule {
     #[staticmethod]
-    pub fn from_bytes(data: &[u8]) -> PyResult<Self> {
+    pub fn from_bytes(data: BytesLike<'_>) -> PyResult<Self> {
         from_bytes(data)
     }
 
@@ -426,9 +496,9 @@ impl PyObjectProtocol for Capsule {
 pub fn encrypt(
     py: Python<'_>,
     delegating_pk: &PublicKey,
-    plaintext: &[u8],
+    plaintext: BytesLike<'_>,
 ) -> PyResult<(Capsule, PyObject)> {
-    umbral_pre::encrypt(&delegating_pk.backend, plaintext)
+    umbral_pre::encrypt(&delegating_pk.backend, plaintext.as_ref())
         .map(|(backend_capsule, ciphertext)| {
             (
                 Capsule {
@@ -445,9 +515,9 @@ pub fn decrypt_original(
     py: Python<'_>,
     delegating_sk: &SecretKey,
     capsule: &Capsule,
-    ciphertext: &[u8],
+    ciphertext: BytesLike<'_>,
 ) -> PyResult<PyObject> {
-    umbral_pre::decrypt_original(&delegating_sk.backend, &capsule.backend, &ciphertext)
+    umbral_pre::decrypt_original(&delegating_sk.backend, &capsule.backend, ciphertext)
         .map(|plaintext| PyBytes::new(py, &plaintext).into())
         .map_err(|err| PyValueError::new_err(format!("{}", err)))
 }
@@ -498,7 +568,7 @@ impl KeyFrag {
     }
 
     #[staticmethod]
-    pub fn from_bytes(data: &[u8]) -> PyResult<Self> {
+    pub fn from_bytes(data: BytesLike<'_>) -> PyResult<Self> {
         from_bytes(data)
     }
 
@@ -542,8 +612,8 @@ impl AsBackend<umbral_pre::VerifiedKeyFrag> for VerifiedKeyFrag {
 #[pymethods]
 impl VerifiedKeyFrag {
     #[staticmethod]
-    pub fn from_verified_bytes(data: &[u8]) -> PyResult<Self> {
-        umbral_pre::VerifiedKeyFrag::from_verified_bytes(data)
+    pub fn from_verified_bytes(data: BytesLike<'_>) -> PyResult<Self> {
+        umbral_pre::VerifiedKeyFrag::from_verified_bytes(data.as_ref())
             .map(|vkfrag| Self { backend: vkfrag })
             .map_err(|err| PyValueError::new_err(format!("{}", err)))
     }
@@ -589,7 +659,7 @@ pub fn generate_kfrags(
     shares: usize,
     sign_delegating_key: bool,
     sign_receiving_key: bool,
-) -> Vec<VerifiedKeyFrag> {
+) -> PyResult<Vec<VerifiedKeyFrag>> {
     let backend_kfrags = umbral_pre::generate_kfrags(
         &delegating_sk.backend,
         &receiving_pk.backend,
@@ -598,13 +668,14 @@ pub fn generate_kfrags(
         shares,
         sign_delegating_key,
         sign_receiving_key,
-    );
+    )
+    .map_err(|err| PyValueError::new_err(format!("{}", err)))?;
 
-    backend_kfrags
+    Ok(backend_kfrags
         .iter()
         .cloned()
         .map(|val| VerifiedKeyFrag { backend: val })
-        .collect()
+        .collect())
 }
 
 #[pyclass(module = "umbral")]
@@ -655,7 +726,7 @@ impl CapsuleFrag {
     }
 
     #[staticmethod]
-    pub fn from_bytes(data: &[u8]) -> PyResult<Self> {
+    pub fn from_bytes(data: BytesLike<'_>) -> PyResult<Self> {
         from_bytes(data)
     }
 
@@ -714,8 +785,8 @@ impl PyObjectProtocol for VerifiedCapsuleFrag {
 #[pymethods]
 impl VerifiedCapsuleFrag {
     #[staticmethod]
-    pub fn from_verified_bytes(data: &[u8]) -> PyResult<Self> {
-        umbral_pre::VerifiedCapsuleFrag::from_verified_bytes(data)
+    pub fn from_verified_bytes(data: BytesLike<'_>) -> PyResult<Self> {
+        umbral_pre::VerifiedCapsuleFrag::from_verified_bytes(data.as_ref())
             .map(|vcfrag| Self { backend: vcfrag })
             .map_err(|err| PyValueError::new_err(format!("{}", err)))
     }
@@ -751,7 +822,7 @@ pub fn decrypt_reencrypted(
     delegating_pk: &PublicKey,
     capsule: &Capsule,
     verified_cfrags: Vec<VerifiedCapsuleFrag>,
-    ciphertext: &[u8],
+    ciphertext: BytesLike<'_>,
 ) -> PyResult<PyObject> {
     let backend_cfrags: Vec<umbral_pre::VerifiedCapsuleFrag> = verified_cfrags
         .iter()
@@ -763,7 +834,7 @@ pub fn decrypt_reencrypted(
         &delegating_pk.backend,
         &capsule.backend,
         backend_cfrags,
-        ciphertext,
+        ciphertext.as_ref(),
     )
     .map(|plaintext| PyBytes::new(py, &plaintext).into())
     .map_err(|err| PyValueError::new_err(format!("{}", err)))
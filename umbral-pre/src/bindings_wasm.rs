@@ -10,12 +10,15 @@ extern crate alloc;
 
 use alloc::boxed::Box;
 use alloc::format;
+use alloc::rc::Rc;
 use alloc::string::String;
 use alloc::{vec, vec::Vec};
+use core::cell::RefCell;
 use core::fmt;
 
-use js_sys::Error;
+use js_sys::{Array, Error, Function, Reflect};
 use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
+use wasm_bindgen::JsCast;
 
 use serde::{Deserialize, Serialize};
 
@@ -42,6 +45,11 @@ impl SecretKey {
         PublicKey(self.0.public_key())
     }
 
+    #[wasm_bindgen(js_name = derivePolicyKey)]
+    pub fn derive_policy_key(&self, label: &[u8]) -> Self {
+        Self(self.0.derive_policy_key(label))
+    }
+
     #[wasm_bindgen(js_name = toSecretBytes)]
     pub fn to_secret_bytes(&self) -> Box<[u8]> {
         self.0
@@ -98,6 +106,16 @@ impl SecretKeyFactory {
         SecretKey(self.0.make_key(label))
     }
 
+    #[wasm_bindgen(js_name = policyMasterPublicKey)]
+    pub fn policy_master_public_key(&self) -> PublicKey {
+        PublicKey(self.0.policy_master_public_key())
+    }
+
+    #[wasm_bindgen(js_name = makePolicyKey)]
+    pub fn make_policy_key(&self, label: &[u8]) -> SecretKey {
+        SecretKey(self.0.make_policy_key(label))
+    }
+
     #[wasm_bindgen(js_name = makeFactory)]
     pub fn make_factory(&self, label: &[u8]) -> Self {
         Self(self.0.make_factory(label))
@@ -144,6 +162,11 @@ impl PublicKey {
             .map_err(map_js_err)
     }
 
+    #[wasm_bindgen(js_name = derivePolicyKey)]
+    pub fn derive_policy_key(&self, label: &[u8]) -> Self {
+        Self(self.0.derive_policy_key(label))
+    }
+
     #[allow(clippy::inherent_to_string)]
     #[wasm_bindgen(js_name = toString)]
     pub fn to_string(&self) -> String {
@@ -586,7 +609,7 @@ pub fn generate_kfrags(
     shares: usize,
     sign_delegating_key: bool,
     sign_receiving_key: bool,
-) -> Vec<JsValue> {
+) -> Result<Vec<JsValue>, JsValue> {
     let backend_kfrags = umbral_pre::generate_kfrags(
         &delegating_sk.0,
         &receiving_pk.0,
@@ -595,17 +618,18 @@ pub fn generate_kfrags(
         shares,
         sign_delegating_key,
         sign_receiving_key,
-    );
+    )
+    .map_err(map_js_err)?;
 
     // TODO (#26): Apparently we cannot just return a vector of things,
     // so we have to convert them to JsValues manually.
     // See https://github.com/rustwasm/wasm-bindgen/issues/111
-    backend_kfrags
+    Ok(backend_kfrags
         .iter()
         .cloned()
         .map(VerifiedKeyFrag)
         .map(JsValue::from)
-        .collect()
+        .collect())
 }
 
 #[wasm_bindgen]
@@ -613,3 +637,75 @@ pub fn reencrypt(capsule: &Capsule, kfrag: &VerifiedKeyFrag) -> VerifiedCapsuleF
     let vcfrag = umbral_pre::reencrypt(&capsule.0, kfrag.0.clone());
     VerifiedCapsuleFrag(vcfrag)
 }
+
+fn get_method(value: &JsValue, name: &str) -> Option<Function> {
+    Reflect::get(value, &JsValue::from_str(name))
+        .ok()
+        .and_then(|method| method.dyn_into::<Function>().ok())
+}
+
+/// Collects the elements of an array passed to `decryptReencrypted()`.
+/// Not a part of the public API.
+///
+/// The elements are passed to `push()` through the JS glue,
+/// which checks that each of them is an instance of `VerifiedCapsuleFrag`,
+/// so neither unverified capsule fragments nor arbitrary objects
+/// with the same methods are accepted.
+#[doc(hidden)]
+#[wasm_bindgen(js_name = __VerifiedCapsuleFragCollector)]
+pub struct VerifiedCapsuleFragCollector(Rc<RefCell<Vec<umbral_pre::VerifiedCapsuleFrag>>>);
+
+#[wasm_bindgen(js_class = __VerifiedCapsuleFragCollector)]
+impl VerifiedCapsuleFragCollector {
+    pub fn push(&self, vcfrag: &VerifiedCapsuleFrag) {
+        self.0.borrow_mut().push(vcfrag.0.clone());
+    }
+}
+
+// `wasm_bindgen` does not support `Vec<CustomStruct>` as a parameter,
+// so we have to collect the array elements through a JS-side wrapper.
+fn verified_cfrags_from_array(
+    array: &Array,
+) -> Result<Vec<umbral_pre::VerifiedCapsuleFrag>, JsValue> {
+    let vcfrags = Rc::new(RefCell::new(Vec::new()));
+    let collector = JsValue::from(VerifiedCapsuleFragCollector(vcfrags.clone()));
+    let push = get_method(&collector, "push")
+        .ok_or_else(|| map_js_err("Internal error: the collector has no `push()` method"))?;
+
+    let result = array.iter().enumerate().try_for_each(|(index, value)| {
+        push.call1(&collector, &value).map(|_| ()).map_err(|_| {
+            map_js_err(format!(
+                "Element {} of the array is not a VerifiedCapsuleFrag",
+                index
+            ))
+        })
+    });
+
+    // Release the JS-side wrapper (and its reference to `vcfrags`).
+    if let Some(free) = get_method(&collector, "free") {
+        free.call0(&collector)?;
+    }
+    result?;
+
+    Ok(vcfrags.take())
+}
+
+/// Decrypts the ciphertext using an array of `VerifiedCapsuleFrag` objects.
+#[wasm_bindgen(js_name = decryptReencrypted)]
+pub fn decrypt_reencrypted(
+    receiving_sk: &SecretKey,
+    delegating_pk: &PublicKey,
+    capsule: &Capsule,
+    verified_cfrags: &Array,
+    ciphertext: &[u8],
+) -> Result<Box<[u8]>, JsValue> {
+    let backend_cfrags = verified_cfrags_from_array(verified_cfrags)?;
+    umbral_pre::decrypt_reencrypted(
+        &receiving_sk.0,
+        &delegating_pk.0,
+        &capsule.0,
+        backend_cfrags,
+        ciphertext,
+    )
+    .map_err(map_js_err)
+}
@@ -0,0 +1,149 @@
+//! `borsh` serialization of the objects.
+//!
+//! Objects are written as their fixed-size byte representation, without a length prefix
+//! (the same way `borsh` encodes byte arrays), so they can be embedded
+//! in the argument structures of Solana or NEAR programs.
+
+use alloc::format;
+
+use borsh::maybestd::io::{Error, ErrorKind, Read, Write};
+use borsh::{BorshDeserialize, BorshSerialize};
+use generic_array::GenericArray;
+
+use crate::audit::AuditEntry;
+use crate::capsule::Capsule;
+use crate::capsule_frag::{CapsuleFrag, UnprovenCapsuleFrag, VerifiedCapsuleFrag};
+use crate::certificate::DelegationCertificate;
+use crate::key_frag::{CompactKeyFrag, KeyFrag, KeyFragID, VerifiedKeyFrag};
+use crate::keys::{PublicKey, Signature};
+use crate::policy::PolicyId;
+use crate::source::SourceId;
+use crate::threshold::PartialDecryption;
+use crate::traits::{DeserializableFromArray, HasTypeName, SerializableToArray};
+
+fn borsh_serialize<T: SerializableToArray>(obj: &T, writer: &mut impl Write) -> Result<(), Error> {
+    writer.write_all(&obj.to_array())
+}
+
+fn borsh_deserialize<T: DeserializableFromArray + HasTypeName>(
+    reader: &mut impl Read,
+) -> Result<T, Error> {
+    let mut bytes = GenericArray::<u8, T::Size>::default();
+    reader.read_exact(&mut bytes)?;
+    T::from_array(&bytes).map_err(|err| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to deserialize {}: {}", T::type_name(), err),
+        )
+    })
+}
+
+macro_rules! impl_borsh_serialize {
+    ($($type:ty),*) => {
+        $(
+            impl BorshSerialize for $type {
+                fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+                    borsh_serialize(self, writer)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_borsh_deserialize {
+    ($($type:ty),*) => {
+        $(
+            impl BorshDeserialize for $type {
+                fn deserialize_reader<R: Read>(reader: &mut R) -> Result<Self, Error> {
+                    borsh_deserialize(reader)
+                }
+            }
+        )*
+    };
+}
+
+impl_borsh_serialize!(
+    AuditEntry,
+    Capsule,
+    CapsuleFrag,
+    CompactKeyFrag,
+    DelegationCertificate,
+    KeyFrag,
+    KeyFragID,
+    PartialDecryption,
+    PolicyId,
+    PublicKey,
+    Signature,
+    SourceId,
+    UnprovenCapsuleFrag,
+    // The verified types can only be deserialized as their unverified counterparts.
+    VerifiedCapsuleFrag,
+    VerifiedKeyFrag
+);
+
+impl_borsh_deserialize!(
+    AuditEntry,
+    Capsule,
+    CapsuleFrag,
+    CompactKeyFrag,
+    DelegationCertificate,
+    KeyFrag,
+    KeyFragID,
+    PartialDecryption,
+    PolicyId,
+    PublicKey,
+    Signature,
+    SourceId,
+    UnprovenCapsuleFrag
+);
+
+#[cfg(test)]
+mod tests {
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{Capsule, CapsuleFrag, KeyFrag, PublicKey, SerializableToArray};
+
+    #[test]
+    fn test_roundtrip() {
+        let Delegation {
+            delegating_pk,
+            capsule,
+            verified_kfrags: vkfrags,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+        let vcfrag = vcfrags[0].clone();
+
+        // The encoding is the plain byte representation
+        let capsule_bytes = capsule.try_to_vec().unwrap();
+        assert_eq!(capsule_bytes, capsule.to_array().to_vec());
+        assert_eq!(Capsule::try_from_slice(&capsule_bytes).unwrap(), capsule);
+
+        let kfrag_bytes = vkfrags[0].try_to_vec().unwrap();
+        assert_eq!(
+            KeyFrag::try_from_slice(&kfrag_bytes).unwrap(),
+            vkfrags[0].clone_unverified()
+        );
+
+        let cfrag_bytes = vcfrag.try_to_vec().unwrap();
+        assert_eq!(
+            CapsuleFrag::try_from_slice(&cfrag_bytes).unwrap(),
+            vcfrag.clone_unverified()
+        );
+
+        // Can be used as a part of a larger structure
+        let pair_bytes = (delegating_pk, capsule).try_to_vec().unwrap();
+        assert_eq!(
+            <(PublicKey, Capsule)>::try_from_slice(&pair_bytes).unwrap(),
+            (delegating_pk, capsule)
+        );
+
+        // Truncated and malformed data is rejected
+        assert!(Capsule::try_from_slice(&capsule_bytes[1..]).is_err());
+        let mut bad_capsule = capsule_bytes;
+        bad_capsule[0] = 0xff;
+        assert!(Capsule::try_from_slice(&bad_capsule).is_err());
+    }
+}
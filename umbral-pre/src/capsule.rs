@@ -1,5 +1,6 @@
 use alloc::vec::Vec;
 use core::fmt;
+use core::str::FromStr;
 
 use generic_array::sequence::Concat;
 use generic_array::GenericArray;
@@ -9,15 +10,16 @@ use typenum::op;
 #[cfg(feature = "serde-support")]
 use crate::serde::{serde_deserialize, serde_serialize, Representation};
 
-use crate::capsule_frag::CapsuleFrag;
-use crate::curve::{CurvePoint, CurveScalar, NonZeroCurveScalar};
-use crate::hashing_ds::{hash_capsule_points, hash_to_polynomial_arg, hash_to_shared_secret};
+use crate::capsule_frag::{CapsuleFrag, ReencryptedParts, UnprovenCapsuleFrag};
+use crate::curve::{batch_invert, CurvePoint, CurveScalar, NonZeroCurveScalar};
+use crate::debug::dump;
+use crate::hashing_ds::{hash_capsule_points, hash_to_shared_secret, PolynomialArgHasher};
 use crate::keys::{PublicKey, SecretKey};
 use crate::params::Parameters;
 use crate::secret_box::SecretBox;
 use crate::traits::{
-    fmt_public, ConstructionError, DeserializableFromArray, HasTypeName, RepresentableAsArray,
-    SerializableToArray,
+    fmt_public, from_str_public, ConstructionError, DeserializableFromArray, DeserializationError,
+    HasTypeName, RepresentableAsArray, SerializableToArray,
 };
 
 #[cfg(feature = "serde-support")]
@@ -25,13 +27,16 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// Errors that can happen when opening a `Capsule` using reencrypted `CapsuleFrag` objects.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
 pub enum OpenReencryptedError {
     /// An empty capsule fragment list is given.
     NoCapsuleFrags,
     /// Capsule fragments are mismatched (originated from [`KeyFrag`](crate::KeyFrag) objects
     /// generated by different [`generate_kfrags`](crate::generate_kfrags) calls).
     MismatchedCapsuleFrags,
-    /// Some of the given capsule fragments are repeated.
+    /// Some of the given capsule fragments were created with the same key fragment,
+    /// but have different contents.
+    /// (Exact duplicates are ignored.)
     RepeatingCapsuleFrags,
     /// Internal validation of the result has failed.
     /// Can be caused by an incorrect (possibly modified) capsule
@@ -50,6 +55,10 @@ impl fmt::Display for OpenReencryptedError {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for OpenReencryptedError {}
+
 /// Encapsulated symmetric key used to encrypt the plaintext.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Capsule {
@@ -119,6 +128,14 @@ impl fmt::Display for Capsule {
     }
 }
 
+impl FromStr for Capsule {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
 pub(crate) type KeySeed = GenericArray<u8, <CurvePoint as RepresentableAsArray>::Size>;
 
 impl Capsule {
@@ -151,6 +168,20 @@ impl Capsule {
         &g * &self.signature == &self.point_v + &(&self.point_e * &h)
     }
 
+    /// Attaches custom parameters to the capsule,
+    /// replacing the default ones it was created with.
+    ///
+    /// Required to verify the capsule fragments created with the key fragments from
+    /// [`generate_kfrags_with_params()`](`crate::generate_kfrags_with_params()`),
+    /// and to decrypt with them.
+    /// The capsule itself does not depend on the parameters.
+    pub fn with_params(self, params: &Parameters) -> Self {
+        Self {
+            params: *params,
+            ..self
+        }
+    }
+
     /// Generates a symmetric key and its associated KEM ciphertext, using the given RNG.
     pub(crate) fn from_public_key(
         rng: &mut (impl CryptoRng + RngCore),
@@ -184,50 +215,107 @@ impl Capsule {
         SecretBox::new(shared_key.as_secret().to_array())
     }
 
-    #[allow(clippy::many_single_char_names)]
     pub(crate) fn open_reencrypted(
         &self,
         receiving_sk: &SecretKey,
         delegating_pk: &PublicKey,
         cfrags: &[CapsuleFrag],
+    ) -> Result<SecretBox<KeySeed>, OpenReencryptedError> {
+        self.open_reencrypted_impl(receiving_sk, delegating_pk, cfrags)
+    }
+
+    pub(crate) fn open_reencrypted_unproven(
+        &self,
+        receiving_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        cfrags: &[UnprovenCapsuleFrag],
+    ) -> Result<SecretBox<KeySeed>, OpenReencryptedError> {
+        self.open_reencrypted_impl(receiving_sk, delegating_pk, cfrags)
+    }
+
+    fn open_reencrypted_impl<C: ReencryptedParts>(
+        &self,
+        receiving_sk: &SecretKey,
+        delegating_pk: &PublicKey,
+        cfrags: &[C],
+    ) -> Result<SecretBox<KeySeed>, OpenReencryptedError> {
+        let precursor = *cfrags
+            .first()
+            .ok_or(OpenReencryptedError::NoCapsuleFrags)?
+            .precursor();
+        let dh_point = &precursor * receiving_sk.to_secret_scalar().as_secret();
+        self.open_reencrypted_with_dh_point(
+            &receiving_sk.public_key(),
+            delegating_pk,
+            cfrags,
+            &dh_point,
+        )
+    }
+
+    /// Opens the capsule given the Diffie-Hellman point
+    /// (the precursor of the capsule fragments multiplied by the receiving secret key),
+    /// which can be calculated without having the receiving secret key in one place.
+    #[allow(clippy::many_single_char_names)]
+    pub(crate) fn open_reencrypted_with_dh_point<C: ReencryptedParts>(
+        &self,
+        receiving_pk: &PublicKey,
+        delegating_pk: &PublicKey,
+        cfrags: &[C],
+        dh_point: &CurvePoint,
     ) -> Result<SecretBox<KeySeed>, OpenReencryptedError> {
         if cfrags.is_empty() {
             return Err(OpenReencryptedError::NoCapsuleFrags);
         }
 
-        let precursor = cfrags[0].precursor;
+        let precursor = *cfrags[0].precursor();
 
-        if !cfrags.iter().all(|cfrag| cfrag.precursor == precursor) {
+        if !cfrags.iter().all(|cfrag| cfrag.precursor() == &precursor) {
             return Err(OpenReencryptedError::MismatchedCapsuleFrags);
         }
 
-        let pub_key = receiving_sk.public_key().to_point();
-        let dh_point = &precursor * receiving_sk.to_secret_scalar().as_secret();
+        // Exact duplicates (e.g. the same proxy response delivered twice) are harmless,
+        // so we skip them. Different fragments created with the same key fragment
+        // will lead to repeating coefficients and will be reported below.
+        let mut unique_cfrags = Vec::<&C>::with_capacity(cfrags.len());
+        for cfrag in cfrags {
+            if !unique_cfrags.iter().any(|other| other.same_parts(cfrag)) {
+                unique_cfrags.push(cfrag);
+            }
+        }
+        let cfrags = unique_cfrags;
+
+        let pub_key = receiving_pk.to_point();
 
         // Combination of CFrags via Shamir's Secret Sharing reconstruction
+        let hasher = PolynomialArgHasher::new(&self.params, &precursor, &pub_key, dh_point);
         let mut lc = Vec::<NonZeroCurveScalar>::with_capacity(cfrags.len());
-        for cfrag in cfrags {
-            let coeff = hash_to_polynomial_arg(&precursor, &pub_key, &dh_point, &cfrag.kfrag_id);
-            lc.push(coeff);
+        for cfrag in cfrags.iter() {
+            lc.push(hasher.hash(cfrag.kfrag_id()));
         }
 
+        // There is a minuscule probability that coefficients for two different frags are equal,
+        // in which case we'd rather fail gracefully.
+        let lambdas = lambda_coeffs(&lc).ok_or(OpenReencryptedError::RepeatingCapsuleFrags)?;
+
         let mut e_prime = CurvePoint::identity();
         let mut v_prime = CurvePoint::identity();
-        for (i, cfrag) in cfrags.iter().enumerate() {
-            // There is a minuscule probability that coefficients for two different frags are equal,
-            // in which case we'd rather fail gracefully.
-            let lambda_i =
-                lambda_coeff(&lc, i).ok_or(OpenReencryptedError::RepeatingCapsuleFrags)?;
-            e_prime = &e_prime + &(&cfrag.point_e1 * &lambda_i);
-            v_prime = &v_prime + &(&cfrag.point_v1 * &lambda_i);
+        for (i, (cfrag, lambda_i)) in cfrags.iter().zip(lambdas.iter()).enumerate() {
+            dump("open_reencrypted", format_args!("lambda[{}]", i), lambda_i);
+            e_prime = &e_prime + &(cfrag.point_e1() * lambda_i);
+            v_prime = &v_prime + &(cfrag.point_v1() * lambda_i);
         }
 
         // Secret value 'd' allows to make Umbral non-interactive
-        let d = hash_to_shared_secret(&precursor, &pub_key, &dh_point);
+        let d = hash_to_shared_secret(&self.params, &precursor, &pub_key, dh_point);
 
         let s = self.signature;
         let h = hash_capsule_points(&self.point_e, &self.point_v);
 
+        dump("open_reencrypted", "e'", &e_prime);
+        dump("open_reencrypted", "v'", &v_prime);
+        dump("open_reencrypted", "d", &CurveScalar::from(&d));
+        dump("open_reencrypted", "h", &CurveScalar::from(&h));
+
         let orig_pub_key = delegating_pk.to_point();
 
         let inv_d = d.invert();
@@ -241,16 +329,33 @@ impl Capsule {
     }
 }
 
-fn lambda_coeff(xs: &[NonZeroCurveScalar], i: usize) -> Option<CurveScalar> {
-    let mut res = CurveScalar::one();
-    for j in 0..xs.len() {
-        if j != i {
-            let inv_diff_opt: Option<CurveScalar> = (&xs[j] - &xs[i]).invert().into();
-            let inv_diff = inv_diff_opt?;
-            res = &(&res * &xs[j]) * &inv_diff;
+/// Calculates the Lagrange coefficients for interpolation at zero
+/// for all the given points.
+/// Returns `None` if there are repeating points.
+pub(crate) fn lambda_coeffs(xs: &[NonZeroCurveScalar]) -> Option<Vec<CurveScalar>> {
+    // All the pairwise differences are inverted at once.
+    let mut diffs = Vec::with_capacity(xs.len() * xs.len().saturating_sub(1));
+    for (i, x_i) in xs.iter().enumerate() {
+        for (j, x_j) in xs.iter().enumerate() {
+            if j != i {
+                diffs.push(x_j - x_i);
+            }
         }
     }
-    Some(res)
+    let inv_diffs = batch_invert(&diffs)?;
+
+    let mut inv_diffs = inv_diffs.iter();
+    let mut result = Vec::with_capacity(xs.len());
+    for i in 0..xs.len() {
+        let mut res = CurveScalar::one();
+        for (j, x_j) in xs.iter().enumerate() {
+            if j != i {
+                res = &(&res * x_j) * inv_diffs.next()?;
+            }
+        }
+        result.push(res);
+    }
+    Some(result)
 }
 
 #[cfg(test)]
@@ -298,7 +403,8 @@ mod tests {
 
         let (capsule, key_seed) = Capsule::from_public_key(&mut OsRng, &delegating_pk);
 
-        let kfrags = generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true);
+        let kfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
 
         let vcfrags: Vec<_> = kfrags
             .iter()
@@ -312,6 +418,31 @@ mod tests {
             .unwrap();
         assert_eq!(key_seed.as_secret(), key_seed_reenc.as_secret());
 
+        // Exact duplicates are ignored, even if their proofs differ
+        // (as happens when a proxy repeats the reencryption)
+        let repeated_vcfrag = reencrypt(&capsule, kfrags[1].clone());
+        let cfrags_with_duplicates = [
+            vcfrags[0].clone().unverify(),
+            vcfrags[1].clone().unverify(),
+            vcfrags[0].clone().unverify(),
+            repeated_vcfrag.unverify(),
+        ];
+        let key_seed_reenc = capsule
+            .open_reencrypted(&receiving_sk, &delegating_pk, &cfrags_with_duplicates)
+            .unwrap();
+        assert_eq!(key_seed.as_secret(), key_seed_reenc.as_secret());
+
+        // Duplicates do not count towards the threshold
+        let result = capsule.open_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &[vcfrags[0].clone().unverify(), vcfrags[0].clone().unverify()],
+        );
+        assert_eq!(
+            result.map(|x| *x.as_secret()),
+            Err(OpenReencryptedError::ValidationFailed)
+        );
+
         // Empty cfrag vector
         let result = capsule.open_reencrypted(&receiving_sk, &delegating_pk, &[]);
         assert_eq!(
@@ -320,7 +451,8 @@ mod tests {
         );
 
         // Mismatched cfrags - each `generate_kfrags()` uses new randoms.
-        let kfrags2 = generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true);
+        let kfrags2 =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
 
         let vcfrags2: Vec<_> = kfrags2
             .iter()
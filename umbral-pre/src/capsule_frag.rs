@@ -1,22 +1,33 @@
+use alloc::vec::Vec;
 use core::fmt;
+use core::str::FromStr;
 
 use generic_array::sequence::Concat;
 use generic_array::GenericArray;
 use rand_core::{CryptoRng, RngCore};
-use typenum::op;
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+use typenum::{op, U32};
 
 #[cfg(feature = "serde-support")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::capsule::Capsule;
 use crate::curve::{CurvePoint, CurveScalar, NonZeroCurveScalar};
-use crate::hashing_ds::{hash_to_cfrag_verification, kfrag_signature_message};
-use crate::key_frag::{KeyFrag, KeyFragID};
-use crate::keys::{PublicKey, Signature};
+use crate::debug::dump;
+use crate::hashing_ds::{
+    cfrag_response_message, hash_to_cfrag_verification, hash_to_reencryption_nonce,
+    kfrag_signature_message,
+};
+use crate::key_frag::{CompactKeyFrag, KeyFrag, KeyFragID};
+use crate::keys::{PublicKey, Signature, Signer};
+use crate::params::Parameters;
 use crate::secret_box::SecretBox;
+use crate::source::{HasSourceId, SourceId};
 use crate::traits::{
-    fmt_public, ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
-    RepresentableAsArray, SerializableToArray,
+    fmt_public, from_str_public, ConstructionError, DeserializableFromArray, DeserializationError,
+    HasTypeName, RepresentableAsArray, SerializableToArray,
 };
 
 #[cfg(feature = "serde-support")]
@@ -76,16 +87,16 @@ impl DeserializableFromArray for CapsuleFragProof {
 impl CapsuleFragProof {
     #[allow(clippy::many_single_char_names)]
     fn from_kfrag_and_cfrag(
-        rng: &mut (impl CryptoRng + RngCore),
+        t: SecretBox<NonZeroCurveScalar>,
         capsule: &Capsule,
         kfrag: KeyFrag,
         cfrag_e1: &CurvePoint,
         cfrag_v1: &CurvePoint,
     ) -> Self {
-        let params = capsule.params;
+        // The parameters the key fragment commitment was created with.
+        let params = kfrag.params;
 
         let rk = kfrag.key;
-        let t = SecretBox::new(NonZeroCurveScalar::random(rng));
 
         // Here are the formulaic constituents shared with `CapsuleFrag::verify()`.
 
@@ -102,7 +113,7 @@ impl CapsuleFragProof {
         let v2 = &v * t.as_secret();
         let u2 = &u * t.as_secret();
 
-        let h = hash_to_cfrag_verification(&[e, *e1, e2, v, *v1, v2, u, u1, u2]);
+        let h = hash_to_cfrag_verification(&params, &[e, *e1, e2, v, *v1, v2, u, u1, u2]);
 
         ////////
 
@@ -195,13 +206,25 @@ impl fmt::Display for CapsuleFrag {
     }
 }
 
+impl FromStr for CapsuleFrag {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
 /// Possible errors that can be returned by [`CapsuleFrag::verify`].
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
 pub enum CapsuleFragVerificationError {
     /// Inconsistent internal state leading to signature verification failure.
     IncorrectKeyFragSignature,
     /// Inconsistent internal state leading to commitment verification failure.
     IncorrectReencryption,
+    /// The capsule is different from the one the fragment was prechecked for
+    /// (see [`CapsuleFrag::precheck`]).
+    CapsuleDigestMismatch,
 }
 
 impl fmt::Display for CapsuleFragVerificationError {
@@ -209,22 +232,51 @@ impl fmt::Display for CapsuleFragVerificationError {
         match self {
             Self::IncorrectKeyFragSignature => write!(f, "Invalid KeyFrag signature"),
             Self::IncorrectReencryption => write!(f, "Failed to verify reencryption proof"),
+            Self::CapsuleDigestMismatch => {
+                write!(f, "The capsule does not match the prechecked digest")
+            }
         }
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for CapsuleFragVerificationError {}
+
 impl CapsuleFrag {
     fn reencrypted(
         rng: &mut (impl CryptoRng + RngCore),
         capsule: &Capsule,
         kfrag: KeyFrag,
+    ) -> Self {
+        let t = SecretBox::new(NonZeroCurveScalar::random(rng));
+        Self::reencrypted_with_proof_randomness(t, capsule, kfrag)
+    }
+
+    fn reencrypted_deterministic(capsule: &Capsule, kfrag: KeyFrag, nonce: &[u8]) -> Self {
+        let t = SecretBox::new(hash_to_reencryption_nonce(
+            &kfrag.params,
+            &kfrag.key,
+            &kfrag.id,
+            capsule,
+            nonce,
+        ));
+        Self::reencrypted_with_proof_randomness(t, capsule, kfrag)
+    }
+
+    fn reencrypted_with_proof_randomness(
+        t: SecretBox<NonZeroCurveScalar>,
+        capsule: &Capsule,
+        kfrag: KeyFrag,
     ) -> Self {
         let rk = kfrag.key;
         let e1 = &capsule.point_e * &rk;
         let v1 = &capsule.point_v * &rk;
+        dump("reencrypt", "e1", &e1);
+        dump("reencrypt", "v1", &v1);
         let id = kfrag.id;
         let precursor = kfrag.precursor;
-        let proof = CapsuleFragProof::from_kfrag_and_cfrag(rng, capsule, kfrag, &e1, &v1);
+        let proof = CapsuleFragProof::from_kfrag_and_cfrag(t, capsule, kfrag, &e1, &v1);
 
         Self {
             point_e1: e1,
@@ -237,7 +289,6 @@ impl CapsuleFrag {
 
     /// Verifies the integrity of the capsule fragment, given the original capsule,
     /// the encrypting party's key, the decrypting party's key, and the signing key.
-    #[allow(clippy::many_single_char_names)]
     pub fn verify(
         self,
         capsule: &Capsule,
@@ -245,6 +296,111 @@ impl CapsuleFrag {
         delegating_pk: &PublicKey,
         receiving_pk: &PublicKey,
     ) -> Result<VerifiedCapsuleFrag, (CapsuleFragVerificationError, Self)> {
+        match self.check(capsule, verifying_pk, delegating_pk, receiving_pk) {
+            Ok(()) => Ok(VerifiedCapsuleFrag { cfrag: self }),
+            Err(err) => Err((err, self)),
+        }
+    }
+
+    /// Performs the same checks as [`verify`](`Self::verify`) without consuming the fragment.
+    ///
+    /// Only needs public data, so it can be used by third parties
+    /// (e.g. auditors or monitoring services) to detect a misbehaving proxy.
+    pub fn check(
+        &self,
+        capsule: &Capsule,
+        verifying_pk: &PublicKey,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+    ) -> Result<(), CapsuleFragVerificationError> {
+        self.check_kfrag_signature(&capsule.params, verifying_pk, delegating_pk, receiving_pk)?;
+        self.check_reencryption(capsule)
+    }
+
+    /// Verifies the part of the capsule fragment that does not depend on the capsule
+    /// (the signature of the key fragment it was created with),
+    /// and binds it to the digest of the capsule
+    /// (as returned by [`canonical_digest`](`crate::SerializableToArray::canonical_digest`)).
+    ///
+    /// Allows the receiver to discard fragments from unauthorized proxies early
+    /// without having the capsule itself.
+    /// The correctness of the reencryption can only be checked with the full capsule,
+    /// via [`PrecheckedCapsuleFrag::verify`].
+    pub fn precheck(
+        self,
+        capsule_digest: &GenericArray<u8, U32>,
+        verifying_pk: &PublicKey,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+    ) -> Result<PrecheckedCapsuleFrag, (CapsuleFragVerificationError, Self)> {
+        self.precheck_with_params(
+            &Parameters::new(),
+            capsule_digest,
+            verifying_pk,
+            delegating_pk,
+            receiving_pk,
+        )
+    }
+
+    /// Same as [`precheck`](`Self::precheck`), for the capsule fragments
+    /// created with the key fragments for the given custom [`Parameters`].
+    pub fn precheck_with_params(
+        self,
+        params: &Parameters,
+        capsule_digest: &GenericArray<u8, U32>,
+        verifying_pk: &PublicKey,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+    ) -> Result<PrecheckedCapsuleFrag, (CapsuleFragVerificationError, Self)> {
+        match self.check_kfrag_signature(params, verifying_pk, delegating_pk, receiving_pk) {
+            Ok(()) => Ok(PrecheckedCapsuleFrag {
+                cfrag: self,
+                capsule_digest: *capsule_digest,
+            }),
+            Err(err) => Err((err, self)),
+        }
+    }
+
+    /// Verifies a signature created by [`VerifiedCapsuleFrag::sign_response`],
+    /// binding this capsule fragment to the request with the given digest.
+    ///
+    /// Note that this only authenticates the response;
+    /// the fragment itself still needs to be verified.
+    pub fn verify_response(
+        &self,
+        signature: &Signature,
+        proxy_pk: &PublicKey,
+        request_digest: &[u8],
+    ) -> bool {
+        signature.verify(proxy_pk, &cfrag_response_message(self, request_digest))
+    }
+
+    fn check_kfrag_signature(
+        &self,
+        params: &Parameters,
+        verifying_pk: &PublicKey,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+    ) -> Result<(), CapsuleFragVerificationError> {
+        if !self.proof.kfrag_signature.verify_with_context(
+            verifying_pk,
+            params.application_context(),
+            kfrag_signature_message(
+                &self.kfrag_id,
+                &self.proof.kfrag_commitment,
+                &self.precursor,
+                Some(delegating_pk),
+                Some(receiving_pk),
+            )
+            .as_ref(),
+        ) {
+            return Err(CapsuleFragVerificationError::IncorrectKeyFragSignature);
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::many_single_char_names)]
+    fn check_reencryption(&self, capsule: &Capsule) -> Result<(), CapsuleFragVerificationError> {
         let params = capsule.params;
 
         // Here are the formulaic constituents shared with
@@ -263,30 +419,10 @@ impl CapsuleFrag {
         let v2 = self.proof.point_v2;
         let u2 = self.proof.kfrag_pok;
 
-        let h = hash_to_cfrag_verification(&[e, e1, e2, v, v1, v2, u, u1, u2]);
+        let h = hash_to_cfrag_verification(&params, &[e, e1, e2, v, v1, v2, u, u1, u2]);
 
         ///////
 
-        let precursor = self.precursor;
-        let kfrag_id = self.kfrag_id;
-
-        if !self.proof.kfrag_signature.verify(
-            verifying_pk,
-            kfrag_signature_message(
-                &kfrag_id,
-                &u1,
-                &precursor,
-                Some(delegating_pk),
-                Some(receiving_pk),
-            )
-            .as_ref(),
-        ) {
-            return Err((
-                CapsuleFragVerificationError::IncorrectKeyFragSignature,
-                self,
-            ));
-        }
-
         // TODO (#46): if one or more of the values here are incorrect,
         // we'll get the wrong `h` (since they're all hashed into it),
         // so perhaps it's enough to check only one of these equations.
@@ -296,10 +432,10 @@ impl CapsuleFrag {
         let correct_rk_commitment = &u * &z == &u2 + &(&u1 * &h);
 
         if !(correct_reencryption_of_e & correct_reencryption_of_v & correct_rk_commitment) {
-            return Err((CapsuleFragVerificationError::IncorrectReencryption, self));
+            return Err(CapsuleFragVerificationError::IncorrectReencryption);
         }
 
-        Ok(VerifiedCapsuleFrag { cfrag: self })
+        Ok(())
     }
 
     /// Explicitly skips verification.
@@ -311,6 +447,31 @@ impl CapsuleFrag {
     }
 }
 
+/// Checks the given capsule fragments (e.g. collected from proxy responses)
+/// with [`CapsuleFrag::check`], and returns the positions of the ones that failed,
+/// along with the reasons.
+///
+/// Only needs public data, so it can be used by third parties
+/// (e.g. auditors or monitoring services) to detect misbehaving proxies.
+pub fn audit_capsule_frags<'a>(
+    capsule: &Capsule,
+    verifying_pk: &PublicKey,
+    delegating_pk: &PublicKey,
+    receiving_pk: &PublicKey,
+    cfrags: impl IntoIterator<Item = &'a CapsuleFrag>,
+) -> Vec<(usize, CapsuleFragVerificationError)> {
+    cfrags
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, cfrag)| {
+            cfrag
+                .check(capsule, verifying_pk, delegating_pk, receiving_pk)
+                .err()
+                .map(|err| (i, err))
+        })
+        .collect()
+}
+
 /// Verified capsule fragment, good for dencryption.
 /// Can be serialized, but cannot be deserialized directly.
 /// It can only be obtained from [`CapsuleFrag::verify`] or [`CapsuleFrag::skip_verification`].
@@ -353,6 +514,16 @@ impl VerifiedCapsuleFrag {
         }
     }
 
+    pub(crate) fn reencrypted_deterministic(
+        capsule: &Capsule,
+        kfrag: KeyFrag,
+        nonce: &[u8],
+    ) -> Self {
+        VerifiedCapsuleFrag {
+            cfrag: CapsuleFrag::reencrypted_deterministic(capsule, kfrag, nonce),
+        }
+    }
+
     /// Restores a verified capsule frag directly from serialized bytes,
     /// skipping [`CapsuleFrag::verify`] call.
     ///
@@ -362,6 +533,43 @@ impl VerifiedCapsuleFrag {
         CapsuleFrag::from_bytes(data).map(|cfrag| Self { cfrag })
     }
 
+    pub(crate) fn precursor(&self) -> CurvePoint {
+        self.cfrag.precursor
+    }
+
+    /// Signs the binding of this capsule fragment to the request it was created for
+    /// (identified by an application-defined `request_digest`) using the given RNG,
+    /// so that responses relayed through untrusted parties cannot be swapped between requests.
+    ///
+    /// The signature can be checked with [`CapsuleFrag::verify_response`].
+    pub fn sign_response_with_rng(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        signer: &Signer,
+        request_digest: &[u8],
+    ) -> Signature {
+        signer.sign_with_rng(rng, &cfrag_response_message(&self.cfrag, request_digest))
+    }
+
+    /// A synonym for [`VerifiedCapsuleFrag::sign_response_with_rng`] with the default RNG.
+    #[cfg(feature = "default-rng")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+    pub fn sign_response(&self, signer: &Signer, request_digest: &[u8]) -> Signature {
+        self.sign_response_with_rng(&mut OsRng, signer, request_digest)
+    }
+
+    /// Returns the capsule frag without the proof of correct reencryption,
+    /// which is only needed for verification.
+    ///
+    /// Intended for receivers archiving verified capsule frags:
+    /// the result serializes to less than half the size, can be restored with
+    /// [`UnprovenCapsuleFrag::from_bytes`](`crate::DeserializableFromArray::from_bytes`),
+    /// and used with [`decrypt_reencrypted_unproven`](`crate::decrypt_reencrypted_unproven`).
+    /// Since it cannot be verified again, make sure the storage is trusted.
+    pub fn strip_proof(&self) -> UnprovenCapsuleFrag {
+        UnprovenCapsuleFrag::from(&self.cfrag)
+    }
+
     /// Clears the verification status from the capsule frag.
     /// Useful for the cases where it needs to be put in the protocol structure
     /// containing [`CapsuleFrag`] types (since those are the ones
@@ -369,6 +577,239 @@ impl VerifiedCapsuleFrag {
     pub fn unverify(self) -> CapsuleFrag {
         self.cfrag
     }
+
+    /// Returns a copy of the capsule frag without the verification status,
+    /// leaving this object intact.
+    pub fn clone_unverified(&self) -> CapsuleFrag {
+        self.cfrag.clone()
+    }
+}
+
+impl From<VerifiedCapsuleFrag> for CapsuleFrag {
+    fn from(verified: VerifiedCapsuleFrag) -> Self {
+        verified.unverify()
+    }
+}
+
+/// A capsule fragment with a verified key fragment signature,
+/// bound to a capsule digest, but not yet verified against the capsule itself.
+/// Can only be obtained from [`CapsuleFrag::precheck`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecheckedCapsuleFrag {
+    cfrag: CapsuleFrag,
+    capsule_digest: GenericArray<u8, U32>,
+}
+
+impl PrecheckedCapsuleFrag {
+    /// Returns the capsule digest this fragment was prechecked for.
+    pub fn capsule_digest(&self) -> &GenericArray<u8, U32> {
+        &self.capsule_digest
+    }
+
+    /// Returns the identifier of the key fragment used to create this capsule fragment.
+    pub fn kfrag_id(&self) -> KeyFragID {
+        self.cfrag.kfrag_id
+    }
+
+    /// Finishes the verification of the capsule fragment,
+    /// checking that the capsule matches the prechecked digest,
+    /// and the correctness of the reencryption.
+    pub fn verify(
+        self,
+        capsule: &Capsule,
+    ) -> Result<VerifiedCapsuleFrag, (CapsuleFragVerificationError, CapsuleFrag)> {
+        if capsule.canonical_digest() != self.capsule_digest {
+            return Err((
+                CapsuleFragVerificationError::CapsuleDigestMismatch,
+                self.cfrag,
+            ));
+        }
+        match self.cfrag.check_reencryption(capsule) {
+            Ok(()) => Ok(VerifiedCapsuleFrag { cfrag: self.cfrag }),
+            Err(err) => Err((err, self.cfrag)),
+        }
+    }
+
+    /// Clears the precheck status from the capsule frag.
+    pub fn unverify(self) -> CapsuleFrag {
+        self.cfrag
+    }
+}
+
+/// A reencrypted fragment of a [`Capsule`] without the proof of correct reencryption.
+///
+/// About half the size of [`CapsuleFrag`] and cheaper to create,
+/// but cannot be verified, and can only be used with
+/// [`decrypt_reencrypted_unproven`](`crate::decrypt_reencrypted_unproven`).
+/// Intended for closed deployments where proxies are trusted,
+/// or for storing verified fragments (see [`VerifiedCapsuleFrag::strip_proof`]).
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnprovenCapsuleFrag {
+    pub(crate) point_e1: CurvePoint,
+    pub(crate) point_v1: CurvePoint,
+    pub(crate) kfrag_id: KeyFragID,
+    pub(crate) precursor: CurvePoint,
+}
+
+impl UnprovenCapsuleFrag {
+    pub(crate) fn reencrypted(capsule: &Capsule, kfrag: &CompactKeyFrag) -> Self {
+        let rk = kfrag.key;
+        let e1 = &capsule.point_e * &rk;
+        let v1 = &capsule.point_v * &rk;
+        dump("reencrypt_unproven", "e1", &e1);
+        dump("reencrypt_unproven", "v1", &v1);
+        Self {
+            point_e1: e1,
+            point_v1: v1,
+            kfrag_id: kfrag.id,
+            precursor: kfrag.precursor,
+        }
+    }
+}
+
+impl RepresentableAsArray for UnprovenCapsuleFrag {
+    type Size = op!(PointSize + PointSize + ScalarSize + PointSize);
+}
+
+impl SerializableToArray for UnprovenCapsuleFrag {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.point_e1
+            .to_array()
+            .concat(self.point_v1.to_array())
+            .concat(self.kfrag_id.to_array())
+            .concat(self.precursor.to_array())
+    }
+}
+
+impl DeserializableFromArray for UnprovenCapsuleFrag {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let (point_e1, rest) = CurvePoint::take(*arr)?;
+        let (point_v1, rest) = CurvePoint::take(rest)?;
+        let (kfrag_id, rest) = KeyFragID::take(rest)?;
+        let precursor = CurvePoint::take_last(rest)?;
+        Ok(Self {
+            point_e1,
+            point_v1,
+            kfrag_id,
+            precursor,
+        })
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for UnprovenCapsuleFrag {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize(self, serializer, Representation::Base64)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for UnprovenCapsuleFrag {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_deserialize(deserializer, Representation::Base64)
+    }
+}
+
+impl HasTypeName for UnprovenCapsuleFrag {
+    fn type_name() -> &'static str {
+        "UnprovenCapsuleFrag"
+    }
+}
+
+impl fmt::Display for UnprovenCapsuleFrag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public::<Self>(self, f)
+    }
+}
+
+/// Access to the parts of a capsule fragment used to open the capsule,
+/// so that the opening does not need to convert the fragments.
+pub(crate) trait ReencryptedParts {
+    fn point_e1(&self) -> &CurvePoint;
+    fn point_v1(&self) -> &CurvePoint;
+    fn kfrag_id(&self) -> &KeyFragID;
+    fn precursor(&self) -> &CurvePoint;
+
+    /// Returns `true` if the parts used to open the capsule are the same.
+    fn same_parts(&self, other: &Self) -> bool {
+        self.point_e1() == other.point_e1()
+            && self.point_v1() == other.point_v1()
+            && self.kfrag_id() == other.kfrag_id()
+            && self.precursor() == other.precursor()
+    }
+}
+
+impl ReencryptedParts for CapsuleFrag {
+    fn point_e1(&self) -> &CurvePoint {
+        &self.point_e1
+    }
+
+    fn point_v1(&self) -> &CurvePoint {
+        &self.point_v1
+    }
+
+    fn kfrag_id(&self) -> &KeyFragID {
+        &self.kfrag_id
+    }
+
+    fn precursor(&self) -> &CurvePoint {
+        &self.precursor
+    }
+}
+
+impl ReencryptedParts for UnprovenCapsuleFrag {
+    fn point_e1(&self) -> &CurvePoint {
+        &self.point_e1
+    }
+
+    fn point_v1(&self) -> &CurvePoint {
+        &self.point_v1
+    }
+
+    fn kfrag_id(&self) -> &KeyFragID {
+        &self.kfrag_id
+    }
+
+    fn precursor(&self) -> &CurvePoint {
+        &self.precursor
+    }
+}
+
+impl From<&CapsuleFrag> for UnprovenCapsuleFrag {
+    fn from(cfrag: &CapsuleFrag) -> Self {
+        Self {
+            point_e1: cfrag.point_e1,
+            point_v1: cfrag.point_v1,
+            kfrag_id: cfrag.kfrag_id,
+            precursor: cfrag.precursor,
+        }
+    }
+}
+
+impl HasSourceId for CapsuleFrag {
+    fn source_id(&self) -> SourceId {
+        SourceId::from_precursor(&self.precursor)
+    }
+}
+
+impl HasSourceId for VerifiedCapsuleFrag {
+    fn source_id(&self) -> SourceId {
+        self.cfrag.source_id()
+    }
+}
+
+impl HasSourceId for UnprovenCapsuleFrag {
+    fn source_id(&self) -> SourceId {
+        SourceId::from_precursor(&self.precursor)
+    }
 }
 
 #[cfg(test)]
@@ -377,11 +818,14 @@ mod tests {
     use alloc::boxed::Box;
     use alloc::vec::Vec;
 
-    use super::{CapsuleFrag, VerifiedCapsuleFrag};
+    use super::{
+        audit_capsule_frags, CapsuleFrag, CapsuleFragVerificationError, UnprovenCapsuleFrag,
+        VerifiedCapsuleFrag,
+    };
 
     use crate::{
         encrypt, generate_kfrags, reencrypt, Capsule, DeserializableFromArray, PublicKey,
-        SecretKey, SerializableToArray, Signer,
+        RepresentableAsArray, SecretKey, SerializableToArray, Signer,
     };
 
     #[cfg(feature = "serde-support")]
@@ -409,7 +853,8 @@ mod tests {
         let plaintext = b"peace at dawn";
         let (capsule, _ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
 
-        let kfrags = generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true);
+        let kfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
 
         let verified_cfrags: Vec<_> = kfrags
             .iter()
@@ -425,6 +870,18 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_unverified_conversions() {
+        let (_delegating_pk, _receiving_pk, _verifying_pk, _capsule, verified_cfrags) =
+            prepare_cfrags();
+        let vcfrag = verified_cfrags[0].clone();
+
+        let cfrag = vcfrag.clone_unverified();
+        assert_eq!(cfrag.to_array(), vcfrag.to_array());
+        assert_eq!(CapsuleFrag::from(vcfrag.clone()), cfrag);
+        assert_eq!(vcfrag.unverify(), cfrag);
+    }
+
     #[test]
     fn test_verify() {
         let (delegating_pk, receiving_pk, verifying_pk, capsule, verified_cfrags) =
@@ -444,6 +901,149 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_precheck() {
+        let (delegating_pk, receiving_pk, verifying_pk, capsule, verified_cfrags) =
+            prepare_cfrags();
+        let capsule_digest = capsule.canonical_digest();
+        let cfrag = verified_cfrags[0].clone_unverified();
+
+        // Only the digest is needed at this stage
+        let prechecked = cfrag
+            .clone()
+            .precheck(
+                &capsule_digest,
+                &verifying_pk,
+                &delegating_pk,
+                &receiving_pk,
+            )
+            .unwrap();
+        assert_eq!(prechecked.capsule_digest(), &capsule_digest);
+        assert_eq!(
+            prechecked.clone().verify(&capsule).unwrap(),
+            verified_cfrags[0]
+        );
+
+        // Wrong capsule
+        let (other_capsule, _ciphertext) = encrypt(&delegating_pk, b"peace at dusk").unwrap();
+        assert_eq!(
+            prechecked.verify(&other_capsule),
+            Err((
+                CapsuleFragVerificationError::CapsuleDigestMismatch,
+                cfrag.clone()
+            ))
+        );
+
+        // A fragment prechecked for a wrong digest fails even with the matching capsule
+        let prechecked = cfrag
+            .clone()
+            .precheck(
+                &other_capsule.canonical_digest(),
+                &verifying_pk,
+                &delegating_pk,
+                &receiving_pk,
+            )
+            .unwrap();
+        assert!(prechecked.verify(&capsule).is_err());
+
+        // Wrong keys are caught without the capsule
+        assert_eq!(
+            cfrag
+                .clone()
+                .precheck(&capsule_digest, &verifying_pk, &receiving_pk, &receiving_pk),
+            Err((
+                CapsuleFragVerificationError::IncorrectKeyFragSignature,
+                cfrag
+            ))
+        );
+    }
+
+    #[test]
+    fn test_response_signature() {
+        let (_delegating_pk, _receiving_pk, _verifying_pk, _capsule, verified_cfrags) =
+            prepare_cfrags();
+        let proxy_signer = Signer::new(SecretKey::random());
+        let proxy_pk = proxy_signer.verifying_key();
+
+        let request_digest = b"request 1";
+        let signature = verified_cfrags[0].sign_response(&proxy_signer, request_digest);
+
+        let cfrag = verified_cfrags[0].clone_unverified();
+        assert!(cfrag.verify_response(&signature, &proxy_pk, request_digest));
+
+        // Bound to the request
+        assert!(!cfrag.verify_response(&signature, &proxy_pk, b"request 2"));
+
+        // Bound to the fragment
+        let other_cfrag = verified_cfrags[1].clone_unverified();
+        assert!(!other_cfrag.verify_response(&signature, &proxy_pk, request_digest));
+
+        // Bound to the proxy
+        let other_pk = SecretKey::random().public_key();
+        assert!(!cfrag.verify_response(&signature, &other_pk, request_digest));
+    }
+
+    #[test]
+    fn test_audit() {
+        let (delegating_pk, receiving_pk, verifying_pk, capsule, verified_cfrags) =
+            prepare_cfrags();
+        let mut cfrags: Vec<_> = verified_cfrags
+            .iter()
+            .map(|vcfrag| vcfrag.clone_unverified())
+            .collect();
+
+        for cfrag in cfrags.iter() {
+            assert!(cfrag
+                .check(&capsule, &verifying_pk, &delegating_pk, &receiving_pk)
+                .is_ok());
+        }
+
+        // A fragment from a different delegation
+        let (_delegating_pk, _receiving_pk, _verifying_pk, _capsule, other_cfrags) =
+            prepare_cfrags();
+        cfrags[1] = other_cfrags[0].clone_unverified();
+
+        let failures = audit_capsule_frags(
+            &capsule,
+            &verifying_pk,
+            &delegating_pk,
+            &receiving_pk,
+            &cfrags,
+        );
+        assert_eq!(
+            failures,
+            [(1, CapsuleFragVerificationError::IncorrectKeyFragSignature)]
+        );
+    }
+
+    #[test]
+    fn test_unproven() {
+        let (_delegating_pk, _receiving_pk, _verifying_pk, _capsule, verified_cfrags) =
+            prepare_cfrags();
+
+        let cfrag = verified_cfrags[0].clone().unverify();
+        let unproven_cfrag = UnprovenCapsuleFrag::from(&cfrag);
+
+        // Same as the proven cfrag, just without the proof
+        let cfrag_arr = cfrag.to_array();
+        let unproven_arr = unproven_cfrag.to_array();
+        assert_eq!(
+            &cfrag_arr[..UnprovenCapsuleFrag::serialized_size()],
+            unproven_arr.as_slice()
+        );
+
+        let unproven_back = UnprovenCapsuleFrag::from_array(&unproven_arr).unwrap();
+        assert_eq!(unproven_back, unproven_cfrag);
+
+        assert_eq!(verified_cfrags[0].strip_proof(), unproven_cfrag);
+
+        #[cfg(feature = "serde-support")]
+        {
+            check_serialization(&unproven_cfrag, Representation::Base64);
+            check_deserialization(&unproven_cfrag);
+        }
+    }
+
     #[cfg(feature = "serde-support")]
     #[test]
     fn test_serde_serialization() {
@@ -0,0 +1,416 @@
+//! CBOR (RFC 8949) encoding of the objects, and COSE_Key (RFC 8152) import and export
+//! for the keys, without depending on a CBOR library.
+//!
+//! Objects are encoded as a single byte string containing their byte representation.
+//! Keys are represented as `EC2` COSE keys on the `secp256k1` curve (RFC 8812),
+//! or on the `P-256` curve (RFC 8152) with the `p256` feature, with the `y` coordinate replaced by its sign bit (point compression).
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use generic_array::GenericArray;
+use typenum::{U42, U77};
+
+use crate::curve::{CurvePoint, CurveType, UmbralCurve};
+use crate::keys::{PublicKey, SecretKey};
+use crate::secret_box::SecretBox;
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
+    SerializableToArray, SerializableToSecretArray,
+};
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_SIMPLE: u8 = 7;
+
+const SIMPLE_FALSE: u64 = 20;
+const SIMPLE_TRUE: u64 = 21;
+
+// COSE_Key labels and values
+const LABEL_KTY: i64 = 1;
+const LABEL_CRV: i64 = -1;
+const LABEL_X: i64 = -2;
+const LABEL_Y: i64 = -3;
+const LABEL_D: i64 = -4;
+const KTY_EC2: i64 = 2;
+const CRV: i64 = CurveType::COSE_CRV;
+
+fn write_head(out: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    if arg < 24 {
+        out.push(major | arg as u8);
+    } else if let Ok(arg) = u8::try_from(arg) {
+        out.push(major | 24);
+        out.push(arg);
+    } else if let Ok(arg) = u16::try_from(arg) {
+        out.push(major | 25);
+        out.extend_from_slice(&arg.to_be_bytes());
+    } else if let Ok(arg) = u32::try_from(arg) {
+        out.push(major | 26);
+        out.extend_from_slice(&arg.to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&arg.to_be_bytes());
+    }
+}
+
+fn write_int(out: &mut Vec<u8>, value: i64) {
+    if value >= 0 {
+        write_head(out, MAJOR_UNSIGNED, value as u64);
+    } else {
+        write_head(out, MAJOR_NEGATIVE, (-1 - value) as u64);
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_head(out, MAJOR_BYTES, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn write_bool(out: &mut Vec<u8>, value: bool) {
+    write_head(
+        out,
+        MAJOR_SIMPLE,
+        if value { SIMPLE_TRUE } else { SIMPLE_FALSE },
+    );
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    Bool(bool),
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn head(&mut self) -> Option<(u8, u64)> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let arg = match info {
+            0..=23 => info as u64,
+            24..=27 => {
+                let len = 1 << (info - 24);
+                self.take(len)?
+                    .iter()
+                    .fold(0u64, |arg, byte| (arg << 8) | (*byte as u64))
+            }
+            // Reserved values and indefinite lengths are not supported.
+            _ => return None,
+        };
+        Some((major, arg))
+    }
+
+    fn value(&mut self) -> Option<Value<'a>> {
+        match self.head()? {
+            (MAJOR_UNSIGNED, arg) => i64::try_from(arg).ok().map(Value::Int),
+            (MAJOR_NEGATIVE, arg) => i64::try_from(arg).ok().map(|arg| Value::Int(-1 - arg)),
+            (MAJOR_BYTES, len) => self.take(usize::try_from(len).ok()?).map(Value::Bytes),
+            (MAJOR_SIMPLE, SIMPLE_FALSE) => Some(Value::Bool(false)),
+            (MAJOR_SIMPLE, SIMPLE_TRUE) => Some(Value::Bool(true)),
+            _ => None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A trait for objects that can be encoded as CBOR.
+/// Implemented for all the objects serializable to arrays.
+pub trait ToCbor {
+    /// Encodes the object as a CBOR byte string.
+    fn to_cbor(&self) -> Box<[u8]>;
+}
+
+/// A trait for objects that can be decoded from CBOR.
+/// Implemented for all the objects deserializable from arrays.
+pub trait FromCbor: Sized {
+    /// Decodes the object from a CBOR byte string.
+    fn from_cbor(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError>;
+}
+
+impl<T: SerializableToArray> ToCbor for T {
+    fn to_cbor(&self) -> Box<[u8]> {
+        let bytes = self.to_array();
+        let mut result = Vec::with_capacity(bytes.len() + 9);
+        write_bytes(&mut result, &bytes);
+        result.into_boxed_slice()
+    }
+}
+
+fn construction_error(type_name: &str, message: &str) -> DeserializationError {
+    DeserializationError::ConstructionFailure(ConstructionError::new(type_name, message))
+}
+
+impl<T: DeserializableFromArray + HasTypeName> FromCbor for T {
+    fn from_cbor(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let mut reader = Reader(data.as_ref());
+        match reader.value() {
+            Some(Value::Bytes(bytes)) if reader.is_empty() => T::from_bytes(bytes),
+            _ => Err(construction_error(
+                T::type_name(),
+                "Expected a single CBOR byte string",
+            )),
+        }
+    }
+}
+
+struct CoseKey<'a> {
+    x: &'a [u8],
+    y: Value<'a>,
+    d: Option<&'a [u8]>,
+}
+
+fn parse_cose_key<'a>(
+    data: &'a [u8],
+    type_name: &str,
+) -> Result<CoseKey<'a>, DeserializationError> {
+    let error = |message| construction_error(type_name, message);
+
+    let mut reader = Reader(data);
+    let entries = match reader.head() {
+        Some((MAJOR_MAP, entries)) => entries,
+        _ => return Err(error("Expected a CBOR map")),
+    };
+
+    let mut labels = Vec::<i64>::new();
+    let mut kty = None;
+    let mut crv = None;
+    let mut x = None;
+    let mut y = None;
+    let mut d = None;
+    for _ in 0..entries {
+        let label = match reader.value() {
+            Some(Value::Int(label)) => label,
+            _ => return Err(error("Invalid COSE_Key label")),
+        };
+        if labels.contains(&label) {
+            return Err(error("Repeating COSE_Key label"));
+        }
+        labels.push(label);
+
+        let value = reader
+            .value()
+            .ok_or_else(|| error("Invalid COSE_Key value"))?;
+        match (label, value) {
+            (LABEL_KTY, Value::Int(value)) => kty = Some(value),
+            (LABEL_CRV, Value::Int(value)) => crv = Some(value),
+            (LABEL_X, Value::Bytes(value)) => x = Some(value),
+            (LABEL_Y, Value::Bytes(_)) | (LABEL_Y, Value::Bool(_)) => y = Some(value),
+            (LABEL_D, Value::Bytes(value)) => d = Some(value),
+            (LABEL_KTY, _) | (LABEL_CRV, _) | (LABEL_X, _) | (LABEL_Y, _) | (LABEL_D, _) => {
+                return Err(error("Invalid COSE_Key value"))
+            }
+            // Other parameters (e.g. `kid` or `alg`) do not affect the key itself.
+            _ => {}
+        }
+    }
+    if !reader.is_empty() {
+        return Err(error("Trailing bytes after the COSE_Key"));
+    }
+
+    if kty != Some(KTY_EC2) {
+        return Err(error("Unsupported COSE key type"));
+    }
+    if crv != Some(CRV) {
+        return Err(error("Unsupported COSE curve"));
+    }
+    match (x, y) {
+        (Some(x), Some(y)) => Ok(CoseKey { x, y, d }),
+        _ => Err(error("Missing COSE_Key coordinates")),
+    }
+}
+
+fn cose_key_point(key: &CoseKey<'_>, type_name: &str) -> Result<PublicKey, DeserializationError> {
+    let mut sec1 = Vec::with_capacity(1 + key.x.len() * 2);
+    match key.y {
+        Value::Bool(sign) => {
+            sec1.push(if sign { 0x03 } else { 0x02 });
+            sec1.extend_from_slice(key.x);
+        }
+        Value::Bytes(y) => {
+            sec1.push(0x04);
+            sec1.extend_from_slice(key.x);
+            sec1.extend_from_slice(y);
+        }
+        Value::Int(_) => unreachable!(),
+    }
+    CurvePoint::from_sec1_bytes(&sec1)
+        .and_then(|point| PublicKey::from_point(&point))
+        .ok_or_else(|| construction_error(type_name, "Invalid COSE_Key coordinates"))
+}
+
+fn write_cose_key_header(out: &mut Vec<u8>, entries: u64, public_key: &PublicKey) {
+    let point = public_key.to_array();
+    write_head(out, MAJOR_MAP, entries);
+    write_int(out, LABEL_KTY);
+    write_int(out, KTY_EC2);
+    write_int(out, LABEL_CRV);
+    write_int(out, CRV);
+    write_int(out, LABEL_X);
+    write_bytes(out, &point[1..]);
+    write_int(out, LABEL_Y);
+    write_bool(out, point[0] & 1 == 1);
+}
+
+impl PublicKey {
+    /// Exports the key as a COSE_Key structure
+    /// (the `y` coordinate is represented by its sign bit).
+    pub fn to_cose_key(&self) -> GenericArray<u8, U42> {
+        let mut result = Vec::with_capacity(42);
+        write_cose_key_header(&mut result, 4, self);
+        GenericArray::clone_from_slice(&result)
+    }
+
+    /// Imports the key from a COSE_Key structure.
+    /// The `y` coordinate can be given either explicitly or as a sign bit.
+    /// If the private part is present, it is ignored.
+    pub fn from_cose_key(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let key = parse_cose_key(data.as_ref(), "PublicKey")?;
+        cose_key_point(&key, "PublicKey")
+    }
+}
+
+impl SecretKey {
+    /// Exports the key as a COSE_Key structure, including the public part.
+    pub fn to_cose_key(&self) -> SecretBox<GenericArray<u8, U77>> {
+        let mut result = Vec::with_capacity(77);
+        write_cose_key_header(&mut result, 5, &self.public_key());
+        write_int(&mut result, LABEL_D);
+        write_bytes(&mut result, self.to_secret_array().as_secret());
+        let array = SecretBox::new(GenericArray::clone_from_slice(&result));
+        // Do not leave the secret in the temporary buffer.
+        result.iter_mut().for_each(|byte| *byte = 0);
+        array
+    }
+
+    /// Imports the key from a COSE_Key structure.
+    /// Fails if the public part does not correspond to the private one.
+    pub fn from_cose_key(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let key = parse_cose_key(data.as_ref(), "SecretKey")?;
+        let d = key
+            .d
+            .ok_or_else(|| construction_error("SecretKey", "Missing COSE_Key private part"))?;
+        let secret_key = SecretKey::from_bytes(d)?;
+        let public_key = cose_key_point(&key, "SecretKey")?;
+        if secret_key.public_key() != public_key {
+            return Err(construction_error(
+                "SecretKey",
+                "COSE_Key public part does not match the private one",
+            ));
+        }
+        Ok(secret_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use elliptic_curve::sec1::ToEncodedPoint;
+
+    use super::{FromCbor, ToCbor, CRV};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{
+        Capsule, CapsuleFrag, KeyFrag, PublicKey, SecretKey, SerializableToArray,
+        SerializableToSecretArray,
+    };
+
+    #[test]
+    fn test_cbor_roundtrip() {
+        let Delegation {
+            delegating_pk,
+            capsule,
+            verified_kfrags: vkfrags,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+        let vcfrag = vcfrags[0].clone();
+
+        // Capsules are shorter than 256 bytes, so a one-byte length is used
+        let capsule_cbor = capsule.to_cbor();
+        assert_eq!(capsule_cbor[..2], [0x58, 98]);
+        assert_eq!(Capsule::from_cbor(&capsule_cbor).unwrap(), capsule);
+
+        let kfrag_cbor = vkfrags[0].to_cbor();
+        assert_eq!(
+            KeyFrag::from_cbor(&kfrag_cbor).unwrap(),
+            vkfrags[0].clone_unverified()
+        );
+
+        let cfrag_cbor = vcfrag.to_cbor();
+        assert_eq!(
+            CapsuleFrag::from_cbor(&cfrag_cbor).unwrap(),
+            vcfrag.clone_unverified()
+        );
+
+        let pk_cbor = delegating_pk.to_cbor();
+        assert_eq!(PublicKey::from_cbor(&pk_cbor).unwrap(), delegating_pk);
+
+        // Trailing bytes are rejected
+        let mut trailing = capsule_cbor.to_vec();
+        trailing.push(0);
+        assert!(Capsule::from_cbor(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_cose_key() {
+        let sk = SecretKey::random();
+        let pk = sk.public_key();
+
+        let pk_cose = pk.to_cose_key();
+        assert_eq!(PublicKey::from_cose_key(&pk_cose).unwrap(), pk);
+
+        let sk_cose = sk.to_cose_key();
+        let sk_back = SecretKey::from_cose_key(sk_cose.as_secret()).unwrap();
+        assert_eq!(
+            sk_back.to_secret_array().as_secret(),
+            sk.to_secret_array().as_secret()
+        );
+        // The public part can be read from the secret key structure
+        assert_eq!(PublicKey::from_cose_key(sk_cose.as_secret()).unwrap(), pk);
+
+        // A public key is not enough to restore the secret key
+        assert!(SecretKey::from_cose_key(&pk_cose).is_err());
+
+        // A structure with the explicit `y` coordinate, and extra parameters
+        // {1: 2, -1: crv, -2: x, -3: y, 2: h'01'}
+        let encoded = pk.to_point().to_affine_point().to_encoded_point(false);
+        let uncompressed = encoded.as_bytes();
+        let mut explicit_y = Vec::from([0xa5, 0x01, 0x02, 0x20, CRV as u8, 0x21, 0x58, 0x20]);
+        explicit_y.extend_from_slice(&uncompressed[1..33]);
+        explicit_y.extend_from_slice(&[0x22, 0x58, 0x20]);
+        explicit_y.extend_from_slice(&uncompressed[33..]);
+        explicit_y.extend_from_slice(&[0x02, 0x41, 0x01]);
+        assert_eq!(PublicKey::from_cose_key(&explicit_y).unwrap(), pk);
+
+        // Wrong curve
+        let mut wrong_curve = pk_cose.to_vec();
+        assert_eq!(wrong_curve[4], CRV as u8);
+        wrong_curve[4] = 0x03;
+        assert!(PublicKey::from_cose_key(&wrong_curve).is_err());
+
+        // Mismatched public part
+        let other_pk = SecretKey::random().public_key().to_array();
+        let mut mismatched = sk_cose.as_secret().to_vec();
+        mismatched[8..40].copy_from_slice(&other_pk[1..]);
+        mismatched[41] = if other_pk[0] == 0x03 { 0xf5 } else { 0xf4 };
+        assert!(SecretKey::from_cose_key(&mismatched).is_err());
+    }
+}
@@ -0,0 +1,462 @@
+//! Signed statements of delegation travelling alongside key and capsule fragments.
+
+use core::fmt;
+use core::str::FromStr;
+
+use generic_array::sequence::Concat;
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+use typenum::op;
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+#[cfg(feature = "serde-support")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::{CapsuleFrag, CapsuleFragVerificationError, VerifiedCapsuleFrag};
+use crate::hashing_ds::delegation_certificate_message;
+use crate::key_frag::{KeyFrag, KeyFragVerificationError, VerifiedKeyFrag};
+use crate::keys::{PublicKey, Signature, Signer};
+use crate::policy::PolicyId;
+use crate::traits::{
+    fmt_public, from_str_public, ConstructionError, DeserializableFromArray, DeserializationError,
+    HasTypeName, RepresentableAsArray, SerializableToArray,
+};
+
+#[cfg(feature = "serde-support")]
+use crate::serde::{serde_deserialize, serde_serialize, Representation};
+
+/// Possible errors that can be returned by [`DelegationCertificate::verify`].
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
+pub enum DelegationCertificateError {
+    /// The certificate was signed by a key other than the trusted one.
+    UnexpectedVerifyingKey,
+    /// The signature does not match the contents of the certificate.
+    IncorrectSignature,
+    /// The given time is before the start of the validity window.
+    NotYetValid,
+    /// The given time is after the end of the validity window.
+    Expired,
+}
+
+impl fmt::Display for DelegationCertificateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedVerifyingKey => {
+                write!(f, "The certificate was signed by an unexpected key")
+            }
+            Self::IncorrectSignature => write!(f, "Failed to verify the certificate signature"),
+            Self::NotYetValid => write!(f, "The certificate is not valid yet"),
+            Self::Expired => write!(f, "The certificate has expired"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for DelegationCertificateError {}
+
+/// A statement signed by the delegating party, describing a delegation:
+/// the receiving key, the policy label, the threshold, and the validity window.
+///
+/// Intended to be distributed along with the key fragments and capsule fragments
+/// of the delegation, so that any party can check their provenance offline.
+/// The signing key must be the same one that was used in
+/// [`generate_kfrags`](`crate::generate_kfrags`).
+///
+/// Timestamps are opaque to the library (e.g. UNIX time in seconds),
+/// and only need to be consistent between the creator and the verifiers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DelegationCertificate {
+    delegating_pk: PublicKey,
+    receiving_pk: PublicKey,
+    verifying_pk: PublicKey,
+    policy_id: PolicyId,
+    threshold: u64,
+    not_before: u64,
+    not_after: u64,
+    signature: Signature,
+}
+
+type PublicKeySize = <PublicKey as RepresentableAsArray>::Size;
+type PolicyIdSize = <PolicyId as RepresentableAsArray>::Size;
+type U64Size = <u64 as RepresentableAsArray>::Size;
+type SignatureSize = <Signature as RepresentableAsArray>::Size;
+
+impl RepresentableAsArray for DelegationCertificate {
+    type Size = op!(PublicKeySize
+        + PublicKeySize
+        + PublicKeySize
+        + PolicyIdSize
+        + U64Size
+        + U64Size
+        + U64Size
+        + SignatureSize);
+}
+
+impl SerializableToArray for DelegationCertificate {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.delegating_pk
+            .to_array()
+            .concat(self.receiving_pk.to_array())
+            .concat(self.verifying_pk.to_array())
+            .concat(self.policy_id.to_array())
+            .concat(self.threshold.to_array())
+            .concat(self.not_before.to_array())
+            .concat(self.not_after.to_array())
+            .concat(self.signature.to_array())
+    }
+}
+
+impl DeserializableFromArray for DelegationCertificate {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let (delegating_pk, rest) = PublicKey::take(*arr)?;
+        let (receiving_pk, rest) = PublicKey::take(rest)?;
+        let (verifying_pk, rest) = PublicKey::take(rest)?;
+        let (policy_id, rest) = PolicyId::take(rest)?;
+        let (threshold, rest) = u64::take(rest)?;
+        let (not_before, rest) = u64::take(rest)?;
+        let (not_after, rest) = u64::take(rest)?;
+        let signature = Signature::take_last(rest)?;
+        Ok(Self {
+            delegating_pk,
+            receiving_pk,
+            verifying_pk,
+            policy_id,
+            threshold,
+            not_before,
+            not_after,
+            signature,
+        })
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for DelegationCertificate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize(self, serializer, Representation::Base64)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for DelegationCertificate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_deserialize(deserializer, Representation::Base64)
+    }
+}
+
+impl HasTypeName for DelegationCertificate {
+    fn type_name() -> &'static str {
+        "DelegationCertificate"
+    }
+}
+
+impl fmt::Display for DelegationCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(self, f)
+    }
+}
+
+impl FromStr for DelegationCertificate {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
+impl DelegationCertificate {
+    /// Creates a certificate for the delegation from `delegating_pk` to `receiving_pk`
+    /// under the given label, valid between `not_before` and `not_after` (inclusive),
+    /// signed by `signer` using the given RNG.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_rng(
+        rng: &mut (impl CryptoRng + RngCore),
+        signer: &Signer,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+        label: &[u8],
+        threshold: usize,
+        not_before: u64,
+        not_after: u64,
+    ) -> Self {
+        let verifying_pk = signer.verifying_key();
+        let policy_id = PolicyId::new(delegating_pk, receiving_pk, label);
+        let threshold = threshold as u64;
+        let message = delegation_certificate_message(
+            delegating_pk,
+            receiving_pk,
+            &verifying_pk,
+            &policy_id,
+            threshold,
+            not_before,
+            not_after,
+        );
+        let signature = signer.sign_with_rng(rng, &message);
+        Self {
+            delegating_pk: *delegating_pk,
+            receiving_pk: *receiving_pk,
+            verifying_pk,
+            policy_id,
+            threshold,
+            not_before,
+            not_after,
+            signature,
+        }
+    }
+
+    /// Creates a certificate using the default RNG.
+    /// See [`new_with_rng`](`Self::new_with_rng`) for details.
+    #[cfg(feature = "default-rng")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+    pub fn new(
+        signer: &Signer,
+        delegating_pk: &PublicKey,
+        receiving_pk: &PublicKey,
+        label: &[u8],
+        threshold: usize,
+        not_before: u64,
+        not_after: u64,
+    ) -> Self {
+        Self::new_with_rng(
+            &mut OsRng,
+            signer,
+            delegating_pk,
+            receiving_pk,
+            label,
+            threshold,
+            not_before,
+            not_after,
+        )
+    }
+
+    /// Checks that the certificate is signed by `verifying_pk`,
+    /// and that `timestamp` is within its validity window.
+    ///
+    /// The verifying key must come from a trusted source:
+    /// the one contained in the certificate proves nothing by itself,
+    /// since anyone can sign a certificate with their own key.
+    pub fn verify(
+        &self,
+        verifying_pk: &PublicKey,
+        timestamp: u64,
+    ) -> Result<(), DelegationCertificateError> {
+        if &self.verifying_pk != verifying_pk {
+            return Err(DelegationCertificateError::UnexpectedVerifyingKey);
+        }
+        let message = delegation_certificate_message(
+            &self.delegating_pk,
+            &self.receiving_pk,
+            &self.verifying_pk,
+            &self.policy_id,
+            self.threshold,
+            self.not_before,
+            self.not_after,
+        );
+        if !self.signature.verify(&self.verifying_pk, &message) {
+            return Err(DelegationCertificateError::IncorrectSignature);
+        }
+        if timestamp < self.not_before {
+            return Err(DelegationCertificateError::NotYetValid);
+        }
+        if timestamp > self.not_after {
+            return Err(DelegationCertificateError::Expired);
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the certificate was issued for the given policy label.
+    pub fn has_label(&self, label: &[u8]) -> bool {
+        PolicyId::new(&self.delegating_pk, &self.receiving_pk, label) == self.policy_id
+    }
+
+    /// Verifies a key fragment against the keys of this certificate.
+    ///
+    /// Note that the certificate itself must be checked separately with
+    /// [`verify`](`Self::verify`).
+    pub fn verify_kfrag(
+        &self,
+        kfrag: KeyFrag,
+    ) -> Result<VerifiedKeyFrag, (KeyFragVerificationError, KeyFrag)> {
+        kfrag.verify(
+            &self.verifying_pk,
+            Some(&self.delegating_pk),
+            Some(&self.receiving_pk),
+        )
+    }
+
+    /// Verifies a capsule fragment against the keys of this certificate.
+    ///
+    /// Note that the certificate itself must be checked separately with
+    /// [`verify`](`Self::verify`).
+    pub fn verify_cfrag(
+        &self,
+        cfrag: CapsuleFrag,
+        capsule: &Capsule,
+    ) -> Result<VerifiedCapsuleFrag, (CapsuleFragVerificationError, CapsuleFrag)> {
+        cfrag.verify(
+            capsule,
+            &self.verifying_pk,
+            &self.delegating_pk,
+            &self.receiving_pk,
+        )
+    }
+
+    /// Returns the public key of the delegating party.
+    pub fn delegating_key(&self) -> &PublicKey {
+        &self.delegating_pk
+    }
+
+    /// Returns the public key of the receiving party.
+    pub fn receiving_key(&self) -> &PublicKey {
+        &self.receiving_pk
+    }
+
+    /// Returns the public key that verifies the certificate and the key fragments.
+    pub fn verifying_key(&self) -> &PublicKey {
+        &self.verifying_pk
+    }
+
+    /// Returns the identifier of the delegation.
+    pub fn policy_id(&self) -> &PolicyId {
+        &self.policy_id
+    }
+
+    /// Returns the number of capsule fragments required for decryption.
+    pub fn threshold(&self) -> u64 {
+        self.threshold
+    }
+
+    /// Returns the start of the validity window.
+    pub fn not_before(&self) -> u64 {
+        self.not_before
+    }
+
+    /// Returns the end of the validity window.
+    pub fn not_after(&self) -> u64 {
+        self.not_after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{DelegationCertificate, DelegationCertificateError};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{
+        generate_kfrags, reencrypt, DeserializableFromArray, PolicyId, PublicKey,
+        RepresentableAsArray, SecretKey, SerializableToArray, Signer,
+    };
+
+    #[cfg(feature = "serde-support")]
+    use crate::serde::tests::{check_deserialization, check_serialization};
+
+    #[cfg(feature = "serde-support")]
+    use crate::serde::Representation;
+
+    #[test]
+    fn test_certificate() {
+        let Delegation {
+            delegating_sk,
+            delegating_pk,
+            signer,
+            verifying_pk,
+            receiving_pk,
+            capsule,
+            verified_kfrags: vkfrags,
+            ..
+        } = prepare_delegation(true, true);
+
+        let cert =
+            DelegationCertificate::new(&signer, &delegating_pk, &receiving_pk, b"foo", 2, 100, 200);
+
+        assert_eq!(cert.verify(&verifying_pk, 150), Ok(()));
+        assert_eq!(
+            cert.verify(&verifying_pk, 99),
+            Err(DelegationCertificateError::NotYetValid)
+        );
+        assert_eq!(
+            cert.verify(&verifying_pk, 201),
+            Err(DelegationCertificateError::Expired)
+        );
+        assert!(cert.has_label(b"foo"));
+        assert!(!cert.has_label(b"bar"));
+
+        let cert_back = DelegationCertificate::from_array(&cert.to_array()).unwrap();
+        assert_eq!(cert_back, cert);
+
+        // Tampered threshold (the last byte of the big-endian threshold,
+        // which follows three public keys and the policy ID)
+        let mut bytes = cert.to_array();
+        let threshold_offset = PublicKey::serialized_size() * 3 + PolicyId::serialized_size();
+        bytes[threshold_offset + u64::serialized_size() - 1] = 3;
+        let tampered = DelegationCertificate::from_array(&bytes).unwrap();
+        assert_eq!(tampered.threshold(), 3);
+        assert_eq!(
+            tampered.verify(&verifying_pk, 150),
+            Err(DelegationCertificateError::IncorrectSignature)
+        );
+
+        // A valid certificate signed by someone else
+        let forged_signer = Signer::new(SecretKey::random());
+        let forged = DelegationCertificate::new(
+            &forged_signer,
+            &delegating_pk,
+            &receiving_pk,
+            b"foo",
+            2,
+            100,
+            200,
+        );
+        assert_eq!(forged.verify(&forged_signer.verifying_key(), 150), Ok(()));
+        assert_eq!(
+            forged.verify(&verifying_pk, 150),
+            Err(DelegationCertificateError::UnexpectedVerifyingKey)
+        );
+
+        // Fragments of the certified delegation
+        let vkfrag = cert.verify_kfrag(vkfrags[0].clone().unverify()).unwrap();
+        let vcfrag = reencrypt(&capsule, vkfrag);
+        assert!(cert.verify_cfrag(vcfrag.unverify(), &capsule).is_ok());
+
+        // Fragments of a delegation by someone else
+        let other_signer = Signer::new(SecretKey::random());
+        let other_vkfrags = generate_kfrags(
+            &delegating_sk,
+            &receiving_pk,
+            &other_signer,
+            2,
+            3,
+            true,
+            true,
+        )
+        .unwrap();
+        assert!(cert
+            .verify_kfrag(other_vkfrags[0].clone().unverify())
+            .is_err());
+    }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_serde_serialization() {
+        let delegating_pk = SecretKey::random().public_key();
+        let signer = Signer::new(SecretKey::random());
+        let receiving_pk = SecretKey::random().public_key();
+        let cert =
+            DelegationCertificate::new(&signer, &delegating_pk, &receiving_pk, b"foo", 2, 100, 200);
+
+        check_serialization(&cert, Representation::Base64);
+        check_deserialization(&cert);
+    }
+}
@@ -0,0 +1,432 @@
+//! Checks of the protocol invariants, for use in the test suites of dependent crates.
+//!
+//! Each check runs the protocol end to end with random keys and returns
+//! a [`ConformanceFailure`] describing the first violated invariant.
+//! Integrators building with a specific set of features (curve backend tuning,
+//! legacy DEM support, etc) can run them in their own tests to make sure
+//! the resulting configuration still behaves as expected:
+//!
+//! ```
+//! # #[cfg(feature = "default-rng")]
+//! umbral_pre::conformance::run_all(&[(1, 1), (2, 3), (3, 5)]).unwrap();
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::key_frag::KeyFrag;
+use crate::keys::{PublicKey, SecretKey, Signature, Signer};
+use crate::pre::{
+    decrypt_original, decrypt_reencrypted, encrypt_with_rng, generate_kfrags_with_rng,
+    reencrypt_with_rng,
+};
+use crate::traits::{DeserializableFromArray, SerializableToArray, SerializableToSecretArray};
+
+/// A violated protocol invariant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConformanceFailure {
+    /// The name of the check that failed.
+    pub check: &'static str,
+    /// The description of the violated invariant.
+    pub reason: &'static str,
+}
+
+impl fmt::Display for ConformanceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Conformance check `{}` failed: {}",
+            self.check, self.reason
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for ConformanceFailure {}
+
+fn fail(check: &'static str, reason: &'static str) -> ConformanceFailure {
+    ConformanceFailure { check, reason }
+}
+
+fn ensure(
+    check: &'static str,
+    condition: bool,
+    reason: &'static str,
+) -> Result<(), ConformanceFailure> {
+    if condition {
+        Ok(())
+    } else {
+        Err(fail(check, reason))
+    }
+}
+
+fn check_roundtrip<T>(check: &'static str, obj: &T) -> Result<(), ConformanceFailure>
+where
+    T: SerializableToArray + DeserializableFromArray + PartialEq,
+{
+    let bytes = obj.to_array();
+    let restored = T::from_array(&bytes);
+    ensure(
+        check,
+        restored.ok().as_ref() == Some(obj),
+        "An object is not restored from its serialized form",
+    )?;
+
+    // Dynamically sized inputs of the wrong length are rejected
+    let mut longer = Vec::from(bytes.as_slice());
+    longer.push(0);
+    ensure(
+        check,
+        T::from_bytes(&longer).is_err()
+            && T::from_bytes(&bytes[..T::serialized_size() - 1]).is_err(),
+        "Serialized data of a wrong length is accepted",
+    )
+}
+
+/// Checks that all the objects survive a serialization roundtrip,
+/// and that inputs of the wrong size are rejected, using the given RNG.
+pub fn check_serialization_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(), ConformanceFailure> {
+    const CHECK: &str = "serialization";
+
+    let delegating_sk = SecretKey::random_with_rng(&mut *rng);
+    let delegating_pk = delegating_sk.public_key();
+    let signer = Signer::new(SecretKey::random_with_rng(&mut *rng));
+    let receiving_pk = SecretKey::random_with_rng(&mut *rng).public_key();
+
+    let sk_restored = SecretKey::from_array(delegating_sk.to_secret_array().as_secret());
+    ensure(
+        CHECK,
+        sk_restored.ok().map(|sk| sk.public_key()) == Some(delegating_pk),
+        "A secret key is not restored from its serialized form",
+    )?;
+    check_roundtrip::<PublicKey>(CHECK, &delegating_pk)?;
+    check_roundtrip::<Signature>(CHECK, &signer.sign_with_rng(&mut *rng, b"message"))?;
+
+    let (capsule, _ciphertext) = encrypt_with_rng(&mut *rng, &delegating_pk, b"peace at dawn")
+        .map_err(|_| fail(CHECK, "Encryption failed"))?;
+    check_roundtrip::<Capsule>(CHECK, &capsule)?;
+
+    let vkfrags = generate_kfrags_with_rng(
+        &mut *rng,
+        &delegating_sk,
+        &receiving_pk,
+        &signer,
+        1,
+        1,
+        true,
+        true,
+    )
+    .map_err(|_| fail(CHECK, "Key fragment generation failed"))?;
+    let vkfrag = vkfrags[0].clone();
+    check_roundtrip::<KeyFrag>(CHECK, &vkfrag.clone_unverified())?;
+
+    let vcfrag = reencrypt_with_rng(&mut *rng, &capsule, vkfrag);
+    check_roundtrip::<CapsuleFrag>(CHECK, &vcfrag.clone_unverified())
+}
+
+/// Checks that the data encrypted for a key can be decrypted with it
+/// (including the empty plaintext), using the given RNG.
+pub fn check_encryption_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(), ConformanceFailure> {
+    const CHECK: &str = "encryption";
+
+    let delegating_sk = SecretKey::random_with_rng(&mut *rng);
+    let delegating_pk = delegating_sk.public_key();
+
+    let plaintexts: [&[u8]; 3] = [b"", b"peace at dawn", &[0xa5u8; 1000]];
+    for plaintext in plaintexts {
+        let (capsule, ciphertext) = encrypt_with_rng(&mut *rng, &delegating_pk, plaintext)
+            .map_err(|_| fail(CHECK, "Encryption failed"))?;
+        let decrypted = decrypt_original(&delegating_sk, &capsule, &ciphertext);
+        ensure(
+            CHECK,
+            decrypted.ok().as_deref() == Some(plaintext),
+            "The decrypted plaintext differs from the original one",
+        )?;
+    }
+
+    // Encryption is randomized
+    let (capsule1, ciphertext1) = encrypt_with_rng(&mut *rng, &delegating_pk, b"peace at dawn")
+        .map_err(|_| fail(CHECK, "Encryption failed"))?;
+    let (capsule2, ciphertext2) = encrypt_with_rng(&mut *rng, &delegating_pk, b"peace at dawn")
+        .map_err(|_| fail(CHECK, "Encryption failed"))?;
+    ensure(
+        CHECK,
+        capsule1 != capsule2 && ciphertext1 != ciphertext2,
+        "Repeated encryption of the same plaintext produces the same output",
+    )
+}
+
+/// Checks that any `threshold` of `shares` capsule fragments can be combined to decrypt,
+/// and fewer than `threshold` cannot, using the given RNG.
+///
+/// Some subsets of the fragments are tested: the first and the last `threshold` of them,
+/// in direct and reverse order.
+pub fn check_threshold_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    threshold: usize,
+    shares: usize,
+) -> Result<(), ConformanceFailure> {
+    const CHECK: &str = "threshold";
+
+    let delegating_sk = SecretKey::random_with_rng(&mut *rng);
+    let delegating_pk = delegating_sk.public_key();
+    let signer = Signer::new(SecretKey::random_with_rng(&mut *rng));
+    let verifying_pk = signer.verifying_key();
+    let receiving_sk = SecretKey::random_with_rng(&mut *rng);
+    let receiving_pk = receiving_sk.public_key();
+
+    let plaintext = b"peace at dawn";
+    let (capsule, ciphertext) = encrypt_with_rng(&mut *rng, &delegating_pk, plaintext)
+        .map_err(|_| fail(CHECK, "Encryption failed"))?;
+
+    let vkfrags = generate_kfrags_with_rng(
+        &mut *rng,
+        &delegating_sk,
+        &receiving_pk,
+        &signer,
+        threshold,
+        shares,
+        true,
+        true,
+    )
+    .map_err(|_| fail(CHECK, "Key fragment generation failed"))?;
+    ensure(
+        CHECK,
+        vkfrags.len() == shares,
+        "Wrong number of key fragments generated",
+    )?;
+
+    let mut vcfrags = Vec::with_capacity(shares);
+    for vkfrag in vkfrags.iter() {
+        let kfrag_verified = vkfrag
+            .clone_unverified()
+            .verify(&verifying_pk, Some(&delegating_pk), Some(&receiving_pk))
+            .is_ok();
+        ensure(CHECK, kfrag_verified, "A key fragment fails verification")?;
+
+        let cfrag = reencrypt_with_rng(&mut *rng, &capsule, vkfrag.clone()).unverify();
+        let vcfrag = cfrag
+            .verify(&capsule, &verifying_pk, &delegating_pk, &receiving_pk)
+            .map_err(|_| fail(CHECK, "A capsule fragment fails verification"))?;
+        vcfrags.push(vcfrag);
+    }
+
+    let subsets = [
+        vcfrags[..threshold].to_vec(),
+        vcfrags[shares - threshold..]
+            .iter()
+            .rev()
+            .cloned()
+            .collect(),
+    ];
+    for subset in subsets {
+        let decrypted =
+            decrypt_reencrypted(&receiving_sk, &delegating_pk, &capsule, subset, &ciphertext);
+        ensure(
+            CHECK,
+            decrypted.ok().as_deref() == Some(&plaintext[..]),
+            "Decryption with `threshold` capsule fragments fails",
+        )?;
+    }
+
+    if threshold > 1 {
+        let decrypted = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            vcfrags[..threshold - 1].to_vec(),
+            &ciphertext,
+        );
+        ensure(
+            CHECK,
+            decrypted.is_err(),
+            "Decryption with fewer than `threshold` capsule fragments succeeds",
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Checks that modified ciphertexts, capsules, and fragments are rejected, using the given RNG.
+pub fn check_tamper_rejection_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+) -> Result<(), ConformanceFailure> {
+    const CHECK: &str = "tamper rejection";
+
+    let delegating_sk = SecretKey::random_with_rng(&mut *rng);
+    let delegating_pk = delegating_sk.public_key();
+    let signer = Signer::new(SecretKey::random_with_rng(&mut *rng));
+    let verifying_pk = signer.verifying_key();
+    let receiving_sk = SecretKey::random_with_rng(&mut *rng);
+    let receiving_pk = receiving_sk.public_key();
+
+    let (capsule, ciphertext) = encrypt_with_rng(&mut *rng, &delegating_pk, b"peace at dawn")
+        .map_err(|_| fail(CHECK, "Encryption failed"))?;
+    let (other_capsule, _ciphertext) =
+        encrypt_with_rng(&mut *rng, &delegating_pk, b"peace at dawn")
+            .map_err(|_| fail(CHECK, "Encryption failed"))?;
+
+    // Every bit of the ciphertext is authenticated
+    for i in 0..ciphertext.len() {
+        let mut tampered = Vec::from(&ciphertext[..]);
+        tampered[i] ^= 1 << (i % 8);
+        ensure(
+            CHECK,
+            decrypt_original(&delegating_sk, &capsule, &tampered).is_err(),
+            "A modified ciphertext is decrypted",
+        )?;
+    }
+
+    // The ciphertext is bound to its capsule
+    ensure(
+        CHECK,
+        decrypt_original(&delegating_sk, &other_capsule, &ciphertext).is_err(),
+        "A ciphertext is decrypted with a different capsule",
+    )?;
+
+    let vkfrags = generate_kfrags_with_rng(
+        &mut *rng,
+        &delegating_sk,
+        &receiving_pk,
+        &signer,
+        1,
+        1,
+        true,
+        true,
+    )
+    .map_err(|_| fail(CHECK, "Key fragment generation failed"))?;
+    let kfrag = vkfrags[0].clone_unverified();
+
+    // Key fragments are bound to the delegation
+    let other_pk = SecretKey::random_with_rng(&mut *rng).public_key();
+    ensure(
+        CHECK,
+        kfrag
+            .clone()
+            .verify(&other_pk, Some(&delegating_pk), Some(&receiving_pk))
+            .is_err()
+            && kfrag
+                .clone()
+                .verify(&verifying_pk, Some(&other_pk), Some(&receiving_pk))
+                .is_err()
+            && kfrag
+                .clone()
+                .verify(&verifying_pk, Some(&delegating_pk), Some(&other_pk))
+                .is_err(),
+        "A key fragment is verified with wrong keys",
+    )?;
+
+    // Capsule fragments are bound to the capsule and the delegation
+    let cfrag = reencrypt_with_rng(&mut *rng, &capsule, vkfrags[0].clone()).unverify();
+    ensure(
+        CHECK,
+        cfrag
+            .check(&other_capsule, &verifying_pk, &delegating_pk, &receiving_pk)
+            .is_err()
+            && cfrag
+                .check(&capsule, &other_pk, &delegating_pk, &receiving_pk)
+                .is_err()
+            && cfrag
+                .check(&capsule, &verifying_pk, &delegating_pk, &other_pk)
+                .is_err(),
+        "A capsule fragment is verified with a wrong capsule or keys",
+    )?;
+
+    // A capsule fragment from a different capsule does not decrypt
+    let other_cfrag = reencrypt_with_rng(&mut *rng, &other_capsule, vkfrags[0].clone());
+    ensure(
+        CHECK,
+        decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            [other_cfrag],
+            &ciphertext,
+        )
+        .is_err(),
+        "A capsule is opened with a capsule fragment of a different capsule",
+    )
+}
+
+/// Runs all the checks, with the threshold checks for each of the given `(threshold, shares)`
+/// pairs, using the given RNG.
+pub fn run_all_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    threshold_params: &[(usize, usize)],
+) -> Result<(), ConformanceFailure> {
+    check_serialization_with_rng(rng)?;
+    check_encryption_with_rng(rng)?;
+    for (threshold, shares) in threshold_params.iter().copied() {
+        check_threshold_with_rng(rng, threshold, shares)?;
+    }
+    check_tamper_rejection_with_rng(rng)
+}
+
+/// A synonym for [`check_serialization_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn check_serialization() -> Result<(), ConformanceFailure> {
+    check_serialization_with_rng(&mut OsRng)
+}
+
+/// A synonym for [`check_encryption_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn check_encryption() -> Result<(), ConformanceFailure> {
+    check_encryption_with_rng(&mut OsRng)
+}
+
+/// A synonym for [`check_threshold_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn check_threshold(threshold: usize, shares: usize) -> Result<(), ConformanceFailure> {
+    check_threshold_with_rng(&mut OsRng, threshold, shares)
+}
+
+/// A synonym for [`check_tamper_rejection_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn check_tamper_rejection() -> Result<(), ConformanceFailure> {
+    check_tamper_rejection_with_rng(&mut OsRng)
+}
+
+/// A synonym for [`run_all_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn run_all(threshold_params: &[(usize, usize)]) -> Result<(), ConformanceFailure> {
+    run_all_with_rng(&mut OsRng, threshold_params)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{check_threshold, run_all, ConformanceFailure};
+
+    #[test]
+    fn test_conformance() {
+        run_all(&[(1, 1), (1, 3), (2, 3), (3, 3), (4, 7)]).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        assert_eq!(
+            check_threshold(3, 2),
+            Err(ConformanceFailure {
+                check: "threshold",
+                reason: "Key fragment generation failed",
+            })
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Rough cost estimates of the protocol operations,
+//! for setting timeouts and admission limits on user-supplied parameters.
+
+use core::convert::TryFrom;
+use core::ops::Add;
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(all(feature = "std", feature = "default-rng"))]
+use rand_core::OsRng;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::key_frag::KeyFrag;
+
+#[cfg(feature = "std")]
+use crate::{
+    curve::{CurvePoint, NonZeroCurveScalar},
+    hashing_ds::hash_to_cfrag_verification,
+    keys::{SecretKey, Signer},
+    params::Parameters,
+};
+
+/// The number of expensive primitive operations an Umbral operation performs.
+///
+/// Cheap operations (scalar field arithmetic, point additions, symmetric encryption)
+/// are not counted, so for large thresholds the estimate is a lower bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct OperationCount {
+    /// Curve point multiplications by a scalar.
+    pub scalar_multiplications: usize,
+    /// Scalar inversions (a batch inversion counts as one).
+    pub inversions: usize,
+    /// Hashes to a scalar.
+    pub hashes: usize,
+    /// ECDSA signature creations.
+    pub signatures: usize,
+    /// ECDSA signature verifications.
+    pub signature_verifications: usize,
+}
+
+// The counts are calculated from user-supplied parameters (e.g. the number of shares),
+// so all the arithmetic saturates instead of overflowing.
+impl Add for OperationCount {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            scalar_multiplications: self
+                .scalar_multiplications
+                .saturating_add(other.scalar_multiplications),
+            inversions: self.inversions.saturating_add(other.inversions),
+            hashes: self.hashes.saturating_add(other.hashes),
+            signatures: self.signatures.saturating_add(other.signatures),
+            signature_verifications: self
+                .signature_verifications
+                .saturating_add(other.signature_verifications),
+        }
+    }
+}
+
+impl OperationCount {
+    /// Returns the count for performing the operation `times` times
+    /// (saturating at `usize::MAX`).
+    pub fn times(&self, times: usize) -> Self {
+        Self {
+            scalar_multiplications: self.scalar_multiplications.saturating_mul(times),
+            inversions: self.inversions.saturating_mul(times),
+            hashes: self.hashes.saturating_mul(times),
+            signatures: self.signatures.saturating_mul(times),
+            signature_verifications: self.signature_verifications.saturating_mul(times),
+        }
+    }
+}
+
+/// Returns the operation count of [`generate_kfrags`](`crate::generate_kfrags`)
+/// for the given `threshold` and `shares`.
+///
+/// The threshold only affects the number of scalar field multiplications
+/// in the polynomial evaluation, which are not counted.
+pub fn generate_kfrags_cost(_threshold: usize, shares: usize) -> OperationCount {
+    let common = OperationCount {
+        scalar_multiplications: 2,
+        inversions: 1,
+        hashes: 1,
+        ..OperationCount::default()
+    };
+    let per_kfrag = OperationCount {
+        scalar_multiplications: 1,
+        hashes: 1,
+        signatures: 2,
+        ..OperationCount::default()
+    };
+    common + per_kfrag.times(shares)
+}
+
+/// Returns the operation count of [`reencrypt`](`crate::reencrypt`).
+pub fn reencrypt_cost() -> OperationCount {
+    OperationCount {
+        scalar_multiplications: 5,
+        hashes: 1,
+        ..OperationCount::default()
+    }
+}
+
+impl KeyFrag {
+    /// Returns the operation count of [`KeyFrag::verify`].
+    pub fn verify_cost() -> OperationCount {
+        OperationCount {
+            scalar_multiplications: 1,
+            signature_verifications: 1,
+            ..OperationCount::default()
+        }
+    }
+}
+
+impl CapsuleFrag {
+    /// Returns the operation count of [`CapsuleFrag::verify`].
+    pub fn verify_cost() -> OperationCount {
+        OperationCount {
+            scalar_multiplications: 6,
+            hashes: 1,
+            signature_verifications: 1,
+            ..OperationCount::default()
+        }
+    }
+}
+
+impl Capsule {
+    /// Returns the operation count of opening the capsule
+    /// with `threshold` already verified capsule fragments
+    /// (the cost of [`decrypt_reencrypted`](`crate::decrypt_reencrypted`)
+    /// without the symmetric decryption).
+    ///
+    /// To get the cost including the fragment verification, add
+    /// `CapsuleFrag::verify_cost().times(threshold)`.
+    pub fn open_cost(threshold: usize) -> OperationCount {
+        OperationCount {
+            scalar_multiplications: threshold.saturating_mul(2).saturating_add(4),
+            inversions: 2,
+            hashes: threshold.saturating_add(2),
+            ..OperationCount::default()
+        }
+    }
+}
+
+/// Durations of the primitive operations, used to turn an [`OperationCount`]
+/// into an estimated time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CostModel {
+    /// The duration of a point multiplication.
+    pub scalar_multiplication: Duration,
+    /// The duration of a scalar inversion.
+    pub inversion: Duration,
+    /// The duration of a hash to a scalar.
+    pub hash: Duration,
+    /// The duration of a signature creation.
+    pub signature: Duration,
+    /// The duration of a signature verification.
+    pub signature_verification: Duration,
+}
+
+impl CostModel {
+    /// Returns the estimated duration of the given operations
+    /// (saturating at [`Duration::MAX`]).
+    pub fn estimate(&self, count: &OperationCount) -> Duration {
+        let scale = |duration: Duration, times: usize| {
+            duration.saturating_mul(u32::try_from(times).unwrap_or(u32::MAX))
+        };
+        scale(self.scalar_multiplication, count.scalar_multiplications)
+            .saturating_add(scale(self.inversion, count.inversions))
+            .saturating_add(scale(self.hash, count.hashes))
+            .saturating_add(scale(self.signature, count.signatures))
+            .saturating_add(scale(
+                self.signature_verification,
+                count.signature_verifications,
+            ))
+    }
+
+    /// Measures the durations of the primitive operations on the current machine,
+    /// averaging over `iterations` runs of each.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn calibrate_with_rng(rng: &mut (impl CryptoRng + RngCore), iterations: u32) -> Self {
+        use std::time::Instant;
+
+        let iterations = iterations.max(1);
+        let measure = |op: &mut dyn FnMut()| {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                op();
+            }
+            start.elapsed() / iterations
+        };
+
+        let scalar = NonZeroCurveScalar::random(rng);
+        let mut point = CurvePoint::generator();
+        let scalar_multiplication = measure(&mut || point = &point * &scalar);
+
+        let mut inverted = scalar.clone();
+        let inversion = measure(&mut || inverted = inverted.invert());
+
+        let params = Parameters::new();
+        let points = [point; 9];
+        let mut digest = scalar;
+        let hash = measure(&mut || digest = hash_to_cfrag_verification(&params, &points));
+
+        let signer = Signer::new(SecretKey::random_with_rng(&mut *rng));
+        let verifying_pk = signer.verifying_key();
+        let message = b"cost model calibration";
+        let mut signature = signer.sign_with_rng(rng, message);
+        let signature_duration = measure(&mut || signature = signer.sign_with_rng(rng, message));
+
+        let mut verified = true;
+        let signature_verification =
+            measure(&mut || verified &= signature.verify(&verifying_pk, message));
+        // Not a `debug_assert!`: a failed verification may be faster than a successful one,
+        // so the measurement would be meaningless.
+        assert!(verified, "The calibration signature failed to verify");
+
+        // Use the results, so that the measured operations are not considered dead code.
+        let _results = (point, inverted, digest, signature);
+
+        Self {
+            scalar_multiplication,
+            inversion,
+            hash,
+            signature: signature_duration,
+            signature_verification,
+        }
+    }
+
+    /// A synonym for [`CostModel::calibrate_with_rng`] with the default RNG.
+    #[cfg(all(feature = "std", feature = "default-rng"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "std", feature = "default-rng"))))]
+    pub fn calibrate(iterations: u32) -> Self {
+        Self::calibrate_with_rng(&mut OsRng, iterations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use core::time::Duration;
+
+    use super::{generate_kfrags_cost, reencrypt_cost, CostModel, OperationCount};
+    use crate::{Capsule, CapsuleFrag, KeyFrag};
+
+    #[test]
+    fn test_counts() {
+        assert_eq!(
+            generate_kfrags_cost(2, 3),
+            OperationCount {
+                scalar_multiplications: 5,
+                inversions: 1,
+                hashes: 4,
+                signatures: 6,
+                signature_verifications: 0,
+            }
+        );
+
+        // Opening grows linearly with the threshold
+        let open2 = Capsule::open_cost(2);
+        let open3 = Capsule::open_cost(3);
+        let open4 = Capsule::open_cost(4);
+        assert_eq!(
+            open4.scalar_multiplications - open3.scalar_multiplications,
+            open3.scalar_multiplications - open2.scalar_multiplications
+        );
+
+        let full_decryption = open3 + CapsuleFrag::verify_cost().times(3);
+        assert_eq!(full_decryption.signature_verifications, 3);
+
+        assert_eq!(KeyFrag::verify_cost().signature_verifications, 1);
+        assert_eq!(reencrypt_cost().signatures, 0);
+    }
+
+    #[test]
+    fn test_estimate() {
+        let model = CostModel {
+            scalar_multiplication: Duration::from_micros(100),
+            inversion: Duration::from_micros(10),
+            hash: Duration::from_micros(1),
+            signature: Duration::from_micros(200),
+            signature_verification: Duration::from_micros(300),
+        };
+        assert_eq!(
+            model.estimate(&reencrypt_cost()),
+            Duration::from_micros(5 * 100 + 1)
+        );
+        assert_eq!(
+            model.estimate(&CapsuleFrag::verify_cost()),
+            Duration::from_micros(6 * 100 + 1 + 300)
+        );
+    }
+
+    #[test]
+    fn test_saturation() {
+        // The counts for unreasonable user-supplied parameters do not overflow
+        let huge = generate_kfrags_cost(2, usize::MAX);
+        assert_eq!(huge.signatures, usize::MAX);
+        assert_eq!((huge + huge).scalar_multiplications, usize::MAX);
+        assert_eq!(Capsule::open_cost(usize::MAX).hashes, usize::MAX);
+
+        let model = CostModel {
+            scalar_multiplication: Duration::from_secs(u64::MAX),
+            inversion: Duration::from_secs(1),
+            hash: Duration::from_secs(1),
+            signature: Duration::from_secs(1),
+            signature_verification: Duration::from_secs(1),
+        };
+        assert_eq!(model.estimate(&huge), Duration::MAX);
+    }
+
+    #[cfg(all(feature = "std", feature = "default-rng"))]
+    #[test]
+    fn test_calibrate() {
+        let model = CostModel::calibrate(2);
+        assert!(model.estimate(&Capsule::open_cost(10)) > Duration::from_nanos(0));
+    }
+}
@@ -2,6 +2,7 @@
 //! `elliptic_curves` has a somewhat unstable API,
 //! and we isolate all the related logic here.
 
+use alloc::vec::Vec;
 use core::default::Default;
 use core::ops::{Add, Mul, Sub};
 
@@ -12,12 +13,19 @@ use elliptic_curve::hash2curve::GroupDigest;
 use elliptic_curve::hash2field::ExpandMsgXmd;
 use elliptic_curve::ops::Reduce;
 use elliptic_curve::sec1::{EncodedPoint, FromEncodedPoint, ModulusSize, ToEncodedPoint};
-use elliptic_curve::{AffinePoint, Field, FieldSize, NonZeroScalar, ProjectiveArithmetic, Scalar};
-use generic_array::GenericArray;
+use elliptic_curve::{
+    AffinePoint, Field, FieldSize, NonZeroScalar, PrimeCurve, ProjectiveArithmetic, Scalar,
+};
+use generic_array::{ArrayLength, GenericArray};
 use k256::Secp256k1;
+#[cfg(feature = "p256")]
+use p256::NistP256;
 use rand_core::{CryptoRng, RngCore};
 use sha2::Sha256;
 use subtle::CtOption;
+use typenum::{U135, U88};
+#[cfg(feature = "p256")]
+use typenum::{U138, U91};
 use zeroize::{DefaultIsZeroes, Zeroize};
 
 use crate::secret_box::CanBeZeroizedOnDrop;
@@ -26,7 +34,103 @@ use crate::traits::{
     SerializableToArray,
 };
 
+/// The curve-specific constants of the scheme and the encodings.
+///
+/// This is not a generic curve parameter: `CurvePoint`, `CurveScalar` and the rest
+/// of the library are concrete types built on the single curve chosen at build time
+/// (secp256k1, or P-256 with the `p256` feature), and users of the crate cannot plug in
+/// their own curve. The trait only keeps the per-curve constants in one place;
+/// adding a curve requires changes to this crate (an implementation of the trait,
+/// a feature selecting it, and the corresponding backend).
+///
+/// Besides the group arithmetic, the curve must have an ECDSA implementation
+/// and SEC1 point encodings in its backend, since the keys are also used for signing.
+/// This currently rules out the pairing-friendly curves (e.g. BLS12-381 G1,
+/// whose backend only implements the `group` traits and has its own point encoding);
+/// supporting them would require a different signature scheme.
+///
+/// The scalars are also assumed to be 256-bit
+/// (hashing to scalars in `NonZeroCurveScalar::from_digest()` reduces a 256-bit digest).
+/// A larger curve such as P-384 would need a wider digest there,
+/// as well as a backend with arithmetic compatible with the current `elliptic_curve`
+/// (the corresponding release of `p384` only provides the curve definition).
+///
+/// Public for the same reason as `CurveScalar` and `CurvePoint` below
+/// (the sizes of the PKCS#8 encodings are taken from it).
+pub trait UmbralCurve: PrimeCurve + ProjectiveArithmetic + GroupDigest {
+    /// The identifier of the curve in the ciphertext header and the envelope suite.
+    const ID: u8;
+
+    /// The point `u` of [`Parameters`](`crate::Parameters`) in the SEC1 uncompressed encoding,
+    /// precalculated as `CurvePoint::from_data(b"PARAMETERS", b"POINT_U")`
+    /// (see `test_precalculated_u()`).
+    /// Hashing to curve is relatively expensive, and `Parameters` are created
+    /// every time a `Capsule` or a `KeyFrag` is constructed or deserialized;
+    /// decoding an uncompressed point only requires checking that it is on the curve.
+    const POINT_U: &'static [u8];
+
+    /// The JWK curve name.
+    const JWK_NAME: &'static str;
+
+    /// The JWE algorithm name.
+    const JWE_ALGORITHM: &'static str;
+
+    /// The COSE elliptic curve identifier.
+    const COSE_CRV: i64;
+
+    /// The DER contents of the curve OID.
+    const PKCS8_OID: &'static [u8];
+
+    /// The size of the DER-encoded SubjectPublicKeyInfo (depends on the length of the OID).
+    type PublicKeyDerSize: ArrayLength<u8>;
+
+    /// The size of the DER-encoded PKCS#8 structure (depends on the length of the OID).
+    type PrivateKeyDerSize: ArrayLength<u8>;
+}
+
+impl UmbralCurve for Secp256k1 {
+    const ID: u8 = 1;
+    const POINT_U: &'static [u8] = &[
+        0x04, 0x07, 0x93, 0x90, 0xd0, 0xfb, 0xe2, 0x20, 0xfc, 0x34, 0xaa, 0xb8, 0xec, 0xbf, 0x49,
+        0x09, 0x80, 0x98, 0x03, 0x68, 0x20, 0xfa, 0xdb, 0x98, 0xc0, 0x31, 0x43, 0xe5, 0x5d, 0xb8,
+        0xec, 0x73, 0xcd, 0x7e, 0xa5, 0x0b, 0x21, 0x9d, 0xa8, 0x6a, 0x70, 0xe8, 0x48, 0x94, 0x13,
+        0xe4, 0xb9, 0x38, 0x8a, 0xfb, 0x63, 0xac, 0x6f, 0x26, 0xe5, 0xec, 0x2c, 0xfd, 0xd9, 0x83,
+        0xb8, 0x3f, 0x0e, 0x84, 0xe7,
+    ];
+    const JWK_NAME: &'static str = "secp256k1";
+    const JWE_ALGORITHM: &'static str = "UMBRAL-SECP256K1";
+    const COSE_CRV: i64 = 8;
+    // 1.3.132.0.10
+    const PKCS8_OID: &'static [u8] = &[0x2b, 0x81, 0x04, 0x00, 0x0a];
+    type PublicKeyDerSize = U88;
+    type PrivateKeyDerSize = U135;
+}
+
+#[cfg(feature = "p256")]
+impl UmbralCurve for NistP256 {
+    const ID: u8 = 2;
+    const POINT_U: &'static [u8] = &[
+        0x04, 0xac, 0x7e, 0xac, 0x51, 0xdb, 0xdf, 0x98, 0xb4, 0x46, 0x40, 0x9e, 0x51, 0xa9, 0x12,
+        0x26, 0x95, 0x96, 0xd1, 0x5e, 0x71, 0xda, 0x0d, 0x59, 0xc1, 0xdd, 0x4b, 0xa0, 0xef, 0x63,
+        0x7b, 0xf0, 0x01, 0x44, 0x41, 0x45, 0xd7, 0x94, 0xd6, 0xb5, 0x90, 0x13, 0x8b, 0x32, 0x8f,
+        0xbe, 0x58, 0xc8, 0x8c, 0xc8, 0x87, 0x5c, 0x3d, 0x1e, 0x92, 0x49, 0xc9, 0x34, 0x19, 0xc0,
+        0xfc, 0x5c, 0x6a, 0xc4, 0x1a,
+    ];
+    const JWK_NAME: &'static str = "P-256";
+    const JWE_ALGORITHM: &'static str = "UMBRAL-P256";
+    const COSE_CRV: i64 = 1;
+    // 1.2.840.10045.3.1.7
+    const PKCS8_OID: &'static [u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+    type PublicKeyDerSize = U91;
+    type PrivateKeyDerSize = U138;
+}
+
+/// The curve the library is instantiated over.
+#[cfg(not(feature = "p256"))]
 pub(crate) type CurveType = Secp256k1;
+/// The curve the library is instantiated over.
+#[cfg(feature = "p256")]
+pub(crate) type CurveType = NistP256;
 type CompressedPointSize = <FieldSize<CurveType> as ModulusSize>::CompressedPointSize;
 
 type BackendScalar = Scalar<CurveType>;
@@ -114,6 +218,19 @@ impl NonZeroCurveScalar {
         Self(source)
     }
 
+    /// Attempts to create a non-zero scalar from its big-endian representation.
+    ///
+    /// Both the range check and the non-zero check are performed in constant time,
+    /// and the result is only branched upon once, at the end.
+    pub(crate) fn from_array_ct(
+        arr: &GenericArray<u8, <CurveScalar as RepresentableAsArray>::Size>,
+    ) -> Option<Self> {
+        // unwrap CtOption into Option
+        let maybe_scalar: Option<BackendNonZeroScalar> =
+            BackendNonZeroScalar::from_repr(*arr).into();
+        maybe_scalar.map(Self)
+    }
+
     pub(crate) fn as_backend_scalar(&self) -> &BackendNonZeroScalar {
         &self.0
     }
@@ -134,6 +251,19 @@ impl NonZeroCurveScalar {
         // There's currently no way to make the required digest output size
         // depend on the target scalar size, so we are hardcoding it to 256 bit
         // (that is, equal to the scalar size).
+        //
+        // The result is `(x mod (n - 1)) + 1`, which is not exactly uniform.
+        // For secp256k1 `n` is within `2^129` of `2^256`, so the bias is negligible.
+        // For P-256 `2^256 - n` is about `2^224`, so the values below `2^224`
+        // are about `2^-32` more likely than the rest
+        // (the statistical distance from uniform is about `2^-32`).
+        // This is acceptable because the scheme only relies on the hashed scalars
+        // (polynomial arguments, shared secrets, and proof challenges)
+        // being unpredictable and collision-resistant, which requires min-entropy
+        // rather than uniformity, and at most one bit of min-entropy is lost.
+        // A wide (e.g. 384-bit) reduction would remove the bias,
+        // but would change the hashes of the secp256k1 configuration,
+        // on which the test vectors and the EVM verification recipe depend.
         Self(<BackendNonZeroScalar as Reduce<U256>>::from_be_bytes_reduced(d.finalize()))
     }
 }
@@ -176,7 +306,21 @@ impl CurvePoint {
     pub(crate) fn from_compressed_array(
         arr: &GenericArray<u8, CompressedPointSize>,
     ) -> Option<Self> {
-        let ep = EncodedPoint::<CurveType>::from_bytes(arr.as_slice()).ok()?;
+        if !matches!(arr[0], 0x02 | 0x03) {
+            return None;
+        }
+        Self::from_sec1_bytes(arr.as_slice())
+    }
+
+    /// Decodes a point in the SEC1 compressed or uncompressed encoding.
+    ///
+    /// The other tags accepted by the backend (the identity, and the compact encoding
+    /// supported by `p256`) are rejected, so that every point has a single compressed encoding.
+    pub(crate) fn from_sec1_bytes(bytes: &[u8]) -> Option<Self> {
+        if !matches!(bytes.first(), Some(0x02) | Some(0x03) | Some(0x04)) {
+            return None;
+        }
+        let ep = EncodedPoint::<CurveType>::from_bytes(bytes).ok()?;
         // Unwrap CtOption into Option
         let cp_opt: Option<BackendPoint> = BackendPoint::from_encoded_point(&ep).into();
         cp_opt.map(Self)
@@ -322,3 +466,97 @@ impl HasTypeName for CurvePoint {
         "CurvePoint"
     }
 }
+
+/// Inverts all the given scalars at the cost of a single inversion
+/// (Montgomery's trick).
+/// Returns `None` if any of the scalars is zero.
+pub(crate) fn batch_invert(scalars: &[CurveScalar]) -> Option<Vec<CurveScalar>> {
+    // `result[i]` is the product of all the scalars before `i`.
+    let mut result = Vec::with_capacity(scalars.len());
+    let mut acc = CurveScalar::one();
+    for scalar in scalars {
+        result.push(acc);
+        acc = &acc * scalar;
+    }
+
+    let mut inv: CurveScalar = Option::from(acc.invert())?;
+    for (res, scalar) in result.iter_mut().zip(scalars.iter()).rev() {
+        *res = &inv * &*res;
+        inv = &inv * scalar;
+    }
+    Some(result)
+}
+
+/// Calculates `sum(scalars[i] * points[i])` with a simultaneous double-and-add
+/// (Straus' method), sharing the doublings between all the terms.
+/// Returns `None` if the lengths of the slices are different.
+///
+/// **Warning:** not constant-time, must only be used with public scalars
+/// (e.g. during verification).
+pub(crate) fn lincomb(points: &[CurvePoint], scalars: &[CurveScalar]) -> Option<CurvePoint> {
+    if points.len() != scalars.len() {
+        return None;
+    }
+    let scalar_bytes: Vec<_> = scalars.iter().map(|scalar| scalar.to_array()).collect();
+    let mut acc = CurvePoint::identity();
+    let byte_size = <CurveScalar as RepresentableAsArray>::serialized_size();
+    for bit in 0..(byte_size * 8) {
+        acc = &acc + &acc;
+        // The scalars are serialized in big-endian order.
+        let (byte, shift) = (bit / 8, 7 - bit % 8);
+        for (point, bytes) in points.iter().zip(scalar_bytes.iter()) {
+            if (bytes[byte] >> shift) & 1 == 1 {
+                acc = &acc + point;
+            }
+        }
+    }
+    Some(acc)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use rand_core::OsRng;
+
+    use super::{batch_invert, lincomb, CurvePoint, CurveScalar, NonZeroCurveScalar};
+
+    fn random_scalars(n: usize) -> Vec<CurveScalar> {
+        (0..n)
+            .map(|_| CurveScalar::from(NonZeroCurveScalar::random(&mut OsRng)))
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_invert() {
+        let scalars = random_scalars(5);
+        let inverses = batch_invert(&scalars).unwrap();
+        for (scalar, inverse) in scalars.iter().zip(inverses.iter()) {
+            assert_eq!(scalar * inverse, CurveScalar::one());
+        }
+
+        let mut with_zero = scalars;
+        with_zero[2] = CurveScalar::default();
+        assert!(batch_invert(&with_zero).is_none());
+
+        assert!(batch_invert(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_lincomb() {
+        let g = CurvePoint::generator();
+        let points: Vec<_> = random_scalars(4).iter().map(|s| &g * s).collect();
+        let scalars = random_scalars(4);
+
+        let expected = points
+            .iter()
+            .zip(scalars.iter())
+            .fold(CurvePoint::identity(), |acc, (point, scalar)| {
+                &acc + &(point * scalar)
+            });
+        assert_eq!(lincomb(&points, &scalars).unwrap(), expected);
+
+        assert!(lincomb(&points, &scalars[1..]).is_none());
+    }
+}
@@ -0,0 +1,25 @@
+//! Dumping of intermediate protocol values, enabled by the `insecure-debug` feature.
+//!
+//! Intended for diagnosing protocol mismatches (e.g. comparing against traces
+//! of another implementation). The dumped values include secrets,
+//! so the feature must never be enabled in production.
+
+use core::fmt;
+
+use crate::traits::SerializableToArray;
+
+/// Prints `value` as hex to `stderr`, tagged with the operation and the value name.
+#[cfg(feature = "insecure-debug")]
+pub(crate) fn dump(operation: &str, name: impl fmt::Display, value: &impl SerializableToArray) {
+    std::eprintln!(
+        "umbral-pre INSECURE DEBUG: {}: {} = {}",
+        operation,
+        name,
+        hex::encode(value.to_array())
+    );
+}
+
+/// Does nothing (the `insecure-debug` feature is not enabled).
+#[cfg(not(feature = "insecure-debug"))]
+#[inline(always)]
+pub(crate) fn dump(_operation: &str, _name: impl fmt::Display, _value: &impl SerializableToArray) {}
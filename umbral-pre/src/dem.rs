@@ -1,19 +1,37 @@
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::fmt;
 
-use aead::{Aead, AeadCore, Payload};
+#[cfg(not(feature = "fips-algorithms"))]
+use aead::{AeadCore, AeadInPlace};
+// `aes-gcm` depends on a newer `aead`, where `NewAead` is called `KeyInit`,
+// and the key size is defined in a separate `KeySizeUser` trait.
+#[cfg(feature = "fips-algorithms")]
+use aes_gcm::aead::{AeadCore, AeadInPlace, KeyInit as NewAead, KeySizeUser};
+#[cfg(feature = "fips-algorithms")]
+use aes_gcm::Aes256Gcm;
+#[cfg(not(feature = "fips-algorithms"))]
 use chacha20poly1305::aead::NewAead;
-use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+#[cfg(not(feature = "fips-algorithms"))]
+use chacha20poly1305::XChaCha20Poly1305;
+use digest::Digest;
 use generic_array::{ArrayLength, GenericArray};
 use hkdf::Hkdf;
 use rand_core::{CryptoRng, RngCore};
 use sha2::Sha256;
 use typenum::Unsigned;
 
+use crate::curve::{CurveType, UmbralCurve};
+use crate::dst;
+use crate::hashing::{Hash, HASH_ID};
 use crate::secret_box::{CanBeZeroizedOnDrop, SecretBox};
 
+#[cfg(feature = "serde-support")]
+use serde::Serialize;
+
 /// Errors that can happen during symmetric encryption.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
 pub enum EncryptionError {
     /// Given plaintext is too large for the backend to handle.
     PlaintextTooLarge,
@@ -27,8 +45,13 @@ impl fmt::Display for EncryptionError {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for EncryptionError {}
+
 /// Errors that can happend during symmetric decryption.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
 pub enum DecryptionError {
     /// Ciphertext (which should be prepended by the nonce) is shorter than the nonce length.
     CiphertextTooShort,
@@ -38,6 +61,12 @@ pub enum DecryptionError {
     /// - the ciphertext is modified or cut short,
     /// - an incorrect authentication data is provided on decryption.
     AuthenticationFailed,
+    /// Failed to allocate memory for the plaintext
+    /// (only returned by the `try_` variants of the decryption functions).
+    AllocationFailed,
+    /// The ciphertext header specifies a format version or algorithms
+    /// different from the ones used by this library (see [`CiphertextHeader`]).
+    UnsupportedHeader,
 }
 
 impl fmt::Display for DecryptionError {
@@ -50,10 +79,132 @@ impl fmt::Display for DecryptionError {
                 either someone tampered with the ciphertext or \
                 you are using an incorrect decryption key."
             ),
+            Self::AllocationFailed => write!(f, "Failed to allocate memory for the plaintext"),
+            Self::UnsupportedHeader => write!(
+                f,
+                "The ciphertext was created with a different format version, curve, hash, or DEM"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for DecryptionError {}
+
+/// The way the nonce for the symmetric encryption of the plaintext is chosen.
+///
+/// Every encryption uses a new [`Capsule`](`crate::Capsule`), and therefore a new symmetric key,
+/// so the uniqueness of nonces is guaranteed regardless of the strategy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NonceStrategy {
+    /// The nonce is taken from the RNG (the default).
+    Random,
+    /// The nonce is derived deterministically from the capsule.
+    /// This lets anyone holding the capsule check which nonce was used
+    /// (see [`has_derived_nonce`](`crate::has_derived_nonce`)),
+    /// and makes the ciphertext a deterministic function of the capsule and the plaintext.
+    Derived,
+}
+
+impl Default for NonceStrategy {
+    fn default() -> Self {
+        Self::Random
+    }
+}
+
+/// The header prepended to every ciphertext produced by [`encrypt`](`crate::encrypt`)
+/// or similar functions, identifying the format and the algorithms used,
+/// so that ciphertexts created with a different configuration are rejected on decryption
+/// instead of failing authentication.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CiphertextHeader {
+    /// The version of the ciphertext format.
+    pub version: u8,
+    /// The identifier of the curve used by the KEM (`1` for secp256k1, `2` for P-256).
+    pub curve: u8,
+    /// The identifier of the hash used by the scheme
+    /// (`1` for SHA-256, `2` for SHA3-256, `3` for BLAKE3, `4` for Keccak-256).
+    /// Ciphertexts do not depend on it directly, but their capsules do.
+    pub hash: u8,
+    /// The identifier of the DEM
+    /// (`1` for XChaCha20-Poly1305, `2` for AES-256-GCM, with the key derived by HKDF-SHA256).
+    pub dem: u8,
+}
+
+impl CiphertextHeader {
+    /// The size of the serialized header.
+    pub const SIZE: usize = 4;
+
+    /// The header of the ciphertexts produced by this library.
+    pub const CURRENT: Self = Self {
+        version: 1,
+        curve: CurveType::ID,
+        hash: HASH_ID,
+        dem: DEM_ID,
+    };
+
+    /// Returns the serialized header.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        [self.version, self.curve, self.hash, self.dem]
+    }
+
+    /// Parses the header at the start of the given ciphertext.
+    pub fn from_ciphertext(ciphertext: &[u8]) -> Result<Self, DecryptionError> {
+        match ciphertext {
+            [version, curve, hash, dem, ..] => Ok(Self {
+                version: *version,
+                curve: *curve,
+                hash: *hash,
+                dem: *dem,
+            }),
+            _ => Err(DecryptionError::CiphertextTooShort),
         }
     }
 }
 
+/// Returns the part of the ciphertext after the header,
+/// checking that the header is [`CiphertextHeader::CURRENT`].
+pub(crate) fn ciphertext_body(ciphertext: &[u8]) -> Result<&[u8], DecryptionError> {
+    if CiphertextHeader::from_ciphertext(ciphertext)? != CiphertextHeader::CURRENT {
+        return Err(DecryptionError::UnsupportedHeader);
+    }
+    Ok(&ciphertext[CiphertextHeader::SIZE..])
+}
+
+/// Returns the nonce used to create the given ciphertext
+/// (produced by [`encrypt`](`crate::encrypt`) or similar functions).
+pub fn ciphertext_nonce(ciphertext: &[u8]) -> Result<&[u8], DecryptionError> {
+    let body = ciphertext_body(ciphertext)?;
+    let nonce_size = <NonceSize as Unsigned>::to_usize();
+    if body.len() < nonce_size {
+        return Err(DecryptionError::CiphertextTooShort);
+    }
+    Ok(&body[..nonce_size])
+}
+
+/// Allocates an empty buffer with the capacity sufficient
+/// to hold the plaintext decrypted from `ciphertext`,
+/// returning an error instead of aborting if the allocation fails.
+pub(crate) fn try_plaintext_buffer(ciphertext: &[u8]) -> Result<Vec<u8>, DecryptionError> {
+    let overhead = CiphertextHeader::SIZE
+        + <NonceSize as Unsigned>::to_usize()
+        + <TagSize as Unsigned>::to_usize();
+    let mut buffer = Vec::new();
+    buffer
+        .try_reserve_exact(ciphertext.len().saturating_sub(overhead))
+        .map_err(|_| DecryptionError::AllocationFailed)?;
+    Ok(buffer)
+}
+
+pub(crate) fn derived_nonce(authenticated_data: &[u8]) -> GenericArray<u8, NonceSize> {
+    let digest = Hash::new_with_dst(dst::DEM_NONCE)
+        .chain_bytes(authenticated_data)
+        .digest()
+        .finalize();
+    GenericArray::<u8, NonceSize>::clone_from_slice(&digest[..<NonceSize as Unsigned>::to_usize()])
+}
+
 pub(crate) fn kdf<T: AsRef<[u8]> + Clone + CanBeZeroizedOnDrop, S: ArrayLength<u8>>(
     seed: &SecretBox<T>,
     salt: Option<&[u8]>,
@@ -71,53 +222,148 @@ pub(crate) fn kdf<T: AsRef<[u8]> + Clone + CanBeZeroizedOnDrop, S: ArrayLength<u
     okm
 }
 
-type NonceSize = <XChaCha20Poly1305 as AeadCore>::NonceSize;
+#[cfg(all(feature = "fips-algorithms", feature = "legacy-dem-kdf"))]
+compile_error!(
+    "The legacy ciphertexts cannot be decrypted with the `fips-algorithms` feature enabled"
+);
 
+// The AEAD used by the DEM, and its identifier in the ciphertext header and the envelope suite.
+#[cfg(not(feature = "fips-algorithms"))]
+type Cipher = XChaCha20Poly1305;
+#[cfg(not(feature = "fips-algorithms"))]
+pub(crate) const DEM_ID: u8 = 1;
+
+#[cfg(feature = "fips-algorithms")]
+type Cipher = Aes256Gcm;
+#[cfg(feature = "fips-algorithms")]
+pub(crate) const DEM_ID: u8 = 2;
+
+pub(crate) type NonceSize = <Cipher as AeadCore>::NonceSize;
+
+#[cfg(not(feature = "fips-algorithms"))]
+type KeySize = <Cipher as NewAead>::KeySize;
+#[cfg(feature = "fips-algorithms")]
+type KeySize = <Cipher as KeySizeUser>::KeySize;
+pub(crate) type TagSize = <Cipher as AeadCore>::TagSize;
+
+#[cfg(not(feature = "fips-algorithms"))]
 impl CanBeZeroizedOnDrop for XChaCha20Poly1305 {
     fn ensure_zeroized_on_drop(&mut self) {
         // `XChaCha20Poly1305` is zeroized on drop in `chacha20poly1305-0.8.0`
     }
 }
 
+#[cfg(feature = "fips-algorithms")]
+impl CanBeZeroizedOnDrop for Aes256Gcm {
+    fn ensure_zeroized_on_drop(&mut self) {
+        // The expanded key and the GHASH key of `Aes256Gcm` are zeroized on drop
+        // with the `zeroize` features of `aes-0.8` and `ghash-0.5` (enabled in `Cargo.toml`).
+    }
+}
+
 #[allow(clippy::upper_case_acronyms)]
 pub(crate) struct DEM {
-    cipher: SecretBox<XChaCha20Poly1305>,
+    cipher: SecretBox<Cipher>,
+    // Kept to derive the legacy key only if a ciphertext without the header is encountered.
+    #[cfg(feature = "legacy-dem-kdf")]
+    legacy_key_seed: SecretBox<Vec<u8>>,
+}
+
+fn make_cipher<T: AsRef<[u8]> + Clone + CanBeZeroizedOnDrop>(
+    key_seed: &SecretBox<T>,
+    info: Option<&[u8]>,
+) -> SecretBox<Cipher> {
+    let key_bytes = kdf::<T, KeySize>(key_seed, None, info);
+    SecretBox::new(Cipher::new(key_bytes.as_secret()))
 }
 
 impl DEM {
-    pub fn new<T: AsRef<[u8]> + Clone + CanBeZeroizedOnDrop>(key_seed: &SecretBox<T>) -> Self {
-        type KeySize = <XChaCha20Poly1305 as NewAead>::KeySize;
-        let key_bytes = kdf::<T, KeySize>(key_seed, None, None);
-        let key = SecretBox::new(*Key::from_slice(key_bytes.as_secret()));
-        let cipher = SecretBox::new(XChaCha20Poly1305::new(key.as_secret()));
-        Self { cipher }
+    /// Creates a DEM with the key derived from `key_seed` and bound to the serialized capsule,
+    /// so that a ciphertext cannot be decrypted with any other capsule,
+    /// even one resulting in the same key seed.
+    pub fn new<T: AsRef<[u8]> + Clone + CanBeZeroizedOnDrop>(
+        key_seed: &SecretBox<T>,
+        capsule_bytes: &[u8],
+    ) -> Self {
+        let info: Vec<u8> = b"DEM_KEY/"
+            .iter()
+            .chain(capsule_bytes.iter())
+            .cloned()
+            .collect();
+        Self {
+            cipher: make_cipher(key_seed, Some(&info)),
+            #[cfg(feature = "legacy-dem-kdf")]
+            legacy_key_seed: SecretBox::new(key_seed.as_secret().as_ref().to_vec()),
+        }
     }
 
     pub fn encrypt(
         &self,
         rng: &mut (impl CryptoRng + RngCore),
+        nonce_strategy: NonceStrategy,
         data: &[u8],
         authenticated_data: &[u8],
     ) -> Result<Box<[u8]>, EncryptionError> {
-        let mut nonce = GenericArray::<u8, NonceSize>::default();
-        rng.fill_bytes(&mut nonce);
-        let nonce = XNonce::from_slice(&nonce);
-        let payload = Payload {
-            msg: data,
-            aad: authenticated_data,
+        let mut result = Vec::new();
+        self.encrypt_into(rng, nonce_strategy, data, authenticated_data, &mut result)?;
+        Ok(result.into_boxed_slice())
+    }
+
+    /// Writes the header, the nonce, the encrypted data and the authentication tag into `out`,
+    /// replacing its previous contents.
+    pub fn encrypt_into(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        nonce_strategy: NonceStrategy,
+        data: &[u8],
+        authenticated_data: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), EncryptionError> {
+        let nonce = match nonce_strategy {
+            NonceStrategy::Random => {
+                let mut nonce = GenericArray::<u8, NonceSize>::default();
+                rng.fill_bytes(&mut nonce);
+                nonce
+            }
+            NonceStrategy::Derived => derived_nonce(authenticated_data),
         };
+        self.encrypt_into_with_nonce(&nonce, data, authenticated_data, out)
+    }
 
-        let mut result = nonce.to_vec();
-        let enc_data = self
-            .cipher
-            .as_secret()
-            .encrypt(nonce, payload)
-            .or(Err(EncryptionError::PlaintextTooLarge))?;
+    /// Same as [`encrypt_into`](`Self::encrypt_into`), but with an explicitly given nonce.
+    pub(crate) fn encrypt_into_with_nonce(
+        &self,
+        nonce: &GenericArray<u8, NonceSize>,
+        data: &[u8],
+        authenticated_data: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), EncryptionError> {
+        let prefix_size = CiphertextHeader::SIZE + <NonceSize as Unsigned>::to_usize();
+        let tag_size = <TagSize as Unsigned>::to_usize();
 
-        // Somewhat inefficient, but it doesn't seem that you can pass
-        // a mutable view of a vector to encrypt_in_place().
-        result.extend(enc_data);
-        Ok(result.into_boxed_slice())
+        out.clear();
+        out.reserve(prefix_size + data.len() + tag_size);
+        out.extend_from_slice(&CiphertextHeader::CURRENT.to_bytes());
+        out.extend_from_slice(nonce);
+        out.extend_from_slice(data);
+
+        let result = self.cipher.as_secret().encrypt_in_place_detached(
+            nonce,
+            authenticated_data,
+            &mut out[prefix_size..],
+        );
+
+        match result {
+            Ok(tag) => {
+                out.extend_from_slice(&tag);
+                Ok(())
+            }
+            Err(_) => {
+                // Do not leave the plaintext in the output buffer.
+                out.clear();
+                Err(EncryptionError::PlaintextTooLarge)
+            }
+        }
     }
 
     pub fn decrypt(
@@ -125,33 +371,91 @@ impl DEM {
         ciphertext: impl AsRef<[u8]>,
         authenticated_data: &[u8],
     ) -> Result<Box<[u8]>, DecryptionError> {
-        let nonce_size = <NonceSize as Unsigned>::to_usize();
-        let buf_size = ciphertext.as_ref().len();
+        let mut result = Vec::new();
+        self.decrypt_into(ciphertext.as_ref(), authenticated_data, &mut result)?;
+        Ok(result.into_boxed_slice())
+    }
+
+    /// Writes the decrypted data into `out`, replacing its previous contents.
+    /// On error, `out` is left empty.
+    pub fn decrypt_into(
+        &self,
+        ciphertext: &[u8],
+        authenticated_data: &[u8],
+        out: &mut Vec<u8>,
+    ) -> Result<(), DecryptionError> {
+        let error = match ciphertext_body(ciphertext)
+            .and_then(|body| decrypt_body(&self.cipher, body, authenticated_data, out))
+        {
+            Ok(()) => return Ok(()),
+            Err(error) => error,
+        };
 
-        if buf_size < nonce_size {
-            return Err(DecryptionError::CiphertextTooShort);
+        // Versions before 0.6 did not prepend the header, and derived the key
+        // from the key seed only (so the ciphertext is not bound to the capsule).
+        // The legacy key is only derived for ciphertexts that do not start with the current header,
+        // so a current ciphertext that failed to decrypt is never retried with it.
+        #[cfg(feature = "legacy-dem-kdf")]
+        if !ciphertext.starts_with(&CiphertextHeader::CURRENT.to_bytes()) {
+            let legacy_cipher = make_cipher(&self.legacy_key_seed, None);
+            if decrypt_body(&legacy_cipher, ciphertext, authenticated_data, out).is_ok() {
+                return Ok(());
+            }
         }
 
-        let nonce = XNonce::from_slice(&ciphertext.as_ref()[..nonce_size]);
-        let payload = Payload {
-            msg: &ciphertext.as_ref()[nonce_size..],
-            aad: authenticated_data,
-        };
-        self.cipher
-            .as_secret()
-            .decrypt(nonce, payload)
-            .map(|pt| pt.into_boxed_slice())
-            .or(Err(DecryptionError::AuthenticationFailed))
+        Err(error)
     }
 }
 
+/// Decrypts the ciphertext without the header (the nonce, the encrypted data, and the tag)
+/// into `out`, replacing its previous contents. On error, `out` is left empty.
+fn decrypt_body(
+    cipher: &SecretBox<Cipher>,
+    body: &[u8],
+    authenticated_data: &[u8],
+    out: &mut Vec<u8>,
+) -> Result<(), DecryptionError> {
+    let nonce_size = <NonceSize as Unsigned>::to_usize();
+    let tag_size = <TagSize as Unsigned>::to_usize();
+
+    out.clear();
+
+    if body.len() < nonce_size {
+        return Err(DecryptionError::CiphertextTooShort);
+    }
+    if body.len() < nonce_size + tag_size {
+        return Err(DecryptionError::AuthenticationFailed);
+    }
+
+    let (nonce, rest) = body.split_at(nonce_size);
+    let (data, tag) = rest.split_at(rest.len() - tag_size);
+
+    out.extend_from_slice(data);
+    let result = cipher.as_secret().decrypt_in_place_detached(
+        GenericArray::<u8, NonceSize>::from_slice(nonce),
+        authenticated_data,
+        out.as_mut_slice(),
+        GenericArray::<u8, TagSize>::from_slice(tag),
+    );
+
+    if result.is_err() {
+        out.clear();
+        return Err(DecryptionError::AuthenticationFailed);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
     use generic_array::GenericArray;
     use typenum::U32;
 
-    use super::kdf;
+    use rand_core::OsRng;
+
+    #[cfg(feature = "legacy-dem-kdf")]
+    use super::make_cipher;
+    use super::{kdf, CiphertextHeader, DecryptionError, NonceStrategy, DEM};
     use crate::curve::CurvePoint;
     use crate::secret_box::SecretBox;
     use crate::{RepresentableAsArray, SerializableToArray};
@@ -170,4 +474,104 @@ mod tests {
         let key_diff = kdf::<PointArray, U32>(&key_box, None, Some(&info[..]));
         assert_ne!(key.as_secret(), key_diff.as_secret());
     }
+
+    #[test]
+    fn test_key_bound_to_capsule() {
+        let key_seed = SecretBox::new(CurvePoint::generator().to_array());
+        let plaintext = b"peace at dawn";
+        let aad = b"authenticated data";
+
+        let dem = DEM::new(&key_seed, b"capsule 1");
+        let ciphertext = dem
+            .encrypt(&mut OsRng, NonceStrategy::Random, plaintext, aad)
+            .unwrap();
+        assert_eq!(&dem.decrypt(&ciphertext, aad).unwrap() as &[u8], plaintext);
+
+        // Same key seed and authenticated data, but a different capsule
+        let dem2 = DEM::new(&key_seed, b"capsule 2");
+        assert!(dem2.decrypt(&ciphertext, aad).is_err());
+    }
+
+    #[test]
+    fn test_header() {
+        let key_seed = SecretBox::new(CurvePoint::generator().to_array());
+        let plaintext = b"peace at dawn";
+        let aad = b"authenticated data";
+
+        let dem = DEM::new(&key_seed, b"capsule");
+        let ciphertext = dem
+            .encrypt(&mut OsRng, NonceStrategy::Random, plaintext, aad)
+            .unwrap();
+        assert_eq!(
+            CiphertextHeader::from_ciphertext(&ciphertext),
+            Ok(CiphertextHeader::CURRENT)
+        );
+        assert_eq!(
+            &ciphertext[..CiphertextHeader::SIZE],
+            &CiphertextHeader::CURRENT.to_bytes()
+        );
+
+        // A ciphertext created with a different curve
+        let mut other_curve = ciphertext.to_vec();
+        other_curve[1] = 3;
+        assert_eq!(
+            CiphertextHeader::from_ciphertext(&other_curve)
+                .unwrap()
+                .curve,
+            3
+        );
+        assert_eq!(
+            dem.decrypt(&other_curve, aad),
+            Err(DecryptionError::UnsupportedHeader)
+        );
+
+        // A ciphertext created with a different hash
+        let mut other_hash = ciphertext.to_vec();
+        other_hash[2] = 5;
+        assert_eq!(
+            CiphertextHeader::from_ciphertext(&other_hash).unwrap().hash,
+            5
+        );
+        assert_eq!(
+            dem.decrypt(&other_hash, aad),
+            Err(DecryptionError::UnsupportedHeader)
+        );
+
+        assert_eq!(
+            dem.decrypt(&ciphertext[..3], aad),
+            Err(DecryptionError::CiphertextTooShort)
+        );
+    }
+
+    #[cfg(feature = "legacy-dem-kdf")]
+    #[test]
+    fn test_legacy_kdf() {
+        let key_seed = SecretBox::new(CurvePoint::generator().to_array());
+        let plaintext = b"peace at dawn";
+        let aad = b"authenticated data";
+
+        let dem = DEM::new(&key_seed, b"capsule");
+
+        // A ciphertext created with the key derived from the key seed only
+        let legacy_dem = DEM {
+            cipher: make_cipher(&key_seed, None),
+            legacy_key_seed: dem.legacy_key_seed.clone(),
+        };
+        let ciphertext = legacy_dem
+            .encrypt(&mut OsRng, NonceStrategy::Random, plaintext, aad)
+            .unwrap();
+        // Legacy ciphertexts did not have the header
+        let ciphertext = &ciphertext[CiphertextHeader::SIZE..];
+
+        assert_eq!(&dem.decrypt(&ciphertext, aad).unwrap() as &[u8], plaintext);
+
+        // A current ciphertext created with the legacy key is not accepted
+        let ciphertext = legacy_dem
+            .encrypt(&mut OsRng, NonceStrategy::Random, plaintext, aad)
+            .unwrap();
+        assert_eq!(
+            dem.decrypt(&ciphertext, aad),
+            Err(DecryptionError::AuthenticationFailed)
+        );
+    }
 }
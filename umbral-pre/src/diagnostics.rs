@@ -0,0 +1,387 @@
+//! Lenient deserialization producing detailed reports,
+//! intended for forensic analysis of corrupted or tampered objects.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::curve::{CurvePoint, CurveScalar};
+use crate::key_frag::{KeyFrag, KeyFragID};
+use crate::keys::{PublicKey, Signature};
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
+    SizeMismatchError,
+};
+
+#[cfg(feature = "serde-support")]
+use serde::Serialize;
+
+/// The reason a field failed to deserialize.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
+pub enum FieldErrorKind {
+    /// The point has an invalid encoding tag (the first byte must be `0x02` or `0x03`).
+    InvalidPointEncoding,
+    /// The encoded coordinate does not correspond to a point on the curve.
+    PointNotOnCurve,
+    /// The scalar is greater or equal to the curve order.
+    ScalarOutOfRange,
+    /// The signature components are zero or out of range.
+    InvalidSignature,
+    /// Any other construction error.
+    InvalidValue(ConstructionError),
+}
+
+impl fmt::Display for FieldErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPointEncoding => write!(f, "invalid point encoding"),
+            Self::PointNotOnCurve => write!(f, "point is not on the curve"),
+            Self::ScalarOutOfRange => write!(f, "scalar is out of range"),
+            Self::InvalidSignature => write!(f, "invalid signature"),
+            Self::InvalidValue(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// A failure to deserialize a specific field of an object.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
+pub struct FieldError {
+    field: String,
+    offset: usize,
+    kind: FieldErrorKind,
+}
+
+impl FieldError {
+    /// The name of the field (with nested fields separated by dots).
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// The offset of the field in the serialized object.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The reason of the failure.
+    pub fn kind(&self) -> &FieldErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (at offset {}): {}",
+            self.field, self.offset, self.kind
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for FieldError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            FieldErrorKind::InvalidValue(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// A detailed report on a failed deserialization.
+///
+/// Unlike [`DeserializationError`], lists all the problems found:
+/// every field that could be extracted from the given bytestring is checked,
+/// even if the bytestring has a wrong length, or some of the previous fields are invalid.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
+pub struct DeserializationReport {
+    type_name: &'static str,
+    size_mismatch: Option<SizeMismatchError>,
+    field_errors: Vec<FieldError>,
+    object_error: Option<ConstructionError>,
+}
+
+impl DeserializationReport {
+    /// The name of the type that was being deserialized.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Returns the size mismatch error if the bytestring had an incorrect length.
+    pub fn size_mismatch(&self) -> Option<&SizeMismatchError> {
+        self.size_mismatch.as_ref()
+    }
+
+    /// Returns the errors for the fields that failed to deserialize.
+    pub fn field_errors(&self) -> &[FieldError] {
+        &self.field_errors
+    }
+
+    /// Returns the error of the object-level check
+    /// (performed if all the fields were deserialized successfully,
+    /// e.g. the self-verification of a [`Capsule`]).
+    pub fn object_error(&self) -> Option<&ConstructionError> {
+        self.object_error.as_ref()
+    }
+}
+
+impl fmt::Display for DeserializationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to deserialize a {} object", self.type_name)?;
+        if let Some(err) = &self.size_mismatch {
+            write!(f, "; {}", err)?;
+        }
+        for err in &self.field_errors {
+            write!(f, "; {}", err)?;
+        }
+        if let Some(err) = &self.object_error {
+            write!(f, "; {}", err)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for DeserializationReport {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.object_error
+            .as_ref()
+            .map(|err| err as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Walks over the fields of a serialized object, collecting errors.
+pub(crate) struct FieldChecker<'a> {
+    data: &'a [u8],
+    offset: usize,
+    prefix: String,
+    errors: Vec<FieldError>,
+}
+
+impl<'a> FieldChecker<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            offset: 0,
+            prefix: String::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn field<T: DeserializableFromArray>(
+        &mut self,
+        name: &str,
+        classify: impl FnOnce(&[u8], ConstructionError) -> FieldErrorKind,
+    ) {
+        let size = T::serialized_size();
+        let offset = self.offset;
+        self.offset += size;
+
+        // Not enough data for this field; this is reported as a size mismatch.
+        if offset + size > self.data.len() {
+            return;
+        }
+
+        let bytes = &self.data[offset..offset + size];
+        if let Err(DeserializationError::ConstructionFailure(err)) = T::from_bytes(bytes) {
+            self.errors.push(FieldError {
+                field: format!("{}{}", self.prefix, name),
+                offset,
+                kind: classify(bytes, err),
+            });
+        }
+    }
+
+    fn point(&mut self, name: &str) {
+        self.field::<CurvePoint>(name, |bytes, _err| match bytes[0] {
+            0x02 | 0x03 => FieldErrorKind::PointNotOnCurve,
+            _ => FieldErrorKind::InvalidPointEncoding,
+        })
+    }
+
+    fn scalar(&mut self, name: &str) {
+        self.field::<CurveScalar>(name, |_bytes, _err| FieldErrorKind::ScalarOutOfRange)
+    }
+
+    fn signature(&mut self, name: &str) {
+        self.field::<Signature>(name, |_bytes, _err| FieldErrorKind::InvalidSignature)
+    }
+
+    fn value<T: DeserializableFromArray>(&mut self, name: &str) {
+        self.field::<T>(name, |_bytes, err| FieldErrorKind::InvalidValue(err))
+    }
+
+    fn nested(&mut self, name: &str, check_fields: impl FnOnce(&mut Self)) {
+        let saved_prefix = self.prefix.clone();
+        self.prefix = format!("{}{}.", self.prefix, name);
+        check_fields(self);
+        self.prefix = saved_prefix;
+    }
+}
+
+fn from_bytes_diagnostic<T: DeserializableFromArray + HasTypeName>(
+    data: &[u8],
+    check_fields: impl FnOnce(&mut FieldChecker<'_>),
+) -> Result<T, DeserializationReport> {
+    let mut checker = FieldChecker::new(data);
+    check_fields(&mut checker);
+
+    let expected_size = T::serialized_size();
+    let size_mismatch = if data.len() != expected_size {
+        Some(SizeMismatchError::new(data.len(), expected_size))
+    } else {
+        None
+    };
+
+    let mut object_error = None;
+    if size_mismatch.is_none() && checker.errors.is_empty() {
+        match T::from_bytes(data) {
+            Ok(obj) => return Ok(obj),
+            Err(DeserializationError::ConstructionFailure(err)) => object_error = Some(err),
+            // Cannot happen since we checked the size above,
+            // but there is nothing to add to the report anyway.
+            Err(DeserializationError::SizeMismatch(_)) => {}
+        }
+    }
+
+    Err(DeserializationReport {
+        type_name: T::type_name(),
+        size_mismatch,
+        field_errors: checker.errors,
+        object_error,
+    })
+}
+
+impl PublicKey {
+    /// Attempts to deserialize the object, returning a detailed report on failure.
+    pub fn from_bytes_diagnostic(data: impl AsRef<[u8]>) -> Result<Self, DeserializationReport> {
+        from_bytes_diagnostic(data.as_ref(), |checker| checker.point("point"))
+    }
+}
+
+impl Signature {
+    /// Attempts to deserialize the object, returning a detailed report on failure.
+    pub fn from_bytes_diagnostic(data: impl AsRef<[u8]>) -> Result<Self, DeserializationReport> {
+        from_bytes_diagnostic(data.as_ref(), |checker| checker.signature("signature"))
+    }
+}
+
+impl Capsule {
+    /// Attempts to deserialize the object, returning a detailed report on failure.
+    pub fn from_bytes_diagnostic(data: impl AsRef<[u8]>) -> Result<Self, DeserializationReport> {
+        from_bytes_diagnostic(data.as_ref(), |checker| {
+            checker.point("point_e");
+            checker.point("point_v");
+            checker.scalar("signature");
+        })
+    }
+}
+
+impl KeyFrag {
+    /// Attempts to deserialize the object, returning a detailed report on failure.
+    pub fn from_bytes_diagnostic(data: impl AsRef<[u8]>) -> Result<Self, DeserializationReport> {
+        from_bytes_diagnostic(data.as_ref(), |checker| {
+            checker.value::<KeyFragID>("id");
+            checker.scalar("key");
+            checker.point("precursor");
+            checker.nested("proof", |checker| {
+                checker.point("commitment");
+                checker.signature("signature_for_proxy");
+                checker.signature("signature_for_receiver");
+                checker.value::<bool>("delegating_key_signed");
+                checker.value::<bool>("receiving_key_signed");
+            });
+        })
+    }
+}
+
+impl CapsuleFrag {
+    /// Attempts to deserialize the object, returning a detailed report on failure.
+    pub fn from_bytes_diagnostic(data: impl AsRef<[u8]>) -> Result<Self, DeserializationReport> {
+        from_bytes_diagnostic(data.as_ref(), |checker| {
+            checker.point("point_e1");
+            checker.point("point_v1");
+            checker.value::<KeyFragID>("kfrag_id");
+            checker.point("precursor");
+            checker.nested("proof", |checker| {
+                checker.point("point_e2");
+                checker.point("point_v2");
+                checker.point("kfrag_commitment");
+                checker.point("kfrag_pok");
+                checker.scalar("signature");
+                checker.signature("kfrag_signature");
+            });
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::FieldErrorKind;
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{encrypt, Capsule, CapsuleFrag, KeyFrag, SecretKey, SerializableToArray};
+
+    #[test]
+    fn test_valid() {
+        let Delegation {
+            capsule,
+            verified_kfrags: vkfrags,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+        let vcfrag = vcfrags[0].clone();
+
+        assert_eq!(
+            Capsule::from_bytes_diagnostic(&capsule.to_array()).unwrap(),
+            capsule
+        );
+        assert_eq!(
+            KeyFrag::from_bytes_diagnostic(&vkfrags[0].to_array()).unwrap(),
+            vkfrags[0].clone().unverify()
+        );
+        assert_eq!(
+            CapsuleFrag::from_bytes_diagnostic(&vcfrag.to_array()).unwrap(),
+            vcfrag.unverify()
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let delegating_pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&delegating_pk, b"peace at dawn").unwrap();
+        let capsule_bytes = capsule.to_array();
+
+        // Invalid encoding of the first point, and a truncated scalar
+        let mut data = capsule_bytes.to_vec();
+        data[0] = 0x05;
+        data.pop();
+        let report = Capsule::from_bytes_diagnostic(&data).unwrap_err();
+        assert!(report.size_mismatch().is_some());
+        assert_eq!(report.field_errors().len(), 1);
+        assert_eq!(report.field_errors()[0].field(), "point_e");
+        assert_eq!(report.field_errors()[0].offset(), 0);
+        assert_eq!(
+            report.field_errors()[0].kind(),
+            &FieldErrorKind::InvalidPointEncoding
+        );
+        assert!(report.object_error().is_none());
+
+        // Valid fields, but the capsule fails self-verification
+        let mut data = capsule_bytes.to_vec();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        let report = Capsule::from_bytes_diagnostic(&data).unwrap_err();
+        assert!(report.size_mismatch().is_none());
+        assert!(report.field_errors().is_empty());
+        assert!(report.object_error().is_some());
+    }
+}
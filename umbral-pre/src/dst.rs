@@ -0,0 +1,68 @@
+//! The domain separation tags used by the scheme.
+//!
+//! Every hash to a scalar or a digest starts with the 4-byte big-endian length
+//! of the tag, followed by the tag itself.
+//! The tags are listed here so that the hashes can be reproduced by independent implementations.
+//!
+//! The hashes binding key fragments and capsule fragments
+//! ([`POLYNOMIAL_ARG`], [`SHARED_SECRET`], [`CFRAG_VERIFICATION`], [`REENCRYPTION_NONCE`],
+//! and [`SIGNATURE`] for the key fragment signatures) also include the application context
+//! of the [`Parameters`](`crate::Parameters`) the fragments are used with.
+//! If the context is not empty, the length of the tag has the [`CONTEXT_FLAG`] bit set,
+//! and the tag is followed by the 4-byte big-endian length of the context and the context itself.
+//! The flag makes sure a hash with a context never coincides with a hash without one,
+//! and with the empty context the hashes are the same as in the previous versions.
+//! The other hashes (including [`CAPSULE_POINTS`] for capsules) do not depend on the context.
+
+/// The bit set in the length of the tag if it is followed by an application context.
+pub const CONTEXT_FLAG: u32 = 1 << 31;
+
+/// Hashing key fragment IDs to polynomial arguments (share indices).
+pub const POLYNOMIAL_ARG: &[u8] = b"POLYNOMIAL_ARG";
+
+/// Hashing the Diffie-Hellman exchange with the receiver to the shared secret.
+pub const SHARED_SECRET: &[u8] = b"SHARED_SECRET";
+
+/// Hashing the capsule points for the capsule signature.
+pub const CAPSULE_POINTS: &[u8] = b"CAPSULE_POINTS";
+
+/// Hashing the points of the reencryption proof to the challenge scalar.
+pub const CFRAG_VERIFICATION: &[u8] = b"CFRAG_VERIFICATION";
+
+/// Deriving the randomness of the reencryption proof from a caller-supplied nonce.
+pub const REENCRYPTION_NONCE: &[u8] = b"REENCRYPTION_NONCE";
+
+/// Hashing a label to the factor of a policy public key.
+pub const POLICY_KEY: &[u8] = b"POLICY_KEY";
+
+/// Deriving a deterministic DEM nonce from the authenticated data.
+pub const DEM_NONCE: &[u8] = b"DEM_NONCE";
+
+/// Deriving policy IDs.
+pub const POLICY_ID: &[u8] = b"POLICY_ID";
+
+/// Deriving source IDs.
+pub const SOURCE_ID: &[u8] = b"SOURCE_ID";
+
+/// The digest of the canonical encoding of an object.
+pub const CANONICAL_DIGEST: &[u8] = b"CANONICAL_DIGEST";
+
+/// Signing an audit log entry, and hashing it to chain the next entry to it.
+pub const AUDIT_ENTRY: &[u8] = b"AUDIT_ENTRY";
+
+/// Hashing a capsule and the resulting capsule fragment for the audit log.
+pub const AUDIT_REENCRYPTION: &[u8] = b"AUDIT_REENCRYPTION";
+
+/// Hashing a capsule, the capsule fragments and the ciphertext for the audit log.
+pub const AUDIT_COMBINATION: &[u8] = b"AUDIT_COMBINATION";
+
+/// Signing key fragments, used only with a non-empty application context
+/// (otherwise the signatures are over the plain hash of the message,
+/// and can be verified by standard ECDSA implementations).
+pub const SIGNATURE: &[u8] = b"SIGNATURE";
+
+/// The domain separation tag for hashing to curve the point `u` of the parameters.
+pub const PARAMETERS: &[u8] = b"PARAMETERS";
+
+/// The data hashed to curve to obtain the point `u` of the parameters.
+pub const POINT_U: &[u8] = b"POINT_U";
@@ -0,0 +1,163 @@
+//! Integrated encryption for a single recipient, without delegation.
+//!
+//! Uses the same KEM and DEM as [`encrypt`](`crate::encrypt`),
+//! but packs the capsule and the ciphertext into a single bytestring
+//! which can only be decrypted by the owner of the secret key.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+#[cfg(feature = "serde-support")]
+use serde::Serialize;
+
+use crate::capsule::Capsule;
+use crate::dem::{DecryptionError, EncryptionError, NonceStrategy, DEM};
+use crate::keys::{PublicKey, SecretKey};
+use crate::traits::{
+    DeserializableFromArray, DeserializationError, RepresentableAsArray, SerializableToArray,
+};
+
+/// Errors that can happen when decrypting a ciphertext produced by [`encrypt_for`].
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
+pub enum IntegratedDecryptionError {
+    /// The capsule at the start of the ciphertext could not be deserialized
+    /// (the ciphertext is malformed or was tampered with).
+    InvalidCapsule(DeserializationError),
+    /// An error when decrypting the ciphertext. See [`DecryptionError`] for the options.
+    OnDecryption(DecryptionError),
+}
+
+impl fmt::Display for IntegratedDecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCapsule(err) => write!(f, "Invalid capsule in the ciphertext: {}", err),
+            Self::OnDecryption(err) => write!(f, "Decryption error: {}", err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for IntegratedDecryptionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidCapsule(err) => Some(err),
+            Self::OnDecryption(err) => Some(err),
+        }
+    }
+}
+
+/// Encrypts the given plaintext for the owner of `pk`, using the given RNG.
+/// Returns the serialized capsule followed by the DEM ciphertext.
+///
+/// The result can be decrypted with [`decrypt_with`].
+pub fn encrypt_for_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    pk: &PublicKey,
+    plaintext: &[u8],
+) -> Result<Box<[u8]>, EncryptionError> {
+    let (capsule, key_seed) = Capsule::from_public_key(rng, pk);
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    let ciphertext = dem.encrypt(rng, NonceStrategy::Random, plaintext, &capsule_bytes)?;
+
+    let mut result = Vec::with_capacity(capsule_bytes.len() + ciphertext.len());
+    result.extend_from_slice(&capsule_bytes);
+    result.extend_from_slice(&ciphertext);
+    Ok(result.into_boxed_slice())
+}
+
+/// A synonym for [`encrypt_for_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn encrypt_for(pk: &PublicKey, plaintext: &[u8]) -> Result<Box<[u8]>, EncryptionError> {
+    encrypt_for_with_rng(&mut OsRng, pk, plaintext)
+}
+
+/// Decrypts a ciphertext produced by [`encrypt_for`] using the recipient's secret key.
+pub fn decrypt_with(
+    sk: &SecretKey,
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<Box<[u8]>, IntegratedDecryptionError> {
+    let ciphertext = ciphertext.as_ref();
+    let capsule_size = Capsule::serialized_size();
+    if ciphertext.len() < capsule_size {
+        return Err(IntegratedDecryptionError::OnDecryption(
+            DecryptionError::CiphertextTooShort,
+        ));
+    }
+
+    let (capsule_bytes, dem_ciphertext) = ciphertext.split_at(capsule_size);
+    let capsule =
+        Capsule::from_bytes(capsule_bytes).map_err(IntegratedDecryptionError::InvalidCapsule)?;
+
+    let key_seed = capsule.open_original(sk);
+    let dem = DEM::new(&key_seed, capsule_bytes);
+    dem.decrypt(dem_ciphertext, capsule_bytes)
+        .map_err(IntegratedDecryptionError::OnDecryption)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{decrypt_with, encrypt_for, IntegratedDecryptionError};
+    use crate::{DecryptionError, SecretKey};
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let sk = SecretKey::random();
+        let pk = sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let ciphertext = encrypt_for(&pk, plaintext).unwrap();
+
+        let plaintext_back = decrypt_with(&sk, &ciphertext).unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        // Wrong key
+        let result = decrypt_with(&SecretKey::random(), &ciphertext);
+        assert_eq!(
+            result,
+            Err(IntegratedDecryptionError::OnDecryption(
+                DecryptionError::AuthenticationFailed
+            ))
+        );
+
+        // Modified ciphertext
+        let mut modified = ciphertext.to_vec();
+        let last = modified.len() - 1;
+        modified[last] ^= 1;
+        let result = decrypt_with(&sk, &modified);
+        assert_eq!(
+            result,
+            Err(IntegratedDecryptionError::OnDecryption(
+                DecryptionError::AuthenticationFailed
+            ))
+        );
+
+        // Modified capsule
+        let mut modified = ciphertext.to_vec();
+        modified[0] = 0xff;
+        let result = decrypt_with(&sk, &modified);
+        assert!(matches!(
+            result,
+            Err(IntegratedDecryptionError::InvalidCapsule(_))
+        ));
+
+        // Truncated ciphertext
+        let result = decrypt_with(&sk, &ciphertext[..10]);
+        assert_eq!(
+            result,
+            Err(IntegratedDecryptionError::OnDecryption(
+                DecryptionError::CiphertextTooShort
+            ))
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! EIP-712 typed structured data hashing of the public objects,
+//! allowing them to be signed with Ethereum wallets and verified by contracts.
+//!
+//! Curve points are represented as `Point(uint256 x,uint256 y)` in the affine form,
+//! and ECDSA signatures as `Signature(uint256 r,uint256 s)`.
+//! The fields of the objects follow their [`Layout`](`crate::format::Layout`),
+//! with the proof fields of [`CapsuleFrag`] flattened into the main struct.
+//! The type of each object is available as [`Eip712Struct::EIP712_TYPE`].
+
+use elliptic_curve::sec1::ToEncodedPoint;
+use sha3::{Digest, Keccak256};
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::certificate::DelegationCertificate;
+use crate::curve::CurvePoint;
+use crate::format::{FieldEncoding, Layout, CAPSULE, CAPSULE_FRAG};
+use crate::keys::PublicKey;
+use crate::traits::SerializableToArray;
+
+const WORD_SIZE: usize = 32;
+
+const POINT_TYPE: &str = "Point(uint256 x,uint256 y)";
+const SIGNATURE_TYPE: &str = "Signature(uint256 r,uint256 s)";
+const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&Keccak256::digest(data));
+    result
+}
+
+/// Hashes the concatenation of the parts, without allocating.
+fn keccak256_parts(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&hasher.finalize());
+    result
+}
+
+fn uint_word(value: u64) -> [u8; WORD_SIZE] {
+    let mut word = [0u8; WORD_SIZE];
+    word[WORD_SIZE - 8..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn point_hash_from_bytes(bytes: &[u8]) -> [u8; 32] {
+    // Only called on the canonical serialization, which contains valid points.
+    let point = CurvePoint::from_sec1_bytes(bytes).unwrap_or_default();
+    let uncompressed = point.to_affine_point().to_encoded_point(false);
+    keccak256_parts(&[
+        &keccak256(POINT_TYPE.as_bytes()),
+        &uncompressed.as_bytes()[1..],
+    ])
+}
+
+fn public_key_hash(pk: &PublicKey) -> [u8; 32] {
+    point_hash_from_bytes(&pk.to_array())
+}
+
+/// Hashes an object given its type and its canonical serialization.
+fn hash_layout(type_string: &str, layout: &Layout, bytes: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(keccak256(type_string.as_bytes()));
+    let mut offset = 0;
+    for field in layout.fields {
+        let size = field.encoding.size();
+        let field_bytes = &bytes[offset..offset + size];
+        offset += size;
+        match field.encoding {
+            FieldEncoding::Point => hasher.update(point_hash_from_bytes(field_bytes)),
+            FieldEncoding::Scalar | FieldEncoding::Identifier => hasher.update(field_bytes),
+            FieldEncoding::Signature => hasher.update(keccak256_parts(&[
+                &keccak256(SIGNATURE_TYPE.as_bytes()),
+                field_bytes,
+            ])),
+            FieldEncoding::Bool => hasher.update(uint_word(u64::from(field_bytes[0]))),
+        }
+    }
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&hasher.finalize());
+    result
+}
+
+/// The EIP-712 domain, identifying the application, the chain, and the contract
+/// the signatures are intended for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Eip712Domain<'a> {
+    /// The name of the signing domain (e.g. the application name).
+    pub name: &'a str,
+    /// The current major version of the signing domain.
+    pub version: &'a str,
+    /// The EIP-155 chain ID.
+    pub chain_id: u64,
+    /// The address of the contract that will verify the signature.
+    pub verifying_contract: [u8; 20],
+}
+
+impl<'a> Eip712Domain<'a> {
+    /// Returns the domain separator (the hash of the domain struct).
+    pub fn separator(&self) -> [u8; 32] {
+        let mut contract = [0u8; WORD_SIZE];
+        contract[WORD_SIZE - 20..].copy_from_slice(&self.verifying_contract);
+        keccak256_parts(&[
+            &keccak256(DOMAIN_TYPE.as_bytes()),
+            &keccak256(self.name.as_bytes()),
+            &keccak256(self.version.as_bytes()),
+            &uint_word(self.chain_id),
+            &contract,
+        ])
+    }
+}
+
+/// A trait for objects that can be hashed as EIP-712 structs.
+pub trait Eip712Struct {
+    /// The encoded type of the struct (including the referenced types),
+    /// as used in the type hash.
+    const EIP712_TYPE: &'static str;
+
+    /// Returns `hashStruct` of the object.
+    fn eip712_struct_hash(&self) -> [u8; 32];
+
+    /// Returns the hash to be signed by a wallet (as in `eth_signTypedData`)
+    /// for the object in the given domain.
+    fn eip712_signing_hash(&self, domain: &Eip712Domain<'_>) -> [u8; 32] {
+        keccak256_parts(&[b"\x19\x01", &domain.separator(), &self.eip712_struct_hash()])
+    }
+}
+
+impl Eip712Struct for Capsule {
+    const EIP712_TYPE: &'static str =
+        "Capsule(Point pointE,Point pointV,uint256 signature)Point(uint256 x,uint256 y)";
+
+    fn eip712_struct_hash(&self) -> [u8; 32] {
+        hash_layout(Self::EIP712_TYPE, &CAPSULE, &self.to_array())
+    }
+}
+
+impl Eip712Struct for CapsuleFrag {
+    const EIP712_TYPE: &'static str = concat!(
+        "CapsuleFrag(Point pointE1,Point pointV1,bytes32 kfragId,Point precursor,",
+        "Point pointE2,Point pointV2,Point kfragCommitment,Point kfragPok,",
+        "uint256 signature,Signature kfragSignature)",
+        "Point(uint256 x,uint256 y)Signature(uint256 r,uint256 s)"
+    );
+
+    fn eip712_struct_hash(&self) -> [u8; 32] {
+        hash_layout(Self::EIP712_TYPE, &CAPSULE_FRAG, &self.to_array())
+    }
+}
+
+/// The signed contents of the certificate (without its own signature).
+impl Eip712Struct for DelegationCertificate {
+    const EIP712_TYPE: &'static str = concat!(
+        "Delegation(Point delegatingKey,Point receivingKey,Point verifyingKey,",
+        "bytes16 policyId,uint64 threshold,uint64 notBefore,uint64 notAfter)",
+        "Point(uint256 x,uint256 y)"
+    );
+
+    fn eip712_struct_hash(&self) -> [u8; 32] {
+        let mut policy_id = [0u8; WORD_SIZE];
+        let policy_id_bytes = self.policy_id().to_array();
+        policy_id[..policy_id_bytes.len()].copy_from_slice(&policy_id_bytes);
+        keccak256_parts(&[
+            &keccak256(Self::EIP712_TYPE.as_bytes()),
+            &public_key_hash(self.delegating_key()),
+            &public_key_hash(self.receiving_key()),
+            &public_key_hash(self.verifying_key()),
+            &policy_id,
+            &uint_word(self.threshold()),
+            &uint_word(self.not_before()),
+            &uint_word(self.not_after()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{keccak256, Eip712Domain, Eip712Struct};
+    use crate::format::{CAPSULE, CAPSULE_FRAG};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{encrypt, Capsule, CapsuleFrag, DelegationCertificate};
+
+    #[cfg(not(any(
+        feature = "p256",
+        feature = "hash-sha3",
+        feature = "hash-blake3",
+        feature = "hash-keccak"
+    )))]
+    use crate::DeserializableFromArray;
+
+    #[test]
+    fn test_keccak256() {
+        assert_eq!(
+            hex::encode(keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+    }
+
+    #[test]
+    fn test_eip712_domain() {
+        // The domain from the EIP-712 example ("Ether Mail")
+        let domain = Eip712Domain {
+            name: "Ether Mail",
+            version: "1",
+            chain_id: 1,
+            verifying_contract: [0xcc; 20],
+        };
+        assert_eq!(
+            hex::encode(domain.separator()),
+            "f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f"
+        );
+    }
+
+    #[test]
+    fn test_struct_types() {
+        // Every field of the layout is a member of the struct
+        let members = |type_string: &str| {
+            let start = type_string.find('(').unwrap();
+            let end = type_string.find(')').unwrap();
+            type_string[start + 1..end].split(',').count()
+        };
+        assert_eq!(members(Capsule::EIP712_TYPE), CAPSULE.fields.len());
+        assert_eq!(members(CapsuleFrag::EIP712_TYPE), CAPSULE_FRAG.fields.len());
+    }
+
+    // The expected values were computed independently of this implementation.
+    // The capsule fragment consists of the points `k * G` for `k = 1..7`,
+    // the ID `0x11...11`, the scalar `0x22...22`, and the signature `(r, s) = (3, 4)`;
+    // the certificate consists of the keys `G`, `2 * G`, `3 * G`, the policy ID `0x33...33`,
+    // the threshold 2, the validity period from 100 to 200, and the signature `(5, 6)`.
+    // They are only valid for secp256k1 and SHA-256, since the capsule check depends on the hash.
+    #[cfg(not(any(
+        feature = "p256",
+        feature = "hash-sha3",
+        feature = "hash-blake3",
+        feature = "hash-keccak"
+    )))]
+    #[test]
+    fn test_known_answers() {
+        let capsule = Capsule::from_bytes(
+            hex::decode(concat!(
+                "031479b41439327ad35a094058e709cbab089d6488fbe6a69dedeebe0973eb08e5",
+                "024523e5667020cdafcc8951a807adbe3064c64e49ba122bf538b1002f51eb1d1e",
+                "f93e73e5eaf552648ddc6960481cee98723738bb4ddb14daebb5549acc8cd0c0",
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(capsule.eip712_struct_hash()),
+            "705f014240ff34f2dd45024ce68e09428350856cbb53ca77561a7edc2741c1bf"
+        );
+
+        let domain = Eip712Domain {
+            name: "Ether Mail",
+            version: "1",
+            chain_id: 1,
+            verifying_contract: [0xcc; 20],
+        };
+        assert_eq!(
+            hex::encode(capsule.eip712_signing_hash(&domain)),
+            "c07b3b2f6400adbd7855859fdc8ac740a24e57eef8ee3f43ea922166e6388a28"
+        );
+
+        let cfrag = CapsuleFrag::from_bytes(
+            hex::decode(concat!(
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+                "1111111111111111111111111111111111111111111111111111111111111111",
+                "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+                "02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13",
+                "022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4",
+                "03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556",
+                "025cbdf0646e5db4eaa398f365f2ea7a0e3d419b7e0330e39ce92bddedcac4f9bc",
+                "2222222222222222222222222222222222222222222222222222222222222222",
+                "0000000000000000000000000000000000000000000000000000000000000003",
+                "0000000000000000000000000000000000000000000000000000000000000004",
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(cfrag.eip712_struct_hash()),
+            "5e2bf5aefe0dc1c5cce0780675ba349315fb69d56281320c4d1de07f5b2c0b2b"
+        );
+
+        let certificate = DelegationCertificate::from_bytes(
+            hex::decode(concat!(
+                "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+                "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+                "33333333333333333333333333333333",
+                "0000000000000002",
+                "0000000000000064",
+                "00000000000000c8",
+                "0000000000000000000000000000000000000000000000000000000000000005",
+                "0000000000000000000000000000000000000000000000000000000000000006",
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            hex::encode(certificate.eip712_struct_hash()),
+            "d4d6befa04daf1be70f70bee1a977e0aadb6a77ef7b999a994f4712cf938f606"
+        );
+    }
+
+    #[test]
+    fn test_hashes() {
+        let Delegation {
+            delegating_pk,
+            signer,
+            receiving_pk,
+            capsule: capsule1,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+
+        let (capsule2, _ciphertext) = encrypt(&delegating_pk, b"peace at dawn").unwrap();
+        assert_ne!(capsule1.eip712_struct_hash(), capsule2.eip712_struct_hash());
+
+        let cfrag1 = vcfrags[0].clone().unverify();
+        let cfrag2 = vcfrags[1].clone().unverify();
+        assert_ne!(cfrag1.eip712_struct_hash(), cfrag2.eip712_struct_hash());
+
+        let certificate =
+            DelegationCertificate::new(&signer, &delegating_pk, &receiving_pk, b"label", 2, 0, 10);
+
+        // The signing hash depends on the domain
+        let domain = Eip712Domain {
+            name: "Umbral",
+            version: "1",
+            chain_id: 1,
+            verifying_contract: [0x11; 20],
+        };
+        let other_domain = Eip712Domain {
+            chain_id: 137,
+            ..domain
+        };
+        assert_ne!(
+            certificate.eip712_signing_hash(&domain),
+            certificate.eip712_signing_hash(&other_domain)
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! A self-contained encrypted container, the recommended way to encrypt data
+//! when no specific format is required.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "serde-support")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::VerifiedCapsuleFrag;
+use crate::curve::CurvePoint;
+use crate::curve::{CurveType, UmbralCurve};
+use crate::dem::{
+    ciphertext_nonce, derived_nonce, CiphertextHeader, DecryptionError, EncryptionError, DEM,
+    DEM_ID,
+};
+use crate::hashing::HASH_ID;
+use crate::keys::PublicKey;
+use crate::pre::ReencryptionError;
+use crate::roles::{AsDelegatingKey, AsReceivingKey};
+use crate::traits::{
+    fmt_public, ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
+    RepresentableAsArray, SerializableToArray,
+};
+
+#[cfg(feature = "serde-support")]
+use crate::serde::{serde_deserialize_bytes, serde_serialize_bytes, Representation};
+
+/// The version of the envelope format produced by this library.
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// The identifier of the cipher suite used in the envelopes produced by this library,
+/// combining the curve of the KEM (`1` for secp256k1, `2` for P-256),
+/// the hash (`1` for SHA-256, `2` for SHA3-256, `3` for BLAKE3, `4` for Keccak-256)
+/// and the DEM (`1` for XChaCha20-Poly1305, `2` for AES-256-GCM)
+/// as `curve + 2 * (hash - 1) + 8 * (dem - 1)`.
+///
+/// With the default features this is `1`, with `p256` it is `2`,
+/// and with `fips-algorithms` (P-256, SHA-256 and AES-256-GCM) it is `10`.
+pub const ENVELOPE_SUITE: u8 = CurveType::ID + 2 * (HASH_ID - 1) + 8 * (DEM_ID - 1);
+
+/// The identifier of the compact envelope profile
+/// (see [`Envelope::to_compact_bytes`]).
+/// Occupies the high four bits of the first byte,
+/// so it cannot be confused with [`ENVELOPE_VERSION`].
+/// Equal to the cipher suite identifier, since the compact form does not include the suite.
+pub const ENVELOPE_COMPACT_PROFILE: u8 = ENVELOPE_SUITE << 4;
+
+const HEADER_SIZE: usize = 2;
+
+const COMPACT_PROFILE_MASK: u8 = 0xf0;
+
+/// An encrypted message along with everything needed to decrypt it
+/// (except the key), created by [`seal`].
+///
+/// The serialized form is the format version, the cipher suite identifier,
+/// the capsule, and the DEM ciphertext (the header, the nonce, the encrypted data and the tag).
+/// The header and the capsule are authenticated along with the user-provided
+/// associated data.
+///
+/// The envelope can be opened by the owner of the public key it was sealed for
+/// with [`unseal`], or, if the access was delegated,
+/// by the receiver using the fragments of its [`capsule`](`Self::capsule`)
+/// with [`unseal_reencrypted`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Envelope {
+    capsule: Capsule,
+    ciphertext: Box<[u8]>,
+}
+
+impl Envelope {
+    /// Returns the capsule (to be reencrypted by proxies if the access was delegated).
+    pub fn capsule(&self) -> &Capsule {
+        &self.capsule
+    }
+
+    /// Serializes the envelope.
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        let capsule_bytes = self.capsule.to_array();
+        let mut result =
+            Vec::with_capacity(HEADER_SIZE + capsule_bytes.len() + self.ciphertext.len());
+        result.extend_from_slice(&header());
+        result.extend_from_slice(&capsule_bytes);
+        result.extend_from_slice(&self.ciphertext);
+        result.into_boxed_slice()
+    }
+
+    /// Restores the envelope from the serialized form.
+    pub fn from_bytes(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let data = data.as_ref();
+        let capsule_size = Capsule::serialized_size();
+        if data.len() < HEADER_SIZE + capsule_size {
+            return Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new("Envelope", "The bytestring is too short"),
+            ));
+        }
+
+        let (header_bytes, rest) = data.split_at(HEADER_SIZE);
+        if header_bytes[0] != ENVELOPE_VERSION {
+            return Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new("Envelope", "Unsupported format version"),
+            ));
+        }
+        if header_bytes[1] != ENVELOPE_SUITE {
+            return Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new("Envelope", "Unsupported cipher suite"),
+            ));
+        }
+
+        let (capsule_bytes, ciphertext) = rest.split_at(capsule_size);
+        let capsule = Capsule::from_bytes(capsule_bytes)?;
+        Ok(Self {
+            capsule,
+            ciphertext: ciphertext.into(),
+        })
+    }
+
+    /// Serializes the envelope in the compact profile, intended for constrained links.
+    ///
+    /// Everything that can be recovered from the deployment context is stripped:
+    /// the version and suite identifiers are replaced by [`ENVELOPE_COMPACT_PROFILE`]
+    /// (the format version and the cipher suite are implied by it),
+    /// the parity bits of the capsule points are packed into the same byte,
+    /// and the DEM ciphertext header (also implied by the profile) and the nonce
+    /// (derived from the capsule) are omitted.
+    ///
+    /// Returns `None` if the envelope was created by a different implementation
+    /// and its nonce cannot be recovered.
+    pub fn to_compact_bytes(&self) -> Option<Box<[u8]>> {
+        let capsule_bytes = self.capsule.to_array();
+        let nonce = ciphertext_nonce(&self.ciphertext).ok()?;
+        if nonce != derived_nonce(&capsule_bytes).as_slice() {
+            return None;
+        }
+
+        let point_size = CurvePoint::serialized_size();
+        let (point_e, rest) = capsule_bytes.split_at(point_size);
+        let (point_v, signature) = rest.split_at(point_size);
+
+        let prefix_size = CiphertextHeader::SIZE + nonce.len();
+
+        let mut result =
+            Vec::with_capacity(capsule_bytes.len() - 1 + self.ciphertext.len() - prefix_size);
+        result.push(ENVELOPE_COMPACT_PROFILE | (point_e[0] & 1) | ((point_v[0] & 1) << 1));
+        result.extend_from_slice(&point_e[1..]);
+        result.extend_from_slice(&point_v[1..]);
+        result.extend_from_slice(signature);
+        result.extend_from_slice(&self.ciphertext[prefix_size..]);
+        Some(result.into_boxed_slice())
+    }
+
+    /// Restores the envelope from the compact profile serialization
+    /// produced by [`to_compact_bytes`](`Self::to_compact_bytes`).
+    ///
+    /// Fails if the data is not in the compact profile;
+    /// in particular, the bytes produced by [`to_bytes`](`Self::to_bytes`) are rejected.
+    pub fn from_compact_bytes(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let data = data.as_ref();
+        let point_size = CurvePoint::serialized_size();
+        let capsule_size = Capsule::serialized_size();
+
+        // The profile byte replaces the prefixes of the two points.
+        if data.len() < capsule_size - 1 {
+            return Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new("Envelope", "The bytestring is too short"),
+            ));
+        }
+
+        let (profile, rest) = data.split_at(1);
+        let profile = profile[0];
+        if profile & COMPACT_PROFILE_MASK != ENVELOPE_COMPACT_PROFILE || profile & 0x0c != 0 {
+            return Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new("Envelope", "Unknown serialization profile"),
+            ));
+        }
+
+        let (x_e, rest) = rest.split_at(point_size - 1);
+        let (x_v, rest) = rest.split_at(point_size - 1);
+        let (signature, ciphertext) = rest.split_at(capsule_size - 2 * point_size);
+
+        let mut capsule_bytes = Vec::with_capacity(capsule_size);
+        capsule_bytes.push(0x02 | (profile & 1));
+        capsule_bytes.extend_from_slice(x_e);
+        capsule_bytes.push(0x02 | ((profile >> 1) & 1));
+        capsule_bytes.extend_from_slice(x_v);
+        capsule_bytes.extend_from_slice(signature);
+        let capsule = Capsule::from_bytes(&capsule_bytes)?;
+
+        let nonce = derived_nonce(&capsule_bytes);
+        let mut full_ciphertext =
+            Vec::with_capacity(CiphertextHeader::SIZE + nonce.len() + ciphertext.len());
+        full_ciphertext.extend_from_slice(&CiphertextHeader::CURRENT.to_bytes());
+        full_ciphertext.extend_from_slice(&nonce);
+        full_ciphertext.extend_from_slice(ciphertext);
+
+        Ok(Self {
+            capsule,
+            ciphertext: full_ciphertext.into_boxed_slice(),
+        })
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for Envelope {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize_bytes(&self.to_bytes(), serializer, Representation::Base64)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for Envelope {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = serde_deserialize_bytes(deserializer, Representation::Base64, "Envelope")?;
+        Self::from_bytes(bytes).map_err(de::Error::custom)
+    }
+}
+
+impl HasTypeName for Envelope {
+    fn type_name() -> &'static str {
+        "Envelope"
+    }
+}
+
+impl fmt::Display for Envelope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(&self.capsule, f)
+    }
+}
+
+fn header() -> [u8; HEADER_SIZE] {
+    [ENVELOPE_VERSION, ENVELOPE_SUITE]
+}
+
+fn envelope_aad(capsule_bytes: &[u8], aad: &[u8]) -> Box<[u8]> {
+    let mut result = Vec::with_capacity(HEADER_SIZE + capsule_bytes.len() + aad.len());
+    result.extend_from_slice(&header());
+    result.extend_from_slice(capsule_bytes);
+    result.extend_from_slice(aad);
+    result.into_boxed_slice()
+}
+
+/// Encrypts `plaintext` for the owner of `delegating_pk`, using the given RNG.
+///
+/// `aad` is the associated data that is authenticated, but not included in the envelope;
+/// the same data must be provided on decryption.
+pub fn seal_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    delegating_pk: &PublicKey,
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<Envelope, EncryptionError> {
+    let (capsule, key_seed) = Capsule::from_public_key(rng, delegating_pk);
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    // The nonce is derived from the capsule, so that it can be elided in the compact form.
+    let mut ciphertext = Vec::new();
+    dem.encrypt_into_with_nonce(
+        &derived_nonce(&capsule_bytes),
+        plaintext,
+        &envelope_aad(&capsule_bytes, aad),
+        &mut ciphertext,
+    )?;
+    Ok(Envelope {
+        capsule,
+        ciphertext: ciphertext.into_boxed_slice(),
+    })
+}
+
+/// A synonym for [`seal_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn seal(
+    delegating_pk: &PublicKey,
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<Envelope, EncryptionError> {
+    seal_with_rng(&mut OsRng, delegating_pk, plaintext, aad)
+}
+
+/// Decrypts an envelope created by [`seal`] using the secret key it was sealed for.
+pub fn unseal(
+    delegating_sk: &impl AsDelegatingKey,
+    envelope: &Envelope,
+    aad: &[u8],
+) -> Result<Box<[u8]>, DecryptionError> {
+    let key_seed = envelope
+        .capsule
+        .open_original(delegating_sk.as_delegating_key());
+    let capsule_bytes = envelope.capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.decrypt(&envelope.ciphertext, &envelope_aad(&capsule_bytes, aad))
+}
+
+/// Decrypts an envelope created by [`seal`] using the capsule fragments
+/// obtained by reencrypting [`Envelope::capsule`]
+/// (see [`decrypt_reencrypted`](`crate::decrypt_reencrypted`) for details).
+pub fn unseal_reencrypted(
+    receiving_sk: &impl AsReceivingKey,
+    delegating_pk: &PublicKey,
+    envelope: &Envelope,
+    verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+    aad: &[u8],
+) -> Result<Box<[u8]>, ReencryptionError> {
+    let cfrags: Vec<_> = verified_cfrags
+        .into_iter()
+        .map(|vcfrag| vcfrag.unverify())
+        .collect();
+    let key_seed = envelope
+        .capsule
+        .open_reencrypted(receiving_sk.as_receiving_key(), delegating_pk, &cfrags)
+        .map_err(ReencryptionError::OnOpen)?;
+    let capsule_bytes = envelope.capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.decrypt(&envelope.ciphertext, &envelope_aad(&capsule_bytes, aad))
+        .map_err(ReencryptionError::OnDecryption)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use typenum::Unsigned;
+
+    use super::{seal, unseal, unseal_reencrypted, Envelope, ENVELOPE_SUITE};
+    use crate::dem::{CiphertextHeader, NonceSize};
+    use crate::{
+        generate_kfrags, reencrypt, Capsule, DecryptionError, DeserializationError,
+        RepresentableAsArray, SecretKey, Signer,
+    };
+
+    #[test]
+    fn test_seal_unseal() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let aad = b"context";
+        let envelope = seal(&delegating_pk, plaintext, aad).unwrap();
+
+        let envelope_bytes = envelope.to_bytes();
+        let envelope_back = Envelope::from_bytes(&envelope_bytes).unwrap();
+        assert_eq!(envelope_back, envelope);
+
+        let plaintext_back = unseal(&delegating_sk, &envelope_back, aad).unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        // The associated data is authenticated
+        assert_eq!(
+            unseal(&delegating_sk, &envelope_back, b"other context"),
+            Err(DecryptionError::AuthenticationFailed)
+        );
+
+        // Delegated decryption
+        let signer = Signer::new(SecretKey::random());
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+        let vcfrags = vkfrags[0..2]
+            .iter()
+            .map(|vkfrag| reencrypt(envelope.capsule(), vkfrag.clone()));
+        let plaintext_bob =
+            unseal_reencrypted(&receiving_sk, &delegating_pk, &envelope, vcfrags, aad).unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_serde_serialization() {
+        let delegating_pk = SecretKey::random().public_key();
+        let envelope = seal(&delegating_pk, b"peace at dawn", b"").unwrap();
+
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert!(json.contains(&base64::encode(envelope.to_bytes())));
+        let envelope_back: Envelope = serde_json::from_str(&json).unwrap();
+        assert_eq!(envelope_back, envelope);
+
+        let packed = rmp_serde::to_vec(&envelope).unwrap();
+        let envelope_back: Envelope = rmp_serde::from_read(&*packed).unwrap();
+        assert_eq!(envelope_back, envelope);
+    }
+
+    #[test]
+    fn test_compact_profile() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let plaintext = b"peace at dawn";
+        let envelope = seal(&delegating_pk, plaintext, b"").unwrap();
+
+        let full_bytes = envelope.to_bytes();
+        let compact_bytes = envelope.to_compact_bytes().unwrap();
+        // The headers, one of the point prefixes and the nonce are stripped
+        let nonce_size = <NonceSize as Unsigned>::to_usize();
+        assert_eq!(
+            compact_bytes.len(),
+            full_bytes.len() - 2 - 1 - CiphertextHeader::SIZE - nonce_size
+        );
+
+        let envelope_back = Envelope::from_compact_bytes(&compact_bytes).unwrap();
+        assert_eq!(envelope_back, envelope);
+        let plaintext_back = unseal(&delegating_sk, &envelope_back, b"").unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        // The two encodings cannot be confused
+        assert!(Envelope::from_bytes(&compact_bytes).is_err());
+        assert!(Envelope::from_compact_bytes(&full_bytes).is_err());
+
+        // A nonce that cannot be recovered
+        let mut other_nonce = full_bytes.to_vec();
+        other_nonce[2 + Capsule::serialized_size()] ^= 1;
+        let other_envelope = Envelope::from_bytes(&other_nonce).unwrap();
+        assert!(other_envelope.to_compact_bytes().is_none());
+    }
+
+    #[test]
+    fn test_envelope_header() {
+        let delegating_pk = SecretKey::random().public_key();
+        let envelope = seal(&delegating_pk, b"peace at dawn", b"").unwrap();
+        let envelope_bytes = envelope.to_bytes();
+
+        let mut wrong_version = envelope_bytes.to_vec();
+        wrong_version[0] = 2;
+        assert!(matches!(
+            Envelope::from_bytes(&wrong_version),
+            Err(DeserializationError::ConstructionFailure(_))
+        ));
+
+        let mut wrong_suite = envelope_bytes.to_vec();
+        wrong_suite[1] = ENVELOPE_SUITE ^ 0x04;
+        assert!(matches!(
+            Envelope::from_bytes(&wrong_suite),
+            Err(DeserializationError::ConstructionFailure(_))
+        ));
+
+        assert!(Envelope::from_bytes(&envelope_bytes[..10]).is_err());
+    }
+}
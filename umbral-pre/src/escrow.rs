@@ -0,0 +1,599 @@
+//! Escrow mode: recombining key fragments into a new one for a replacement proxy.
+//!
+//! **Warning:** this mode deliberately weakens the threshold guarantees of Umbral,
+//! and is only available with the `escrow` feature.
+//! Normally, the re-encryption capability can only be extended by the delegating party.
+//! In escrow mode, `threshold` holders of key fragments, coordinated by the receiving party,
+//! create a new key fragment for a replacement proxy without the delegating party's involvement.
+//! The new fragment is a regular share of the same delegation
+//! (it cannot be used to decrypt anything on its own, and neither the delegating
+//! nor the receiving secret key is revealed to anyone), but:
+//!
+//! * it is not signed by the delegating party, so it can only be used as a [`CompactKeyFrag`]
+//!   with [`reencrypt_compact`](`crate::reencrypt_compact`), and the resulting capsule fragments
+//!   can only be decrypted with
+//!   [`decrypt_reencrypted_unproven`](`crate::decrypt_reencrypted_unproven`);
+//! * the holders cannot check that the new fragment is going to a legitimate proxy,
+//!   so they must trust the receiving party to coordinate the process honestly;
+//! * the receiving party colluding with the replacement proxy learns the key fragments
+//!   of all the participating holders, which is enough to recover the delegating secret key
+//!   (the same as the receiving party colluding with `threshold` proxies in regular Umbral,
+//!   but here a single proxy suffices).
+//!
+//! The process has three steps:
+//!
+//! 1. each participating holder publishes an [`EscrowAnnouncement`] for its key fragment;
+//! 2. the receiving party creates an [`EscrowRequest`] for each holder
+//!    with [`escrow_requests`], and sends it to the holder over a confidential channel;
+//! 3. each holder responds with an [`EscrowShare`] (see [`EscrowRequest::respond`]),
+//!    sending it to the replacement proxy over a confidential channel,
+//!    and the proxy combines the shares with [`combine_escrow_shares`].
+//!
+//! The requests contain random masks adding up to zero, so that the replacement proxy
+//! only learns the new key fragment, and not the fragments of the holders.
+//! If fewer than `threshold` holders participate, the new fragment is invalid,
+//! which is only detected when decrypting.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use generic_array::sequence::Concat;
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+use typenum::op;
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+use crate::curve::{batch_invert, CurvePoint, CurveScalar, NonZeroCurveScalar};
+use crate::hashing_ds::PolynomialArgHasher;
+use crate::key_frag::{CompactKeyFrag, KeyFragID, VerifiedKeyFrag};
+use crate::keys::SecretKey;
+use crate::params::Parameters;
+use crate::secret_box::SecretBox;
+use crate::traits::{
+    fmt_public, fmt_secret, from_str_public, ConstructionError, DeserializableFromArray,
+    DeserializationError, HasTypeName, RepresentableAsArray, SerializableToArray,
+    SerializableToSecretArray,
+};
+
+type IdSize = <KeyFragID as RepresentableAsArray>::Size;
+type ScalarSize = <CurveScalar as RepresentableAsArray>::Size;
+type PointSize = <CurvePoint as RepresentableAsArray>::Size;
+
+/// Public information about a key fragment participating in the escrow,
+/// published by its holder.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowAnnouncement {
+    kfrag_id: KeyFragID,
+    precursor: CurvePoint,
+}
+
+impl EscrowAnnouncement {
+    /// Creates an announcement for the given key fragment.
+    pub fn new(verified_kfrag: &VerifiedKeyFrag) -> Self {
+        let kfrag = CompactKeyFrag::from(verified_kfrag);
+        Self {
+            kfrag_id: kfrag.id,
+            precursor: kfrag.precursor,
+        }
+    }
+}
+
+impl RepresentableAsArray for EscrowAnnouncement {
+    type Size = op!(IdSize + PointSize);
+}
+
+impl SerializableToArray for EscrowAnnouncement {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.kfrag_id.to_array().concat(self.precursor.to_array())
+    }
+}
+
+impl DeserializableFromArray for EscrowAnnouncement {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let (kfrag_id, rest) = KeyFragID::take(*arr)?;
+        let precursor = CurvePoint::take_last(rest)?;
+        Ok(Self {
+            kfrag_id,
+            precursor,
+        })
+    }
+}
+
+impl HasTypeName for EscrowAnnouncement {
+    fn type_name() -> &'static str {
+        "EscrowAnnouncement"
+    }
+}
+
+impl fmt::Display for EscrowAnnouncement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(self, f)
+    }
+}
+
+impl FromStr for EscrowAnnouncement {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
+/// A request to a key fragment holder to contribute to the new key fragment,
+/// created by the receiving party.
+///
+/// Contains a secret mask, and must only be sent over a confidential channel.
+#[derive(Clone)]
+pub struct EscrowRequest {
+    kfrag_id: KeyFragID,
+    new_kfrag_id: KeyFragID,
+    precursor: CurvePoint,
+    coefficient: CurveScalar,
+    mask: SecretBox<CurveScalar>,
+}
+
+impl EscrowRequest {
+    /// Returns the identifier of the key fragment this request is addressed to.
+    pub fn kfrag_id(&self) -> KeyFragID {
+        self.kfrag_id
+    }
+
+    /// Returns the identifier of the key fragment being created.
+    pub fn new_kfrag_id(&self) -> KeyFragID {
+        self.new_kfrag_id
+    }
+
+    /// Creates the holder's contribution to the new key fragment.
+    ///
+    /// **Warning:** by responding, the holder helps to extend the delegation
+    /// to a new proxy without the consent of the delegating party.
+    /// Only respond to requests from a receiving party you trust,
+    /// and only send the result to the proxy it has designated.
+    pub fn respond(&self, verified_kfrag: &VerifiedKeyFrag) -> Result<EscrowShare, EscrowError> {
+        let kfrag = CompactKeyFrag::from(verified_kfrag);
+        if kfrag.id != self.kfrag_id || kfrag.precursor != self.precursor {
+            return Err(EscrowError::KeyFragMismatch);
+        }
+        let weighted = SecretBox::new(&kfrag.key * &self.coefficient);
+        let value = SecretBox::new(weighted.as_secret() + self.mask.as_secret());
+        Ok(EscrowShare {
+            kfrag_id: self.kfrag_id,
+            new_kfrag_id: self.new_kfrag_id,
+            precursor: self.precursor,
+            value,
+        })
+    }
+}
+
+impl RepresentableAsArray for EscrowRequest {
+    type Size = op!(IdSize + IdSize + PointSize + ScalarSize + ScalarSize);
+}
+
+impl SerializableToSecretArray for EscrowRequest {
+    fn to_secret_array(&self) -> SecretBox<GenericArray<u8, Self::Size>> {
+        SecretBox::new(
+            self.kfrag_id
+                .to_array()
+                .concat(self.new_kfrag_id.to_array())
+                .concat(self.precursor.to_array())
+                .concat(self.coefficient.to_array())
+                .concat(self.mask.as_secret().to_array()),
+        )
+    }
+}
+
+impl DeserializableFromArray for EscrowRequest {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let (kfrag_id, rest) = KeyFragID::take(*arr)?;
+        let (new_kfrag_id, rest) = KeyFragID::take(rest)?;
+        let (precursor, rest) = CurvePoint::take(rest)?;
+        let (coefficient, rest) = CurveScalar::take(rest)?;
+        let mask = CurveScalar::take_last(rest)?;
+        Ok(Self {
+            kfrag_id,
+            new_kfrag_id,
+            precursor,
+            coefficient,
+            mask: SecretBox::new(mask),
+        })
+    }
+}
+
+impl HasTypeName for EscrowRequest {
+    fn type_name() -> &'static str {
+        "EscrowRequest"
+    }
+}
+
+impl fmt::Display for EscrowRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_secret::<Self>(f)
+    }
+}
+
+/// A contribution of a single key fragment holder to the new key fragment.
+///
+/// Must only be sent to the replacement proxy over a confidential channel.
+#[derive(Clone)]
+pub struct EscrowShare {
+    kfrag_id: KeyFragID,
+    new_kfrag_id: KeyFragID,
+    precursor: CurvePoint,
+    value: SecretBox<CurveScalar>,
+}
+
+impl EscrowShare {
+    /// Returns the identifier of the key fragment this share was created with.
+    pub fn kfrag_id(&self) -> KeyFragID {
+        self.kfrag_id
+    }
+
+    /// Returns the identifier of the key fragment being created.
+    pub fn new_kfrag_id(&self) -> KeyFragID {
+        self.new_kfrag_id
+    }
+}
+
+impl RepresentableAsArray for EscrowShare {
+    type Size = op!(IdSize + IdSize + PointSize + ScalarSize);
+}
+
+impl SerializableToSecretArray for EscrowShare {
+    fn to_secret_array(&self) -> SecretBox<GenericArray<u8, Self::Size>> {
+        SecretBox::new(
+            self.kfrag_id
+                .to_array()
+                .concat(self.new_kfrag_id.to_array())
+                .concat(self.precursor.to_array())
+                .concat(self.value.as_secret().to_array()),
+        )
+    }
+}
+
+impl DeserializableFromArray for EscrowShare {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let (kfrag_id, rest) = KeyFragID::take(*arr)?;
+        let (new_kfrag_id, rest) = KeyFragID::take(rest)?;
+        let (precursor, rest) = CurvePoint::take(rest)?;
+        let value = CurveScalar::take_last(rest)?;
+        Ok(Self {
+            kfrag_id,
+            new_kfrag_id,
+            precursor,
+            value: SecretBox::new(value),
+        })
+    }
+}
+
+impl HasTypeName for EscrowShare {
+    fn type_name() -> &'static str {
+        "EscrowShare"
+    }
+}
+
+impl fmt::Display for EscrowShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_secret::<Self>(f)
+    }
+}
+
+/// Errors that can happen during the escrow recombination.
+#[derive(Debug, PartialEq)]
+pub enum EscrowError {
+    /// No announcements or shares were provided.
+    NoParticipants,
+    /// The announcements or shares belong to different delegations
+    /// (or, for shares, to different new key fragments).
+    MismatchedParticipants,
+    /// Some of the key fragments participate more than once.
+    RepeatingKeyFrags,
+    /// The key fragment does not correspond to the request.
+    KeyFragMismatch,
+}
+
+impl fmt::Display for EscrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoParticipants => write!(f, "No participants in the escrow"),
+            Self::MismatchedParticipants => write!(
+                f,
+                "The participants belong to different delegations or new key fragments"
+            ),
+            Self::RepeatingKeyFrags => write!(f, "Some of the key fragments are repeated"),
+            Self::KeyFragMismatch => {
+                write!(f, "The key fragment does not correspond to the request")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for EscrowError {}
+
+/// Returns the Lagrange basis polynomials for the points `xs` evaluated at `x`,
+/// or `None` if some of the points are repeated.
+fn lagrange_coeffs_at(
+    xs: &[NonZeroCurveScalar],
+    x: &NonZeroCurveScalar,
+) -> Option<Vec<CurveScalar>> {
+    let mut diffs = Vec::with_capacity(xs.len() * xs.len().saturating_sub(1));
+    for (i, x_i) in xs.iter().enumerate() {
+        for (j, x_j) in xs.iter().enumerate() {
+            if j != i {
+                diffs.push(x_i - x_j);
+            }
+        }
+    }
+    let inv_diffs = batch_invert(&diffs)?;
+
+    let mut inv_diffs = inv_diffs.iter();
+    let mut result = Vec::with_capacity(xs.len());
+    for i in 0..xs.len() {
+        let mut res = CurveScalar::one();
+        for (j, x_j) in xs.iter().enumerate() {
+            if j != i {
+                res = &(&res * &(x - x_j)) * inv_diffs.next()?;
+            }
+        }
+        result.push(res);
+    }
+    Some(result)
+}
+
+/// Creates the requests to the key fragment holders (one per announcement, in the same order)
+/// for a new key fragment of the same delegation, using the given RNG.
+///
+/// At least `threshold` announcements must be provided
+/// for the resulting key fragment to be valid.
+/// See the [module-level documentation](`self`) for the implications of using this.
+pub fn escrow_requests_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    receiving_sk: &SecretKey,
+    announcements: &[EscrowAnnouncement],
+) -> Result<Box<[EscrowRequest]>, EscrowError> {
+    escrow_requests_with_params_and_rng(rng, &Parameters::new(), receiving_sk, announcements)
+}
+
+/// Same as [`escrow_requests_with_rng`], for the key fragments
+/// created with the given custom [`Parameters`].
+pub fn escrow_requests_with_params_and_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    params: &Parameters,
+    receiving_sk: &SecretKey,
+    announcements: &[EscrowAnnouncement],
+) -> Result<Box<[EscrowRequest]>, EscrowError> {
+    let precursor = announcements
+        .first()
+        .ok_or(EscrowError::NoParticipants)?
+        .precursor;
+    if announcements.iter().any(|ann| ann.precursor != precursor) {
+        return Err(EscrowError::MismatchedParticipants);
+    }
+
+    // The indices of the key fragments in the generating polynomial
+    // can only be calculated by the receiving party.
+    let receiving_pk_point = receiving_sk.public_key().to_point();
+    let dh_point = &precursor * receiving_sk.to_secret_scalar().as_secret();
+    let hasher = PolynomialArgHasher::new(params, &precursor, &receiving_pk_point, &dh_point);
+
+    let new_kfrag_id = KeyFragID::random(rng);
+    let xs: Vec<_> = announcements
+        .iter()
+        .map(|ann| hasher.hash(&ann.kfrag_id))
+        .collect();
+    let coefficients = lagrange_coeffs_at(&xs, &hasher.hash(&new_kfrag_id))
+        .ok_or(EscrowError::RepeatingKeyFrags)?;
+
+    // Random masks adding up to zero
+    let mut masks = Vec::<SecretBox<CurveScalar>>::with_capacity(announcements.len());
+    let mut mask_sum = SecretBox::new(CurveScalar::default());
+    for _ in 1..announcements.len() {
+        let mask = SecretBox::new(CurveScalar::from(NonZeroCurveScalar::random(rng)));
+        *mask_sum.as_mut_secret() = mask_sum.as_secret() + mask.as_secret();
+        masks.push(mask);
+    }
+    masks.push(SecretBox::new(
+        &CurveScalar::default() - mask_sum.as_secret(),
+    ));
+
+    Ok(announcements
+        .iter()
+        .zip(coefficients)
+        .zip(masks)
+        .map(|((ann, coefficient), mask)| EscrowRequest {
+            kfrag_id: ann.kfrag_id,
+            new_kfrag_id,
+            precursor,
+            coefficient,
+            mask,
+        })
+        .collect())
+}
+
+/// A synonym for [`escrow_requests_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn escrow_requests(
+    receiving_sk: &SecretKey,
+    announcements: &[EscrowAnnouncement],
+) -> Result<Box<[EscrowRequest]>, EscrowError> {
+    escrow_requests_with_rng(&mut OsRng, receiving_sk, announcements)
+}
+
+/// Combines the shares from all the holders the requests were sent to
+/// into the new key fragment.
+///
+/// The result cannot be verified, and is only valid if all the requested holders responded
+/// and there were at least `threshold` of them.
+pub fn combine_escrow_shares(shares: &[EscrowShare]) -> Result<CompactKeyFrag, EscrowError> {
+    let first = shares.first().ok_or(EscrowError::NoParticipants)?;
+    if shares
+        .iter()
+        .any(|share| share.new_kfrag_id != first.new_kfrag_id || share.precursor != first.precursor)
+    {
+        return Err(EscrowError::MismatchedParticipants);
+    }
+    for (i, share) in shares.iter().enumerate() {
+        if shares[..i]
+            .iter()
+            .any(|other| other.kfrag_id == share.kfrag_id)
+        {
+            return Err(EscrowError::RepeatingKeyFrags);
+        }
+    }
+
+    let mut key = SecretBox::new(CurveScalar::default());
+    for share in shares {
+        *key.as_mut_secret() = key.as_secret() + share.value.as_secret();
+    }
+
+    Ok(CompactKeyFrag {
+        id: first.new_kfrag_id,
+        key: *key.as_secret(),
+        precursor: first.precursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{
+        combine_escrow_shares, escrow_requests, EscrowAnnouncement, EscrowError, EscrowRequest,
+        EscrowShare,
+    };
+    use crate::{
+        decrypt_reencrypted_unproven, encrypt, generate_kfrags, reencrypt_compact,
+        reencrypt_unproven, DeserializableFromArray, OpenReencryptedError, ReencryptionError,
+        SecretKey, SerializableToArray, SerializableToSecretArray, Signer,
+    };
+
+    #[test]
+    fn test_escrow() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+
+        // The proxy holding the third fragment is gone; the first two recombine.
+        // Simulate network transfer for all the messages.
+        let announcements: Vec<_> = vkfrags[0..2]
+            .iter()
+            .map(EscrowAnnouncement::new)
+            .map(|ann| EscrowAnnouncement::from_array(&ann.to_array()).unwrap())
+            .collect();
+        let requests = escrow_requests(&receiving_sk, &announcements).unwrap();
+        let shares: Vec<_> = requests
+            .iter()
+            .zip(vkfrags[0..2].iter())
+            .map(|(request, vkfrag)| {
+                EscrowRequest::from_array(request.to_secret_array().as_secret())
+                    .unwrap()
+                    .respond(vkfrag)
+                    .unwrap()
+            })
+            .map(|share| EscrowShare::from_array(share.to_secret_array().as_secret()).unwrap())
+            .collect();
+        let new_kfrag = combine_escrow_shares(&shares).unwrap();
+        assert_eq!(new_kfrag.id(), requests[0].new_kfrag_id());
+
+        // The new fragment works together with any of the original ones
+        let new_cfrag = reencrypt_compact(&capsule, &new_kfrag);
+        for vkfrag in vkfrags.iter() {
+            let cfrag = reencrypt_unproven(&capsule, vkfrag.clone());
+            let plaintext_bob = decrypt_reencrypted_unproven(
+                &receiving_sk,
+                &delegating_pk,
+                &capsule,
+                [cfrag, new_cfrag.clone()],
+                &ciphertext,
+            )
+            .unwrap();
+            assert_eq!(&plaintext_bob as &[u8], plaintext);
+        }
+
+        // But not on its own
+        let result = decrypt_reencrypted_unproven(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            [new_cfrag],
+            &ciphertext,
+        );
+        assert_eq!(
+            result,
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::ValidationFailed
+            ))
+        );
+    }
+
+    #[test]
+    fn test_escrow_errors() {
+        let delegating_sk = SecretKey::random();
+        let signer = Signer::new(SecretKey::random());
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+        let other_vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+
+        assert_eq!(
+            escrow_requests(&receiving_sk, &[]).err(),
+            Some(EscrowError::NoParticipants)
+        );
+        assert_eq!(
+            escrow_requests(
+                &receiving_sk,
+                &[
+                    EscrowAnnouncement::new(&vkfrags[0]),
+                    EscrowAnnouncement::new(&other_vkfrags[1])
+                ]
+            )
+            .err(),
+            Some(EscrowError::MismatchedParticipants)
+        );
+        assert_eq!(
+            escrow_requests(
+                &receiving_sk,
+                &[
+                    EscrowAnnouncement::new(&vkfrags[0]),
+                    EscrowAnnouncement::new(&vkfrags[0])
+                ]
+            )
+            .err(),
+            Some(EscrowError::RepeatingKeyFrags)
+        );
+
+        let announcements: Vec<_> = vkfrags[0..2].iter().map(EscrowAnnouncement::new).collect();
+        let requests = escrow_requests(&receiving_sk, &announcements).unwrap();
+        assert_eq!(
+            requests[0].respond(&vkfrags[1]).err(),
+            Some(EscrowError::KeyFragMismatch)
+        );
+
+        let share = requests[0].respond(&vkfrags[0]).unwrap();
+        assert_eq!(
+            combine_escrow_shares(&[]).err(),
+            Some(EscrowError::NoParticipants)
+        );
+        assert_eq!(
+            combine_escrow_shares(&[share.clone(), share]).err(),
+            Some(EscrowError::RepeatingKeyFrags)
+        );
+    }
+}
@@ -0,0 +1,562 @@
+//! FlatBuffers encoding of the objects, without depending on the `flatbuffers` crate,
+//! for the network transport in latency-sensitive services.
+//!
+//! The buffers correspond to the schema in `fbs/umbral.fbs` (shipped with the crate),
+//! so they can be exchanged with the code generated from it by `flatc`.
+//! The fields of the objects are stored separately, and the views in this module
+//! (e.g. [`CapsuleFrag`]) give access to them after checking only the structure of the buffer;
+//! the values of the fields are validated when the view is converted to the native object.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::format::{Field, FieldEncoding, Layout, CAPSULE, CAPSULE_FRAG, KEY_FRAG};
+use crate::key_frag::KeyFragID;
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, RepresentableAsArray,
+    SerializableToArray,
+};
+
+const UOFFSET_SIZE: usize = 4;
+const VTABLE_HEADER_SIZE: usize = 4;
+
+// The value of an absent boolean field.
+static FALSE: [u8; 1] = [0];
+
+/// A trait for objects that can be encoded as FlatBuffers (with the object as the root table).
+pub trait ToFlatBuffer {
+    /// Encodes the object as a FlatBuffer.
+    fn to_flatbuffer(&self) -> Box<[u8]>;
+}
+
+fn construction_error(type_name: &'static str, message: &str) -> DeserializationError {
+    DeserializationError::ConstructionFailure(ConstructionError::new(type_name, message))
+}
+
+/// Returns the name of the field in the schema (nested fields are flattened).
+fn schema_name(field: &Field) -> &'static str {
+    field.name.rsplit('.').next().unwrap_or(field.name)
+}
+
+fn read_bytes(buf: &[u8], pos: usize, size: usize) -> Result<&[u8], &'static str> {
+    pos.checked_add(size)
+        .and_then(|end| buf.get(pos..end))
+        .ok_or("Offset out of bounds")
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Result<usize, &'static str> {
+    let bytes = read_bytes(buf, pos, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Result<usize, &'static str> {
+    let bytes = read_bytes(buf, pos, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+fn read_i32(buf: &[u8], pos: usize) -> Result<i64, &'static str> {
+    let bytes = read_bytes(buf, pos, 4)?;
+    Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).into())
+}
+
+/// Returns the position the offset stored at `pos` points to.
+fn follow(buf: &[u8], pos: usize) -> Result<usize, &'static str> {
+    pos.checked_add(read_u32(buf, pos)?)
+        .ok_or("Offset out of bounds")
+}
+
+/// A table in a buffer, with the vtable checked to be in bounds.
+#[derive(Clone, Copy, Debug)]
+struct Table<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    vtable_pos: usize,
+    vtable_size: usize,
+    table_size: usize,
+}
+
+impl<'a> Table<'a> {
+    fn at(buf: &'a [u8], pos: usize) -> Result<Self, &'static str> {
+        let malformed = "Malformed table";
+        let soffset = read_i32(buf, pos)?;
+        let vtable_pos = usize::try_from(pos as i64 - soffset).map_err(|_| malformed)?;
+        let vtable_size = read_u16(buf, vtable_pos)?;
+        let table_size = read_u16(buf, vtable_pos + 2)?;
+        if vtable_size < VTABLE_HEADER_SIZE || vtable_size % 2 != 0 || table_size < UOFFSET_SIZE {
+            return Err(malformed);
+        }
+        read_bytes(buf, vtable_pos, vtable_size)?;
+        read_bytes(buf, pos, table_size)?;
+        Ok(Self {
+            buf,
+            pos,
+            vtable_pos,
+            vtable_size,
+            table_size,
+        })
+    }
+
+    fn root(buf: &'a [u8]) -> Result<Self, &'static str> {
+        Self::at(buf, follow(buf, 0)?)
+    }
+
+    /// Returns the position of the field with the given index and size in the buffer,
+    /// or `None` if the field is absent.
+    fn field_pos(&self, index: usize, size: usize) -> Result<Option<usize>, &'static str> {
+        let slot = VTABLE_HEADER_SIZE + 2 * index;
+        if slot >= self.vtable_size {
+            return Ok(None);
+        }
+        let offset = read_u16(self.buf, self.vtable_pos + slot)?;
+        if offset == 0 {
+            return Ok(None);
+        }
+        if offset < UOFFSET_SIZE || offset + size > self.table_size {
+            return Err("Field out of bounds");
+        }
+        Ok(Some(self.pos + offset))
+    }
+
+    fn field(&self, index: usize, size: usize) -> Result<Option<&'a [u8]>, &'static str> {
+        Ok(self
+            .field_pos(index, size)?
+            .map(|pos| &self.buf[pos..pos + size]))
+    }
+}
+
+/// Checks that all the fields of the object are present
+/// (except the boolean ones, which are `false` by default).
+fn check_object(layout: &Layout, table: &Table<'_>) -> Result<(), DeserializationError> {
+    for (index, field) in layout.fields.iter().enumerate() {
+        let value = table
+            .field(index, field.encoding.size())
+            .map_err(|message| construction_error(layout.type_name, message))?;
+        if value.is_none() && field.encoding != FieldEncoding::Bool {
+            return Err(construction_error(
+                layout.type_name,
+                &format!("Missing field `{}`", schema_name(field)),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn object_field<'a>(layout: &Layout, table: &Table<'a>, index: usize) -> &'a [u8] {
+    // The table was checked with `check_object()` on creation.
+    let size = layout.fields[index].encoding.size();
+    table.field(index, size).ok().flatten().unwrap_or(&FALSE)
+}
+
+/// Returns the canonical byte representation of the object.
+fn object_bytes(layout: &Layout, table: &Table<'_>) -> Vec<u8> {
+    let mut result = Vec::with_capacity(layout.size());
+    for index in 0..layout.fields.len() {
+        result.extend_from_slice(object_field(layout, table, index));
+    }
+    result
+}
+
+struct Builder {
+    buf: Vec<u8>,
+}
+
+impl Builder {
+    fn new() -> Self {
+        // The placeholder for the offset of the root table
+        Self {
+            buf: [0u8; UOFFSET_SIZE].to_vec(),
+        }
+    }
+
+    fn pad_to(&mut self, alignment: usize) {
+        while self.buf.len() % alignment != 0 {
+            self.buf.push(0);
+        }
+    }
+
+    fn push_u16(&mut self, value: usize) {
+        // The sizes of our tables are well within the `u16` range.
+        self.buf.extend_from_slice(&(value as u16).to_le_bytes());
+    }
+
+    /// Writes a table with the given inline fields (preceded by its vtable),
+    /// and returns the position of the table.
+    fn push_table(&mut self, fields: &[&[u8]]) -> usize {
+        self.pad_to(2);
+        let vtable_pos = self.buf.len();
+        let table_size = UOFFSET_SIZE + fields.iter().map(|field| field.len()).sum::<usize>();
+        self.push_u16(VTABLE_HEADER_SIZE + 2 * fields.len());
+        self.push_u16(table_size);
+        let mut offset = UOFFSET_SIZE;
+        for field in fields {
+            self.push_u16(offset);
+            offset += field.len();
+        }
+
+        self.pad_to(UOFFSET_SIZE);
+        let table_pos = self.buf.len();
+        self.buf
+            .extend_from_slice(&((table_pos - vtable_pos) as i32).to_le_bytes());
+        for field in fields {
+            self.buf.extend_from_slice(field);
+        }
+        table_pos
+    }
+
+    /// Writes a vector of offsets (filled in later with `set_offset()`),
+    /// and returns the position of the vector.
+    fn push_offset_vector(&mut self, len: usize) -> usize {
+        self.pad_to(UOFFSET_SIZE);
+        let vector_pos = self.buf.len();
+        self.buf.extend_from_slice(&(len as u32).to_le_bytes());
+        self.buf.resize(vector_pos + UOFFSET_SIZE * (len + 1), 0);
+        vector_pos
+    }
+
+    fn push_object(&mut self, layout: &Layout, bytes: &[u8]) -> usize {
+        let mut fields = Vec::with_capacity(layout.fields.len());
+        let mut offset = 0;
+        for field in layout.fields {
+            let size = field.encoding.size();
+            fields.push(&bytes[offset..offset + size]);
+            offset += size;
+        }
+        self.push_table(&fields)
+    }
+
+    /// Makes the offset at `pos` point to `target` (which must be after `pos`).
+    fn set_offset(&mut self, pos: usize, target: usize) {
+        self.buf[pos..pos + UOFFSET_SIZE].copy_from_slice(&((target - pos) as u32).to_le_bytes());
+    }
+
+    fn finish(mut self, root: usize) -> Box<[u8]> {
+        self.set_offset(0, root);
+        self.buf.into_boxed_slice()
+    }
+}
+
+macro_rules! object_view {
+    ($(#[$attr:meta])* $name:ident, $native:ty, $layout:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug)]
+        pub struct $name<'a>(Table<'a>);
+
+        impl<'a> $name<'a> {
+            /// Creates a view of the object in the buffer (as the root table),
+            /// checking the structure of the buffer, but not the values of the fields.
+            pub fn from_buffer(buf: &'a [u8]) -> Result<Self, DeserializationError> {
+                let table = Table::root(buf)
+                    .map_err(|message| construction_error($layout.type_name, message))?;
+                Self::from_table(table)
+            }
+
+            fn from_table(table: Table<'a>) -> Result<Self, DeserializationError> {
+                check_object(&$layout, &table)?;
+                Ok(Self(table))
+            }
+
+            /// Returns the bytes of the field with the given name (as in the schema),
+            /// or `None` if there is no such field.
+            pub fn field(&self, name: &str) -> Option<&'a [u8]> {
+                $layout
+                    .fields
+                    .iter()
+                    .position(|field| schema_name(field) == name)
+                    .map(|index| object_field(&$layout, &self.0, index))
+            }
+        }
+
+        impl<'a> TryFrom<&$name<'a>> for $native {
+            type Error = DeserializationError;
+
+            fn try_from(view: &$name<'a>) -> Result<Self, Self::Error> {
+                Self::from_bytes(object_bytes(&$layout, &view.0))
+            }
+        }
+
+        impl<'a> TryFrom<$name<'a>> for $native {
+            type Error = DeserializationError;
+
+            fn try_from(view: $name<'a>) -> Result<Self, Self::Error> {
+                Self::try_from(&view)
+            }
+        }
+
+        impl ToFlatBuffer for $native {
+            fn to_flatbuffer(&self) -> Box<[u8]> {
+                let mut builder = Builder::new();
+                let root = builder.push_object(&$layout, &self.to_array());
+                builder.finish(root)
+            }
+        }
+    };
+}
+
+object_view!(
+    /// A view of a FlatBuffers-encoded [`Capsule`](`crate::Capsule`).
+    Capsule,
+    crate::Capsule,
+    CAPSULE
+);
+
+object_view!(
+    /// A view of a FlatBuffers-encoded [`KeyFrag`](`crate::KeyFrag`).
+    KeyFrag,
+    crate::KeyFrag,
+    KEY_FRAG
+);
+
+object_view!(
+    /// A view of a FlatBuffers-encoded [`CapsuleFrag`](`crate::CapsuleFrag`).
+    CapsuleFrag,
+    crate::CapsuleFrag,
+    CAPSULE_FRAG
+);
+
+// Verified objects can be sent, but are received as their unverified counterparts.
+
+impl ToFlatBuffer for crate::VerifiedKeyFrag {
+    fn to_flatbuffer(&self) -> Box<[u8]> {
+        let mut builder = Builder::new();
+        let root = builder.push_object(&KEY_FRAG, &self.to_array());
+        builder.finish(root)
+    }
+}
+
+impl ToFlatBuffer for crate::VerifiedCapsuleFrag {
+    fn to_flatbuffer(&self) -> Box<[u8]> {
+        let mut builder = Builder::new();
+        let root = builder.push_object(&CAPSULE_FRAG, &self.to_array());
+        builder.finish(root)
+    }
+}
+
+/// A view of a FlatBuffers-encoded request to reencrypt capsules
+/// with the key fragment with the given ID (held by the proxy).
+#[derive(Clone, Copy, Debug)]
+pub struct ReencryptionRequest<'a> {
+    table: Table<'a>,
+    capsules_pos: usize,
+    capsules_len: usize,
+}
+
+impl<'a> ReencryptionRequest<'a> {
+    const CAPSULES: usize = 0;
+    const KFRAG_ID: usize = 1;
+
+    /// Encodes a request to reencrypt `capsules` with the key fragment with the ID `kfrag_id`.
+    pub fn encode(capsules: &[crate::Capsule], kfrag_id: &KeyFragID) -> Box<[u8]> {
+        let mut builder = Builder::new();
+        let kfrag_id = kfrag_id.to_array();
+        let root = builder.push_table(&[&[0u8; UOFFSET_SIZE][..], kfrag_id.as_slice()]);
+        let vector_pos = builder.push_offset_vector(capsules.len());
+        builder.set_offset(root + UOFFSET_SIZE, vector_pos);
+        for (i, capsule) in capsules.iter().enumerate() {
+            let capsule_pos = builder.push_object(&CAPSULE, &capsule.to_array());
+            builder.set_offset(vector_pos + UOFFSET_SIZE * (i + 1), capsule_pos);
+        }
+        builder.finish(root)
+    }
+
+    /// Creates a view of the request in the buffer,
+    /// checking the structure of the buffer, but not the values of the fields.
+    pub fn from_buffer(buf: &'a [u8]) -> Result<Self, DeserializationError> {
+        let error = |message: &str| construction_error("ReencryptionRequest", message);
+
+        let table = Table::root(buf).map_err(error)?;
+        if table
+            .field(Self::KFRAG_ID, KeyFragID::serialized_size())
+            .map_err(error)?
+            .is_none()
+        {
+            return Err(error("Missing field `kfrag_id`"));
+        }
+
+        let capsules_field = table
+            .field_pos(Self::CAPSULES, UOFFSET_SIZE)
+            .map_err(error)?
+            .ok_or_else(|| error("Missing field `capsules`"))?;
+        let capsules_pos = follow(buf, capsules_field).map_err(error)?;
+        let capsules_len = read_u32(buf, capsules_pos).map_err(error)?;
+        let capsules_size = capsules_len
+            .checked_add(1)
+            .and_then(|len| len.checked_mul(UOFFSET_SIZE))
+            .ok_or_else(|| error("Offset out of bounds"))?;
+        read_bytes(buf, capsules_pos, capsules_size).map_err(error)?;
+        for i in 0..capsules_len {
+            let capsule_pos = follow(buf, capsules_pos + UOFFSET_SIZE * (i + 1)).map_err(error)?;
+            Capsule::from_table(Table::at(buf, capsule_pos).map_err(error)?)?;
+        }
+
+        Ok(Self {
+            table,
+            capsules_pos,
+            capsules_len,
+        })
+    }
+
+    /// Returns the ID of the key fragment to reencrypt with.
+    pub fn kfrag_id(&self) -> KeyFragID {
+        // The field was checked on creation,
+        // and an identifier is valid for any bytes of the correct length.
+        let size = KeyFragID::serialized_size();
+        let bytes = self.table.field(Self::KFRAG_ID, size).ok().flatten();
+        KeyFragID::from_bytes(bytes.unwrap_or(&[])).unwrap()
+    }
+
+    /// Returns the number of capsules in the request.
+    pub fn num_capsules(&self) -> usize {
+        self.capsules_len
+    }
+
+    /// Returns the views of the capsules in the request.
+    pub fn capsules(&self) -> impl Iterator<Item = Capsule<'a>> {
+        let request = *self;
+        (0..self.capsules_len).map(move |i| request.capsule(i))
+    }
+
+    fn capsule(&self, i: usize) -> Capsule<'a> {
+        // The capsules were checked on creation.
+        let buf = self.table.buf;
+        let capsule_pos = follow(buf, self.capsules_pos + UOFFSET_SIZE * (i + 1)).unwrap();
+        Capsule(Table::at(buf, capsule_pos).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+    use core::convert::TryFrom;
+
+    use super::{Capsule, CapsuleFrag, KeyFrag, ReencryptionRequest, ToFlatBuffer};
+    use crate::format::{CAPSULE, CAPSULE_FRAG};
+    use crate::{
+        encrypt, generate_kfrags, reencrypt, DeserializableFromArray, SecretKey,
+        SerializableToArray, Signer,
+    };
+
+    #[test]
+    fn test_roundtrip() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let receiving_pk = SecretKey::random().public_key();
+
+        let (capsule, _ciphertext) = encrypt(&delegating_pk, b"peace at dawn").unwrap();
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, false).unwrap();
+        let vcfrag = reencrypt(&capsule, vkfrags[0].clone());
+
+        let buf = capsule.to_flatbuffer();
+        let view = Capsule::from_buffer(&buf).unwrap();
+        assert_eq!(crate::Capsule::try_from(view).unwrap(), capsule);
+        let (offset, size) = CAPSULE.field_range("point_v").unwrap();
+        assert_eq!(
+            view.field("point_v").unwrap(),
+            &capsule.to_array()[offset..offset + size]
+        );
+        assert!(view.field("point_x").is_none());
+
+        let buf = vkfrags[0].to_flatbuffer();
+        let view = KeyFrag::from_buffer(&buf).unwrap();
+        assert_eq!(
+            crate::KeyFrag::try_from(view).unwrap(),
+            vkfrags[0].clone_unverified()
+        );
+        assert_eq!(view.field("delegating_key_signed").unwrap(), &[1]);
+        assert_eq!(view.field("receiving_key_signed").unwrap(), &[0]);
+
+        let buf = vcfrag.to_flatbuffer();
+        let view = CapsuleFrag::from_buffer(&buf).unwrap();
+        assert_eq!(
+            crate::CapsuleFrag::try_from(&view).unwrap(),
+            vcfrag.clone_unverified()
+        );
+        let (offset, size) = CAPSULE_FRAG.field_range("kfrag_id").unwrap();
+        assert_eq!(
+            view.field("kfrag_id").unwrap(),
+            &vcfrag.to_array()[offset..offset + size]
+        );
+    }
+
+    #[test]
+    fn test_reencryption_request() {
+        let pk = SecretKey::random().public_key();
+        let capsules = (0..3)
+            .map(|_| encrypt(&pk, b"peace at dawn").unwrap().0)
+            .collect::<Vec<_>>();
+        let kfrag_id = crate::KeyFragID::from_bytes(&[7u8; 32]).unwrap();
+
+        let buf = ReencryptionRequest::encode(&capsules, &kfrag_id);
+        let request = ReencryptionRequest::from_buffer(&buf).unwrap();
+        assert_eq!(request.kfrag_id(), kfrag_id);
+        assert_eq!(request.num_capsules(), 3);
+        let capsules_back = request
+            .capsules()
+            .map(|capsule| crate::Capsule::try_from(capsule).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(capsules_back, capsules);
+
+        // An empty request
+        let buf = ReencryptionRequest::encode(&[], &kfrag_id);
+        let request = ReencryptionRequest::from_buffer(&buf).unwrap();
+        assert_eq!(request.capsules().count(), 0);
+
+        // Truncated buffers are rejected
+        let buf = ReencryptionRequest::encode(&capsules, &kfrag_id);
+        assert!(ReencryptionRequest::from_buffer(&buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_foreign_layout() {
+        // A buffer with the vtable after the table, and the fields in the reverse order,
+        // as other FlatBuffers implementations may produce.
+        let pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+        let bytes = capsule.to_array();
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&4u32.to_le_bytes());
+        buf.extend_from_slice(&(-102i32).to_le_bytes());
+        buf.extend_from_slice(&bytes[66..]); // signature
+        buf.extend_from_slice(&bytes[33..66]); // point_v
+        buf.extend_from_slice(&bytes[..33]); // point_e
+        for value in [10u16, 102, 69, 36, 4] {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let view = Capsule::from_buffer(&buf).unwrap();
+        assert_eq!(crate::Capsule::try_from(view).unwrap(), capsule);
+    }
+
+    #[test]
+    fn test_malformed() {
+        let pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+        let buf = capsule.to_flatbuffer();
+
+        // Truncated
+        assert!(Capsule::from_buffer(&buf[..buf.len() - 1]).is_err());
+        assert!(Capsule::from_buffer(&buf[..2]).is_err());
+
+        // Missing required field (the vtable starts right after the root offset)
+        let mut missing = buf.to_vec();
+        missing[8..10].copy_from_slice(&[0, 0]);
+        assert!(Capsule::from_buffer(&missing).is_err());
+
+        // Field out of the table bounds
+        let mut out_of_bounds = buf.to_vec();
+        out_of_bounds[8..10].copy_from_slice(&100u16.to_le_bytes());
+        assert!(Capsule::from_buffer(&out_of_bounds).is_err());
+
+        // Invalid field values are only detected on conversion
+        let mut invalid = buf.to_vec();
+        let table_pos = 16;
+        invalid[table_pos + 4] = 0x04;
+        let view = Capsule::from_buffer(&invalid).unwrap();
+        assert!(crate::Capsule::try_from(view).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! A machine-readable description of the byte layout of the serialized objects.
+//!
+//! All the objects are serialized as a concatenation of their fields, in the order
+//! given by the corresponding [`Layout`], without separators or length prefixes.
+//! The constants here are checked against the actual serialization in the test suite,
+//! so they can be used to generate independent implementations of the format.
+
+/// The encoding of a single field of a serialized object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldEncoding {
+    /// A curve point (secp256k1, or P-256 with the `p256` feature) in the SEC1 compressed form
+    /// (`0x02` or `0x03` depending on the parity of `y`, followed by big-endian `x`).
+    Point,
+    /// A curve scalar as a big-endian integer, less than the curve order.
+    Scalar,
+    /// An ECDSA signature as big-endian `r` followed by big-endian `s`
+    /// (with low `s` for secp256k1).
+    Signature,
+    /// An opaque 32-byte identifier.
+    Identifier,
+    /// A boolean flag: `0` or `1`.
+    Bool,
+}
+
+impl FieldEncoding {
+    /// Returns the size of the encoded field in bytes.
+    pub const fn size(&self) -> usize {
+        match self {
+            Self::Point => 33,
+            Self::Scalar => 32,
+            Self::Signature => 64,
+            Self::Identifier => 32,
+            Self::Bool => 1,
+        }
+    }
+}
+
+/// A named field of a serialized object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Field {
+    /// The name of the field (nested fields are separated by a dot).
+    pub name: &'static str,
+    /// The encoding of the field.
+    pub encoding: FieldEncoding,
+}
+
+/// The layout of a serialized object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    /// The name of the object type.
+    pub type_name: &'static str,
+    /// The fields in the order of serialization.
+    pub fields: &'static [Field],
+}
+
+impl Layout {
+    /// Returns the total size of the serialized object in bytes.
+    pub const fn size(&self) -> usize {
+        let mut size = 0;
+        let mut i = 0;
+        while i < self.fields.len() {
+            size += self.fields[i].encoding.size();
+            i += 1;
+        }
+        size
+    }
+
+    /// Returns the offset and the size of the field with the given name,
+    /// or `None` if there is no such field.
+    pub fn field_range(&self, name: &str) -> Option<(usize, usize)> {
+        let mut offset = 0;
+        for field in self.fields {
+            if field.name == name {
+                return Some((offset, field.encoding.size()));
+            }
+            offset += field.encoding.size();
+        }
+        None
+    }
+}
+
+const fn field(name: &'static str, encoding: FieldEncoding) -> Field {
+    Field { name, encoding }
+}
+
+/// The layout of [`PublicKey`](`crate::PublicKey`).
+pub const PUBLIC_KEY: Layout = Layout {
+    type_name: "PublicKey",
+    fields: &[field("point", FieldEncoding::Point)],
+};
+
+/// The layout of [`Signature`](`crate::Signature`).
+pub const SIGNATURE: Layout = Layout {
+    type_name: "Signature",
+    fields: &[field("signature", FieldEncoding::Signature)],
+};
+
+/// The layout of [`Capsule`](`crate::Capsule`).
+pub const CAPSULE: Layout = Layout {
+    type_name: "Capsule",
+    fields: &[
+        field("point_e", FieldEncoding::Point),
+        field("point_v", FieldEncoding::Point),
+        field("signature", FieldEncoding::Scalar),
+    ],
+};
+
+/// The layout of [`KeyFrag`](`crate::KeyFrag`).
+pub const KEY_FRAG: Layout = Layout {
+    type_name: "KeyFrag",
+    fields: &[
+        field("id", FieldEncoding::Identifier),
+        field("key", FieldEncoding::Scalar),
+        field("precursor", FieldEncoding::Point),
+        field("proof.commitment", FieldEncoding::Point),
+        field("proof.signature_for_proxy", FieldEncoding::Signature),
+        field("proof.signature_for_receiver", FieldEncoding::Signature),
+        field("proof.delegating_key_signed", FieldEncoding::Bool),
+        field("proof.receiving_key_signed", FieldEncoding::Bool),
+    ],
+};
+
+/// The layout of [`CapsuleFrag`](`crate::CapsuleFrag`).
+pub const CAPSULE_FRAG: Layout = Layout {
+    type_name: "CapsuleFrag",
+    fields: &[
+        field("point_e1", FieldEncoding::Point),
+        field("point_v1", FieldEncoding::Point),
+        field("kfrag_id", FieldEncoding::Identifier),
+        field("precursor", FieldEncoding::Point),
+        field("proof.point_e2", FieldEncoding::Point),
+        field("proof.point_v2", FieldEncoding::Point),
+        field("proof.kfrag_commitment", FieldEncoding::Point),
+        field("proof.kfrag_pok", FieldEncoding::Point),
+        field("proof.signature", FieldEncoding::Scalar),
+        field("proof.kfrag_signature", FieldEncoding::Signature),
+    ],
+};
+
+/// The layouts of all the objects described in this module.
+pub const LAYOUTS: &[Layout] = &[PUBLIC_KEY, SIGNATURE, CAPSULE, KEY_FRAG, CAPSULE_FRAG];
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Layout, CAPSULE, CAPSULE_FRAG, KEY_FRAG, PUBLIC_KEY, SIGNATURE};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{
+        Capsule, CapsuleFrag, KeyFrag, PublicKey, RepresentableAsArray, SerializableToArray,
+        Signature,
+    };
+
+    fn field_bytes<'a>(layout: &Layout, bytes: &'a [u8], name: &str) -> &'a [u8] {
+        let (offset, size) = layout.field_range(name).unwrap();
+        &bytes[offset..offset + size]
+    }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(PUBLIC_KEY.size(), PublicKey::serialized_size());
+        assert_eq!(SIGNATURE.size(), Signature::serialized_size());
+        assert_eq!(CAPSULE.size(), Capsule::serialized_size());
+        assert_eq!(KEY_FRAG.size(), KeyFrag::serialized_size());
+        assert_eq!(CAPSULE_FRAG.size(), CapsuleFrag::serialized_size());
+
+        // The constants can be used in const contexts
+        let buffer = [0u8; Capsule::SERIALIZED_SIZE];
+        assert_eq!(buffer.len(), CAPSULE.size());
+        assert_eq!(PublicKey::SERIALIZED_SIZE, PUBLIC_KEY.size());
+        assert_eq!(Signature::SERIALIZED_SIZE, SIGNATURE.size());
+        assert_eq!(KeyFrag::SERIALIZED_SIZE, KEY_FRAG.size());
+        assert_eq!(CapsuleFrag::SERIALIZED_SIZE, CAPSULE_FRAG.size());
+    }
+
+    #[test]
+    fn test_field_order() {
+        let Delegation {
+            capsule,
+            verified_kfrags: vkfrags,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(false, true);
+        let kfrag = vkfrags[0].clone_unverified();
+        let cfrag = vcfrags[0].clone_unverified();
+
+        let bytes = capsule.to_array();
+        assert_eq!(
+            field_bytes(&CAPSULE, &bytes, "point_e"),
+            capsule.point_e.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&CAPSULE, &bytes, "point_v"),
+            capsule.point_v.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&CAPSULE, &bytes, "signature"),
+            capsule.signature.to_array().as_slice()
+        );
+
+        let bytes = kfrag.to_array();
+        assert_eq!(
+            field_bytes(&KEY_FRAG, &bytes, "id"),
+            kfrag.id.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&KEY_FRAG, &bytes, "key"),
+            kfrag.key.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&KEY_FRAG, &bytes, "precursor"),
+            kfrag.precursor.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&KEY_FRAG, &bytes, "proof.commitment"),
+            kfrag.proof.commitment.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&KEY_FRAG, &bytes, "proof.signature_for_receiver"),
+            kfrag.proof.signature_for_receiver.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&KEY_FRAG, &bytes, "proof.delegating_key_signed"),
+            &[0]
+        );
+        assert_eq!(
+            field_bytes(&KEY_FRAG, &bytes, "proof.receiving_key_signed"),
+            &[1]
+        );
+
+        let bytes = cfrag.to_array();
+        assert_eq!(
+            field_bytes(&CAPSULE_FRAG, &bytes, "point_e1"),
+            cfrag.point_e1.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&CAPSULE_FRAG, &bytes, "point_v1"),
+            cfrag.point_v1.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&CAPSULE_FRAG, &bytes, "kfrag_id"),
+            kfrag.id.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&CAPSULE_FRAG, &bytes, "precursor"),
+            kfrag.precursor.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&CAPSULE_FRAG, &bytes, "proof.kfrag_commitment"),
+            kfrag.proof.commitment.to_array().as_slice()
+        );
+        assert_eq!(
+            field_bytes(&CAPSULE_FRAG, &bytes, "proof.kfrag_signature"),
+            kfrag.proof.signature_for_receiver.to_array().as_slice()
+        );
+
+        // Points are compressed
+        for field in ["point_e1", "point_v1", "precursor"] {
+            let prefix = field_bytes(&CAPSULE_FRAG, &bytes, field)[0];
+            assert!(prefix == 2 || prefix == 3);
+        }
+    }
+}
@@ -1,14 +1,73 @@
 use digest::Digest;
+#[cfg(not(any(
+    feature = "hash-sha3",
+    feature = "hash-blake3",
+    feature = "hash-keccak"
+)))]
 use sha2::Sha256;
 
 use crate::curve::{CurvePoint, NonZeroCurveScalar};
+use crate::dst;
 use crate::secret_box::{CanBeZeroizedOnDrop, SecretBox};
 use crate::traits::SerializableToArray;
 
+#[cfg(any(
+    all(feature = "hash-sha3", feature = "hash-blake3"),
+    all(feature = "hash-sha3", feature = "hash-keccak"),
+    all(feature = "hash-blake3", feature = "hash-keccak")
+))]
+compile_error!("Only one of the `hash-*` features can be enabled");
+
+#[cfg(all(
+    feature = "fips-algorithms",
+    any(
+        feature = "hash-sha3",
+        feature = "hash-blake3",
+        feature = "hash-keccak"
+    )
+))]
+compile_error!("The `fips-algorithms` feature requires SHA-256 as the hash");
+
 // Our hash of choice.
+// It has to have the output size equal to the scalar size (see `NonZeroCurveScalar::from_digest`).
+// Only byte-oriented hashes fit here. An algebraic hash like Poseidon would need
+// to absorb field elements instead of bytes, with parameters standardized for the scalar field
+// of the curve (none exist for secp256k1 or P-256), so it cannot be plugged in as a digest.
+#[cfg(not(any(
+    feature = "hash-sha3",
+    feature = "hash-blake3",
+    feature = "hash-keccak"
+)))]
 pub(crate) type BackendDigest = Sha256;
+#[cfg(feature = "hash-sha3")]
+pub(crate) type BackendDigest = sha3::Sha3_256;
+#[cfg(all(feature = "hash-blake3", not(feature = "hash-sha3")))]
+pub(crate) type BackendDigest = blake3::Hasher;
+#[cfg(all(
+    feature = "hash-keccak",
+    not(any(feature = "hash-sha3", feature = "hash-blake3"))
+))]
+pub(crate) type BackendDigest = sha3::Keccak256;
+
+// The identifier of the hash in the ciphertext header and the envelope suite.
+#[cfg(not(any(
+    feature = "hash-sha3",
+    feature = "hash-blake3",
+    feature = "hash-keccak"
+)))]
+pub(crate) const HASH_ID: u8 = 1;
+#[cfg(feature = "hash-sha3")]
+pub(crate) const HASH_ID: u8 = 2;
+#[cfg(all(feature = "hash-blake3", not(feature = "hash-sha3")))]
+pub(crate) const HASH_ID: u8 = 3;
+#[cfg(all(
+    feature = "hash-keccak",
+    not(any(feature = "hash-sha3", feature = "hash-blake3"))
+))]
+pub(crate) const HASH_ID: u8 = 4;
 
 // Wraps BackendDigest for easier replacement, and standardizes the use of DST.
+#[derive(Clone)]
 pub(crate) struct Hash(BackendDigest);
 
 impl Hash {
@@ -17,8 +76,33 @@ impl Hash {
     }
 
     pub fn new_with_dst(dst: &[u8]) -> Self {
-        let dst_len = (dst.len() as u32).to_be_bytes();
-        Self::new().chain_bytes(dst_len).chain_bytes(dst)
+        Self::new_with_dst_and_context(dst, &[])
+    }
+
+    /// Starts the hash with the domain separation tag and the application context
+    /// (see the [`dst`](`crate::dst`) module for the encoding).
+    pub fn new_with_dst_and_context(dst: &[u8], context: &[u8]) -> Self {
+        let hash = Self::new();
+        if context.is_empty() {
+            hash.chain_bytes((dst.len() as u32).to_be_bytes())
+                .chain_bytes(dst)
+        } else {
+            hash.chain_bytes((dst.len() as u32 | dst::CONTEXT_FLAG).to_be_bytes())
+                .chain_bytes(dst)
+                .chain_bytes((context.len() as u32).to_be_bytes())
+                .chain_bytes(context)
+        }
+    }
+
+    /// Creates a hash for signing a message.
+    /// Without an application context it is a plain hash of the message,
+    /// so that the signatures can be verified by standard ECDSA implementations.
+    pub fn new_for_signing(context: &[u8]) -> Self {
+        if context.is_empty() {
+            Self::new()
+        } else {
+            Self::new_with_dst_and_context(dst::SIGNATURE, context)
+        }
     }
 
     pub fn chain_bytes<T: AsRef<[u8]>>(self, bytes: T) -> Self {
@@ -38,6 +122,7 @@ impl Hash {
     }
 }
 
+#[derive(Clone)]
 pub(crate) struct ScalarDigest(Hash);
 
 impl ScalarDigest {
@@ -45,6 +130,10 @@ impl ScalarDigest {
         Self(Hash::new_with_dst(dst))
     }
 
+    pub fn new_with_dst_and_context(dst: &[u8], context: &[u8]) -> Self {
+        Self(Hash::new_with_dst_and_context(dst, context))
+    }
+
     pub fn chain_bytes<T: AsRef<[u8]>>(self, bytes: T) -> Self {
         Self(self.0.chain_bytes(bytes))
     }
@@ -76,7 +165,9 @@ impl ScalarDigest {
 #[cfg(test)]
 mod tests {
 
-    use super::ScalarDigest;
+    use digest::Digest;
+
+    use super::{Hash, ScalarDigest};
     use crate::curve::{CurvePoint, CurveScalar};
 
     #[test]
@@ -110,5 +201,73 @@ mod tests {
             .finalize()
             .into();
         assert_ne!(s, s_diff_tag);
+
+        // The empty context is the same as no context
+        let s_empty_context: CurveScalar = ScalarDigest::new_with_dst_and_context(b"abc", b"")
+            .chain_points(&[p1, p2])
+            .chain_bytes(bytes)
+            .finalize()
+            .into();
+        assert_eq!(s, s_empty_context);
+
+        let s_context: CurveScalar = ScalarDigest::new_with_dst_and_context(b"abc", b"ctx")
+            .chain_points(&[p1, p2])
+            .chain_bytes(bytes)
+            .finalize()
+            .into();
+        assert_ne!(s, s_context);
+
+        let s_other_context: CurveScalar = ScalarDigest::new_with_dst_and_context(b"abc", b"ctx2")
+            .chain_points(&[p1, p2])
+            .chain_bytes(bytes)
+            .finalize()
+            .into();
+        assert_ne!(s_context, s_other_context);
+    }
+
+    #[test]
+    fn test_context_encoding() {
+        // The tag and the context are length-prefixed separately,
+        // so moving bytes between them changes the hash.
+        let h1 = Hash::new_with_dst_and_context(b"ab", b"cd")
+            .digest()
+            .finalize();
+        let h2 = Hash::new_with_dst_and_context(b"abc", b"d")
+            .digest()
+            .finalize();
+        assert_ne!(h1, h2);
+
+        // A hash with a context cannot be reproduced by a hash without one
+        // by moving the context into the data.
+        let with_context = Hash::new_with_dst_and_context(b"ab", b"cd")
+            .chain_bytes(b"data")
+            .digest()
+            .finalize();
+        let without_context = Hash::new_with_dst(b"ab")
+            .chain_bytes(2u32.to_be_bytes())
+            .chain_bytes(b"cd")
+            .chain_bytes(b"data")
+            .digest()
+            .finalize();
+        assert_ne!(with_context, without_context);
+    }
+
+    #[cfg(all(feature = "hash-keccak", not(feature = "p256")))]
+    #[test]
+    fn test_keccak_scalar_digest() {
+        use crate::traits::SerializableToArray;
+
+        // The construction that has to be reproduced in an EVM contract:
+        // `(keccak256(len(tag) || tag || data) mod (n - 1)) + 1`.
+        // The expected value was computed independently of this library,
+        // for the tag `abc` and the compressed generator point as the data.
+        let s: CurveScalar = ScalarDigest::new_with_dst(b"abc")
+            .chain_point(&CurvePoint::generator())
+            .finalize()
+            .into();
+        assert_eq!(
+            hex::encode(s.to_array()),
+            "96963bbbc775e48906ba3e12adcc6c11fe252e6b5410c7ba5920935e4ec000cb"
+        );
     }
 }
@@ -4,32 +4,55 @@
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 
-use crate::curve::{CurvePoint, NonZeroCurveScalar};
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::curve::{CurvePoint, CurveScalar, NonZeroCurveScalar};
+use crate::dst;
 use crate::hashing::ScalarDigest;
 use crate::key_frag::KeyFragID;
 use crate::keys::PublicKey;
+use crate::params::Parameters;
+use crate::policy::PolicyId;
+use crate::secret_box::SecretBox;
 use crate::traits::SerializableToArray;
 
-pub(crate) fn hash_to_polynomial_arg(
-    precursor: &CurvePoint,
-    pubkey: &CurvePoint,
-    dh_point: &CurvePoint,
-    kfrag_id: &KeyFragID,
-) -> NonZeroCurveScalar {
-    ScalarDigest::new_with_dst(b"POLYNOMIAL_ARG")
-        .chain_point(precursor)
-        .chain_point(pubkey)
-        .chain_point(dh_point)
-        .chain_bytes(kfrag_id)
-        .finalize()
+/// Hashes key fragment IDs to polynomial arguments (share indices).
+///
+/// The prefix common for all fragments of a delegation is absorbed once on creation,
+/// and the resulting state is reused for every fragment.
+#[derive(Clone)]
+pub(crate) struct PolynomialArgHasher(ScalarDigest);
+
+impl PolynomialArgHasher {
+    pub fn new(
+        params: &Parameters,
+        precursor: &CurvePoint,
+        pubkey: &CurvePoint,
+        dh_point: &CurvePoint,
+    ) -> Self {
+        Self(
+            ScalarDigest::new_with_dst_and_context(
+                dst::POLYNOMIAL_ARG,
+                params.application_context(),
+            )
+            .chain_point(precursor)
+            .chain_point(pubkey)
+            .chain_point(dh_point),
+        )
+    }
+
+    pub fn hash(&self, kfrag_id: &KeyFragID) -> NonZeroCurveScalar {
+        self.0.clone().chain_bytes(kfrag_id).finalize()
+    }
 }
 
 pub(crate) fn hash_to_shared_secret(
+    params: &Parameters,
     precursor: &CurvePoint,
     pubkey: &CurvePoint,
     dh_point: &CurvePoint,
 ) -> NonZeroCurveScalar {
-    ScalarDigest::new_with_dst(b"SHARED_SECRET")
+    ScalarDigest::new_with_dst_and_context(dst::SHARED_SECRET, params.application_context())
         .chain_point(precursor)
         .chain_point(pubkey)
         .chain_point(dh_point)
@@ -40,18 +63,48 @@ pub(crate) fn hash_capsule_points(
     capsule_e: &CurvePoint,
     capsule_v: &CurvePoint,
 ) -> NonZeroCurveScalar {
-    ScalarDigest::new_with_dst(b"CAPSULE_POINTS")
+    ScalarDigest::new_with_dst(dst::CAPSULE_POINTS)
         .chain_point(capsule_e)
         .chain_point(capsule_v)
         .finalize()
 }
 
-pub(crate) fn hash_to_cfrag_verification(points: &[CurvePoint]) -> NonZeroCurveScalar {
-    ScalarDigest::new_with_dst(b"CFRAG_VERIFICATION")
+pub(crate) fn hash_to_cfrag_verification(
+    params: &Parameters,
+    points: &[CurvePoint],
+) -> NonZeroCurveScalar {
+    ScalarDigest::new_with_dst_and_context(dst::CFRAG_VERIFICATION, params.application_context())
         .chain_points(points)
         .finalize()
 }
 
+/// Derives the randomness of the reencryption proof from a caller-supplied nonce.
+/// The key fragment's secret is included, so the result cannot be predicted by anyone else.
+pub(crate) fn hash_to_reencryption_nonce(
+    params: &Parameters,
+    kfrag_key: &CurveScalar,
+    kfrag_id: &KeyFragID,
+    capsule: &Capsule,
+    nonce: &[u8],
+) -> NonZeroCurveScalar {
+    ScalarDigest::new_with_dst_and_context(dst::REENCRYPTION_NONCE, params.application_context())
+        .chain_secret_bytes(&SecretBox::new(kfrag_key.to_array()))
+        .chain_bytes(kfrag_id)
+        .chain_bytes(capsule.to_array())
+        .chain_bytes(nonce)
+        .finalize()
+}
+
+pub(crate) fn hash_to_policy_key_factor(
+    master_pk: &CurvePoint,
+    label: &[u8],
+) -> NonZeroCurveScalar {
+    ScalarDigest::new_with_dst(dst::POLICY_KEY)
+        .chain_point(master_pk)
+        .chain_bytes(label)
+        .finalize()
+}
+
 pub(crate) fn kfrag_signature_message(
     kfrag_id: &KeyFragID,
     commitment: &CurvePoint,
@@ -60,6 +113,28 @@ pub(crate) fn kfrag_signature_message(
     maybe_receiving_pk: Option<&PublicKey>,
 ) -> Box<[u8]> {
     let mut result = Vec::<u8>::new();
+    write_kfrag_signature_message(
+        &mut result,
+        kfrag_id,
+        commitment,
+        precursor,
+        maybe_delegating_pk,
+        maybe_receiving_pk,
+    );
+    result.into_boxed_slice()
+}
+
+/// Writes the message returned by [`kfrag_signature_message`] into `result`,
+/// replacing its previous contents, so that the buffer can be reused.
+pub(crate) fn write_kfrag_signature_message(
+    result: &mut Vec<u8>,
+    kfrag_id: &KeyFragID,
+    commitment: &CurvePoint,
+    precursor: &CurvePoint,
+    maybe_delegating_pk: Option<&PublicKey>,
+    maybe_receiving_pk: Option<&PublicKey>,
+) {
+    result.clear();
 
     result.extend_from_slice(&kfrag_id.to_array());
     result.extend_from_slice(&commitment.to_array());
@@ -80,6 +155,89 @@ pub(crate) fn kfrag_signature_message(
         }
         None => result.extend_from_slice(&false.to_array()),
     };
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn delegation_certificate_message(
+    delegating_pk: &PublicKey,
+    receiving_pk: &PublicKey,
+    verifying_pk: &PublicKey,
+    policy_id: &PolicyId,
+    threshold: u64,
+    not_before: u64,
+    not_after: u64,
+) -> Box<[u8]> {
+    let mut result = Vec::<u8>::new();
+
+    result.extend_from_slice(b"DELEGATION_CERTIFICATE");
+    result.extend_from_slice(&delegating_pk.to_array());
+    result.extend_from_slice(&receiving_pk.to_array());
+    result.extend_from_slice(&verifying_pk.to_array());
+    result.extend_from_slice(&policy_id.to_array());
+    result.extend_from_slice(&threshold.to_array());
+    result.extend_from_slice(&not_before.to_array());
+    result.extend_from_slice(&not_after.to_array());
+
+    result.into_boxed_slice()
+}
+
+pub(crate) fn capsule_statement_message(capsule: &Capsule, statement: &[u8]) -> Box<[u8]> {
+    let mut result = Vec::<u8>::new();
+
+    result.extend_from_slice(b"CAPSULE_STATEMENT");
+    result.extend_from_slice(&capsule.canonical_digest());
+    result.extend_from_slice(&(statement.len() as u64).to_array());
+    result.extend_from_slice(statement);
 
     result.into_boxed_slice()
 }
+
+pub(crate) fn cfrag_response_message(cfrag: &CapsuleFrag, request_digest: &[u8]) -> Box<[u8]> {
+    let mut result = Vec::<u8>::new();
+
+    result.extend_from_slice(b"CFRAG_RESPONSE");
+    result.extend_from_slice(&cfrag.canonical_digest());
+    result.extend_from_slice(&(request_digest.len() as u64).to_array());
+    result.extend_from_slice(request_digest);
+
+    result.into_boxed_slice()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::PolynomialArgHasher;
+    use crate::curve::{CurvePoint, CurveScalar};
+    use crate::dst;
+    use crate::hashing::ScalarDigest;
+    use crate::key_frag::KeyFragID;
+    use crate::params::Parameters;
+    use crate::traits::{DeserializableFromArray, RepresentableAsArray};
+
+    #[test]
+    fn test_polynomial_arg_hasher() {
+        let precursor = CurvePoint::generator();
+        let pubkey = &precursor + &precursor;
+        let dh_point = &pubkey + &precursor;
+        let params = Parameters::new();
+        let hasher = PolynomialArgHasher::new(&params, &precursor, &pubkey, &dh_point);
+
+        for i in 0..3u8 {
+            let id_bytes: Vec<u8> = core::iter::repeat(i)
+                .take(KeyFragID::serialized_size())
+                .collect();
+            let kfrag_id = KeyFragID::from_bytes(&id_bytes).unwrap();
+            let expected: CurveScalar = ScalarDigest::new_with_dst(dst::POLYNOMIAL_ARG)
+                .chain_point(&precursor)
+                .chain_point(&pubkey)
+                .chain_point(&dh_point)
+                .chain_bytes(&kfrag_id)
+                .finalize()
+                .into();
+            let result: CurveScalar = hasher.hash(&kfrag_id).into();
+            assert_eq!(result, expected);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Minimal JSON support for the text-based key and message formats
+//! (JWK, JWE, keystores), which only need objects with string, integer,
+//! and nested object members.
+
+use alloc::string::String;
+
+// Nesting limit for the members that are skipped.
+const MAX_DEPTH: usize = 16;
+
+/// Appends a member with the given raw JSON value to a JSON object being built
+/// (`json` must start with `{`, and the closing `}` is added by the caller).
+/// The name is not escaped, so it must not contain `"` or `\`.
+#[cfg(feature = "keystore")]
+pub(crate) fn push_member(json: &mut String, name: &str, value: &str) {
+    if json.len() > 1 {
+        json.push(',');
+    }
+    json.push('"');
+    json.push_str(name);
+    json.push_str("\":");
+    json.push_str(value);
+}
+
+/// Appends a member with a string value to a JSON object being built.
+/// The name and the value are not escaped, so they must not contain `"` or `\`.
+pub(crate) fn push_string_member(json: &mut String, name: &str, value: &str) {
+    if json.len() > 1 {
+        json.push(',');
+    }
+    json.push('"');
+    json.push_str(name);
+    json.push_str("\":\"");
+    json.push_str(value);
+    json.push('"');
+}
+
+struct Parser<'a> {
+    data: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.data.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.skip_whitespace();
+        if self.peek()? == byte {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn parse_string(&mut self) -> Option<&'a str> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match self.peek()? {
+                b'"' => break,
+                b'\\' => self.pos += 2,
+                byte if byte < 0x20 => return None,
+                _ => self.pos += 1,
+            }
+        }
+        let result = self.data.get(start..self.pos)?;
+        self.pos += 1;
+        Some(result)
+    }
+
+    fn skip_value(&mut self, depth: usize) -> Option<()> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        self.skip_whitespace();
+        match self.peek()? {
+            b'"' => self.parse_string().map(|_| ()),
+            b'{' => self.parse_sequence(b'{', b'}', |parser| {
+                parser.parse_string()?;
+                parser.expect(b':')?;
+                parser.skip_value(depth + 1)
+            }),
+            b'[' => self.parse_sequence(b'[', b']', |parser| parser.skip_value(depth + 1)),
+            _ => {
+                // Numbers, `true`, `false`, and `null`
+                let start = self.pos;
+                while let Some(b'a'..=b'z' | b'0'..=b'9' | b'+' | b'-' | b'.' | b'E') = self.peek()
+                {
+                    self.pos += 1;
+                }
+                if self.pos > start {
+                    Some(())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn parse_sequence(
+        &mut self,
+        open: u8,
+        close: u8,
+        mut parse_item: impl FnMut(&mut Self) -> Option<()>,
+    ) -> Option<()> {
+        self.expect(open)?;
+        self.skip_whitespace();
+        if self.peek()? == close {
+            self.pos += 1;
+            return Some(());
+        }
+        loop {
+            parse_item(self)?;
+            self.skip_whitespace();
+            match self.peek()? {
+                b',' => self.pos += 1,
+                byte if byte == close => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+/// Parses a JSON object, returning the raw values (as they appear in the text)
+/// of the members with the given names (in the same order),
+/// and skipping all the other members.
+/// Duplicate requested members are rejected.
+pub(crate) fn parse_members<'a, const N: usize>(
+    json: &'a str,
+    names: [&str; N],
+) -> Result<[Option<&'a str>; N], &'static str> {
+    let malformed = "Malformed JSON object";
+
+    let mut values = [None; N];
+    let mut duplicate = false;
+
+    let mut parser = Parser { data: json, pos: 0 };
+    parser
+        .parse_sequence(b'{', b'}', |parser| {
+            let name = parser.parse_string()?;
+            parser.expect(b':')?;
+            parser.skip_whitespace();
+            let start = parser.pos;
+            parser.skip_value(0)?;
+            if let Some(i) = names.iter().position(|known| *known == name) {
+                duplicate |= values[i].is_some();
+                values[i] = Some(&json[start..parser.pos]);
+            }
+            Some(())
+        })
+        .ok_or(malformed)?;
+    parser.skip_whitespace();
+    if parser.pos != json.len() {
+        return Err(malformed);
+    }
+    if duplicate {
+        return Err("Duplicate JSON object members");
+    }
+    Ok(values)
+}
+
+/// Returns the contents of a raw JSON string value
+/// (without processing escape sequences; this is sufficient for base64url and hex data
+/// and the fixed identifiers the formats use),
+/// or `None` if the value is not a string.
+pub(crate) fn string_value(raw: &str) -> Option<&str> {
+    let mut parser = Parser { data: raw, pos: 0 };
+    let result = parser.parse_string()?;
+    if parser.pos == raw.len() {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+/// Returns the value of a raw JSON non-negative integer value,
+/// or `None` if the value is not an integer or does not fit into `u64`.
+#[cfg(feature = "keystore")]
+pub(crate) fn u64_value(raw: &str) -> Option<u64> {
+    let canonical = raw == "0" || !raw.starts_with('0');
+    if canonical && !raw.is_empty() && raw.bytes().all(|byte| byte.is_ascii_digit()) {
+        raw.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Parses a JSON object, returning the values of the members with the given names
+/// (in the same order), and skipping all the other members.
+///
+/// The requested members must have string values, which are returned as they are
+/// (see [`string_value`]).
+/// Duplicate requested members are rejected.
+pub(crate) fn parse_string_members<'a, const N: usize>(
+    json: &'a str,
+    names: [&str; N],
+) -> Result<[Option<&'a str>; N], &'static str> {
+    let raw_values = parse_members(json, names)?;
+    let mut values = [None; N];
+    for (value, raw_value) in values.iter_mut().zip(raw_values.iter()) {
+        if let Some(raw_value) = raw_value {
+            *value = Some(string_value(raw_value).ok_or("Malformed JSON object")?);
+        }
+    }
+    Ok(values)
+}
@@ -0,0 +1,253 @@
+//! JWE (RFC 7516) compact serialization of the encryption results,
+//! allowing them to be handled by the tools working with JOSE objects.
+//!
+//! The [`Capsule`] takes the place of the encrypted key, and the DEM ciphertext
+//! is split into the initialization vector, the ciphertext, and the authentication tag
+//! (the DEM ciphertext header is implied by the protected header, and is omitted).
+//! The protected header identifies the key management algorithm as [`JWE_ALGORITHM`]
+//! and the content encryption as [`JWE_ENCRYPTION`]
+//! (XChaCha20-Poly1305, or AES-256-GCM with the `fips-algorithms` feature).
+//!
+//! Note that, unlike regular JWE, the protected header is not used
+//! as the associated data (the DEM authenticates the capsule instead),
+//! so generic JOSE libraries cannot decrypt these objects;
+//! use [`from_jwe_compact`] and the regular decryption functions.
+//! Since only the `alg` and `enc` header parameters (which are checked on parsing)
+//! affect the processing, this does not allow an attacker to change the outcome.
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use typenum::Unsigned;
+
+use crate::capsule::Capsule;
+use crate::curve::{CurveType, UmbralCurve};
+use crate::dem::{ciphertext_body, CiphertextHeader, DecryptionError, NonceSize, TagSize};
+use crate::json::{parse_string_members, push_string_member};
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, SerializableToArray,
+};
+
+/// The value of the `alg` header parameter of the JWE objects produced by this library
+/// (`UMBRAL-SECP256K1`, or `UMBRAL-P256` with the `p256` feature).
+pub const JWE_ALGORITHM: &str = CurveType::JWE_ALGORITHM;
+
+/// The value of the `enc` header parameter of the JWE objects produced by this library.
+#[cfg(not(feature = "fips-algorithms"))]
+pub const JWE_ENCRYPTION: &str = "XC20P";
+
+/// The value of the `enc` header parameter of the JWE objects produced by this library.
+#[cfg(feature = "fips-algorithms")]
+pub const JWE_ENCRYPTION: &str = "A256GCM";
+
+const SEGMENTS: usize = 5;
+
+fn construction_error(message: &str) -> DeserializationError {
+    DeserializationError::ConstructionFailure(ConstructionError::new("JWE", message))
+}
+
+fn encode_base64url(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+fn decode_base64url(encoded: &str) -> Option<Vec<u8>> {
+    base64::decode_config(encoded, base64::URL_SAFE_NO_PAD).ok()
+}
+
+/// Serializes the capsule and the ciphertext produced by [`encrypt`](`crate::encrypt`)
+/// (or a similar function) into the JWE compact serialization.
+///
+/// Fails if the ciphertext was created with a different format version or algorithms,
+/// or is too short to contain the nonce and the authentication tag.
+pub fn to_jwe_compact(
+    capsule: &Capsule,
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<String, DecryptionError> {
+    let ciphertext = ciphertext_body(ciphertext.as_ref())?;
+    let nonce_size = <NonceSize as Unsigned>::to_usize();
+    let tag_size = <TagSize as Unsigned>::to_usize();
+    if ciphertext.len() < nonce_size + tag_size {
+        return Err(DecryptionError::CiphertextTooShort);
+    }
+    let (nonce, rest) = ciphertext.split_at(nonce_size);
+    let (encrypted, tag) = rest.split_at(rest.len() - tag_size);
+
+    let mut header = String::from("{");
+    push_string_member(&mut header, "alg", JWE_ALGORITHM);
+    push_string_member(&mut header, "enc", JWE_ENCRYPTION);
+    header.push('}');
+
+    let segments = [
+        encode_base64url(header.as_bytes()),
+        encode_base64url(&capsule.to_array()),
+        encode_base64url(nonce),
+        encode_base64url(encrypted),
+        encode_base64url(tag),
+    ];
+    Ok(segments.join("."))
+}
+
+/// Parses a JWE compact serialization produced by [`to_jwe_compact`],
+/// returning the capsule and the ciphertext, which can be passed to
+/// [`decrypt_original`](`crate::decrypt_original`) or
+/// [`decrypt_reencrypted`](`crate::decrypt_reencrypted`).
+pub fn from_jwe_compact(jwe: &str) -> Result<(Capsule, Box<[u8]>), DeserializationError> {
+    let segments: Vec<&str> = jwe.trim().split('.').collect();
+    if segments.len() != SEGMENTS {
+        return Err(construction_error("Wrong number of segments"));
+    }
+
+    let header = decode_base64url(segments[0])
+        .and_then(|header| String::from_utf8(header).ok())
+        .ok_or_else(|| construction_error("Invalid protected header encoding"))?;
+    let [alg, enc, crit] =
+        parse_string_members(&header, ["alg", "enc", "crit"]).map_err(construction_error)?;
+    if alg != Some(JWE_ALGORITHM) {
+        return Err(construction_error("Unsupported key management algorithm"));
+    }
+    if enc != Some(JWE_ENCRYPTION) {
+        return Err(construction_error(
+            "Unsupported content encryption algorithm",
+        ));
+    }
+    // No extensions are supported. A well-formed `crit` (an array) is rejected by the parser
+    // as a non-string value, and a string one is rejected here.
+    if crit.is_some() {
+        return Err(construction_error("Unsupported critical header parameters"));
+    }
+
+    let capsule_bytes = decode_base64url(segments[1])
+        .ok_or_else(|| construction_error("Invalid encrypted key encoding"))?;
+    let capsule = Capsule::from_bytes(capsule_bytes)?;
+
+    let nonce = decode_base64url(segments[2])
+        .filter(|nonce| nonce.len() == <NonceSize as Unsigned>::to_usize())
+        .ok_or_else(|| construction_error("Invalid initialization vector"))?;
+    let encrypted = decode_base64url(segments[3])
+        .ok_or_else(|| construction_error("Invalid ciphertext encoding"))?;
+    let tag = decode_base64url(segments[4])
+        .filter(|tag| tag.len() == <TagSize as Unsigned>::to_usize())
+        .ok_or_else(|| construction_error("Invalid authentication tag"))?;
+
+    let mut ciphertext =
+        Vec::with_capacity(CiphertextHeader::SIZE + nonce.len() + encrypted.len() + tag.len());
+    ciphertext.extend_from_slice(&CiphertextHeader::CURRENT.to_bytes());
+    ciphertext.extend_from_slice(&nonce);
+    ciphertext.extend_from_slice(&encrypted);
+    ciphertext.extend_from_slice(&tag);
+    Ok((capsule, ciphertext.into_boxed_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    use typenum::Unsigned;
+
+    use super::{
+        encode_base64url, from_jwe_compact, to_jwe_compact, JWE_ALGORITHM, JWE_ENCRYPTION,
+    };
+    use crate::dem::{CiphertextHeader, NonceSize, TagSize};
+    use crate::{decrypt_original, encrypt, DecryptionError, SecretKey};
+
+    fn replace_segment(jwe: &str, index: usize, segment: &str) -> String {
+        let mut segments: Vec<&str> = jwe.split('.').collect();
+        segments[index] = segment;
+        segments.join(".")
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let sk = SecretKey::random();
+        let pk = sk.public_key();
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&pk, plaintext).unwrap();
+
+        let jwe = to_jwe_compact(&capsule, &ciphertext).unwrap();
+        let segments: Vec<&str> = jwe.split('.').collect();
+        assert_eq!(segments.len(), 5);
+        assert_eq!(
+            segments[0],
+            encode_base64url(
+                format!(
+                    r#"{{"alg":"{}","enc":"{}"}}"#,
+                    JWE_ALGORITHM, JWE_ENCRYPTION
+                )
+                .as_bytes()
+            )
+        );
+
+        let (capsule_back, ciphertext_back) = from_jwe_compact(&jwe).unwrap();
+        assert_eq!(capsule_back, capsule);
+        assert_eq!(ciphertext_back, ciphertext);
+
+        let plaintext_back = decrypt_original(&sk, &capsule_back, &ciphertext_back).unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        // Empty plaintext leads to an empty ciphertext segment
+        let (capsule, ciphertext) = encrypt(&pk, b"").unwrap();
+        let jwe = to_jwe_compact(&capsule, &ciphertext).unwrap();
+        assert_eq!(jwe.split('.').nth(3), Some(""));
+        let overhead = CiphertextHeader::SIZE + NonceSize::to_usize() + TagSize::to_usize();
+        assert_eq!(ciphertext.len(), overhead);
+        assert_eq!(from_jwe_compact(&jwe).unwrap().1, ciphertext);
+
+        assert_eq!(
+            to_jwe_compact(&capsule, &ciphertext[..overhead - 1]),
+            Err(DecryptionError::CiphertextTooShort)
+        );
+
+        let mut other_version = ciphertext.to_vec();
+        other_version[0] = 2;
+        assert_eq!(
+            to_jwe_compact(&capsule, &other_version),
+            Err(DecryptionError::UnsupportedHeader)
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let pk = SecretKey::random().public_key();
+        let (capsule, ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+        let jwe = to_jwe_compact(&capsule, &ciphertext).unwrap();
+
+        // Other header parameters are allowed, but not `crit`
+        let header = encode_base64url(
+            format!(
+                r#"{{"enc":"{}","kid":"1","alg":"{}"}}"#,
+                JWE_ENCRYPTION, JWE_ALGORITHM
+            )
+            .as_bytes(),
+        );
+        assert!(from_jwe_compact(&replace_segment(&jwe, 0, &header)).is_ok());
+        let header = encode_base64url(
+            format!(
+                r#"{{"alg":"{}","enc":"{}","crit":["exp"],"exp":1}}"#,
+                JWE_ALGORITHM, JWE_ENCRYPTION
+            )
+            .as_bytes(),
+        );
+        assert!(from_jwe_compact(&replace_segment(&jwe, 0, &header)).is_err());
+
+        // Wrong algorithms
+        let header = encode_base64url(
+            format!(r#"{{"alg":"ECDH-ES","enc":"{}"}}"#, JWE_ENCRYPTION).as_bytes(),
+        );
+        assert!(from_jwe_compact(&replace_segment(&jwe, 0, &header)).is_err());
+        let header = encode_base64url(
+            format!(r#"{{"alg":"{}","enc":"A128GCM"}}"#, JWE_ALGORITHM).as_bytes(),
+        );
+        assert!(from_jwe_compact(&replace_segment(&jwe, 0, &header)).is_err());
+
+        // Malformed segments
+        assert!(from_jwe_compact(&replace_segment(&jwe, 1, "AAAA")).is_err());
+        assert!(from_jwe_compact(&replace_segment(&jwe, 2, "AAAA")).is_err());
+        assert!(from_jwe_compact(&replace_segment(&jwe, 4, "AAAA")).is_err());
+        assert!(from_jwe_compact(&replace_segment(&jwe, 3, "!")).is_err());
+        assert!(from_jwe_compact(&[&jwe, ".AAAA"].concat()).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! JSON Web Key (RFC 7517) encoding of the keys, with the `secp256k1` curve name of RFC 8812
+//! (or `P-256` of RFC 7518 with the `p256` feature), without depending on a JSON library.
+//!
+//! The export produces the members required by RFC 7518 (`kty`, `crv`, `x`, `y`,
+//! and `d` for secret keys); the import ignores any other members (`kid`, `use`, etc).
+
+use alloc::string::String;
+
+use digest::Digest;
+use elliptic_curve::sec1::ToEncodedPoint;
+use generic_array::GenericArray;
+use sha2::Sha256;
+use typenum::U32;
+use zeroize::Zeroize;
+
+use crate::curve::{CurveType, UmbralCurve};
+use crate::json::{parse_string_members, push_string_member};
+use crate::keys::{PublicKey, SecretKey};
+use crate::secret_box::SecretBox;
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, SerializableToSecretArray,
+};
+
+const KEY_TYPE: &str = "EC";
+const CURVE_NAME: &str = CurveType::JWK_NAME;
+const COORDINATE_SIZE: usize = 32;
+
+fn construction_error(type_name: &str, message: &str) -> DeserializationError {
+    DeserializationError::ConstructionFailure(ConstructionError::new(type_name, message))
+}
+
+fn encode_base64url(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+/// Decodes a base64url-encoded fixed-size value.
+fn decode_coordinate(encoded: &str) -> Option<GenericArray<u8, U32>> {
+    let mut decoded = base64::decode_config(encoded, base64::URL_SAFE_NO_PAD).ok()?;
+    let result = if decoded.len() == COORDINATE_SIZE {
+        Some(GenericArray::clone_from_slice(&decoded))
+    } else {
+        None
+    };
+    decoded.zeroize();
+    result
+}
+
+fn coordinates(public_key: &PublicKey) -> (String, String) {
+    let point = public_key.to_point().to_affine_point();
+    let uncompressed = point.to_encoded_point(false);
+    let (x, y) = uncompressed.as_bytes()[1..].split_at(COORDINATE_SIZE);
+    (encode_base64url(x), encode_base64url(y))
+}
+
+/// The members of a JWK object we are interested in.
+struct JwkMembers<'a> {
+    kty: Option<&'a str>,
+    crv: Option<&'a str>,
+    x: Option<&'a str>,
+    y: Option<&'a str>,
+    d: Option<&'a str>,
+}
+
+fn parse_jwk(json: &str) -> Result<JwkMembers<'_>, &'static str> {
+    let [kty, crv, x, y, d] = parse_string_members(json, ["kty", "crv", "x", "y", "d"])?;
+    let members = JwkMembers { kty, crv, x, y, d };
+
+    if members.kty != Some(KEY_TYPE) {
+        return Err("Unsupported JWK key type");
+    }
+    if members.crv != Some(CURVE_NAME) {
+        return Err("Unsupported JWK curve");
+    }
+    Ok(members)
+}
+
+fn public_key_from_members(members: &JwkMembers<'_>) -> Result<PublicKey, &'static str> {
+    let invalid = "Invalid JWK public key";
+    let x = decode_coordinate(members.x.ok_or(invalid)?).ok_or(invalid)?;
+    let y = decode_coordinate(members.y.ok_or(invalid)?).ok_or(invalid)?;
+    let mut uncompressed = [0u8; 1 + 2 * COORDINATE_SIZE];
+    uncompressed[0] = 0x04;
+    uncompressed[1..1 + COORDINATE_SIZE].copy_from_slice(&x);
+    uncompressed[1 + COORDINATE_SIZE..].copy_from_slice(&y);
+    PublicKey::from_sec1_bytes(uncompressed).map_err(|_| invalid)
+}
+
+impl PublicKey {
+    /// Exports the key as a JWK JSON object.
+    pub fn to_jwk(&self) -> String {
+        let (x, y) = coordinates(self);
+        let mut json = String::from("{");
+        push_string_member(&mut json, "kty", KEY_TYPE);
+        push_string_member(&mut json, "crv", CURVE_NAME);
+        push_string_member(&mut json, "x", &x);
+        push_string_member(&mut json, "y", &y);
+        json.push('}');
+        json
+    }
+
+    /// Imports the key from a JWK JSON object.
+    /// If the object contains a private key, it is ignored.
+    pub fn from_jwk(json: &str) -> Result<Self, DeserializationError> {
+        parse_jwk(json)
+            .and_then(|members| public_key_from_members(&members))
+            .map_err(|msg| construction_error("PublicKey", msg))
+    }
+
+    /// Returns the JWK thumbprint of the key (RFC 7638),
+    /// suitable for use as a `kid` value (after base64url encoding).
+    pub fn jwk_thumbprint(&self) -> [u8; 32] {
+        // The required members in lexicographic order, without whitespace.
+        let (x, y) = coordinates(self);
+        let mut json = String::from("{");
+        push_string_member(&mut json, "crv", CURVE_NAME);
+        push_string_member(&mut json, "kty", KEY_TYPE);
+        push_string_member(&mut json, "x", &x);
+        push_string_member(&mut json, "y", &y);
+        json.push('}');
+
+        let mut result = [0u8; 32];
+        result.copy_from_slice(&Sha256::digest(json.as_bytes()));
+        result
+    }
+}
+
+impl SecretKey {
+    /// Exports the key (along with the public key) as a JWK JSON object.
+    pub fn to_jwk(&self) -> SecretBox<String> {
+        let (x, y) = coordinates(&self.public_key());
+        let mut d = encode_base64url(self.to_secret_array().as_secret());
+        let mut json = String::from("{");
+        push_string_member(&mut json, "kty", KEY_TYPE);
+        push_string_member(&mut json, "crv", CURVE_NAME);
+        push_string_member(&mut json, "x", &x);
+        push_string_member(&mut json, "y", &y);
+        push_string_member(&mut json, "d", &d);
+        json.push('}');
+        d.zeroize();
+        SecretBox::new(json)
+    }
+
+    /// Imports the key from a JWK JSON object.
+    /// Fails if the public key in the object does not correspond to the private one.
+    pub fn from_jwk(json: &str) -> Result<Self, DeserializationError> {
+        let members = parse_jwk(json).map_err(|msg| construction_error("SecretKey", msg))?;
+        let d = members
+            .d
+            .ok_or_else(|| construction_error("SecretKey", "JWK does not contain a private key"))?;
+        let scalar = SecretBox::new(
+            decode_coordinate(d)
+                .ok_or_else(|| construction_error("SecretKey", "Invalid JWK private key"))?,
+        );
+        let secret_key = SecretKey::from_bytes(scalar.as_secret())?;
+        let public_key = public_key_from_members(&members)
+            .map_err(|msg| construction_error("SecretKey", msg))?;
+        if secret_key.public_key() != public_key {
+            return Err(construction_error(
+                "SecretKey",
+                "JWK public key does not match the private one",
+            ));
+        }
+        Ok(secret_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::string::String;
+
+    use super::CURVE_NAME;
+    use crate::{DeserializableFromArray, PublicKey, SecretKey, SerializableToSecretArray};
+
+    #[cfg(not(feature = "p256"))]
+    const SK_ONE_X: &str = "eb5mfvncu6xVoGKVzocLBwKb_NstzijZWfKBWxb4F5g";
+    #[cfg(not(feature = "p256"))]
+    const SK_ONE_Y: &str = "SDradyajxGVdpPv8DhEIqP0XtEimhVQZnEfQj_sQ1Lg";
+    #[cfg(not(feature = "p256"))]
+    const SK_ONE_THUMBPRINT: &str =
+        "d8917cbe0f5eb49ce31706709a4be104b2d9d1b7cc55538f8af611e6516d71e7";
+
+    #[cfg(feature = "p256")]
+    const SK_ONE_X: &str = "axfR8uEsQkf4vOblY6RA8ncDfYEt6zOg9KE5RdiYwpY";
+    #[cfg(feature = "p256")]
+    const SK_ONE_Y: &str = "T-NC4v4af5uO5-tKfA-eFivOM1drMV7Oy7ZAaDe_UfU";
+    #[cfg(feature = "p256")]
+    const SK_ONE_THUMBPRINT: &str =
+        "c71d01700fb0328870f1ab580c939eea9786328764946db04470655c732f9743";
+    const SK_ONE_D: &str = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAE";
+
+    fn sk_one() -> SecretKey {
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        SecretKey::from_bytes(one).unwrap()
+    }
+
+    #[test]
+    fn test_known_encoding() {
+        let sk = sk_one();
+        let pk = sk.public_key();
+
+        let expected_pk = [
+            r#"{"kty":"EC","crv":""#,
+            CURVE_NAME,
+            r#"","x":""#,
+            SK_ONE_X,
+            r#"","y":""#,
+            SK_ONE_Y,
+            r#""}"#,
+        ]
+        .concat();
+        assert_eq!(pk.to_jwk(), expected_pk);
+
+        let expected_sk = [
+            &expected_pk[..expected_pk.len() - 1],
+            r#","d":""#,
+            SK_ONE_D,
+            r#""}"#,
+        ]
+        .concat();
+        assert_eq!(sk.to_jwk().as_secret(), &expected_sk);
+
+        // RFC 7638 thumbprint
+        assert_eq!(hex::encode(pk.jwk_thumbprint()), SK_ONE_THUMBPRINT);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let sk = SecretKey::random();
+        let pk = sk.public_key();
+
+        assert_eq!(PublicKey::from_jwk(&pk.to_jwk()).unwrap(), pk);
+
+        let sk_jwk = sk.to_jwk();
+        let sk_back = SecretKey::from_jwk(sk_jwk.as_secret()).unwrap();
+        assert_eq!(
+            sk_back.to_secret_array().as_secret(),
+            sk.to_secret_array().as_secret()
+        );
+
+        // A public key can be extracted from a secret JWK
+        assert_eq!(PublicKey::from_jwk(sk_jwk.as_secret()).unwrap(), pk);
+
+        // But not the other way around
+        assert!(SecretKey::from_jwk(&pk.to_jwk()).is_err());
+    }
+
+    #[test]
+    fn test_parsing() {
+        // Other members and whitespace are allowed
+        let json = [
+            "{\n  \"kid\": \"key-1\",\n  \"key_ops\": [\"verify\", {\"a\": [1, -2.5e3]}],",
+            "\n  \"ext\": true, \"crv\": \"",
+            CURVE_NAME,
+            "\", \"y\": \"",
+            SK_ONE_Y,
+            "\",\n  \"x\": \"",
+            SK_ONE_X,
+            "\", \"kty\": \"EC\", \"nested\": null\n}\n",
+        ]
+        .concat();
+        assert_eq!(PublicKey::from_jwk(&json).unwrap(), sk_one().public_key());
+
+        let valid = sk_one().public_key().to_jwk();
+
+        // Wrong key type or curve
+        assert!(PublicKey::from_jwk(&valid.replace("\"EC\"", "\"OKP\"")).is_err());
+        assert!(PublicKey::from_jwk(&valid.replace(CURVE_NAME, "P-384")).is_err());
+
+        // Duplicate members
+        let duplicate = valid.replace("{", r#"{"kty":"EC","#);
+        assert!(PublicKey::from_jwk(&duplicate).is_err());
+
+        // Trailing data and truncation
+        assert!(PublicKey::from_jwk(&[&valid, "{}"].concat()).is_err());
+        assert!(PublicKey::from_jwk(&valid[..valid.len() - 1]).is_err());
+
+        // A point not on the curve
+        let off_curve = valid.replace(SK_ONE_Y, SK_ONE_X);
+        assert!(PublicKey::from_jwk(&off_curve).is_err());
+
+        // Mismatched public key in a secret JWK
+        let sk_jwk = sk_one().to_jwk();
+        let other_x = {
+            let other = SecretKey::random().public_key().to_jwk();
+            let start = other.find("\"x\":\"").unwrap() + 5;
+            String::from(&other[start..start + SK_ONE_X.len()])
+        };
+        let mismatched = sk_jwk.as_secret().replace(SK_ONE_X, &other_x);
+        assert!(SecretKey::from_jwk(&mismatched).is_err());
+    }
+}
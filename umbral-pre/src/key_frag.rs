@@ -1,23 +1,33 @@
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::fmt;
+use core::str::FromStr;
 
 use generic_array::sequence::Concat;
 use generic_array::GenericArray;
 use rand_core::{CryptoRng, RngCore};
+use subtle::{Choice, ConstantTimeEq};
 use typenum::{op, U32};
 
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
 #[cfg(feature = "serde-support")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::curve::{CurvePoint, CurveScalar, NonZeroCurveScalar};
-use crate::hashing_ds::{hash_to_polynomial_arg, hash_to_shared_secret, kfrag_signature_message};
+use crate::curve::{lincomb, CurvePoint, CurveScalar, NonZeroCurveScalar};
+use crate::hashing_ds::{
+    hash_to_shared_secret, write_kfrag_signature_message, PolynomialArgHasher,
+};
 use crate::keys::{PublicKey, SecretKey, Signature, Signer};
 use crate::params::Parameters;
+#[cfg(feature = "std")]
+use crate::pool::BufferPool;
 use crate::secret_box::SecretBox;
+use crate::source::{HasSourceId, SourceId};
 use crate::traits::{
-    fmt_public, ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
-    RepresentableAsArray, SerializableToArray,
+    fmt_public, from_str_public, ConstructionError, DeserializableFromArray, DeserializationError,
+    HasTypeName, RepresentableAsArray, SerializableToArray, SizeMismatchError,
 };
 
 #[cfg(feature = "serde-support")]
@@ -26,12 +36,14 @@ use crate::serde::{serde_deserialize, serde_serialize, Representation};
 #[allow(clippy::upper_case_acronyms)]
 type KeyFragIDSize = U32;
 
+/// A unique identifier of a [`KeyFrag`], shared by the [`CapsuleFrag`](`crate::CapsuleFrag`)
+/// objects created with it.
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub(crate) struct KeyFragID(GenericArray<u8, KeyFragIDSize>);
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyFragID(GenericArray<u8, KeyFragIDSize>);
 
 impl KeyFragID {
-    fn random(rng: &mut impl RngCore) -> Self {
+    pub(crate) fn random(rng: &mut impl RngCore) -> Self {
         let mut bytes = GenericArray::<u8, KeyFragIDSize>::default();
         rng.fill_bytes(&mut bytes);
         Self(bytes)
@@ -60,6 +72,54 @@ impl DeserializableFromArray for KeyFragID {
     }
 }
 
+impl ConstantTimeEq for KeyFragID {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.as_slice().ct_eq(other.0.as_slice())
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for KeyFragID {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize(self, serializer, Representation::Hex)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for KeyFragID {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_deserialize(deserializer, Representation::Hex)
+    }
+}
+
+impl HasTypeName for KeyFragID {
+    fn type_name() -> &'static str {
+        "KeyFragID"
+    }
+}
+
+impl fmt::Display for KeyFragID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(self, f)
+    }
+}
+
+impl FromStr for KeyFragID {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct KeyFragProof {
     pub(crate) commitment: CurvePoint,
@@ -123,35 +183,40 @@ impl KeyFragProof {
         kfrag_key: &CurveScalar,
         sign_delegating_key: bool,
         sign_receiving_key: bool,
+        message: &mut Vec<u8>,
     ) -> Self {
         let commitment = &base.params.u * kfrag_key;
 
         let maybe_delegating_pk = Some(&base.delegating_pk);
         let maybe_receiving_pk = Some(&base.receiving_pk);
 
-        let signature_for_receiver = base.signer.sign_with_rng(
-            rng,
-            kfrag_signature_message(
-                kfrag_id,
-                &commitment,
-                &base.precursor,
-                maybe_delegating_pk,
-                maybe_receiving_pk,
-            )
-            .as_ref(),
-        );
+        let context = base.params.application_context();
 
-        let signature_for_proxy = base.signer.sign_with_rng(
-            rng,
-            kfrag_signature_message(
+        write_kfrag_signature_message(
+            message,
+            kfrag_id,
+            &commitment,
+            &base.precursor,
+            maybe_delegating_pk,
+            maybe_receiving_pk,
+        );
+        let signature_for_receiver = base.signer.sign_with_context_and_rng(rng, context, message);
+
+        // If both keys are signed, the messages are the same,
+        // and the signature can be reused (allowing the compact serialization to omit it).
+        let signature_for_proxy = if sign_delegating_key && sign_receiving_key {
+            signature_for_receiver.clone()
+        } else {
+            write_kfrag_signature_message(
+                message,
                 kfrag_id,
                 &commitment,
                 &base.precursor,
                 none_unless(maybe_delegating_pk, sign_delegating_key),
                 none_unless(maybe_receiving_pk, sign_receiving_key),
-            )
-            .as_ref(),
-        );
+            );
+            base.signer.sign_with_context_and_rng(rng, context, message)
+        };
 
         Self {
             commitment,
@@ -163,10 +228,32 @@ impl KeyFragProof {
     }
 }
 
+// Flags of the compact key fragment serialization.
+const COMPACT_DELEGATING_KEY_SIGNED: u8 = 1;
+const COMPACT_RECEIVING_KEY_SIGNED: u8 = 2;
+const COMPACT_HAS_PROXY_SIGNATURE: u8 = 4;
+
+/// Public properties of the delegation a key fragment was created for,
+/// as returned by [`KeyFrag::delegation_info`].
+///
+/// The threshold and the policy label are not stored in key fragments,
+/// and have to be tracked by the application separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DelegationInfo {
+    /// The identifier of the key fragment.
+    pub id: KeyFragID,
+    /// Whether the delegating key was included in the signature
+    /// (and, therefore, has to be provided to [`KeyFrag::verify`]).
+    pub delegating_key_signed: bool,
+    /// Whether the receiving key was included in the signature
+    /// (and, therefore, has to be provided to [`KeyFrag::verify`]).
+    pub receiving_key_signed: bool,
+}
+
 /// A fragment of the encrypting party's key used to create a [`CapsuleFrag`](`crate::CapsuleFrag`).
 #[derive(Clone, Debug, PartialEq)]
 pub struct KeyFrag {
-    params: Parameters,
+    pub(crate) params: Parameters,
     pub(crate) id: KeyFragID,
     pub(crate) key: CurveScalar,
     pub(crate) precursor: CurvePoint,
@@ -238,8 +325,17 @@ impl fmt::Display for KeyFrag {
     }
 }
 
+impl FromStr for KeyFrag {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
 /// Possible errors that can be returned by [`KeyFrag::verify`].
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
 pub enum KeyFragVerificationError {
     /// Inconsistent internal state leading to commitment verification failure.
     IncorrectCommitment,
@@ -264,12 +360,17 @@ impl fmt::Display for KeyFragVerificationError {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for KeyFragVerificationError {}
+
 impl KeyFrag {
     fn from_base(
         rng: &mut (impl CryptoRng + RngCore),
         base: &KeyFragBase<'_>,
         sign_delegating_key: bool,
         sign_receiving_key: bool,
+        message: &mut Vec<u8>,
     ) -> Self {
         let kfrag_id = KeyFragID::random(rng);
 
@@ -277,12 +378,7 @@ impl KeyFrag {
         // Sharing corresponds to x in the tuple (x, f(x)), with f being the
         // generating polynomial), is used to prevent reconstruction of the
         // re-encryption key without Bob's intervention
-        let share_index = hash_to_polynomial_arg(
-            &base.precursor,
-            &base.receiving_pk.to_point(),
-            &base.dh_point,
-            &kfrag_id,
-        );
+        let share_index = base.polynomial_arg_hasher.hash(&kfrag_id);
 
         // The re-encryption key share is the result of evaluating the generating
         // polynomial for the index value
@@ -295,6 +391,7 @@ impl KeyFrag {
             &rk,
             sign_delegating_key,
             sign_receiving_key,
+            message,
         );
 
         Self {
@@ -306,6 +403,18 @@ impl KeyFrag {
         }
     }
 
+    /// Attaches custom parameters to the key fragment,
+    /// replacing the default ones it was created with on deserialization.
+    ///
+    /// Required to verify the key fragments created by
+    /// [`generate_kfrags_with_params()`](`crate::generate_kfrags_with_params()`).
+    pub fn with_params(self, params: &Parameters) -> Self {
+        Self {
+            params: *params,
+            ..self
+        }
+    }
+
     /// Verifies the integrity of the key fragment, given the signing key,
     /// and (optionally) the encrypting party's and decrypting party's keys.
     ///
@@ -317,6 +426,23 @@ impl KeyFrag {
         verifying_pk: &PublicKey,
         maybe_delegating_pk: Option<&PublicKey>,
         maybe_receiving_pk: Option<&PublicKey>,
+    ) -> Result<VerifiedKeyFrag, (KeyFragVerificationError, Self)> {
+        self.verify_impl(
+            true,
+            verifying_pk,
+            maybe_delegating_pk,
+            maybe_receiving_pk,
+            &mut Vec::new(),
+        )
+    }
+
+    fn verify_impl(
+        self,
+        check_commitment: bool,
+        verifying_pk: &PublicKey,
+        maybe_delegating_pk: Option<&PublicKey>,
+        maybe_receiving_pk: Option<&PublicKey>,
+        message: &mut Vec<u8>,
     ) -> Result<VerifiedKeyFrag, (KeyFragVerificationError, Self)> {
         let u = self.params.u;
 
@@ -326,7 +452,7 @@ impl KeyFrag {
         let precursor = self.precursor;
 
         // We check that the commitment is well-formed
-        if commitment != &u * &key {
+        if check_commitment && commitment != &u * &key {
             return Err((KeyFragVerificationError::IncorrectCommitment, self));
         }
 
@@ -342,16 +468,18 @@ impl KeyFrag {
 
         // Check the signature
 
-        if !self.proof.signature_for_proxy.verify(
+        write_kfrag_signature_message(
+            message,
+            &kfrag_id,
+            &commitment,
+            &precursor,
+            none_unless(maybe_delegating_pk, self.proof.delegating_key_signed),
+            none_unless(maybe_receiving_pk, self.proof.receiving_key_signed),
+        );
+        if !self.proof.signature_for_proxy.verify_with_context(
             verifying_pk,
-            kfrag_signature_message(
-                &kfrag_id,
-                &commitment,
-                &precursor,
-                none_unless(maybe_delegating_pk, self.proof.delegating_key_signed),
-                none_unless(maybe_receiving_pk, self.proof.receiving_key_signed),
-            )
-            .as_ref(),
+            self.params.application_context(),
+            message,
         ) {
             return Err((KeyFragVerificationError::IncorrectSignature, self));
         }
@@ -359,6 +487,151 @@ impl KeyFrag {
         Ok(VerifiedKeyFrag { kfrag: self })
     }
 
+    /// Verifies many key fragments at once, possibly from different delegations,
+    /// using the given RNG.
+    /// Each item contains the key fragment and the keys to verify it with,
+    /// in the same order as the arguments of [`verify`](`Self::verify`).
+    ///
+    /// The commitments of all the fragments are checked with a single random linear combination
+    /// (falling back to the individual checks if the combined one fails).
+    /// The signatures are verified one by one, since ECDSA does not support batching,
+    /// and dominate the cost, so this is not significantly faster
+    /// than calling [`verify`](`Self::verify`) for each fragment.
+    ///
+    /// The results are returned in the order of the items.
+    pub fn verify_batch_with_rng<'a>(
+        rng: &mut (impl CryptoRng + RngCore),
+        items: impl IntoIterator<
+            Item = (
+                Self,
+                &'a PublicKey,
+                Option<&'a PublicKey>,
+                Option<&'a PublicKey>,
+            ),
+        >,
+    ) -> Vec<Result<VerifiedKeyFrag, (KeyFragVerificationError, Self)>> {
+        Self::verify_batch_impl(rng, items, &mut Vec::new())
+    }
+
+    /// A variant of [`verify_batch_with_rng`](`Self::verify_batch_with_rng`)
+    /// attaching the given custom parameters to every key fragment before verification
+    /// (see [`with_params`](`Self::with_params`)).
+    pub fn verify_batch_with_params_and_rng<'a>(
+        rng: &mut (impl CryptoRng + RngCore),
+        params: &Parameters,
+        items: impl IntoIterator<
+            Item = (
+                Self,
+                &'a PublicKey,
+                Option<&'a PublicKey>,
+                Option<&'a PublicKey>,
+            ),
+        >,
+    ) -> Vec<Result<VerifiedKeyFrag, (KeyFragVerificationError, Self)>> {
+        let items = items.into_iter().map(
+            |(kfrag, verifying_pk, maybe_delegating_pk, maybe_receiving_pk)| {
+                (
+                    kfrag.with_params(params),
+                    verifying_pk,
+                    maybe_delegating_pk,
+                    maybe_receiving_pk,
+                )
+            },
+        );
+        Self::verify_batch_impl(rng, items, &mut Vec::new())
+    }
+
+    /// Same as [`verify_batch_with_rng`](`Self::verify_batch_with_rng`),
+    /// but uses a buffer from `pool` for the signed messages
+    /// instead of allocating one.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn verify_batch_with_pool_and_rng<'a>(
+        rng: &mut (impl CryptoRng + RngCore),
+        pool: &BufferPool,
+        items: impl IntoIterator<
+            Item = (
+                Self,
+                &'a PublicKey,
+                Option<&'a PublicKey>,
+                Option<&'a PublicKey>,
+            ),
+        >,
+    ) -> Vec<Result<VerifiedKeyFrag, (KeyFragVerificationError, Self)>> {
+        Self::verify_batch_impl(rng, items, &mut pool.take())
+    }
+
+    fn verify_batch_impl<'a>(
+        rng: &mut (impl CryptoRng + RngCore),
+        items: impl IntoIterator<
+            Item = (
+                Self,
+                &'a PublicKey,
+                Option<&'a PublicKey>,
+                Option<&'a PublicKey>,
+            ),
+        >,
+        message: &mut Vec<u8>,
+    ) -> Vec<Result<VerifiedKeyFrag, (KeyFragVerificationError, Self)>> {
+        let items: Vec<_> = items.into_iter().collect();
+
+        // The combined check only makes sense if all the fragments share the parameters.
+        let commitments_valid = match items.first() {
+            Some((first, _, _, _)) => {
+                let u = first.params.u;
+                let mut combined_key = CurveScalar::default();
+                let mut commitments = Vec::with_capacity(items.len());
+                let mut coeffs = Vec::with_capacity(items.len());
+                let mut same_params = true;
+                for (kfrag, _, _, _) in items.iter() {
+                    if kfrag.params != first.params {
+                        same_params = false;
+                        break;
+                    }
+                    let coeff = CurveScalar::from(NonZeroCurveScalar::random(rng));
+                    combined_key = &combined_key + &(&kfrag.key * &coeff);
+                    commitments.push(kfrag.proof.commitment);
+                    coeffs.push(coeff);
+                }
+                // The commitments are public, so a variable-time algorithm can be used.
+                same_params && lincomb(&commitments, &coeffs) == Some(&u * &combined_key)
+            }
+            None => true,
+        };
+
+        items
+            .into_iter()
+            .map(
+                |(kfrag, verifying_pk, maybe_delegating_pk, maybe_receiving_pk)| {
+                    kfrag.verify_impl(
+                        !commitments_valid,
+                        verifying_pk,
+                        maybe_delegating_pk,
+                        maybe_receiving_pk,
+                        message,
+                    )
+                },
+            )
+            .collect()
+    }
+
+    /// A synonym for [`verify_batch_with_rng`](`Self::verify_batch_with_rng`)
+    /// with the default RNG.
+    #[cfg(feature = "default-rng")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+    pub fn verify_batch<'a>(
+        items: impl IntoIterator<
+            Item = (
+                Self,
+                &'a PublicKey,
+                Option<&'a PublicKey>,
+                Option<&'a PublicKey>,
+            ),
+        >,
+    ) -> Vec<Result<VerifiedKeyFrag, (KeyFragVerificationError, Self)>> {
+        Self::verify_batch_with_rng(&mut OsRng, items)
+    }
+
     /// Explicitly skips verification.
     /// Useful in cases when the verifying keys are impossible to obtain independently.
     ///
@@ -366,6 +639,123 @@ impl KeyFrag {
     pub fn skip_verification(self) -> VerifiedKeyFrag {
         VerifiedKeyFrag { kfrag: self }
     }
+
+    /// Returns the identifier of this key fragment.
+    pub fn id(&self) -> KeyFragID {
+        self.id
+    }
+
+    /// Returns the public properties of the delegation this key fragment belongs to.
+    pub fn delegation_info(&self) -> DelegationInfo {
+        DelegationInfo {
+            id: self.id,
+            delegating_key_signed: self.proof.delegating_key_signed,
+            receiving_key_signed: self.proof.receiving_key_signed,
+        }
+    }
+
+    /// Serializes the key fragment in a variable-length form that omits the signature
+    /// for the proxy if it is the same as the signature for the receiver
+    /// (which is the case for fragments created with both the delegating
+    /// and the receiving keys signed), saving 64 bytes.
+    ///
+    /// The form is the identifier, the key, the precursor, the commitment,
+    /// the signature for the receiver, a byte with the flags
+    /// (`1` if the delegating key was signed, `2` if the receiving key was signed,
+    /// `4` if the signature for the proxy follows),
+    /// and, if included, the signature for the proxy.
+    ///
+    /// Not to be confused with [`CompactKeyFrag`], which cannot be verified at all.
+    pub fn to_compact_bytes(&self) -> Box<[u8]> {
+        let signature_size = Signature::serialized_size();
+        let prefix_size = Self::serialized_size() - 2 * signature_size - 2;
+
+        let proof = &self.proof;
+        let has_proxy_signature = !(proof.delegating_key_signed
+            && proof.receiving_key_signed
+            && proof.signature_for_proxy == proof.signature_for_receiver);
+        let mut flags = 0;
+        if proof.delegating_key_signed {
+            flags |= COMPACT_DELEGATING_KEY_SIGNED;
+        }
+        if proof.receiving_key_signed {
+            flags |= COMPACT_RECEIVING_KEY_SIGNED;
+        }
+        if has_proxy_signature {
+            flags |= COMPACT_HAS_PROXY_SIGNATURE;
+        }
+
+        let arr = self.to_array();
+        let mut result = Vec::with_capacity(prefix_size + 2 * signature_size + 1);
+        result.extend_from_slice(&arr[..prefix_size]);
+        result.extend_from_slice(&proof.signature_for_receiver.to_array());
+        result.push(flags);
+        if has_proxy_signature {
+            result.extend_from_slice(&proof.signature_for_proxy.to_array());
+        }
+        result.into_boxed_slice()
+    }
+
+    /// Restores the key fragment from the form produced by
+    /// [`to_compact_bytes`](`Self::to_compact_bytes`).
+    pub fn from_compact_bytes(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let data = data.as_ref();
+        let signature_size = Signature::serialized_size();
+        let prefix_size = Self::serialized_size() - 2 * signature_size - 2;
+        let flags_position = prefix_size + signature_size;
+
+        let flags = *data.get(flags_position).ok_or_else(|| {
+            DeserializationError::SizeMismatch(SizeMismatchError::new(
+                data.len(),
+                flags_position + 1,
+            ))
+        })?;
+        let construction_error = |message| {
+            DeserializationError::ConstructionFailure(ConstructionError::new("KeyFrag", message))
+        };
+        let all_flags = COMPACT_DELEGATING_KEY_SIGNED
+            | COMPACT_RECEIVING_KEY_SIGNED
+            | COMPACT_HAS_PROXY_SIGNATURE;
+        if flags & !all_flags != 0 {
+            return Err(construction_error("Unknown compact serialization flags"));
+        }
+        let delegating_key_signed = flags & COMPACT_DELEGATING_KEY_SIGNED != 0;
+        let receiving_key_signed = flags & COMPACT_RECEIVING_KEY_SIGNED != 0;
+        let has_proxy_signature = flags & COMPACT_HAS_PROXY_SIGNATURE != 0;
+        if !(has_proxy_signature || delegating_key_signed && receiving_key_signed) {
+            return Err(construction_error("The signature for the proxy is missing"));
+        }
+
+        let expected_size = flags_position
+            + 1
+            + if has_proxy_signature {
+                signature_size
+            } else {
+                0
+            };
+        if data.len() != expected_size {
+            return Err(DeserializationError::SizeMismatch(SizeMismatchError::new(
+                data.len(),
+                expected_size,
+            )));
+        }
+
+        let signature_for_receiver = &data[prefix_size..flags_position];
+        let signature_for_proxy = if has_proxy_signature {
+            &data[flags_position + 1..]
+        } else {
+            signature_for_receiver
+        };
+
+        // Reassemble the full serialization, so that all the fields are validated as usual.
+        let mut full = Vec::with_capacity(Self::serialized_size());
+        full.extend_from_slice(&data[..prefix_size]);
+        full.extend_from_slice(signature_for_proxy);
+        full.extend_from_slice(signature_for_receiver);
+        full.push(u8::from(delegating_key_signed));
+        full.push(u8::from(receiving_key_signed));
+        Self::from_bytes(full)
+    }
 }
 
 /// Verified key fragment, good for reencryption.
@@ -400,14 +790,16 @@ impl fmt::Display for VerifiedKeyFrag {
 }
 
 impl VerifiedKeyFrag {
+    /// `message` is a buffer for the signed messages, reused between the calls.
     pub(crate) fn from_base(
         rng: &mut (impl CryptoRng + RngCore),
         base: &KeyFragBase<'_>,
         sign_delegating_key: bool,
         sign_receiving_key: bool,
+        message: &mut Vec<u8>,
     ) -> Self {
         Self {
-            kfrag: KeyFrag::from_base(rng, base, sign_delegating_key, sign_receiving_key),
+            kfrag: KeyFrag::from_base(rng, base, sign_delegating_key, sign_receiving_key, message),
         }
     }
 
@@ -420,6 +812,22 @@ impl VerifiedKeyFrag {
         KeyFrag::from_bytes(data).map(|kfrag| Self { kfrag })
     }
 
+    /// Returns the identifier of this key fragment.
+    pub fn id(&self) -> KeyFragID {
+        self.kfrag.id
+    }
+
+    /// Returns the public properties of the delegation this key fragment belongs to.
+    pub fn delegation_info(&self) -> DelegationInfo {
+        self.kfrag.delegation_info()
+    }
+
+    /// Serializes the key fragment in the compact form
+    /// (see [`KeyFrag::to_compact_bytes`]).
+    pub fn to_compact_bytes(&self) -> Box<[u8]> {
+        self.kfrag.to_compact_bytes()
+    }
+
     /// Clears the verification status from the keyfrag.
     /// Useful for the cases where it needs to be put in the protocol structure
     /// containing [`KeyFrag`] types (since those are the ones
@@ -427,28 +835,156 @@ impl VerifiedKeyFrag {
     pub fn unverify(self) -> KeyFrag {
         self.kfrag
     }
+
+    /// Returns a copy of the keyfrag without the verification status,
+    /// leaving this object intact.
+    pub fn clone_unverified(&self) -> KeyFrag {
+        self.kfrag.clone()
+    }
+}
+
+impl From<VerifiedKeyFrag> for KeyFrag {
+    fn from(verified: VerifiedKeyFrag) -> Self {
+        verified.unverify()
+    }
+}
+
+/// A key fragment without the commitment and the signatures,
+/// that is, without the material necessary to verify it
+/// or to prove the correctness of reencryption.
+///
+/// About a third of the size of [`KeyFrag`].
+/// Can only be used with [`reencrypt_compact`](`crate::reencrypt_compact`),
+/// creating an [`UnprovenCapsuleFrag`](`crate::UnprovenCapsuleFrag`).
+/// Intended for closed deployments where key fragments travel over authenticated channels
+/// and proxies are trusted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompactKeyFrag {
+    pub(crate) id: KeyFragID,
+    pub(crate) key: CurveScalar,
+    pub(crate) precursor: CurvePoint,
+}
+
+impl CompactKeyFrag {
+    /// Returns the identifier of this key fragment.
+    pub fn id(&self) -> KeyFragID {
+        self.id
+    }
+}
+
+impl From<&VerifiedKeyFrag> for CompactKeyFrag {
+    fn from(vkfrag: &VerifiedKeyFrag) -> Self {
+        Self {
+            id: vkfrag.kfrag.id,
+            key: vkfrag.kfrag.key,
+            precursor: vkfrag.kfrag.precursor,
+        }
+    }
+}
+
+impl RepresentableAsArray for CompactKeyFrag {
+    type Size = op!(ScalarSize + ScalarSize + PointSize);
+}
+
+impl SerializableToArray for CompactKeyFrag {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.id
+            .to_array()
+            .concat(self.key.to_array())
+            .concat(self.precursor.to_array())
+    }
+}
+
+impl DeserializableFromArray for CompactKeyFrag {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let (id, rest) = KeyFragID::take(*arr)?;
+        let (key, rest) = CurveScalar::take(rest)?;
+        let precursor = CurvePoint::take_last(rest)?;
+        Ok(Self { id, key, precursor })
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for CompactKeyFrag {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize(self, serializer, Representation::Base64)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for CompactKeyFrag {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_deserialize(deserializer, Representation::Base64)
+    }
+}
+
+impl HasTypeName for CompactKeyFrag {
+    fn type_name() -> &'static str {
+        "CompactKeyFrag"
+    }
+}
+
+impl fmt::Display for CompactKeyFrag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(self, f)
+    }
+}
+
+impl FromStr for CompactKeyFrag {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
+impl HasSourceId for CompactKeyFrag {
+    fn source_id(&self) -> SourceId {
+        SourceId::from_precursor(&self.precursor)
+    }
+}
+
+impl HasSourceId for KeyFrag {
+    fn source_id(&self) -> SourceId {
+        SourceId::from_precursor(&self.precursor)
+    }
+}
+
+impl HasSourceId for VerifiedKeyFrag {
+    fn source_id(&self) -> SourceId {
+        self.kfrag.source_id()
+    }
 }
 
 pub(crate) struct KeyFragBase<'a> {
     signer: &'a Signer,
     precursor: CurvePoint,
-    dh_point: CurvePoint,
     params: Parameters,
     delegating_pk: PublicKey,
     receiving_pk: PublicKey,
     coefficients: Box<[SecretBox<NonZeroCurveScalar>]>,
+    polynomial_arg_hasher: PolynomialArgHasher,
 }
 
 impl<'a> KeyFragBase<'a> {
     pub fn new(
         rng: &mut (impl CryptoRng + RngCore),
+        params: &Parameters,
         delegating_sk: &SecretKey,
         receiving_pk: &PublicKey,
         signer: &'a Signer,
         threshold: usize,
     ) -> Self {
         let g = CurvePoint::generator();
-        let params = Parameters::new();
+        let params = *params;
 
         let delegating_pk = delegating_sk.public_key();
 
@@ -462,7 +998,7 @@ impl<'a> KeyFragBase<'a> {
         let dh_point = &receiving_pk_point * private_precursor.as_secret();
 
         // Secret value 'd' allows to make Umbral non-interactive
-        let d = hash_to_shared_secret(&precursor, &receiving_pk_point, &dh_point);
+        let d = hash_to_shared_secret(&params, &precursor, &receiving_pk_point, &dh_point);
 
         // Coefficients of the generating polynomial
         let coefficient0 =
@@ -477,17 +1013,25 @@ impl<'a> KeyFragBase<'a> {
         Self {
             signer,
             precursor,
-            dh_point,
             params,
             delegating_pk,
             receiving_pk: *receiving_pk,
             coefficients: coefficients.into_boxed_slice(),
+            polynomial_arg_hasher: PolynomialArgHasher::new(
+                &params,
+                &precursor,
+                &receiving_pk_point,
+                &dh_point,
+            ),
         }
     }
 }
 
 // Coefficients of the generating polynomial
-fn poly_eval(coeffs: &[SecretBox<NonZeroCurveScalar>], x: &NonZeroCurveScalar) -> CurveScalar {
+pub(crate) fn poly_eval(
+    coeffs: &[SecretBox<NonZeroCurveScalar>],
+    x: &NonZeroCurveScalar,
+) -> CurveScalar {
     let mut result: SecretBox<CurveScalar> =
         SecretBox::new(coeffs[coeffs.len() - 1].as_secret().into());
     for i in (0..coeffs.len() - 1).rev() {
@@ -503,12 +1047,16 @@ fn poly_eval(coeffs: &[SecretBox<NonZeroCurveScalar>], x: &NonZeroCurveScalar) -
 mod tests {
 
     use alloc::boxed::Box;
+    use alloc::vec::Vec;
 
     use rand_core::OsRng;
 
-    use super::{KeyFrag, KeyFragBase, KeyFragVerificationError, VerifiedKeyFrag};
+    use super::{DelegationInfo, KeyFrag, KeyFragBase, KeyFragVerificationError, VerifiedKeyFrag};
 
-    use crate::{DeserializableFromArray, PublicKey, SecretKey, SerializableToArray, Signer};
+    use crate::{
+        DeserializableFromArray, DeserializationError, Parameters, PublicKey, RepresentableAsArray,
+        SecretKey, SerializableToArray, Signer,
+    };
 
     #[cfg(feature = "serde-support")]
     use crate::serde::tests::{check_deserialization, check_serialization};
@@ -529,16 +1077,52 @@ mod tests {
         let receiving_sk = SecretKey::random();
         let receiving_pk = receiving_sk.public_key();
 
-        let base = KeyFragBase::new(&mut OsRng, &delegating_sk, &receiving_pk, &signer, 2);
+        let base = KeyFragBase::new(
+            &mut OsRng,
+            &Parameters::new(),
+            &delegating_sk,
+            &receiving_pk,
+            &signer,
+            2,
+        );
         let vkfrags = [
-            VerifiedKeyFrag::from_base(&mut OsRng, &base, sign_delegating_key, sign_receiving_key),
-            VerifiedKeyFrag::from_base(&mut OsRng, &base, sign_delegating_key, sign_receiving_key),
-            VerifiedKeyFrag::from_base(&mut OsRng, &base, sign_delegating_key, sign_receiving_key),
+            VerifiedKeyFrag::from_base(
+                &mut OsRng,
+                &base,
+                sign_delegating_key,
+                sign_receiving_key,
+                &mut Vec::new(),
+            ),
+            VerifiedKeyFrag::from_base(
+                &mut OsRng,
+                &base,
+                sign_delegating_key,
+                sign_receiving_key,
+                &mut Vec::new(),
+            ),
+            VerifiedKeyFrag::from_base(
+                &mut OsRng,
+                &base,
+                sign_delegating_key,
+                sign_receiving_key,
+                &mut Vec::new(),
+            ),
         ];
 
         (delegating_pk, receiving_pk, verifying_pk, Box::new(vkfrags))
     }
 
+    #[test]
+    fn test_unverified_conversions() {
+        let (_delegating_pk, _receiving_pk, _verifying_pk, vkfrags) = prepare_kfrags(true, true);
+        let vkfrag = vkfrags[0].clone();
+
+        let kfrag = vkfrag.clone_unverified();
+        assert_eq!(kfrag.to_array(), vkfrag.to_array());
+        assert_eq!(KeyFrag::from(vkfrag.clone()), kfrag);
+        assert_eq!(vkfrag.unverify(), kfrag);
+    }
+
     #[test]
     fn test_verify() {
         for sign_dk in [false, true].iter().copied() {
@@ -591,6 +1175,141 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compact_serialization() {
+        for sign_dk in [false, true].iter().copied() {
+            for sign_rk in [false, true].iter().copied() {
+                let (delegating_pk, receiving_pk, verifying_pk, vkfrags) =
+                    prepare_kfrags(sign_dk, sign_rk);
+                let kfrag = vkfrags[0].clone_unverified();
+
+                let compact = vkfrags[0].to_compact_bytes();
+                let expected_size = if sign_dk && sign_rk {
+                    KeyFrag::serialized_size() - 65
+                } else {
+                    KeyFrag::serialized_size() - 1
+                };
+                assert_eq!(compact.len(), expected_size);
+
+                let kfrag_back = KeyFrag::from_compact_bytes(&compact).unwrap();
+                assert_eq!(kfrag_back, kfrag);
+                assert!(kfrag_back
+                    .verify(&verifying_pk, Some(&delegating_pk), Some(&receiving_pk))
+                    .is_ok());
+            }
+        }
+
+        let (_delegating_pk, _receiving_pk, _verifying_pk, vkfrags) = prepare_kfrags(false, true);
+        let compact = vkfrags[0].to_compact_bytes();
+        let flags_position = compact.len() - 65;
+
+        // The signature for the proxy can only be omitted if both keys are signed
+        let mut missing_signature = compact[..flags_position + 1].to_vec();
+        missing_signature[flags_position] = 2;
+        assert!(matches!(
+            KeyFrag::from_compact_bytes(&missing_signature),
+            Err(DeserializationError::ConstructionFailure(_))
+        ));
+
+        let mut unknown_flags = compact.to_vec();
+        unknown_flags[flags_position] |= 8;
+        assert!(matches!(
+            KeyFrag::from_compact_bytes(&unknown_flags),
+            Err(DeserializationError::ConstructionFailure(_))
+        ));
+
+        assert!(matches!(
+            KeyFrag::from_compact_bytes(&compact[..compact.len() - 1]),
+            Err(DeserializationError::SizeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_delegation_info() {
+        let (_delegating_pk, _receiving_pk, _verifying_pk, vkfrags) = prepare_kfrags(false, true);
+        let vkfrag = vkfrags[0].clone();
+        let expected = DelegationInfo {
+            id: vkfrag.id(),
+            delegating_key_signed: false,
+            receiving_key_signed: true,
+        };
+        assert_eq!(vkfrag.delegation_info(), expected);
+
+        // Survives a serialization roundtrip
+        let kfrag = KeyFrag::from_array(&vkfrag.to_array()).unwrap();
+        assert_eq!(kfrag.delegation_info(), expected);
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let (delegating_pk1, receiving_pk1, verifying_pk1, vkfrags1) = prepare_kfrags(true, true);
+        let (delegating_pk2, receiving_pk2, verifying_pk2, vkfrags2) = prepare_kfrags(true, false);
+
+        let kfrags1: Vec<_> = vkfrags1
+            .iter()
+            .map(|vkfrag| vkfrag.clone_unverified())
+            .collect();
+        let kfrags2: Vec<_> = vkfrags2
+            .iter()
+            .map(|vkfrag| vkfrag.clone_unverified())
+            .collect();
+
+        let mut bad_kfrag = kfrags1[2].clone();
+        bad_kfrag.key = kfrags1[1].key;
+
+        let items = [
+            (
+                kfrags1[0].clone(),
+                &verifying_pk1,
+                Some(&delegating_pk1),
+                Some(&receiving_pk1),
+            ),
+            (
+                kfrags2[0].clone(),
+                &verifying_pk2,
+                Some(&delegating_pk2),
+                None,
+            ),
+            (
+                kfrags1[1].clone(),
+                &verifying_pk2,
+                Some(&delegating_pk1),
+                Some(&receiving_pk1),
+            ),
+            (
+                bad_kfrag.clone(),
+                &verifying_pk1,
+                Some(&delegating_pk1),
+                Some(&receiving_pk1),
+            ),
+            (
+                kfrags2[1].clone(),
+                &verifying_pk2,
+                Some(&delegating_pk2),
+                Some(&receiving_pk2),
+            ),
+        ];
+
+        let results = KeyFrag::verify_batch(items);
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0], Ok(vkfrags1[0].clone()));
+        assert_eq!(results[1], Ok(vkfrags2[0].clone()));
+        assert_eq!(
+            results[2],
+            Err((
+                KeyFragVerificationError::IncorrectSignature,
+                kfrags1[1].clone()
+            ))
+        );
+        assert_eq!(
+            results[3],
+            Err((KeyFragVerificationError::IncorrectCommitment, bad_kfrag))
+        );
+        assert_eq!(results[4], Ok(vkfrags2[1].clone()));
+
+        assert!(KeyFrag::verify_batch([]).is_empty());
+    }
+
     #[cfg(feature = "serde-support")]
     #[test]
     fn test_serde_serialization() {
@@ -602,5 +1321,9 @@ mod tests {
 
         check_serialization(&kfrag, Representation::Base64);
         check_deserialization(&kfrag);
+
+        let kfrag_id = kfrag.id();
+        check_serialization(&kfrag_id, Representation::Hex);
+        check_deserialization(&kfrag_id);
     }
 }
@@ -0,0 +1,145 @@
+//! A lookup table for values associated with key fragments,
+//! resistant to timing side-channels.
+
+use alloc::vec::Vec;
+use core::cmp::max;
+
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+use crate::key_frag::KeyFragID;
+use crate::traits::DeserializableFromArray;
+
+/// A map from [`KeyFragID`] to arbitrary values (e.g. the stored key fragments themselves),
+/// intended for proxies that do not want to leak which delegations they hold
+/// through lookup timing.
+///
+/// A lookup compares the given identifier with every stored one in constant time,
+/// and does not stop at the first match.
+/// Additionally, the table can be padded (see [`with_padding`](`Self::with_padding`)),
+/// in which case every lookup performs at least the given number of comparisons,
+/// hiding the number of stored entries as well.
+#[derive(Clone, Debug)]
+pub struct KeyFragIndex<T> {
+    entries: Vec<(KeyFragID, T)>,
+    padding: usize,
+}
+
+impl<T> KeyFragIndex<T> {
+    /// Creates an empty index without padding.
+    pub fn new() -> Self {
+        Self::with_padding(0)
+    }
+
+    /// Creates an empty index, every lookup in which scans at least `padding` entries.
+    pub fn with_padding(padding: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            padding,
+        }
+    }
+
+    /// Returns the number of stored entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if there are no stored entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn find(&self, id: &KeyFragID) -> Option<usize> {
+        // Used to keep the number of comparisons the same in the padded part of the table.
+        // Deserialization of `KeyFragID` cannot fail, so it is safe to unwrap.
+        let dummy_id = KeyFragID::from_array(&Default::default()).unwrap();
+
+        let mut found = Choice::from(0);
+        // `usize` does not implement `ConditionallySelectable`, so we have to use `u64`.
+        let mut position = 0u64;
+        for i in 0..max(self.entries.len(), self.padding) {
+            let (entry_id, is_real) = match self.entries.get(i) {
+                Some((entry_id, _)) => (entry_id, Choice::from(1)),
+                None => (&dummy_id, Choice::from(0)),
+            };
+            let matches = entry_id.ct_eq(id) & is_real;
+            position.conditional_assign(&(i as u64), matches);
+            found |= matches;
+        }
+
+        if found.into() {
+            Some(position as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Adds a value associated with the given identifier,
+    /// returning the previously associated value, if any.
+    pub fn insert(&mut self, id: KeyFragID, value: T) -> Option<T> {
+        match self.find(&id) {
+            Some(position) => Some(core::mem::replace(&mut self.entries[position].1, value)),
+            None => {
+                self.entries.push((id, value));
+                None
+            }
+        }
+    }
+
+    /// Returns the value associated with the given identifier.
+    pub fn get(&self, id: &KeyFragID) -> Option<&T> {
+        self.find(id).map(|position| &self.entries[position].1)
+    }
+
+    /// Removes the value associated with the given identifier and returns it.
+    pub fn remove(&mut self, id: &KeyFragID) -> Option<T> {
+        self.find(id)
+            .map(|position| self.entries.swap_remove(position).1)
+    }
+}
+
+impl<T> Default for KeyFragIndex<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::KeyFragIndex;
+    use crate::{generate_kfrags, SecretKey, Signer};
+
+    #[test]
+    fn test_index() {
+        let delegating_sk = SecretKey::random();
+        let receiving_pk = SecretKey::random().public_key();
+        let signer = Signer::new(SecretKey::random());
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+
+        for padding in [0, 10] {
+            let mut index = KeyFragIndex::with_padding(padding);
+            assert!(index.is_empty());
+
+            assert_eq!(index.insert(vkfrags[0].id(), vkfrags[0].clone()), None);
+            assert_eq!(index.insert(vkfrags[1].id(), vkfrags[1].clone()), None);
+            assert_eq!(index.len(), 2);
+
+            assert_eq!(index.get(&vkfrags[0].id()), Some(&vkfrags[0]));
+            assert_eq!(index.get(&vkfrags[1].id()), Some(&vkfrags[1]));
+            assert_eq!(index.get(&vkfrags[2].id()), None);
+
+            // Replacing an existing entry
+            assert_eq!(
+                index.insert(vkfrags[1].id(), vkfrags[2].clone()),
+                Some(vkfrags[1].clone())
+            );
+            assert_eq!(index.len(), 2);
+
+            assert_eq!(index.remove(&vkfrags[0].id()), Some(vkfrags[0].clone()));
+            assert_eq!(index.get(&vkfrags[0].id()), None);
+            assert_eq!(index.get(&vkfrags[1].id()), Some(&vkfrags[2]));
+            assert_eq!(index.len(), 1);
+        }
+    }
+}
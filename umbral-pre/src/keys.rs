@@ -1,6 +1,8 @@
+use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::cmp::Ordering;
 use core::fmt;
+use core::str::FromStr;
 
 use digest::Digest;
 use ecdsa::{Signature as BackendSignature, SignatureSize, SigningKey, VerifyingKey};
@@ -9,6 +11,7 @@ use generic_array::GenericArray;
 use rand_core::{CryptoRng, RngCore};
 use signature::{DigestVerifier, RandomizedDigestSigner, Signature as SignatureTrait};
 use typenum::{Unsigned, U32, U64};
+use zeroize::Zeroize;
 
 #[cfg(feature = "default-rng")]
 use rand_core::OsRng;
@@ -19,10 +22,13 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use crate::curve::{CurvePoint, CurveScalar, CurveType, NonZeroCurveScalar};
 use crate::dem::kdf;
 use crate::hashing::{BackendDigest, Hash, ScalarDigest};
-use crate::secret_box::SecretBox;
+use crate::hashing_ds::hash_to_policy_key_factor;
+use crate::roles::SigningSecretKey;
+use crate::secret_box::{CanBeZeroizedOnDrop, SecretBox};
 use crate::traits::{
-    fmt_public, fmt_secret, ConstructionError, DeserializableFromArray, HasTypeName,
-    RepresentableAsArray, SerializableToArray, SerializableToSecretArray, SizeMismatchError,
+    fmt_public, fmt_secret, from_str_public, ConstructionError, DeserializableFromArray,
+    DeserializationError, HasTypeName, RepresentableAsArray, SerializableToArray,
+    SerializableToSecretArray, SizeMismatchError,
 };
 
 #[cfg(feature = "serde-support")]
@@ -74,11 +80,117 @@ impl<'de> Deserialize<'de> for Signature {
     }
 }
 
+// The size of each of the signature components.
+const SIGNATURE_SCALAR_SIZE: usize = 32;
+
+const DER_SEQUENCE_TAG: u8 = 0x30;
+const DER_INTEGER_TAG: u8 = 0x02;
+
+fn push_der_integer(result: &mut Vec<u8>, bytes: &[u8]) {
+    // Minimal encoding: no leading zeros except the one keeping the number positive
+    let start = bytes
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(bytes.len() - 1);
+    let bytes = &bytes[start..];
+    let pad = bytes[0] & 0x80 != 0;
+    result.push(DER_INTEGER_TAG);
+    result.push((bytes.len() + usize::from(pad)) as u8);
+    if pad {
+        result.push(0);
+    }
+    result.extend_from_slice(bytes);
+}
+
+fn take_der_integer<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let current: &'a [u8] = data;
+    match current {
+        [DER_INTEGER_TAG, length, rest @ ..] if *length as usize <= rest.len() => {
+            let (bytes, rest) = rest.split_at(*length as usize);
+            *data = rest;
+            match bytes {
+                // Negative numbers
+                [first, ..] if first & 0x80 != 0 => None,
+                // Non-minimal encoding
+                [0, second, ..] if second & 0x80 == 0 => None,
+                [0, value @ ..] => Some(value),
+                [_, ..] => Some(bytes),
+                [] => None,
+            }
+        }
+        _ => None,
+    }
+}
+
 impl Signature {
     /// Verifies that the given message was signed with the secret counterpart of the given key.
     /// The message is hashed internally.
     pub fn verify(&self, verifying_pk: &PublicKey, message: &[u8]) -> bool {
-        verifying_pk.verify_digest(digest_for_signing(message), self)
+        self.verify_with_context(verifying_pk, &[], message)
+    }
+
+    /// Verifies a signature created by [`Signer::sign_with_context_and_rng`].
+    pub(crate) fn verify_with_context(
+        &self,
+        verifying_pk: &PublicKey,
+        context: &[u8],
+        message: &[u8],
+    ) -> bool {
+        verifying_pk.verify_digest(digest_for_signing(context, message), self)
+    }
+
+    /// Returns the ASN.1 DER encoding of the signature (`SEQUENCE { r INTEGER, s INTEGER }`),
+    /// as used by OpenSSL and Bitcoin.
+    ///
+    /// The fixed-size compact form (`r` and `s` as 32-byte big-endian numbers)
+    /// is the regular serialization, see [`SerializableToArray::to_array`].
+    pub fn to_der(&self) -> Box<[u8]> {
+        let arr = self.to_array();
+        let (r, s) = arr.split_at(SIGNATURE_SCALAR_SIZE);
+        let mut content = Vec::with_capacity(2 * (SIGNATURE_SCALAR_SIZE + 3));
+        push_der_integer(&mut content, r);
+        push_der_integer(&mut content, s);
+
+        // The content is at most 70 bytes, so the short form of the length is enough.
+        let mut result = Vec::with_capacity(content.len() + 2);
+        result.push(DER_SEQUENCE_TAG);
+        result.push(content.len() as u8);
+        result.extend_from_slice(&content);
+        result.into_boxed_slice()
+    }
+
+    /// Restores the signature from the ASN.1 DER encoding.
+    ///
+    /// Only the strict DER is accepted (minimal lengths and integers, no trailing data).
+    /// As with [`from_bytes`](`DeserializableFromArray::from_bytes`),
+    /// the signatures with a non-normalized `s` will fail verification.
+    pub fn from_der(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let error = || {
+            DeserializationError::ConstructionFailure(ConstructionError::new(
+                "Signature",
+                "Invalid DER encoding",
+            ))
+        };
+
+        let mut content = match data.as_ref() {
+            [DER_SEQUENCE_TAG, length, content @ ..]
+                if *length < 0x80 && *length as usize == content.len() =>
+            {
+                content
+            }
+            _ => return Err(error()),
+        };
+        let r = take_der_integer(&mut content).ok_or_else(error)?;
+        let s = take_der_integer(&mut content).ok_or_else(error)?;
+        if !content.is_empty() || r.len() > SIGNATURE_SCALAR_SIZE || s.len() > SIGNATURE_SCALAR_SIZE
+        {
+            return Err(error());
+        }
+
+        let mut arr = GenericArray::<u8, <Self as RepresentableAsArray>::Size>::default();
+        arr[SIGNATURE_SCALAR_SIZE - r.len()..SIGNATURE_SCALAR_SIZE].copy_from_slice(r);
+        arr[2 * SIGNATURE_SCALAR_SIZE - s.len()..].copy_from_slice(s);
+        Self::from_array(&arr).map_err(DeserializationError::ConstructionFailure)
     }
 }
 
@@ -94,6 +206,14 @@ impl fmt::Display for Signature {
     }
 }
 
+impl FromStr for Signature {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
 // TODO (#89): derive `ZeroizeOnDrop` for `SecretKey` when it's available.
 // For now we know that `BackendSecretKey` is zeroized on drop (as of elliptic-curve=0.11),
 // but cannot check that at compile-time.
@@ -123,6 +243,19 @@ impl SecretKey {
         PublicKey(self.0.public_key())
     }
 
+    /// Derives a secret key for the policy with the given label.
+    ///
+    /// The corresponding public key can be obtained without the knowledge of this secret key
+    /// by calling [`PublicKey::derive_policy_key`] on `self.public_key()` with the same label.
+    ///
+    /// **Warning:** the derivation is not hardened: the holder of a derived secret key
+    /// who also knows the label and the original public key can recover the original secret key.
+    pub fn derive_policy_key(&self, label: &[u8]) -> Self {
+        let factor = hash_to_policy_key_factor(&self.public_key().to_point(), label);
+        let scalar = SecretBox::new(self.to_secret_scalar().as_secret() * &factor);
+        Self::from_nonzero_scalar(scalar)
+    }
+
     fn from_nonzero_scalar(scalar: SecretBox<NonZeroCurveScalar>) -> Self {
         let backend_scalar_ref = scalar.as_secret().as_backend_scalar();
         Self::new(BackendSecretKey::<CurveType>::from(backend_scalar_ref))
@@ -135,6 +268,44 @@ impl SecretKey {
             *backend_scalar.as_secret(),
         ))
     }
+
+    /// Deserializes a secret key from `data`, zeroizing `data` afterwards
+    /// (whether the deserialization succeeded or not).
+    ///
+    /// Unlike [`DeserializableFromArray::from_bytes`], keeps the intermediate copies
+    /// of the key bytes in [`SecretBox`]es, so that they are wiped on drop,
+    /// and returns the key itself in a [`SecretBox`].
+    pub fn from_secret_bytes(data: &mut [u8]) -> Result<SecretBox<Self>, DeserializationError> {
+        let expected_size = <Self as RepresentableAsArray>::Size::to_usize();
+        let result = if data.len() == expected_size {
+            let arr = SecretBox::new_with(
+                |arr: &mut GenericArray<u8, <Self as RepresentableAsArray>::Size>| {
+                    arr.copy_from_slice(data)
+                },
+            );
+            NonZeroCurveScalar::from_array_ct(arr.as_secret())
+                .map(|scalar| SecretBox::new(Self::from_nonzero_scalar(SecretBox::new(scalar))))
+                .ok_or_else(|| {
+                    DeserializationError::ConstructionFailure(ConstructionError::new(
+                        "SecretKey",
+                        "Internal backend error",
+                    ))
+                })
+        } else {
+            Err(DeserializationError::SizeMismatch(SizeMismatchError::new(
+                data.len(),
+                expected_size,
+            )))
+        };
+        data.zeroize();
+        result
+    }
+}
+
+impl CanBeZeroizedOnDrop for SecretKey {
+    fn ensure_zeroized_on_drop(&mut self) {
+        // `BackendSecretKey` is zeroized on drop (see the TODO above).
+    }
 }
 
 impl RepresentableAsArray for SecretKey {
@@ -149,9 +320,11 @@ impl SerializableToSecretArray for SecretKey {
 
 impl DeserializableFromArray for SecretKey {
     fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
-        BackendSecretKey::<CurveType>::from_be_bytes(arr.as_slice())
-            .map(Self::new)
-            .map_err(|_| ConstructionError::new("SecretKey", "Internal backend error"))
+        // Parsing and range-checking are done in constant time,
+        // so that the time spent does not depend on the (possibly secret) contents of `arr`.
+        NonZeroCurveScalar::from_array_ct(arr)
+            .map(|scalar| Self::from_nonzero_scalar(SecretBox::new(scalar)))
+            .ok_or_else(|| ConstructionError::new("SecretKey", "Internal backend error"))
     }
 }
 
@@ -167,8 +340,8 @@ impl fmt::Display for SecretKey {
     }
 }
 
-fn digest_for_signing(message: &[u8]) -> BackendDigest {
-    Hash::new().chain_bytes(message).digest()
+fn digest_for_signing(context: &[u8], message: &[u8]) -> BackendDigest {
+    Hash::new_for_signing(context).chain_bytes(message).digest()
 }
 
 /// An object used to sign messages.
@@ -178,14 +351,35 @@ fn digest_for_signing(message: &[u8]) -> BackendDigest {
 pub struct Signer(SigningKey<CurveType>);
 
 impl Signer {
-    /// Creates a new signer out of a secret key.
-    pub fn new(sk: SecretKey) -> Self {
-        Self(SigningKey::<CurveType>::from(sk.0))
+    /// Creates a new signer out of a secret key
+    /// (either a plain [`SecretKey`] or a [`SigningSecretKey`]).
+    pub fn new(sk: impl Into<SigningSecretKey>) -> Self {
+        Self(SigningKey::<CurveType>::from(sk.into().into_secret_key().0))
     }
 
     /// Signs the given message using the given RNG.
     pub fn sign_with_rng(&self, rng: &mut (impl CryptoRng + RngCore), message: &[u8]) -> Signature {
-        let digest = digest_for_signing(message);
+        self.sign_with_context_and_rng(rng, &[], message)
+    }
+
+    /// Signs the given message bound to an application context
+    /// (the plain hash of the message is signed if the context is empty).
+    pub(crate) fn sign_with_context_and_rng(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        context: &[u8],
+        message: &[u8],
+    ) -> Signature {
+        let digest = digest_for_signing(context, message);
+        self.sign_digest_with_rng(rng, digest)
+    }
+
+    /// Signs an already started hash (e.g. one beginning with a domain separation tag).
+    pub(crate) fn sign_digest_with_rng(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        digest: BackendDigest,
+    ) -> Signature {
         Signature(self.0.sign_digest_with_rng(rng, digest))
     }
 
@@ -226,6 +420,44 @@ impl PublicKey {
         CurvePoint::from_backend_point(&self.0.to_projective())
     }
 
+    /// Creates a public key from a curve point (fails if the point is the identity).
+    pub(crate) fn from_point(point: &CurvePoint) -> Option<Self> {
+        BackendPublicKey::<CurveType>::from_affine(point.to_affine_point())
+            .ok()
+            .map(Self)
+    }
+
+    /// Deserializes the public key from a SEC1 encoding,
+    /// either compressed (33 bytes) or uncompressed (65 bytes).
+    ///
+    /// Intended for keys coming from systems that only produce uncompressed points
+    /// (e.g. OpenSSL-based ones); the key is always serialized in the compressed form,
+    /// and [`from_bytes`](`DeserializableFromArray::from_bytes`) only accepts that form.
+    pub fn from_sec1_bytes(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        CurvePoint::from_sec1_bytes(data.as_ref())
+            .and_then(|point| Self::from_point(&point))
+            .ok_or_else(|| {
+                DeserializationError::ConstructionFailure(ConstructionError::new(
+                    "PublicKey",
+                    "Invalid SEC1 encoding",
+                ))
+            })
+    }
+
+    /// Derives the public key of the policy with the given label,
+    /// matching the result of [`SecretKey::derive_policy_key`]
+    /// called on the secret counterpart of this key.
+    ///
+    /// Allows an encrypting party to obtain a policy public key
+    /// from the delegator's public key and the label alone.
+    pub fn derive_policy_key(&self, label: &[u8]) -> Self {
+        let point = self.to_point();
+        let factor = hash_to_policy_key_factor(&point, label);
+        // The point of a public key is never the identity, and the factor is non-zero,
+        // so the product is not the identity either, and we can safely unwrap.
+        Self::from_point(&(&point * &factor)).unwrap()
+    }
+
     /// Verifies the signature.
     pub(crate) fn verify_digest(
         &self,
@@ -290,6 +522,14 @@ impl fmt::Display for PublicKey {
     }
 }
 
+impl FromStr for PublicKey {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
 type SecretKeyFactorySeedSize = U32; // the size of the seed material for key derivation
 type SecretKeyFactoryDerivedSize = U64; // the size of the derived key (before hashing to scalar)
 type SecretKeyFactorySeed = GenericArray<u8, SecretKeyFactorySeedSize>;
@@ -345,17 +585,46 @@ impl SecretKeyFactory {
             .cloned()
             .chain(label.iter().cloned())
             .collect();
+        self.make_key_from_info(&info)
+    }
+
+    fn make_key_from_info(&self, info: &[u8]) -> SecretKey {
         let key =
-            kdf::<SecretKeyFactorySeed, SecretKeyFactoryDerivedSize>(&self.0, None, Some(&info));
+            kdf::<SecretKeyFactorySeed, SecretKeyFactoryDerivedSize>(&self.0, None, Some(info));
         let nz_scalar = SecretBox::new(
-            ScalarDigest::new_with_dst(&info)
+            ScalarDigest::new_with_dst(info)
                 .chain_secret_bytes(&key)
                 .finalize(),
         );
         SecretKey::from_nonzero_scalar(nz_scalar)
     }
 
+    fn policy_master_key(&self) -> SecretKey {
+        self.make_key_from_info(b"POLICY_MASTER_KEY")
+    }
+
+    /// Returns the public key from which encrypting parties can derive policy public keys
+    /// with [`PublicKey::derive_policy_key`].
+    pub fn policy_master_public_key(&self) -> PublicKey {
+        self.policy_master_key().public_key()
+    }
+
+    /// Creates a `SecretKey` for the policy with the given label.
+    ///
+    /// Its public key is equal to the result of [`PublicKey::derive_policy_key`]
+    /// called on [`policy_master_public_key`](`Self::policy_master_public_key`)
+    /// with the same label.
+    pub fn make_policy_key(&self, label: &[u8]) -> SecretKey {
+        self.policy_master_key().derive_policy_key(label)
+    }
+
     /// Creates a `SecretKeyFactory` deterministically from the given label.
+    ///
+    /// Can be used to build a hierarchy of factories: the derived factory can be handed over
+    /// to a subordinate party, letting it derive keys in its own namespace,
+    /// while the seed of this factory stays private.
+    /// The derived factory cannot be used to recover this one,
+    /// nor to derive keys of its other sub-factories.
     pub fn make_factory(&self, label: &[u8]) -> Self {
         let prefix = b"FACTORY_DERIVATION/";
         let info: Vec<u8> = prefix
@@ -400,20 +669,122 @@ impl fmt::Display for SecretKeyFactory {
 #[cfg(test)]
 mod tests {
 
-    use super::{PublicKey, SecretKey, SecretKeyFactory, Signer};
-    use crate::{DeserializableFromArray, SerializableToArray, SerializableToSecretArray};
+    use alloc::format;
+
+    use elliptic_curve::sec1::ToEncodedPoint;
+
+    use super::{PublicKey, SecretKey, SecretKeyFactory, Signature, Signer};
+    use crate::{
+        DeserializableFromArray, DeserializationError, SerializableToArray,
+        SerializableToSecretArray,
+    };
 
     #[cfg(feature = "serde-support")]
     use crate::serde::tests::{check_deserialization, check_serialization};
     #[cfg(feature = "serde-support")]
     use crate::serde::Representation;
 
+    #[test]
+    fn test_sec1_public_key() {
+        let pk = SecretKey::random().public_key();
+        let compressed = pk.to_array();
+        let uncompressed = pk.to_point().to_affine_point().to_encoded_point(false);
+
+        assert_eq!(uncompressed.as_bytes().len(), 65);
+        assert_eq!(
+            PublicKey::from_sec1_bytes(uncompressed.as_bytes()).unwrap(),
+            pk
+        );
+        assert_eq!(PublicKey::from_sec1_bytes(&compressed).unwrap(), pk);
+
+        // Still serialized as compressed
+        let pk_back = PublicKey::from_sec1_bytes(uncompressed.as_bytes()).unwrap();
+        assert_eq!(pk_back.to_array(), compressed);
+
+        // The strict deserialization does not accept the uncompressed form
+        assert!(PublicKey::from_bytes(uncompressed.as_bytes()).is_err());
+
+        // Invalid encodings
+        let mut not_on_curve = uncompressed.as_bytes().to_vec();
+        not_on_curve[64] ^= 1;
+        assert!(PublicKey::from_sec1_bytes(&not_on_curve).is_err());
+        assert!(PublicKey::from_sec1_bytes([0u8]).is_err());
+        assert!(PublicKey::from_sec1_bytes(&uncompressed.as_bytes()[..64]).is_err());
+    }
+
     #[test]
     fn test_serialize_secret_key() {
         let sk = SecretKey::random();
         let sk_arr = sk.to_secret_array();
         let sk_back = SecretKey::from_array(sk_arr.as_secret()).unwrap();
         assert!(sk.to_secret_array().as_secret() == sk_back.to_secret_array().as_secret());
+
+        let sk_vec = sk.to_secret_vec();
+        let sk_back = SecretKey::from_bytes(sk_vec.as_secret()).unwrap();
+        assert!(sk.to_secret_array().as_secret() == sk_back.to_secret_array().as_secret());
+
+        let mut buf = [0u8; 32];
+        assert_eq!(sk.write_secret_to(&mut buf), Ok(32));
+        assert!(sk.write_secret_to(&mut buf[..31]).is_err());
+        assert!(buf == sk.to_secret_array().as_secret().as_slice());
+    }
+
+    #[test]
+    fn test_from_secret_bytes() {
+        let sk = SecretKey::random();
+
+        let mut sk_vec = sk.to_secret_array().as_secret().to_vec();
+        let sk_back = SecretKey::from_secret_bytes(&mut sk_vec).unwrap();
+        assert!(
+            sk.to_secret_array().as_secret() == sk_back.as_secret().to_secret_array().as_secret()
+        );
+        assert!(sk_vec.iter().all(|byte| *byte == 0));
+
+        // The input is wiped even if it is invalid
+        let mut short = sk.to_secret_array().as_secret()[..31].to_vec();
+        assert!(matches!(
+            SecretKey::from_secret_bytes(&mut short),
+            Err(DeserializationError::SizeMismatch(_))
+        ));
+        assert!(short.iter().all(|byte| *byte == 0));
+
+        let mut zero = [0u8; 32];
+        assert!(matches!(
+            SecretKey::from_secret_bytes(&mut zero),
+            Err(DeserializationError::ConstructionFailure(_))
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_invalid_secret_key() {
+        // The curve order
+        #[cfg(not(feature = "p256"))]
+        let order = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+            .unwrap();
+        #[cfg(feature = "p256")]
+        let order = hex::decode("ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551")
+            .unwrap();
+
+        // Zero is not a valid secret key
+        assert!(SecretKey::from_bytes(&[0u8; 32]).is_err());
+
+        // Neither are the values greater or equal to the curve order
+        assert!(SecretKey::from_bytes(&order).is_err());
+        assert!(SecretKey::from_bytes(&[0xffu8; 32]).is_err());
+
+        // The largest valid value
+        let mut order_minus_one = order;
+        order_minus_one[31] -= 1;
+        let sk = SecretKey::from_bytes(&order_minus_one).unwrap();
+        assert_eq!(
+            sk.to_secret_array().as_secret().as_slice(),
+            &order_minus_one
+        );
+
+        // The smallest valid value
+        let mut one = [0u8; 32];
+        one[31] = 1;
+        assert!(SecretKey::from_bytes(&one).is_ok());
     }
 
     #[test]
@@ -435,6 +806,66 @@ mod tests {
         assert!(sk1.to_secret_array().as_secret() != sk3.to_secret_array().as_secret());
     }
 
+    #[test]
+    fn test_secret_key_factory_hierarchy() {
+        let root = SecretKeyFactory::random();
+        let dept1 = root.make_factory(b"dept1");
+        let dept2 = root.make_factory(b"dept2");
+
+        // Derivation is deterministic
+        let dept1_again = root.make_factory(b"dept1");
+        assert!(dept1.to_secret_array().as_secret() == dept1_again.to_secret_array().as_secret());
+
+        // Different labels and levels lead to different keys
+        let sk_root = root.make_key(b"foo");
+        let sk1 = dept1.make_key(b"foo");
+        let sk2 = dept2.make_key(b"foo");
+        assert!(sk1.to_secret_array().as_secret() != sk2.to_secret_array().as_secret());
+        assert!(sk1.to_secret_array().as_secret() != sk_root.to_secret_array().as_secret());
+
+        // A sub-factory is not the same as a key derived with the same label
+        assert!(
+            dept1.to_secret_array().as_secret()[..]
+                != root.make_key(b"dept1").to_secret_array().as_secret()[..]
+        );
+
+        // Nested derivation
+        let team = dept1.make_factory(b"team");
+        let sk_team = team.make_key(b"foo");
+        assert!(sk_team.to_secret_array().as_secret() != sk1.to_secret_array().as_secret());
+        assert!(
+            sk_team.to_secret_array().as_secret()
+                == root
+                    .make_factory(b"dept1")
+                    .make_factory(b"team")
+                    .make_key(b"foo")
+                    .to_secret_array()
+                    .as_secret()
+        );
+    }
+
+    #[test]
+    fn test_policy_keys() {
+        let skf = SecretKeyFactory::random();
+        let master_pk = skf.policy_master_public_key();
+
+        let sk1 = skf.make_policy_key(b"foo");
+        let sk2 = skf.make_policy_key(b"bar");
+
+        assert_eq!(sk1.public_key(), master_pk.derive_policy_key(b"foo"));
+        assert_eq!(sk2.public_key(), master_pk.derive_policy_key(b"bar"));
+        assert_ne!(sk1.public_key(), sk2.public_key());
+
+        // The master key is not the same as the one obtained via `make_key()`
+        assert_ne!(master_pk, skf.make_key(b"POLICY_MASTER_KEY").public_key());
+
+        let sk = SecretKey::random();
+        assert_eq!(
+            sk.derive_policy_key(b"foo").public_key(),
+            sk.public_key().derive_policy_key(b"foo")
+        );
+    }
+
     #[test]
     fn test_serialize_public_key() {
         let sk = SecretKey::random();
@@ -444,6 +875,26 @@ mod tests {
         assert_eq!(pk, pk_back);
     }
 
+    #[test]
+    fn test_public_key_from_str() {
+        let pk = SecretKey::random().public_key();
+        let pk_hex = hex::encode(pk.to_array());
+
+        // The abbreviated and the full `Display` forms
+        assert_eq!(format!("{}", pk), format!("PublicKey:{}", &pk_hex[..16]));
+        assert_eq!(format!("{:#}", pk), format!("PublicKey:{}", pk_hex));
+
+        assert_eq!(format!("{:#}", pk).parse::<PublicKey>().unwrap(), pk);
+        assert_eq!(pk_hex.parse::<PublicKey>().unwrap(), pk);
+        assert_eq!(format!("0x{}", pk_hex).parse::<PublicKey>().unwrap(), pk);
+
+        // Wrong type name, invalid hex, wrong length
+        assert!(format!("Capsule:{}", pk_hex).parse::<PublicKey>().is_err());
+        assert!(pk_hex[1..].parse::<PublicKey>().is_err());
+        assert!(pk_hex[2..].parse::<PublicKey>().is_err());
+        assert!(format!("{}", pk).parse::<PublicKey>().is_err());
+    }
+
     #[test]
     fn test_sign_and_verify() {
         let sk = SecretKey::random();
@@ -458,6 +909,44 @@ mod tests {
         assert!(signature.verify(&vk, message));
     }
 
+    #[test]
+    fn test_signature_der() {
+        let signer = Signer::new(SecretKey::random());
+        let message = b"peace at dawn";
+        let signature = signer.sign(message);
+
+        let der = signature.to_der();
+        let signature_back = Signature::from_der(&der).unwrap();
+        assert_eq!(signature_back, signature);
+        assert!(signature_back.verify(&signer.verifying_key(), message));
+
+        // The compact form is the regular serialization
+        assert_eq!(
+            Signature::from_bytes(signature.to_array()).unwrap(),
+            signature
+        );
+
+        // A known encoding: `r` with leading zeros, and `s` with the high bit set
+        let mut arr = [0u8; 64];
+        arr[31] = 1;
+        arr[32] = 0x80;
+        let signature = Signature::from_bytes(&arr).unwrap();
+        let mut expected = [0x30u8, 0x26, 0x02, 0x01, 0x01, 0x02, 0x21, 0x00, 0x80].to_vec();
+        expected.extend_from_slice(&[0u8; 31]);
+        assert_eq!(&signature.to_der() as &[u8], &expected[..]);
+        assert_eq!(Signature::from_der(&expected).unwrap(), signature);
+
+        // Non-minimal integer encoding
+        let mut non_minimal = [0x30u8, 0x07, 0x02, 0x02, 0x00, 0x01].to_vec();
+        non_minimal.extend_from_slice(&[0x02, 0x01, 0x01]);
+        assert!(Signature::from_der(&non_minimal).is_err());
+
+        // Trailing data
+        let mut trailing = der.to_vec();
+        trailing.push(0);
+        assert!(Signature::from_der(&trailing).is_err());
+    }
+
     #[cfg(feature = "serde-support")]
     #[test]
     fn test_serde_serialization() {
@@ -0,0 +1,390 @@
+//! An encrypted JSON keystore for secret keys, modeled after the Ethereum keystore format
+//! (version 3), but using XChaCha20-Poly1305 instead of AES-CTR with a separate MAC.
+//!
+//! The keystore looks like
+//!
+//! ```json
+//! {
+//!     "version": 1,
+//!     "publicKey": "<hex>",
+//!     "crypto": {
+//!         "cipher": "xchacha20-poly1305",
+//!         "cipherparams": {"nonce": "<hex>"},
+//!         "ciphertext": "<hex>",
+//!         "kdf": "scrypt",
+//!         "kdfparams": {"dklen": 32, "n": 262144, "p": 1, "r": 8, "salt": "<hex>"}
+//!     }
+//! }
+//! ```
+//!
+//! The symmetric key is derived from the password with `scrypt`,
+//! and the public key is authenticated as the associated data,
+//! so it can be used to identify the keystore without the password.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use aead::AeadInPlace;
+use chacha20poly1305::aead::NewAead;
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+use crate::dem::TagSize;
+use crate::json::{parse_members, push_member, push_string_member, string_value, u64_value};
+use crate::keys::{PublicKey, SecretKey};
+#[cfg(feature = "fips-algorithms")]
+use crate::secret_box::CanBeZeroizedOnDrop;
+use crate::secret_box::SecretBox;
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, SerializableToArray,
+    SerializableToSecretArray,
+};
+
+const VERSION: u64 = 1;
+const CIPHER: &str = "xchacha20-poly1305";
+const KDF: &str = "scrypt";
+
+const KEY_SIZE: usize = 32;
+const SALT_SIZE: usize = 32;
+const NONCE_SIZE: usize = 24;
+const TAG_SIZE: usize = 16;
+
+// `scrypt` parameters used when creating keystores.
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+/// The default `scrypt` cost parameter (the base 2 logarithm of `n`)
+/// used by [`SecretKey::to_keystore`], same as in the Ethereum keystores.
+pub const KEYSTORE_DEFAULT_LOG_N: u8 = 18;
+
+/// The maximum `scrypt` cost parameter (the base 2 logarithm of `n`)
+/// accepted when creating or reading a keystore.
+/// With `r = 8` it corresponds to 1 GiB of memory used by the key derivation.
+pub const KEYSTORE_MAX_LOG_N: u8 = 20;
+
+// The maximum memory (in units of `128 * n * r` bytes) and parallelization
+// accepted when reading a keystore, to prevent denial of service with crafted parameters.
+const MAX_COST: u64 = (SCRYPT_R as u64) << KEYSTORE_MAX_LOG_N;
+const MAX_P: u64 = 16;
+
+/// Errors that can happen when creating or reading a keystore.
+#[derive(Debug, PartialEq)]
+pub enum KeystoreError {
+    /// The key derivation parameters are invalid or exceed the allowed limits.
+    InvalidParameters,
+    /// The keystore is malformed or uses an unsupported format.
+    Malformed(DeserializationError),
+    /// The password is incorrect, or the keystore was tampered with.
+    DecryptionFailed,
+}
+
+impl fmt::Display for KeystoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidParameters => write!(f, "Invalid key derivation parameters"),
+            Self::Malformed(error) => write!(f, "Malformed keystore: {}", error),
+            Self::DecryptionFailed => write!(f, "Incorrect password or corrupted keystore"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for KeystoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Malformed(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+fn malformed(message: &str) -> KeystoreError {
+    KeystoreError::Malformed(DeserializationError::ConstructionFailure(
+        ConstructionError::new("Keystore", message),
+    ))
+}
+
+fn derive_key(
+    password: &[u8],
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<SecretBox<[u8; KEY_SIZE]>, KeystoreError> {
+    let params = scrypt::Params::new(log_n, r, p).map_err(|_| KeystoreError::InvalidParameters)?;
+    let mut result = Ok(());
+    let key = SecretBox::new_with(|key: &mut [u8; KEY_SIZE]| {
+        result = scrypt::scrypt(password, salt, &params, key);
+    });
+    result.map_err(|_| KeystoreError::InvalidParameters)?;
+    Ok(key)
+}
+
+// Without `fips-algorithms` the DEM uses the same cipher and provides the implementation.
+#[cfg(feature = "fips-algorithms")]
+impl CanBeZeroizedOnDrop for XChaCha20Poly1305 {
+    fn ensure_zeroized_on_drop(&mut self) {
+        // `XChaCha20Poly1305` is zeroized on drop in `chacha20poly1305-0.9`
+    }
+}
+
+fn make_cipher(key: &SecretBox<[u8; KEY_SIZE]>) -> SecretBox<XChaCha20Poly1305> {
+    SecretBox::new(XChaCha20Poly1305::new(Key::from_slice(key.as_secret())))
+}
+
+/// Returns the contents of a string member decoded from hex.
+fn hex_member(raw: Option<&str>, message: &str) -> Result<Vec<u8>, KeystoreError> {
+    let value = raw
+        .and_then(string_value)
+        .ok_or_else(|| malformed(message))?;
+    hex::decode(value).map_err(|_| malformed(message))
+}
+
+fn u64_member(raw: Option<&str>, message: &str) -> Result<u64, KeystoreError> {
+    raw.and_then(u64_value).ok_or_else(|| malformed(message))
+}
+
+impl SecretKey {
+    /// Encrypts the key with a key derived from `password` using `scrypt`,
+    /// and returns it as a JSON keystore similar to the Ethereum ones
+    /// (with XChaCha20-Poly1305 as the cipher).
+    /// The public key is stored unencrypted, to allow identifying the keystore.
+    ///
+    /// `log_n` is the `scrypt` cost parameter (the base 2 logarithm of `n`),
+    /// which must not exceed [`KEYSTORE_MAX_LOG_N`].
+    pub fn to_keystore_with_rng(
+        &self,
+        rng: &mut (impl CryptoRng + RngCore),
+        password: &[u8],
+        log_n: u8,
+    ) -> Result<String, KeystoreError> {
+        if log_n > KEYSTORE_MAX_LOG_N {
+            return Err(KeystoreError::InvalidParameters);
+        }
+
+        let mut salt = [0u8; SALT_SIZE];
+        rng.fill_bytes(&mut salt);
+        let mut nonce = [0u8; NONCE_SIZE];
+        rng.fill_bytes(&mut nonce);
+
+        let key = derive_key(password, &salt, log_n, SCRYPT_R, SCRYPT_P)?;
+        let public_key = self.public_key().to_array();
+
+        let mut ciphertext = [0u8; KEY_SIZE + TAG_SIZE];
+        ciphertext[..KEY_SIZE].copy_from_slice(self.to_secret_array().as_secret());
+        let tag = make_cipher(&key)
+            .as_secret()
+            .encrypt_in_place_detached(
+                XNonce::from_slice(&nonce),
+                &public_key,
+                &mut ciphertext[..KEY_SIZE],
+            )
+            // The plaintext has a fixed small size, so the encryption cannot fail.
+            .map_err(|_| KeystoreError::InvalidParameters)?;
+        ciphertext[KEY_SIZE..].copy_from_slice(&tag);
+
+        let mut cipher_params = String::from("{");
+        push_string_member(&mut cipher_params, "nonce", &hex::encode(nonce));
+        cipher_params.push('}');
+
+        let mut kdf_params = String::from("{");
+        push_member(&mut kdf_params, "dklen", &format!("{}", KEY_SIZE));
+        push_member(&mut kdf_params, "n", &format!("{}", 1u64 << log_n));
+        push_member(&mut kdf_params, "p", &format!("{}", SCRYPT_P));
+        push_member(&mut kdf_params, "r", &format!("{}", SCRYPT_R));
+        push_string_member(&mut kdf_params, "salt", &hex::encode(salt));
+        kdf_params.push('}');
+
+        let mut crypto = String::from("{");
+        push_string_member(&mut crypto, "cipher", CIPHER);
+        push_member(&mut crypto, "cipherparams", &cipher_params);
+        push_string_member(&mut crypto, "ciphertext", &hex::encode(ciphertext));
+        push_string_member(&mut crypto, "kdf", KDF);
+        push_member(&mut crypto, "kdfparams", &kdf_params);
+        crypto.push('}');
+
+        let mut json = String::from("{");
+        push_member(&mut json, "version", &format!("{}", VERSION));
+        push_string_member(&mut json, "publicKey", &hex::encode(public_key));
+        push_member(&mut json, "crypto", &crypto);
+        json.push('}');
+        Ok(json)
+    }
+
+    /// A synonym for [`to_keystore_with_rng`](`Self::to_keystore_with_rng`)
+    /// with the default RNG and [`KEYSTORE_DEFAULT_LOG_N`].
+    #[cfg(feature = "default-rng")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+    pub fn to_keystore(&self, password: &[u8]) -> Result<String, KeystoreError> {
+        self.to_keystore_with_rng(&mut OsRng, password, KEYSTORE_DEFAULT_LOG_N)
+    }
+
+    /// Decrypts the key from a JSON keystore created by
+    /// [`to_keystore`](`Self::to_keystore`).
+    ///
+    /// Keystores with key derivation parameters above [`KEYSTORE_MAX_LOG_N`]
+    /// (for `r = 8`) are rejected without running the key derivation.
+    pub fn from_keystore(json: &str, password: &[u8]) -> Result<Self, KeystoreError> {
+        let [version, public_key, crypto] =
+            parse_members(json, ["version", "publicKey", "crypto"]).map_err(malformed)?;
+        if u64_member(version, "Missing or invalid version")? != VERSION {
+            return Err(malformed("Unsupported keystore version"));
+        }
+        let public_key_bytes = hex_member(public_key, "Missing or invalid public key")?;
+        let public_key =
+            PublicKey::from_bytes(&public_key_bytes).map_err(KeystoreError::Malformed)?;
+
+        let crypto = crypto.ok_or_else(|| malformed("Missing crypto parameters"))?;
+        let [cipher, cipher_params, ciphertext, kdf, kdf_params] = parse_members(
+            crypto,
+            ["cipher", "cipherparams", "ciphertext", "kdf", "kdfparams"],
+        )
+        .map_err(malformed)?;
+        if cipher.and_then(string_value) != Some(CIPHER) {
+            return Err(malformed("Unsupported cipher"));
+        }
+        if kdf.and_then(string_value) != Some(KDF) {
+            return Err(malformed("Unsupported key derivation function"));
+        }
+
+        let cipher_params = cipher_params.ok_or_else(|| malformed("Missing cipher parameters"))?;
+        let [nonce] = parse_members(cipher_params, ["nonce"]).map_err(malformed)?;
+        let nonce = hex_member(nonce, "Missing or invalid nonce")?;
+        if nonce.len() != NONCE_SIZE {
+            return Err(malformed("Invalid nonce length"));
+        }
+
+        let mut ciphertext = hex_member(ciphertext, "Missing or invalid ciphertext")?;
+        if ciphertext.len() != KEY_SIZE + TAG_SIZE {
+            return Err(malformed("Invalid ciphertext length"));
+        }
+
+        let kdf_params =
+            kdf_params.ok_or_else(|| malformed("Missing key derivation parameters"))?;
+        let [dklen, n, p, r, salt] =
+            parse_members(kdf_params, ["dklen", "n", "p", "r", "salt"]).map_err(malformed)?;
+        if u64_member(dklen, "Missing or invalid dklen")? != KEY_SIZE as u64 {
+            return Err(KeystoreError::InvalidParameters);
+        }
+        let n = u64_member(n, "Missing or invalid n")?;
+        let p = u64_member(p, "Missing or invalid p")?;
+        let r = u64_member(r, "Missing or invalid r")?;
+        let salt = hex_member(salt, "Missing or invalid salt")?;
+
+        if !n.is_power_of_two() || n < 2 {
+            return Err(KeystoreError::InvalidParameters);
+        }
+        let log_n = n.trailing_zeros();
+        if r == 0 || p == 0 || p > MAX_P || log_n > u32::from(KEYSTORE_MAX_LOG_N) {
+            return Err(KeystoreError::InvalidParameters);
+        }
+        if r > MAX_COST >> log_n {
+            return Err(KeystoreError::InvalidParameters);
+        }
+
+        // The values were checked against the limits above, so the casts are lossless.
+        let key = derive_key(password, &salt, log_n as u8, r as u32, p as u32)?;
+
+        let (data, tag) = ciphertext.split_at_mut(KEY_SIZE);
+        let result = make_cipher(&key).as_secret().decrypt_in_place_detached(
+            XNonce::from_slice(&nonce),
+            &public_key_bytes,
+            data,
+            GenericArray::<u8, TagSize>::from_slice(tag),
+        );
+        let plaintext = SecretBox::new(ciphertext);
+        result.map_err(|_| KeystoreError::DecryptionFailed)?;
+
+        let secret_key = SecretKey::from_bytes(&plaintext.as_secret()[..KEY_SIZE])
+            .map_err(KeystoreError::Malformed)?;
+        if secret_key.public_key() != public_key {
+            return Err(malformed("The public key does not match the secret key"));
+        }
+        Ok(secret_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand_core::OsRng;
+
+    use super::{KeystoreError, KEYSTORE_MAX_LOG_N};
+    use crate::{SecretKey, SerializableToArray, SerializableToSecretArray};
+
+    // A small cost parameter to keep the tests fast.
+    const LOG_N: u8 = 4;
+
+    #[test]
+    fn test_roundtrip() {
+        let sk = SecretKey::random();
+        let keystore = sk
+            .to_keystore_with_rng(&mut OsRng, b"password", LOG_N)
+            .unwrap();
+        assert!(keystore.contains(&hex::encode(sk.public_key().to_array())));
+        assert!(!keystore.contains(&hex::encode(sk.to_secret_array().as_secret())));
+
+        let sk_back = SecretKey::from_keystore(&keystore, b"password").unwrap();
+        assert_eq!(
+            sk_back.to_secret_array().as_secret(),
+            sk.to_secret_array().as_secret()
+        );
+
+        assert_eq!(
+            SecretKey::from_keystore(&keystore, b"wrong password").err(),
+            Some(KeystoreError::DecryptionFailed)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let sk = SecretKey::random();
+        assert_eq!(
+            sk.to_keystore_with_rng(&mut OsRng, b"password", KEYSTORE_MAX_LOG_N + 1),
+            Err(KeystoreError::InvalidParameters)
+        );
+
+        let keystore = sk
+            .to_keystore_with_rng(&mut OsRng, b"password", LOG_N)
+            .unwrap();
+
+        // The public key is authenticated
+        let other_pk = hex::encode(SecretKey::random().public_key().to_array());
+        let pk = hex::encode(sk.public_key().to_array());
+        let tampered = keystore.replace(&pk, &other_pk);
+        assert_eq!(
+            SecretKey::from_keystore(&tampered, b"password").err(),
+            Some(KeystoreError::DecryptionFailed)
+        );
+
+        // Excessive key derivation parameters are rejected before running the KDF
+        let expensive = keystore.replace("\"n\":16", "\"n\":1099511627776");
+        assert_eq!(
+            SecretKey::from_keystore(&expensive, b"password").err(),
+            Some(KeystoreError::InvalidParameters)
+        );
+        let expensive = keystore.replace("\"r\":8", "\"r\":1000000");
+        assert_eq!(
+            SecretKey::from_keystore(&expensive, b"password").err(),
+            Some(KeystoreError::InvalidParameters)
+        );
+
+        let unsupported = keystore.replace("xchacha20-poly1305", "aes-128-ctr");
+        assert!(matches!(
+            SecretKey::from_keystore(&unsupported, b"password"),
+            Err(KeystoreError::Malformed(_))
+        ));
+
+        assert!(matches!(
+            SecretKey::from_keystore("{}", b"password"),
+            Err(KeystoreError::Malformed(_))
+        ));
+    }
+}
@@ -12,10 +12,128 @@
 //!
 //! * `default-rng` - adds methods that use the system RNG (default).
 //! * `serde-support` - implements `serde`-based serialization and deserialization.
+//!        Human-readable formats (JSON, TOML, etc.) represent objects as strings
+//!        (hex for public keys and identifiers, base64 for the rest),
+//!        and binary ones as plain bytestrings;
+//!        the deserialization accepts the same representation.
+//! * `borsh` - implements `borsh` serialization and deserialization
+//!        (objects are encoded as their fixed-size byte representation).
+//! * `ssz` - implements SSZ encoding and hash tree roots
+//!        for [`Capsule`], [`CapsuleFrag`] and [`PublicKey`].
+//! * `protobuf` - adds a `protobuf` submodule with `prost` messages
+//!        corresponding to the schema in `proto/umbral.proto`,
+//!        and conversions between them and the native types.
+//! * `flatbuffers` - adds a `flatbuffers` submodule with the encoding of the objects
+//!        (and of reencryption requests) corresponding to the schema in `fbs/umbral.fbs`,
+//!        with views giving access to the fields before the objects are fully validated.
+//! * `eip712` - implements EIP-712 typed structured data hashing
+//!        for [`Capsule`], [`CapsuleFrag`] and [`DelegationCertificate`],
+//!        allowing them to be signed with Ethereum wallets.
+//! * `keystore` - adds an encrypted JSON keystore format for [`SecretKey`]
+//!        (see `SecretKey::to_keystore()`), with the key derived from a password by `scrypt`.
+//! * `conformance` - adds a `conformance` submodule with checks of the protocol invariants
+//!        that dependent crates can run against their configuration.
+//! * `escrow` - adds an `escrow` submodule allowing `threshold` key fragment holders,
+//!        coordinated by the receiving party, to create a key fragment for a replacement proxy
+//!        without the delegating party. **Weakens the security model**,
+//!        see the module documentation before enabling.
+//! * `std` - implements `std::error::Error` for the error types,
+//!        with the wrapped errors available via `source()`,
+//!        adds `std::io`-based serialization methods
+//!        (e.g. `SerializableToArray::write_into()`),
+//!        and the thread-safe serialization buffer pool `BufferPool`
+//!        (with the batch APIs accepting it).
 //! * `bindings-python` - adds a `bindings_python` submodule allowing dependent crates
 //!        to use and re-export some of the Python-wrapped Umbral types.
 //! * `bindings-wasm` - adds a `bindings_wasm` submodule allowing dependent crates
 //!        to use and re-export some of the WASM-wrapped Umbral types.
+//! * `legacy-dem-kdf` - allows decrypting ciphertexts created by versions before 0.6
+//!        (where the symmetric key was not bound to the serialized capsule)
+//!        by falling back to the old key derivation for ciphertexts without the current header.
+//!        Note that this is a downgrade: with this feature enabled, a ciphertext
+//!        without the header is accepted even though it is not bound to the capsule
+//!        and does not identify the curve, the hash and the DEM,
+//!        so it should only be enabled while old ciphertexts are being migrated.
+//! * `insecure-debug` - **never use in production.** Prints intermediate values
+//!        of the protocol (including secret ones) to `stderr` during reencryption
+//!        and decryption, for debugging and comparison with other implementations.
+//! * `p256` - builds the whole library on the NIST P-256 curve instead of secp256k1.
+//!        The sizes of the serialized objects stay the same, but the feature is not additive:
+//!        all the parties must use the same curve, and ciphertexts, keys and signatures
+//!        are not interchangeable between the two configurations
+//!        (ciphertexts from the other configuration are rejected by their header).
+//!        Hashing to scalars reduces a 256-bit digest modulo the group order,
+//!        which on P-256 is biased by about `2^-32` (see `NonZeroCurveScalar::from_digest()`).
+//! * `hash-sha3`, `hash-blake3`, `hash-keccak` - replace SHA-256 with SHA3-256, BLAKE3,
+//!        or Keccak-256 in hashing to scalars, signing, and deriving identifiers and digests,
+//!        keeping the same domain separation tags.
+//!        At most one of them can be enabled, and, same as `p256`, they are not additive:
+//!        capsules, key fragments and signatures do not verify across configurations
+//!        (and ciphertexts are rejected by their header).
+//!        Standard constructions with a fixed hash (hashing to curve, HKDF,
+//!        and the external formats) are not affected.
+//!        With `hash-keccak` the hashes to scalars used in the capsule
+//!        and capsule fragment verification can be reproduced in an EVM contract:
+//!        a scalar is `(keccak256(len(tag) || tag || data) mod (n - 1)) + 1`
+//!        (so that it is never zero), where `n` is the order of secp256k1,
+//!        `len(tag)` is a 4-byte big-endian length of the domain separation tag,
+//!        and the points in `data` are in the compressed form.
+//! * `fips-algorithms` - restricts the scheme to the FIPS-approved algorithms:
+//!        P-256 (enables `p256`), SHA-256, and AES-256-GCM instead of XChaCha20-Poly1305
+//!        for the DEM (with a 96-bit nonce, and the DEM identifier `2` in the ciphertext header).
+//!        Cannot be combined with the `hash-*` features or `legacy-dem-kdf`.
+//!        Note that the primitives are still provided by the RustCrypto crates,
+//!        which are not FIPS-validated modules, and that the formats with fixed algorithms
+//!        (`age` files and key stores) are not affected.
+//!
+//! The curve backend (`k256` 0.10) does not have features switching precomputed tables,
+//! the use of `critical-section`, or the 32-bit field arithmetic implementation
+//! (the field implementation is selected by the target pointer width),
+//! so there are no such features to forward.
+//!
+//! ## Embedded targets
+//!
+//! For 32-bit embedded targets (e.g. Cortex-M) use `default-features = false`
+//! (since the system RNG is usually not available there, and the functions taking
+//! an explicit RNG should be used instead).
+//! On 32-bit targets the curve backend selects its 32-bit field arithmetic implementation
+//! automatically.
+//!
+//! The library only keeps large or variable-sized temporaries
+//! (key fragment lists, polynomial coefficients, signed messages) on the heap.
+//! The peak stack usage is not measured,
+//! so the stack size has to be determined on the target.
+//!
+//! ## Canonical encoding
+//!
+//! Every object has exactly one valid byte representation,
+//! which does not change between versions:
+//!
+//! * curve points are encoded in the SEC1 compressed form (33 bytes);
+//!   the uncompressed form and the point at infinity are rejected
+//!   (except by [`PublicKey::from_sec1_bytes`], which accepts the uncompressed form
+//!   for interoperability);
+//! * scalars are encoded as 32-byte big-endian integers, and must be less than the curve order;
+//! * flags are encoded as a single byte, `0` or `1`;
+//! * composite objects are the concatenation of their fields in a fixed order,
+//!   without separators or length prefixes.
+//!
+//! For copying objects between tools, [`PublicKey`], [`Capsule`] and [`KeyFrag`]
+//! also have a checksummed text representation, see [`Armored`].
+//!
+//! Test vectors for the encoding are published in `vectors/canonical_encoding.json`,
+//! and the layouts of the objects are described in the [`format`] module.
+//!
+//! ## Application context
+//!
+//! Independent deployments can make sure their key fragments and capsule fragments
+//! are not accepted by each other by using [`Parameters`] with an application context
+//! (see [`Parameters::with_application_context`]),
+//! passed to [`generate_kfrags_with_params_and_rng`] and attached to the capsules and key fragments
+//! with [`Capsule::with_params`] and [`KeyFrag::with_params`].
+//! The context is included in the hashes listed in the [`dst`] module,
+//! and in the hash of the key fragment signatures.
+//! The test vectors are only valid for the empty (default) context.
 //!
 //! # Usage
 //!
@@ -55,7 +173,8 @@
 //!
 //! let shares = 3; // how many fragments to create
 //! let threshold = 2; // how many should be enough to decrypt
-//! let verified_kfrags = generate_kfrags(&alice_sk, &bob_pk, &signer, threshold, shares, true, true);
+//! let verified_kfrags =
+//!     generate_kfrags(&alice_sk, &bob_pk, &signer, threshold, shares, true, true).unwrap();
 //!
 //! // Bob asks several Ursulas to re-encrypt the capsule so he can open it.
 //! // Each Ursula performs re-encryption on the capsule using the kfrag provided by Alice,
@@ -123,36 +242,185 @@ pub mod bindings_python;
 #[cfg(feature = "bindings-wasm")]
 pub mod bindings_wasm;
 
+#[cfg(feature = "conformance")]
+pub mod conformance;
+
+pub mod dst;
+
+#[cfg(feature = "escrow")]
+pub mod escrow;
+
+#[cfg(feature = "flatbuffers")]
+pub mod flatbuffers;
+
+pub mod format;
+
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+
+mod abi;
+mod age;
+mod armor;
+mod audit;
+mod batch;
 mod capsule;
 mod capsule_frag;
+mod cbor;
+mod certificate;
+mod cost;
 mod curve;
+mod debug;
 mod dem;
+mod diagnostics;
+mod ecies;
+mod envelope;
 mod hashing;
 mod hashing_ds;
+mod json;
+mod jwe;
+mod jwk;
 mod key_frag;
+mod key_frag_index;
 mod keys;
+mod message_kit;
+mod msgpack;
 mod params;
+mod pkcs8;
+mod planner;
+mod policy;
 mod pre;
+mod qr;
+mod receipt;
+mod roles;
 mod secret_box;
+mod source;
+mod stream;
+mod threshold;
+mod time_bounded;
 mod traits;
+mod versioned;
+mod view;
 
 #[cfg(any(feature = "serde-support", feature = "bindings-wasm"))]
 mod serde;
 
+#[cfg(feature = "borsh")]
+mod borsh;
+
+#[cfg(feature = "std")]
+mod pool;
+
+#[cfg(feature = "ssz")]
+mod ssz;
+
+#[cfg(feature = "eip712")]
+mod eip712;
+
+#[cfg(feature = "keystore")]
+mod keystore;
+
+#[cfg(test)]
+mod test_utils;
+
+// The reference vectors are generated for secp256k1 and SHA-256.
+#[cfg(all(
+    test,
+    not(any(
+        feature = "p256",
+        feature = "hash-sha3",
+        feature = "hash-blake3",
+        feature = "hash-keccak"
+    ))
+))]
+mod test_vectors;
+
+pub use age::{AgeFile, AGE_STANZA_TYPE};
+pub use armor::Armored;
+pub use audit::{verify_audit_log, AuditEntry, AuditLog, AuditOperation, AuditSink};
+pub use batch::{batch_from_bytes, batch_to_bytes, BatchError};
 pub use capsule::{Capsule, OpenReencryptedError};
-pub use capsule_frag::{CapsuleFrag, CapsuleFragVerificationError, VerifiedCapsuleFrag};
-pub use dem::{DecryptionError, EncryptionError};
-pub use key_frag::{KeyFrag, KeyFragVerificationError, VerifiedKeyFrag};
+pub use capsule_frag::{
+    audit_capsule_frags, CapsuleFrag, CapsuleFragVerificationError, PrecheckedCapsuleFrag,
+    UnprovenCapsuleFrag, VerifiedCapsuleFrag,
+};
+pub use cbor::{FromCbor, ToCbor};
+pub use certificate::{DelegationCertificate, DelegationCertificateError};
+pub use cost::{generate_kfrags_cost, reencrypt_cost, CostModel, OperationCount};
+pub use dem::{
+    ciphertext_nonce, CiphertextHeader, DecryptionError, EncryptionError, NonceStrategy,
+};
+pub use diagnostics::{DeserializationReport, FieldError, FieldErrorKind};
+pub use ecies::{decrypt_with, encrypt_for_with_rng, IntegratedDecryptionError};
+pub use envelope::{
+    seal_with_rng, unseal, unseal_reencrypted, Envelope, ENVELOPE_COMPACT_PROFILE, ENVELOPE_SUITE,
+    ENVELOPE_VERSION,
+};
+pub use jwe::{from_jwe_compact, to_jwe_compact, JWE_ALGORITHM, JWE_ENCRYPTION};
+pub use key_frag::{
+    CompactKeyFrag, DelegationInfo, KeyFrag, KeyFragID, KeyFragVerificationError, VerifiedKeyFrag,
+};
+pub use key_frag_index::KeyFragIndex;
 pub use keys::{PublicKey, SecretKey, SecretKeyFactory, Signature, Signer};
+pub use message_kit::MessageKit;
+pub use msgpack::{FromMessagePack, ToMessagePack};
+pub use params::Parameters;
+pub use planner::{ProxyStatus, QueryPlanner};
+pub use policy::PolicyId;
 pub use pre::{
-    decrypt_original, decrypt_reencrypted, encrypt_with_rng, generate_kfrags_with_rng,
-    reencrypt_with_rng, ReencryptionError,
+    decrypt_original, decrypt_original_into, decrypt_reencrypted, decrypt_reencrypted_into,
+    decrypt_reencrypted_unproven, encrypt_into_with_rng, encrypt_with_nonce_strategy_with_rng,
+    encrypt_with_rng, generate_kfrags_batch_with_params_and_rng, generate_kfrags_batch_with_rng,
+    generate_kfrags_with_params_and_rng, generate_kfrags_with_rng, has_derived_nonce,
+    reencrypt_compact, reencrypt_deterministic, reencrypt_unproven, reencrypt_with_rng,
+    try_decrypt_original, try_decrypt_reencrypted, try_generate_kfrags_with_params_and_rng,
+    try_generate_kfrags_with_rng, GenerateKFragsError, ReencryptionError, MAX_SHARES,
+};
+pub use qr::{from_qr_string, to_qr_string, QR_PREFIX};
+pub use receipt::{sign_capsule_statement_with_rng, verify_capsule_statement};
+pub use roles::{
+    AsDelegatingKey, AsReceivingKey, DelegatingSecretKey, ReceivingSecretKey, SigningSecretKey,
 };
 pub use secret_box::{CanBeZeroizedOnDrop, SecretBox};
+pub use source::{group_by_source, HasSourceId, SourceId};
+pub use stream::StreamDeserializer;
+pub use threshold::{
+    decrypt_reencrypted_threshold, split_receiving_key_with_rng, PartialDecryption,
+    ReceivingKeyShare, SplitReceivingKeyError, ThresholdDecryptionError,
+};
+pub use time_bounded::{TimeBounded, TimeBoundedCapsuleFrag, TimeBoundedKeyFrag};
 pub use traits::{
     ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
     RepresentableAsArray, SerializableToArray, SerializableToSecretArray, SizeMismatchError,
 };
+pub use versioned::{VersionedSerialization, VERSIONED_MAGIC};
+pub use view::{CapsuleFragRef, CapsuleRef};
+
+#[cfg(feature = "std")]
+pub use batch::batch_to_bytes_with_pool;
+#[cfg(feature = "std")]
+pub use pool::{BufferPool, PooledBuffer};
+#[cfg(feature = "std")]
+pub use pre::generate_kfrags_batch_with_pool_and_rng;
+
+#[cfg(feature = "ssz")]
+pub use ssz::{SszDecode, SszEncode};
 
+#[cfg(feature = "eip712")]
+pub use eip712::{Eip712Domain, Eip712Struct};
+
+#[cfg(feature = "keystore")]
+pub use keystore::{KeystoreError, KEYSTORE_DEFAULT_LOG_N, KEYSTORE_MAX_LOG_N};
+
+#[cfg(feature = "default-rng")]
+pub use ecies::encrypt_for;
+#[cfg(feature = "default-rng")]
+pub use envelope::seal;
+#[cfg(feature = "default-rng")]
+pub use pre::{
+    encrypt, encrypt_into, encrypt_with_nonce_strategy, generate_kfrags, generate_kfrags_batch,
+    generate_kfrags_with_params, reencrypt, try_generate_kfrags,
+};
+#[cfg(feature = "default-rng")]
+pub use receipt::sign_capsule_statement;
 #[cfg(feature = "default-rng")]
-pub use pre::{encrypt, generate_kfrags, reencrypt};
+pub use threshold::split_receiving_key;
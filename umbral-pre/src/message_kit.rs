@@ -0,0 +1,242 @@
+//! A container for the encryption results.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "serde-support")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::VerifiedCapsuleFrag;
+use crate::dem::{DecryptionError, EncryptionError};
+use crate::keys::PublicKey;
+use crate::pre::{self, ReencryptionError};
+use crate::roles::{AsDelegatingKey, AsReceivingKey};
+use crate::traits::{
+    fmt_public, ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
+    RepresentableAsArray, SerializableToArray,
+};
+
+#[cfg(feature = "serde-support")]
+use crate::serde::{serde_deserialize_bytes, serde_serialize_bytes, Representation};
+
+/// A capsule together with the ciphertext it was created for,
+/// as returned by [`encrypt`](`crate::encrypt`).
+///
+/// The serialized form is the capsule followed by the DEM ciphertext,
+/// without a header (unlike [`Envelope`](`crate::Envelope`)),
+/// so it can also be assembled from the results of [`encrypt`](`crate::encrypt`)
+/// by other implementations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessageKit {
+    capsule: Capsule,
+    ciphertext: Box<[u8]>,
+}
+
+impl MessageKit {
+    /// Creates a message kit from a capsule and the corresponding ciphertext.
+    pub fn new(capsule: Capsule, ciphertext: impl AsRef<[u8]>) -> Self {
+        Self {
+            capsule,
+            ciphertext: ciphertext.as_ref().into(),
+        }
+    }
+
+    /// Encrypts `plaintext` for the owner of `delegating_pk` using the given RNG
+    /// (see [`encrypt_with_rng`](`crate::encrypt_with_rng`)).
+    pub fn encrypt_with_rng(
+        rng: &mut (impl CryptoRng + RngCore),
+        delegating_pk: &PublicKey,
+        plaintext: &[u8],
+    ) -> Result<Self, EncryptionError> {
+        let (capsule, ciphertext) = pre::encrypt_with_rng(rng, delegating_pk, plaintext)?;
+        Ok(Self {
+            capsule,
+            ciphertext,
+        })
+    }
+
+    /// A synonym for [`encrypt_with_rng`](`Self::encrypt_with_rng`) with the default RNG.
+    #[cfg(feature = "default-rng")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+    pub fn encrypt(delegating_pk: &PublicKey, plaintext: &[u8]) -> Result<Self, EncryptionError> {
+        Self::encrypt_with_rng(&mut OsRng, delegating_pk, plaintext)
+    }
+
+    /// Returns the capsule (to be reencrypted by proxies if the access was delegated).
+    pub fn capsule(&self) -> &Capsule {
+        &self.capsule
+    }
+
+    /// Returns the DEM ciphertext.
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    /// Decrypts the ciphertext using the secret key it was encrypted for
+    /// (see [`decrypt_original`](`crate::decrypt_original`)).
+    pub fn decrypt_original(
+        &self,
+        delegating_sk: &impl AsDelegatingKey,
+    ) -> Result<Box<[u8]>, DecryptionError> {
+        pre::decrypt_original(delegating_sk, &self.capsule, &self.ciphertext)
+    }
+
+    /// Decrypts the ciphertext using the capsule fragments obtained by reencrypting
+    /// [`capsule`](`Self::capsule`)
+    /// (see [`decrypt_reencrypted`](`crate::decrypt_reencrypted`)).
+    pub fn decrypt_reencrypted(
+        &self,
+        receiving_sk: &impl AsReceivingKey,
+        delegating_pk: &PublicKey,
+        verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+    ) -> Result<Box<[u8]>, ReencryptionError> {
+        pre::decrypt_reencrypted(
+            receiving_sk,
+            delegating_pk,
+            &self.capsule,
+            verified_cfrags,
+            &self.ciphertext,
+        )
+    }
+
+    /// Serializes the message kit.
+    pub fn to_bytes(&self) -> Box<[u8]> {
+        let capsule_bytes = self.capsule.to_array();
+        let mut result = Vec::with_capacity(capsule_bytes.len() + self.ciphertext.len());
+        result.extend_from_slice(&capsule_bytes);
+        result.extend_from_slice(&self.ciphertext);
+        result.into_boxed_slice()
+    }
+
+    /// Restores the message kit from the serialized form.
+    pub fn from_bytes(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let data = data.as_ref();
+        let capsule_size = Capsule::serialized_size();
+        if data.len() < capsule_size {
+            return Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new("MessageKit", "The bytestring is too short"),
+            ));
+        }
+
+        let (capsule_bytes, ciphertext) = data.split_at(capsule_size);
+        let capsule = Capsule::from_bytes(capsule_bytes)?;
+        Ok(Self::new(capsule, ciphertext))
+    }
+}
+
+impl From<(Capsule, Box<[u8]>)> for MessageKit {
+    fn from((capsule, ciphertext): (Capsule, Box<[u8]>)) -> Self {
+        Self {
+            capsule,
+            ciphertext,
+        }
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for MessageKit {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize_bytes(&self.to_bytes(), serializer, Representation::Base64)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for MessageKit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = serde_deserialize_bytes(deserializer, Representation::Base64, "MessageKit")?;
+        Self::from_bytes(bytes).map_err(de::Error::custom)
+    }
+}
+
+impl HasTypeName for MessageKit {
+    fn type_name() -> &'static str {
+        "MessageKit"
+    }
+}
+
+impl fmt::Display for MessageKit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(&self.capsule, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::MessageKit;
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{reencrypt, Capsule, DecryptionError, RepresentableAsArray, SerializableToArray};
+
+    #[test]
+    fn test_message_kit() {
+        let Delegation {
+            delegating_sk,
+            delegating_pk,
+            receiving_sk,
+            verified_kfrags: vkfrags,
+            ..
+        } = prepare_delegation(true, true);
+        let plaintext = b"peace at dawn";
+
+        let message_kit = MessageKit::encrypt(&delegating_pk, plaintext).unwrap();
+        let message_kit_bytes = message_kit.to_bytes();
+        assert_eq!(
+            message_kit_bytes.len(),
+            Capsule::serialized_size() + message_kit.ciphertext().len()
+        );
+        let message_kit_back = MessageKit::from_bytes(&message_kit_bytes).unwrap();
+        assert_eq!(message_kit_back, message_kit);
+
+        let plaintext_back = message_kit_back.decrypt_original(&delegating_sk).unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        // Delegated decryption
+        let vcfrags = vkfrags[0..2]
+            .iter()
+            .map(|vkfrag| reencrypt(message_kit.capsule(), vkfrag.clone()));
+        let plaintext_bob = message_kit
+            .decrypt_reencrypted(&receiving_sk, &delegating_pk, vcfrags)
+            .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+
+        // Wrong key
+        assert_eq!(
+            message_kit.decrypt_original(&receiving_sk),
+            Err(DecryptionError::AuthenticationFailed)
+        );
+
+        // Too short
+        assert!(MessageKit::from_bytes(&message_kit_bytes[..10]).is_err());
+    }
+
+    #[test]
+    fn test_compatibility() {
+        let Delegation {
+            capsule,
+            ciphertext,
+            ..
+        } = prepare_delegation(true, true);
+
+        let message_kit = MessageKit::new(capsule, &ciphertext);
+        assert_eq!(message_kit, MessageKit::from((capsule, ciphertext.clone())));
+
+        let mut expected_bytes = capsule.to_vec();
+        expected_bytes.extend_from_slice(&ciphertext);
+        assert_eq!(&message_kit.to_bytes() as &[u8], &expected_bytes[..]);
+    }
+}
@@ -0,0 +1,167 @@
+//! MessagePack encoding of the objects, without depending on a MessagePack library.
+//!
+//! Objects are encoded as a single `bin` value containing their byte representation,
+//! which is the same as what `serde`-based MessagePack encoders (e.g. `rmp-serde`)
+//! produce with the `serde-support` feature enabled,
+//! so the two can be used interchangeably.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
+    SerializableToArray,
+};
+
+const BIN8: u8 = 0xc4;
+const BIN16: u8 = 0xc5;
+const BIN32: u8 = 0xc6;
+
+/// A trait for objects that can be encoded as MessagePack.
+/// Implemented for all the objects serializable to arrays.
+pub trait ToMessagePack {
+    /// Encodes the object as a MessagePack `bin` value.
+    fn to_msgpack(&self) -> Box<[u8]>;
+}
+
+/// A trait for objects that can be decoded from MessagePack.
+/// Implemented for all the objects deserializable from arrays.
+pub trait FromMessagePack: Sized {
+    /// Decodes the object from a MessagePack `bin` value
+    /// (any of `bin 8`, `bin 16` or `bin 32` encodings is accepted).
+    fn from_msgpack(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError>;
+}
+
+impl<T: SerializableToArray> ToMessagePack for T {
+    fn to_msgpack(&self) -> Box<[u8]> {
+        let bytes = self.to_array();
+        let len = bytes.len();
+        let mut result = Vec::with_capacity(len + 5);
+        if let Ok(len) = u8::try_from(len) {
+            result.push(BIN8);
+            result.push(len);
+        } else if let Ok(len) = u16::try_from(len) {
+            result.push(BIN16);
+            result.extend_from_slice(&len.to_be_bytes());
+        } else {
+            // The serialized objects are never that large, but we are being thorough.
+            result.push(BIN32);
+            result.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+        result.extend_from_slice(&bytes);
+        result.into_boxed_slice()
+    }
+}
+
+fn split_header(data: &[u8]) -> Option<(usize, &[u8])> {
+    let (tag, rest) = data.split_first()?;
+    let len_size = match *tag {
+        BIN8 => 1,
+        BIN16 => 2,
+        BIN32 => 4,
+        _ => return None,
+    };
+    if rest.len() < len_size {
+        return None;
+    }
+    let (len_bytes, payload) = rest.split_at(len_size);
+    let len = len_bytes
+        .iter()
+        .fold(0usize, |len, byte| (len << 8) | (*byte as usize));
+    Some((len, payload))
+}
+
+impl<T: DeserializableFromArray + HasTypeName> FromMessagePack for T {
+    fn from_msgpack(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let (len, payload) = split_header(data.as_ref()).ok_or_else(|| {
+            DeserializationError::ConstructionFailure(ConstructionError::new(
+                T::type_name(),
+                "Expected a MessagePack bin value",
+            ))
+        })?;
+        if len != payload.len() {
+            return Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new(
+                    T::type_name(),
+                    "MessagePack bin length does not match the payload",
+                ),
+            ));
+        }
+        T::from_bytes(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{FromMessagePack, ToMessagePack};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{
+        encrypt, Capsule, CapsuleFrag, DeserializationError, KeyFrag, SecretKey,
+        SerializableToArray,
+    };
+
+    #[test]
+    fn test_roundtrip() {
+        let Delegation {
+            capsule,
+            verified_kfrags: vkfrags,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+        let vcfrag = vcfrags[0].clone();
+
+        let capsule_packed = capsule.to_msgpack();
+        assert_eq!(Capsule::from_msgpack(&capsule_packed).unwrap(), capsule);
+
+        let kfrag = vkfrags[0].clone_unverified();
+        let kfrag_packed = vkfrags[0].to_msgpack();
+        assert_eq!(KeyFrag::from_msgpack(&kfrag_packed).unwrap(), kfrag);
+
+        let cfrag = vcfrag.clone_unverified();
+        let cfrag_packed = vcfrag.to_msgpack();
+        assert_eq!(CapsuleFrag::from_msgpack(&cfrag_packed).unwrap(), cfrag);
+
+        // Compatible with the `serde`-based encoding
+        #[cfg(feature = "serde-support")]
+        {
+            assert_eq!(
+                rmp_serde::to_vec(&capsule).unwrap(),
+                capsule_packed.to_vec()
+            );
+            assert_eq!(rmp_serde::to_vec(&kfrag).unwrap(), kfrag_packed.to_vec());
+            assert_eq!(rmp_serde::to_vec(&cfrag).unwrap(), cfrag_packed.to_vec());
+        }
+
+        // Non-minimal length encodings are accepted
+        let mut capsule_bin32 = [0xc6u8, 0, 0, 0, 0].to_vec();
+        capsule_bin32[4] = capsule.to_array().len() as u8;
+        capsule_bin32.extend_from_slice(&capsule.to_array());
+        assert_eq!(Capsule::from_msgpack(&capsule_bin32).unwrap(), capsule);
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let capsule = encrypt(&SecretKey::random().public_key(), b"peace at dawn")
+            .unwrap()
+            .0;
+        let mut packed = capsule.to_msgpack().to_vec();
+
+        // Wrong length
+        packed[1] -= 1;
+        assert!(matches!(
+            Capsule::from_msgpack(&packed),
+            Err(DeserializationError::ConstructionFailure(_))
+        ));
+
+        // Not a `bin` value
+        packed[0] = 0xd9;
+        assert!(matches!(
+            Capsule::from_msgpack(&packed),
+            Err(DeserializationError::ConstructionFailure(_))
+        ));
+
+        assert!(Capsule::from_msgpack(b"").is_err());
+    }
+}
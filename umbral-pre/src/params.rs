@@ -1,30 +1,116 @@
-use crate::curve::CurvePoint;
+use crate::curve::{CurvePoint, CurveType, UmbralCurve};
+use crate::dst;
+use crate::traits::ConstructionError;
 
 /// An object containing shared scheme parameters.
+///
+/// The default parameters are used unless custom ones are explicitly attached to the objects
+/// (see [`generate_kfrags_with_params_and_rng`](`crate::generate_kfrags_with_params_and_rng`)).
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Parameters {
     pub(crate) u: CurvePoint,
+    // Stored inline (instead of a `Box`) to keep the type `Copy`.
+    context: [u8; Parameters::MAX_APPLICATION_CONTEXT_SIZE],
+    context_len: usize,
 }
 
 impl Parameters {
+    /// The maximum length of the application context in bytes.
+    pub const MAX_APPLICATION_CONTEXT_SIZE: usize = 64;
+
+    fn from_point_u(u: CurvePoint) -> Self {
+        Self {
+            u,
+            context: [0u8; Self::MAX_APPLICATION_CONTEXT_SIZE],
+            context_len: 0,
+        }
+    }
+
     /// Creates a new parameter object.
     pub fn new() -> Self {
         // The goal is to find two distinct points `g` and `u` for which `log_g(u)` is unknown.
         // `g` is fixed to be the generator because it has to be the same
         // as the one used for secret/public keys, and it is standardized (for a given curve).
 
-        // Only fails when the given binary string is too large, which is not the case here,
-        // so we can safely unwrap.
-        let u = CurvePoint::from_data(b"PARAMETERS", b"POINT_U").unwrap();
+        // The constant is a valid point, so we can safely unwrap.
+        let u = CurvePoint::from_sec1_bytes(CurveType::POINT_U).unwrap();
+
+        Self::from_point_u(u)
+    }
+
+    /// Creates a parameter object with the point `u` obtained by hashing the given seed
+    /// to the curve, so that nobody knows its discrete logarithm.
+    ///
+    /// The default parameters correspond to the seed `b"POINT_U"`.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        // Hashing to curve can only fail for a DST longer than 255 bytes,
+        // and our DST is fixed.
+        let u = CurvePoint::from_data(dst::PARAMETERS, seed).unwrap();
+        Self::from_point_u(u)
+    }
+
+    /// Creates a parameter object with the given point `u` (in any of the SEC1 encodings).
+    ///
+    /// **Warning:** the discrete logarithm of `u` with respect to the generator
+    /// must be unknown to everyone, otherwise the correctness proofs can be forged.
+    /// Unless `u` comes from a published nothing-up-my-sleeve procedure,
+    /// use [`from_seed`](`Self::from_seed`) instead.
+    pub fn from_point_u_bytes(bytes: &[u8]) -> Result<Self, ConstructionError> {
+        let u = CurvePoint::from_sec1_bytes(bytes)
+            .ok_or_else(|| ConstructionError::new("Parameters", "Invalid point"))?;
+        if u == CurvePoint::identity() || u == CurvePoint::generator() {
+            return Err(ConstructionError::new(
+                "Parameters",
+                "The point must be distinct from the identity and the generator",
+            ));
+        }
+        Ok(Self::from_point_u(u))
+    }
+
+    /// Returns the parameters with the given application context
+    /// (replacing the current one, which is empty by default).
+    ///
+    /// The context is included in the hashes binding the key fragments
+    /// (their signatures and share indices) and the capsule fragments (their correctness proofs),
+    /// so that the fragments created by independent deployments
+    /// do not verify with each other's parameters.
+    /// See the [`dst`](`crate::dst`) module for the details.
+    ///
+    /// Returns an error if the context is longer than
+    /// [`MAX_APPLICATION_CONTEXT_SIZE`](`Self::MAX_APPLICATION_CONTEXT_SIZE`).
+    pub fn with_application_context(self, context: &[u8]) -> Result<Self, ConstructionError> {
+        if context.len() > Self::MAX_APPLICATION_CONTEXT_SIZE {
+            return Err(ConstructionError::new(
+                "Parameters",
+                "The application context is too long",
+            ));
+        }
+        let mut result = Self::from_point_u(self.u);
+        result.context[..context.len()].copy_from_slice(context);
+        result.context_len = context.len();
+        Ok(result)
+    }
+
+    /// Returns the application context (empty by default).
+    pub fn application_context(&self) -> &[u8] {
+        &self.context[..self.context_len]
+    }
+}
 
-        Self { u }
+impl Default for Parameters {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use elliptic_curve::sec1::ToEncodedPoint;
+
     use super::Parameters;
+    use crate::curve::CurvePoint;
+    use crate::dst;
 
     #[test]
     fn test_default() {
@@ -32,4 +118,46 @@ mod tests {
         let p2 = Parameters::new();
         assert_eq!(p1, p2);
     }
+
+    #[test]
+    fn test_precalculated_u() {
+        let u = CurvePoint::from_data(dst::PARAMETERS, dst::POINT_U).unwrap();
+        assert_eq!(Parameters::new().u, u);
+        assert_eq!(Parameters::from_seed(dst::POINT_U), Parameters::new());
+    }
+
+    #[test]
+    fn test_custom() {
+        let params = Parameters::from_seed(b"my application");
+        assert_ne!(params, Parameters::new());
+
+        let u = params.u.to_affine_point().to_encoded_point(true);
+        assert_eq!(
+            Parameters::from_point_u_bytes(u.as_bytes()).unwrap(),
+            params
+        );
+
+        let g = CurvePoint::generator()
+            .to_affine_point()
+            .to_encoded_point(true);
+        assert!(Parameters::from_point_u_bytes(g.as_bytes()).is_err());
+        assert!(Parameters::from_point_u_bytes(&[0u8]).is_err());
+        assert!(Parameters::from_point_u_bytes(&[5u8; 33]).is_err());
+    }
+
+    #[test]
+    fn test_application_context() {
+        let params = Parameters::new();
+        assert_eq!(params.application_context(), b"");
+
+        let params_ctx = params.with_application_context(b"my application").unwrap();
+        assert_eq!(params_ctx.application_context(), b"my application");
+        assert_eq!(params_ctx.u, params.u);
+        assert_ne!(params_ctx, params);
+        assert_eq!(params_ctx.with_application_context(b"").unwrap(), params);
+
+        let long_context = [1u8; Parameters::MAX_APPLICATION_CONTEXT_SIZE + 1];
+        assert!(params.with_application_context(&long_context).is_err());
+        assert!(params.with_application_context(&long_context[1..]).is_ok());
+    }
 }
@@ -0,0 +1,239 @@
+//! Selection of the proxies to query for capsule fragments.
+
+use alloc::vec::Vec;
+
+use crate::key_frag::KeyFragID;
+
+/// The caller's knowledge about a proxy, used to prioritize the queries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProxyStatus {
+    /// Whether the proxy is believed to be reachable.
+    /// Unavailable proxies are only queried when the available ones are not enough.
+    pub available: bool,
+    /// The expected response time, in the units of caller's choice.
+    /// Faster proxies are queried first.
+    pub latency: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum QueryState {
+    Idle,
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Clone, Debug)]
+struct Candidate<P> {
+    kfrag_id: KeyFragID,
+    proxy: P,
+    state: QueryState,
+}
+
+/// A scheduler of the requests for capsule fragments.
+///
+/// Given the proxies holding the key fragments of a policy,
+/// selects the smallest set of them to query in order to obtain `threshold`
+/// capsule fragments (with distinct key fragment IDs),
+/// and, as the results are reported back, the replacements for the failed ones.
+///
+/// A typical usage is to call [`next_queries`](`Self::next_queries`),
+/// send the requests, report the results with [`record_success`](`Self::record_success`)
+/// and [`record_failure`](`Self::record_failure`), and repeat
+/// until the planner [`is_complete`](`Self::is_complete`)
+/// or [`is_exhausted`](`Self::is_exhausted`).
+#[derive(Clone, Debug)]
+pub struct QueryPlanner<P> {
+    threshold: usize,
+    candidates: Vec<Candidate<P>>,
+}
+
+impl<P: Clone + PartialEq> QueryPlanner<P> {
+    /// Creates a planner from the assignment of key fragments to proxies
+    /// and the status of each proxy.
+    pub fn new(
+        threshold: usize,
+        assignment: impl IntoIterator<Item = (KeyFragID, P, ProxyStatus)>,
+    ) -> Self {
+        let mut prioritized: Vec<_> = assignment.into_iter().collect();
+        // The sort is stable, so the proxies with the same priority
+        // are queried in the order they were given.
+        prioritized.sort_by_key(|(_, _, status)| (!status.available, status.latency));
+        let candidates = prioritized
+            .into_iter()
+            .map(|(kfrag_id, proxy, _)| Candidate {
+                kfrag_id,
+                proxy,
+                state: QueryState::Idle,
+            })
+            .collect();
+        Self {
+            threshold,
+            candidates,
+        }
+    }
+
+    fn covered_ids(&self, states: &[QueryState]) -> Vec<KeyFragID> {
+        let mut ids = Vec::<KeyFragID>::new();
+        for candidate in self.candidates.iter() {
+            if states.contains(&candidate.state) && !ids.contains(&candidate.kfrag_id) {
+                ids.push(candidate.kfrag_id);
+            }
+        }
+        ids
+    }
+
+    /// Returns the proxies that need to be queried (in addition to the ones
+    /// already queried and not yet reported) to reach the threshold,
+    /// and marks them as pending.
+    ///
+    /// Returns an empty list if enough queries are already pending or succeeded,
+    /// or if there are no more proxies to query.
+    pub fn next_queries(&mut self) -> Vec<P> {
+        let mut covered_ids = self.covered_ids(&[QueryState::Pending, QueryState::Succeeded]);
+        let mut result = Vec::new();
+        for candidate in self.candidates.iter_mut() {
+            if covered_ids.len() >= self.threshold {
+                break;
+            }
+            if candidate.state != QueryState::Idle || covered_ids.contains(&candidate.kfrag_id) {
+                continue;
+            }
+            candidate.state = QueryState::Pending;
+            covered_ids.push(candidate.kfrag_id);
+            result.push(candidate.proxy.clone());
+        }
+        result
+    }
+
+    fn set_state(&mut self, proxy: &P, state: QueryState) {
+        for candidate in self.candidates.iter_mut() {
+            if &candidate.proxy == proxy && candidate.state == QueryState::Pending {
+                candidate.state = state;
+            }
+        }
+    }
+
+    /// Records that the given proxy has returned a valid capsule fragment.
+    pub fn record_success(&mut self, proxy: &P) {
+        self.set_state(proxy, QueryState::Succeeded);
+    }
+
+    /// Records that the given proxy has failed to respond or returned an invalid fragment.
+    /// The following call to [`next_queries`](`Self::next_queries`)
+    /// will select a replacement, if there is one.
+    pub fn record_failure(&mut self, proxy: &P) {
+        self.set_state(proxy, QueryState::Failed);
+    }
+
+    /// Returns `true` if the capsule fragments for `threshold` distinct key fragments
+    /// have been received.
+    pub fn is_complete(&self) -> bool {
+        self.covered_ids(&[QueryState::Succeeded]).len() >= self.threshold
+    }
+
+    /// Returns `true` if the threshold can no longer be reached:
+    /// even if all the pending and not yet queried proxies succeed,
+    /// there will not be enough distinct key fragments.
+    pub fn is_exhausted(&self) -> bool {
+        self.covered_ids(&[QueryState::Idle, QueryState::Pending, QueryState::Succeeded])
+            .len()
+            < self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{ProxyStatus, QueryPlanner};
+    use crate::{generate_kfrags, SecretKey, Signer};
+
+    #[test]
+    fn test_query_planner() {
+        let delegating_sk = SecretKey::random();
+        let receiving_pk = SecretKey::random().public_key();
+        let signer = Signer::new(SecretKey::random());
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 4, true, true).unwrap();
+
+        let statuses = [
+            ProxyStatus {
+                available: true,
+                latency: 30,
+            },
+            ProxyStatus {
+                available: false,
+                latency: 1,
+            },
+            ProxyStatus {
+                available: true,
+                latency: 10,
+            },
+            ProxyStatus {
+                available: true,
+                latency: 20,
+            },
+        ];
+        let assignment: Vec<_> = vkfrags
+            .iter()
+            .zip(statuses.iter())
+            .enumerate()
+            .map(|(proxy, (vkfrag, status))| (vkfrag.id(), proxy, *status))
+            .collect();
+
+        let mut planner = QueryPlanner::new(2, assignment);
+        assert!(!planner.is_complete());
+
+        // The fastest available proxies go first
+        assert_eq!(planner.next_queries(), [2, 3]);
+        // Nothing else is needed while the queries are pending
+        assert!(planner.next_queries().is_empty());
+
+        planner.record_success(&2);
+        planner.record_failure(&3);
+        assert!(!planner.is_complete());
+
+        // Backfill with the next available proxy
+        assert_eq!(planner.next_queries(), [0]);
+        planner.record_failure(&0);
+
+        // Unavailable proxies are the last resort
+        assert_eq!(planner.next_queries(), [1]);
+        assert!(!planner.is_exhausted());
+        planner.record_failure(&1);
+
+        assert!(planner.next_queries().is_empty());
+        assert!(planner.is_exhausted());
+        assert!(!planner.is_complete());
+    }
+
+    #[test]
+    fn test_query_planner_duplicate_kfrags() {
+        let delegating_sk = SecretKey::random();
+        let receiving_pk = SecretKey::random().public_key();
+        let signer = Signer::new(SecretKey::random());
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 2, true, true).unwrap();
+
+        let status = ProxyStatus {
+            available: true,
+            latency: 0,
+        };
+        // The same key fragment is held by two proxies
+        let assignment = [
+            (vkfrags[0].id(), "a", status),
+            (vkfrags[0].id(), "b", status),
+            (vkfrags[1].id(), "c", status),
+        ];
+
+        let mut planner = QueryPlanner::new(2, assignment);
+        assert_eq!(planner.next_queries(), ["a", "c"]);
+        planner.record_success(&"c");
+        planner.record_failure(&"a");
+        assert_eq!(planner.next_queries(), ["b"]);
+        planner.record_success(&"b");
+        assert!(planner.is_complete());
+    }
+}
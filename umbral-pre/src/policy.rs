@@ -0,0 +1,155 @@
+//! Identifiers of delegations shared between independent components.
+
+use core::fmt;
+use core::str::FromStr;
+
+use digest::Digest;
+use generic_array::sequence::Split;
+use generic_array::GenericArray;
+use typenum::U16;
+
+#[cfg(feature = "serde-support")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::dst;
+use crate::hashing::Hash;
+use crate::keys::PublicKey;
+use crate::traits::{
+    fmt_public, from_str_public, ConstructionError, DeserializableFromArray, DeserializationError,
+    HasTypeName, RepresentableAsArray, SerializableToArray,
+};
+
+#[cfg(feature = "serde-support")]
+use crate::serde::{serde_deserialize, serde_serialize, Representation};
+
+type PolicyIdSize = U16;
+
+/// An identifier of a delegation from the owner of a delegating key
+/// to the owner of a receiving key under a given label.
+///
+/// Intended to be used to reference a delegation in stores, revocations, and receipts,
+/// so that independent parties computing it get the same result.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PolicyId(GenericArray<u8, PolicyIdSize>);
+
+impl PolicyId {
+    /// Computes the identifier of the delegation from `delegating_pk` to `receiving_pk`
+    /// under the given label.
+    pub fn new(delegating_pk: &PublicKey, receiving_pk: &PublicKey, label: &[u8]) -> Self {
+        let digest = Hash::new_with_dst(dst::POLICY_ID)
+            .chain_bytes(delegating_pk.to_array())
+            .chain_bytes(receiving_pk.to_array())
+            .chain_bytes(label)
+            .digest()
+            .finalize();
+        let (id, _): (GenericArray<u8, PolicyIdSize>, GenericArray<u8, _>) = digest.split();
+        Self(id)
+    }
+}
+
+impl AsRef<[u8]> for PolicyId {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl RepresentableAsArray for PolicyId {
+    type Size = PolicyIdSize;
+}
+
+impl SerializableToArray for PolicyId {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.0
+    }
+}
+
+impl DeserializableFromArray for PolicyId {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        Ok(Self(*arr))
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for PolicyId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize(self, serializer, Representation::Hex)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for PolicyId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_deserialize(deserializer, Representation::Hex)
+    }
+}
+
+impl HasTypeName for PolicyId {
+    fn type_name() -> &'static str {
+        "PolicyId"
+    }
+}
+
+impl fmt::Display for PolicyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(self, f)
+    }
+}
+
+impl FromStr for PolicyId {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::PolicyId;
+    use crate::{DeserializableFromArray, SecretKey, SerializableToArray};
+
+    #[cfg(feature = "serde-support")]
+    use crate::serde::tests::{check_deserialization, check_serialization};
+
+    #[cfg(feature = "serde-support")]
+    use crate::serde::Representation;
+
+    #[test]
+    fn test_policy_id() {
+        let delegating_pk = SecretKey::random().public_key();
+        let receiving_pk = SecretKey::random().public_key();
+
+        let id = PolicyId::new(&delegating_pk, &receiving_pk, b"foo");
+        let id_same = PolicyId::new(&delegating_pk, &receiving_pk, b"foo");
+        assert_eq!(id, id_same);
+
+        let id_diff_label = PolicyId::new(&delegating_pk, &receiving_pk, b"bar");
+        assert_ne!(id, id_diff_label);
+
+        let id_swapped = PolicyId::new(&receiving_pk, &delegating_pk, b"foo");
+        assert_ne!(id, id_swapped);
+
+        let id_back = PolicyId::from_array(&id.to_array()).unwrap();
+        assert_eq!(id, id_back);
+    }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_serde_serialization() {
+        let delegating_pk = SecretKey::random().public_key();
+        let receiving_pk = SecretKey::random().public_key();
+        let id = PolicyId::new(&delegating_pk, &receiving_pk, b"foo");
+
+        check_serialization(&id, Representation::Hex);
+        check_deserialization(&id);
+    }
+}
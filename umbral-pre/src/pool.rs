@@ -0,0 +1,236 @@
+//! A pool of reusable byte buffers for serializing objects on hot paths.
+
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use zeroize::Zeroize;
+
+use crate::traits::{RepresentableAsArray, SerializableToArray};
+
+/// A thread-safe pool of byte buffers.
+///
+/// Buffers are allocated with a fixed capacity when the pool is created,
+/// and returned to the pool (with their contents zeroized) when the [`PooledBuffer`]
+/// handle is dropped, so a service processing many requests
+/// does not need to allocate memory for serialization per request.
+/// If the pool is empty, a new buffer is allocated, and it is kept in the pool
+/// after use as long as the pool holds less than the initial number of buffers.
+///
+/// Besides [`serialize_batch`](`Self::serialize_batch`), the pool is accepted by
+/// [`batch_to_bytes_with_pool`](`crate::batch_to_bytes_with_pool`),
+/// [`generate_kfrags_batch_with_pool_and_rng`](`crate::generate_kfrags_batch_with_pool_and_rng`)
+/// and [`KeyFrag::verify_batch_with_pool_and_rng`](`crate::KeyFrag::verify_batch_with_pool_and_rng`),
+/// which use a buffer from it for the serialized objects or the signed messages.
+/// The combination of capsule fragments works with points and scalars
+/// rather than byte buffers, so it does not use the pool.
+#[derive(Debug)]
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    buffer_capacity: usize,
+    max_buffers: usize,
+}
+
+impl BufferPool {
+    /// Creates a pool of `buffers` buffers with the given capacity each.
+    pub fn new(buffer_capacity: usize, buffers: usize) -> Self {
+        Self {
+            buffers: Mutex::new(
+                (0..buffers)
+                    .map(|_| Vec::with_capacity(buffer_capacity))
+                    .collect(),
+            ),
+            buffer_capacity,
+            max_buffers: buffers,
+        }
+    }
+
+    /// Creates a pool of `buffers` buffers, each able to hold
+    /// `objects_per_buffer` serialized objects of type `T`.
+    pub fn for_type<T: RepresentableAsArray>(objects_per_buffer: usize, buffers: usize) -> Self {
+        Self::new(T::serialized_size() * objects_per_buffer, buffers)
+    }
+
+    /// Returns the capacity of the buffers in this pool.
+    pub fn buffer_capacity(&self) -> usize {
+        self.buffer_capacity
+    }
+
+    /// Takes an empty buffer from the pool, allocating a new one if there are none left.
+    pub fn take(&self) -> PooledBuffer<'_> {
+        let buffer = self
+            .buffers
+            .lock()
+            .ok()
+            .and_then(|mut buffers| buffers.pop())
+            .unwrap_or_else(|| Vec::with_capacity(self.buffer_capacity));
+        PooledBuffer { pool: self, buffer }
+    }
+
+    fn put_back(&self, mut buffer: Vec<u8>) {
+        buffer.zeroize();
+        if let Ok(mut buffers) = self.buffers.lock() {
+            if buffers.len() < self.max_buffers {
+                buffers.push(buffer);
+            }
+        }
+    }
+
+    /// Serializes a sequence of objects back to back into a buffer from the pool.
+    ///
+    /// This is the same as concatenating the results of `to_array()` of each object.
+    pub fn serialize_batch<'a, T: SerializableToArray + 'a>(
+        &self,
+        objects: impl IntoIterator<Item = &'a T>,
+    ) -> PooledBuffer<'_> {
+        let mut buffer = self.take();
+        for object in objects {
+            buffer.extend_from_slice(&object.to_array());
+        }
+        buffer
+    }
+}
+
+/// A buffer taken from a [`BufferPool`].
+/// Returned to the pool when dropped.
+#[derive(Debug)]
+pub struct PooledBuffer<'a> {
+    pool: &'a BufferPool,
+    buffer: Vec<u8>,
+}
+
+impl<'a> Deref for PooledBuffer<'a> {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+impl<'a> DerefMut for PooledBuffer<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buffer
+    }
+}
+
+impl<'a> AsRef<[u8]> for PooledBuffer<'a> {
+    fn as_ref(&self) -> &[u8] {
+        &self.buffer
+    }
+}
+
+impl<'a> Drop for PooledBuffer<'a> {
+    fn drop(&mut self) {
+        self.pool.put_back(core::mem::take(&mut self.buffer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+    use std::thread;
+
+    use rand_core::OsRng;
+
+    use super::BufferPool;
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{
+        batch_to_bytes, batch_to_bytes_with_pool, generate_kfrags_batch_with_pool_and_rng,
+        CapsuleFrag, DeserializableFromArray, KeyFrag, Parameters, SerializableToArray,
+    };
+
+    #[test]
+    fn test_serialize_batch() {
+        let cfrags = prepare_delegation(true, true)
+            .verified_cfrags
+            .iter()
+            .map(|vcfrag| vcfrag.clone_unverified())
+            .collect::<Vec<_>>();
+
+        let pool = BufferPool::for_type::<CapsuleFrag>(3, 2);
+        let buffer = pool.serialize_batch(&cfrags);
+
+        // No reallocation was needed
+        assert_eq!(buffer.capacity(), pool.buffer_capacity());
+
+        let expected = cfrags
+            .iter()
+            .flat_map(|cfrag| cfrag.to_array())
+            .collect::<Vec<_>>();
+        assert_eq!(buffer.as_slice(), expected.as_slice());
+
+        // The buffer is reused and comes back empty
+        drop(buffer);
+        let buffer = pool.take();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.capacity(), pool.buffer_capacity());
+    }
+
+    #[test]
+    fn test_batch_apis() {
+        let Delegation {
+            delegating_sk,
+            delegating_pk,
+            signer,
+            verifying_pk,
+            receiving_pk,
+            ..
+        } = prepare_delegation(true, true);
+
+        let pool = BufferPool::new(1024, 1);
+
+        let batch = generate_kfrags_batch_with_pool_and_rng(
+            &mut OsRng,
+            &pool,
+            &Parameters::new(),
+            [(b"label 1", &delegating_sk), (b"label 2", &delegating_sk)],
+            &receiving_pk,
+            &signer,
+            2,
+            3,
+            true,
+            true,
+        )
+        .unwrap();
+        let (_policy_id, vkfrags) = &batch[0];
+
+        let bytes = batch_to_bytes_with_pool(&pool, vkfrags);
+        assert_eq!(bytes.as_slice(), &batch_to_bytes(vkfrags) as &[u8]);
+        drop(bytes);
+
+        let items = vkfrags.iter().map(|vkfrag| {
+            (
+                KeyFrag::from_array(&vkfrag.to_array()).unwrap(),
+                &verifying_pk,
+                Some(&delegating_pk),
+                Some(&receiving_pk),
+            )
+        });
+        let results = KeyFrag::verify_batch_with_pool_and_rng(&mut OsRng, &pool, items);
+        assert!(results.iter().all(|result| result.is_ok()));
+
+        // The buffer was returned to the pool
+        assert!(pool.take().is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_use() {
+        let pool = Arc::new(BufferPool::new(16, 2));
+        let handles = (0..8u8)
+            .map(|i| {
+                let pool = pool.clone();
+                thread::spawn(move || {
+                    let mut buffer = pool.take();
+                    buffer.push(i);
+                    assert_eq!(buffer.as_slice(), &[i]);
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(pool.take().is_empty());
+    }
+}
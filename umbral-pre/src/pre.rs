@@ -8,17 +8,29 @@ use rand_core::{CryptoRng, RngCore};
 use rand_core::OsRng;
 
 use crate::capsule::{Capsule, OpenReencryptedError};
-use crate::capsule_frag::VerifiedCapsuleFrag;
-use crate::dem::{DecryptionError, EncryptionError, DEM};
-use crate::key_frag::{KeyFragBase, VerifiedKeyFrag};
+use crate::capsule_frag::{UnprovenCapsuleFrag, VerifiedCapsuleFrag};
+use crate::dem::{
+    ciphertext_nonce, derived_nonce, try_plaintext_buffer, DecryptionError, EncryptionError,
+    NonceStrategy, DEM,
+};
+use crate::key_frag::{CompactKeyFrag, KeyFragBase, VerifiedKeyFrag};
 use crate::keys::{PublicKey, SecretKey, Signer};
+use crate::params::Parameters;
+use crate::policy::PolicyId;
+#[cfg(feature = "std")]
+use crate::pool::BufferPool;
+use crate::roles::{AsDelegatingKey, AsReceivingKey};
 use crate::traits::SerializableToArray;
 
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 
+#[cfg(feature = "serde-support")]
+use serde::Serialize;
+
 /// Errors that can happen when decrypting a reencrypted ciphertext.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
 pub enum ReencryptionError {
     /// An error when opening a capsule. See [`OpenReencryptedError`] for the options.
     OnOpen(OpenReencryptedError),
@@ -35,6 +47,57 @@ impl fmt::Display for ReencryptionError {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for ReencryptionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OnOpen(err) => Some(err),
+            Self::OnDecryption(err) => Some(err),
+        }
+    }
+}
+
+/// The maximum number of key fragments [`generate_kfrags`](`crate::generate_kfrags`)
+/// can create in one call.
+pub const MAX_SHARES: usize = 1024;
+
+/// Errors that can happen when creating key fragments.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
+pub enum GenerateKFragsError {
+    /// `threshold` is zero.
+    ZeroThreshold,
+    /// `shares` is zero.
+    ZeroShares,
+    /// `threshold` is greater than `shares` (the resulting fragments would be useless).
+    ThresholdExceedsShares,
+    /// `shares` is greater than [`MAX_SHARES`].
+    TooManyShares,
+    /// Failed to allocate memory for the result
+    /// (only returned by [`try_generate_kfrags`](`crate::try_generate_kfrags`)
+    /// and [`try_generate_kfrags_with_rng`]).
+    AllocationFailed,
+}
+
+impl fmt::Display for GenerateKFragsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroThreshold => write!(f, "Threshold must be non-zero"),
+            Self::ZeroShares => write!(f, "The number of shares must be non-zero"),
+            Self::ThresholdExceedsShares => {
+                write!(f, "Threshold cannot exceed the number of shares")
+            }
+            Self::TooManyShares => write!(f, "The number of shares cannot exceed {}", MAX_SHARES),
+            Self::AllocationFailed => write!(f, "Failed to allocate memory for the fragments"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for GenerateKFragsError {}
+
 /// Encrypts the given plaintext message using a DEM scheme,
 /// and encapsulates the key for later reencryption.
 /// Returns the KEM [`Capsule`] and the ciphertext.
@@ -42,13 +105,44 @@ pub fn encrypt_with_rng(
     rng: &mut (impl CryptoRng + RngCore),
     delegating_pk: &PublicKey,
     plaintext: &[u8],
+) -> Result<(Capsule, Box<[u8]>), EncryptionError> {
+    encrypt_with_nonce_strategy_with_rng(rng, delegating_pk, plaintext, NonceStrategy::Random)
+}
+
+/// Same as [`encrypt_with_rng`], but chooses the DEM nonce according to `nonce_strategy`.
+pub fn encrypt_with_nonce_strategy_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    delegating_pk: &PublicKey,
+    plaintext: &[u8],
+    nonce_strategy: NonceStrategy,
 ) -> Result<(Capsule, Box<[u8]>), EncryptionError> {
     let (capsule, key_seed) = Capsule::from_public_key(rng, delegating_pk);
-    let dem = DEM::new(&key_seed);
-    dem.encrypt(rng, plaintext, &capsule.to_array())
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.encrypt(rng, nonce_strategy, plaintext, &capsule_bytes)
         .map(|ciphertext| (capsule, ciphertext))
 }
 
+/// A synonym for [`encrypt_with_nonce_strategy_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn encrypt_with_nonce_strategy(
+    delegating_pk: &PublicKey,
+    plaintext: &[u8],
+    nonce_strategy: NonceStrategy,
+) -> Result<(Capsule, Box<[u8]>), EncryptionError> {
+    encrypt_with_nonce_strategy_with_rng(&mut OsRng, delegating_pk, plaintext, nonce_strategy)
+}
+
+/// Returns `true` if the nonce of the given ciphertext was derived from `capsule`
+/// (that is, it was created with [`NonceStrategy::Derived`]).
+pub fn has_derived_nonce(capsule: &Capsule, ciphertext: impl AsRef<[u8]>) -> bool {
+    match ciphertext_nonce(ciphertext.as_ref()) {
+        Ok(nonce) => nonce == derived_nonce(&capsule.to_array()).as_slice(),
+        Err(_) => false,
+    }
+}
+
 /// A synonym for [`encrypt`] with the default RNG.
 #[cfg(feature = "default-rng")]
 #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
@@ -59,23 +153,88 @@ pub fn encrypt(
     encrypt_with_rng(&mut OsRng, delegating_pk, plaintext)
 }
 
+/// Same as [`encrypt_with_rng`], but writes the ciphertext into the given buffer
+/// (replacing its previous contents) instead of allocating a new one.
+///
+/// Intended for encrypting many messages in a loop,
+/// since the capacity of the buffer is reused.
+pub fn encrypt_into_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    delegating_pk: &PublicKey,
+    plaintext: &[u8],
+    ciphertext: &mut Vec<u8>,
+) -> Result<Capsule, EncryptionError> {
+    let (capsule, key_seed) = Capsule::from_public_key(rng, delegating_pk);
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.encrypt_into(
+        rng,
+        NonceStrategy::Random,
+        plaintext,
+        &capsule_bytes,
+        ciphertext,
+    )
+    .map(|_| capsule)
+}
+
+/// A synonym for [`encrypt_into_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn encrypt_into(
+    delegating_pk: &PublicKey,
+    plaintext: &[u8],
+    ciphertext: &mut Vec<u8>,
+) -> Result<Capsule, EncryptionError> {
+    encrypt_into_with_rng(&mut OsRng, delegating_pk, plaintext, ciphertext)
+}
+
 /// Attempts to decrypt the ciphertext using the receiver's secret key.
 pub fn decrypt_original(
-    delegating_sk: &SecretKey,
+    delegating_sk: &impl AsDelegatingKey,
     capsule: &Capsule,
     ciphertext: impl AsRef<[u8]>,
 ) -> Result<Box<[u8]>, DecryptionError> {
-    let key_seed = capsule.open_original(delegating_sk);
-    let dem = DEM::new(&key_seed);
-    dem.decrypt(ciphertext, &capsule.to_array())
+    let key_seed = capsule.open_original(delegating_sk.as_delegating_key());
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.decrypt(ciphertext, &capsule_bytes)
+}
+
+/// Same as [`decrypt_original`], but writes the plaintext into the given buffer
+/// (replacing its previous contents) instead of allocating a new one.
+/// On error, the buffer is left empty.
+pub fn decrypt_original_into(
+    delegating_sk: &impl AsDelegatingKey,
+    capsule: &Capsule,
+    ciphertext: impl AsRef<[u8]>,
+    plaintext: &mut Vec<u8>,
+) -> Result<(), DecryptionError> {
+    let key_seed = capsule.open_original(delegating_sk.as_delegating_key());
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.decrypt_into(ciphertext.as_ref(), &capsule_bytes, plaintext)
+}
+
+/// A variant of [`decrypt_original`] that returns [`DecryptionError::AllocationFailed`]
+/// instead of aborting if the memory for the plaintext cannot be allocated.
+pub fn try_decrypt_original(
+    delegating_sk: &impl AsDelegatingKey,
+    capsule: &Capsule,
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<Box<[u8]>, DecryptionError> {
+    let mut plaintext = try_plaintext_buffer(ciphertext.as_ref())?;
+    decrypt_original_into(delegating_sk, capsule, ciphertext, &mut plaintext)?;
+    Ok(plaintext.into_boxed_slice())
 }
 
 /// Creates `shares` fragments of `delegating_sk`,
 /// which will be possible to reencrypt to allow the creator of `receiving_pk`
 /// decrypt the ciphertext encrypted with `delegating_sk`.
 ///
-/// `threshold` sets the number of fragments necessary for decryption
-/// (that is, fragments created with `threshold > num_frags` will be useless).
+/// `threshold` sets the number of fragments necessary for decryption.
+/// It must be non-zero and not greater than `shares`,
+/// and `shares` must be non-zero and not greater than [`MAX_SHARES`];
+/// otherwise an error is returned.
 ///
 /// `signer` is used to sign the resulting [`KeyFrag`](`crate::KeyFrag`) objects,
 /// which can be later verified by the associated public key.
@@ -85,10 +244,117 @@ pub fn decrypt_original(
 /// corresponds to given delegating or receiving public keys
 /// by supplying them to [`KeyFrag::verify()`](`crate::KeyFrag::verify`).
 ///
-/// Returns a boxed slice of `shares` KeyFrags
+/// Returns a boxed slice of `shares` KeyFrags.
 #[allow(clippy::too_many_arguments)]
 pub fn generate_kfrags_with_rng(
     rng: &mut (impl CryptoRng + RngCore),
+    delegating_sk: &impl AsDelegatingKey,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Box<[VerifiedKeyFrag]>, GenerateKFragsError> {
+    check_kfrags_args(threshold, shares)?;
+    Ok(generate_kfrags_unchecked(
+        rng,
+        &Parameters::new(),
+        delegating_sk.as_delegating_key(),
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+        Vec::with_capacity(shares),
+        &mut Vec::new(),
+    ))
+}
+
+/// A variant of [`generate_kfrags_with_rng`] creating key fragments
+/// for the given custom [`Parameters`].
+///
+/// The resulting key fragments (after deserialization) and capsule fragments will only verify
+/// if the same parameters are attached to the key fragments
+/// with [`KeyFrag::with_params`](`crate::KeyFrag::with_params`)
+/// and to the capsules with [`Capsule::with_params`](`crate::Capsule::with_params`).
+#[allow(clippy::too_many_arguments)]
+pub fn generate_kfrags_with_params_and_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    params: &Parameters,
+    delegating_sk: &impl AsDelegatingKey,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Box<[VerifiedKeyFrag]>, GenerateKFragsError> {
+    check_kfrags_args(threshold, shares)?;
+    Ok(generate_kfrags_unchecked(
+        rng,
+        params,
+        delegating_sk.as_delegating_key(),
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+        Vec::with_capacity(shares),
+        &mut Vec::new(),
+    ))
+}
+
+/// A synonym for [`generate_kfrags_with_params_and_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+#[allow(clippy::too_many_arguments)]
+pub fn generate_kfrags_with_params(
+    params: &Parameters,
+    delegating_sk: &impl AsDelegatingKey,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Box<[VerifiedKeyFrag]>, GenerateKFragsError> {
+    generate_kfrags_with_params_and_rng(
+        &mut OsRng,
+        params,
+        delegating_sk,
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+    )
+}
+
+fn check_kfrags_args(threshold: usize, shares: usize) -> Result<(), GenerateKFragsError> {
+    if threshold == 0 {
+        return Err(GenerateKFragsError::ZeroThreshold);
+    }
+    if shares == 0 {
+        return Err(GenerateKFragsError::ZeroShares);
+    }
+    if threshold > shares {
+        return Err(GenerateKFragsError::ThresholdExceedsShares);
+    }
+    if shares > MAX_SHARES {
+        return Err(GenerateKFragsError::TooManyShares);
+    }
+    Ok(())
+}
+
+// `result` is expected to have the capacity for `shares` fragments.
+#[allow(clippy::too_many_arguments)]
+fn generate_kfrags_unchecked(
+    rng: &mut (impl CryptoRng + RngCore),
+    params: &Parameters,
     delegating_sk: &SecretKey,
     receiving_pk: &PublicKey,
     signer: &Signer,
@@ -96,16 +362,18 @@ pub fn generate_kfrags_with_rng(
     shares: usize,
     sign_delegating_key: bool,
     sign_receiving_key: bool,
+    mut result: Vec<VerifiedKeyFrag>,
+    message: &mut Vec<u8>,
 ) -> Box<[VerifiedKeyFrag]> {
-    let base = KeyFragBase::new(rng, delegating_sk, receiving_pk, signer, threshold);
+    let base = KeyFragBase::new(rng, params, delegating_sk, receiving_pk, signer, threshold);
 
-    let mut result = Vec::<VerifiedKeyFrag>::new();
     for _ in 0..shares {
         result.push(VerifiedKeyFrag::from_base(
             rng,
             &base,
             sign_delegating_key,
             sign_receiving_key,
+            message,
         ));
     }
 
@@ -117,14 +385,14 @@ pub fn generate_kfrags_with_rng(
 #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
 #[allow(clippy::too_many_arguments)]
 pub fn generate_kfrags(
-    delegating_sk: &SecretKey,
+    delegating_sk: &impl AsDelegatingKey,
     receiving_pk: &PublicKey,
     signer: &Signer,
     threshold: usize,
     shares: usize,
     sign_delegating_key: bool,
     sign_receiving_key: bool,
-) -> Box<[VerifiedKeyFrag]> {
+) -> Result<Box<[VerifiedKeyFrag]>, GenerateKFragsError> {
     generate_kfrags_with_rng(
         &mut OsRng,
         delegating_sk,
@@ -137,69 +405,473 @@ pub fn generate_kfrags(
     )
 }
 
-/// Reencrypts a [`Capsule`] object with a key fragment, creating a capsule fragment.
-///
-/// Having `threshold` (see [`generate_kfrags()`](`crate::generate_kfrags()`))
-/// distinct fragments (along with the original capsule and the corresponding secret key)
-/// allows one to decrypt the original plaintext.
+/// A variant of [`generate_kfrags_with_rng`] that returns
+/// [`GenerateKFragsError::AllocationFailed`] instead of aborting
+/// if the memory for the resulting fragments cannot be allocated.
 ///
-/// One can call [`KeyFrag::verify()`](`crate::KeyFrag::verify`)
-/// before reencryption to check its integrity.
-pub fn reencrypt_with_rng(
+/// Only the allocation of the result (which is proportional to `shares`) is fallible;
+/// the temporary allocations of a fixed or `threshold`-proportional size are not.
+#[allow(clippy::too_many_arguments)]
+pub fn try_generate_kfrags_with_rng(
     rng: &mut (impl CryptoRng + RngCore),
-    capsule: &Capsule,
-    verified_kfrag: VerifiedKeyFrag,
-) -> VerifiedCapsuleFrag {
-    VerifiedCapsuleFrag::reencrypted(rng, capsule, verified_kfrag.unverify())
+    delegating_sk: &impl AsDelegatingKey,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Box<[VerifiedKeyFrag]>, GenerateKFragsError> {
+    try_generate_kfrags_with_params_and_rng(
+        rng,
+        &Parameters::new(),
+        delegating_sk,
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+    )
 }
 
-/// A synonym for [`reencrypt_with_rng`] with the default RNG.
+/// A variant of [`try_generate_kfrags_with_rng`] creating key fragments
+/// for the given custom [`Parameters`]
+/// (see [`generate_kfrags_with_params_and_rng`]).
+#[allow(clippy::too_many_arguments)]
+pub fn try_generate_kfrags_with_params_and_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    params: &Parameters,
+    delegating_sk: &impl AsDelegatingKey,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Box<[VerifiedKeyFrag]>, GenerateKFragsError> {
+    check_kfrags_args(threshold, shares)?;
+    let mut result = Vec::new();
+    result
+        .try_reserve_exact(shares)
+        .map_err(|_| GenerateKFragsError::AllocationFailed)?;
+    Ok(generate_kfrags_unchecked(
+        rng,
+        params,
+        delegating_sk.as_delegating_key(),
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+        result,
+        &mut Vec::new(),
+    ))
+}
+
+/// A synonym for [`try_generate_kfrags_with_rng`] with the default RNG.
 #[cfg(feature = "default-rng")]
 #[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
-pub fn reencrypt(capsule: &Capsule, verified_kfrag: VerifiedKeyFrag) -> VerifiedCapsuleFrag {
-    reencrypt_with_rng(&mut OsRng, capsule, verified_kfrag)
+#[allow(clippy::too_many_arguments)]
+pub fn try_generate_kfrags(
+    delegating_sk: &impl AsDelegatingKey,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Box<[VerifiedKeyFrag]>, GenerateKFragsError> {
+    try_generate_kfrags_with_rng(
+        &mut OsRng,
+        delegating_sk,
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+    )
 }
 
-/// Decrypts the ciphertext using previously reencrypted capsule fragments.
-///
-/// `decrypting_sk` is the secret key whose associated public key was used in
-/// [`generate_kfrags()`](`crate::generate_kfrags()`).
+// The key fragments created for one of the delegations in a batch.
+type PolicyKeyFrags = (PolicyId, Box<[VerifiedKeyFrag]>);
+
+/// Creates key fragments for several delegations to the same receiver at once
+/// (e.g. when a new receiver is granted access to many datasets,
+/// each encrypted to its own policy key).
 ///
-/// `delegating_pk` is the public key of the encrypting party.
-/// Used to check the validity of decryption.
+/// `delegations` is a sequence of pairs of a label and a delegating key;
+/// for each of them `shares` fragments are created as in [`generate_kfrags_with_rng`],
+/// and returned along with the corresponding [`PolicyId`]
+/// (calculated from the public key of the delegating key, `receiving_pk`, and the label),
+/// in the same order as the delegations were given.
 ///
-/// One can call [`CapsuleFrag::verify()`](`crate::CapsuleFrag::verify`)
-/// before reencryption to check its integrity.
-pub fn decrypt_reencrypted(
-    receiving_sk: &SecretKey,
-    delegating_pk: &PublicKey,
-    capsule: &Capsule,
-    verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
-    ciphertext: impl AsRef<[u8]>,
-) -> Result<Box<[u8]>, ReencryptionError> {
-    let cfrags: Vec<_> = verified_cfrags
-        .into_iter()
-        .map(|vcfrag| vcfrag.unverify())
-        .collect();
-    let key_seed = capsule
-        .open_reencrypted(receiving_sk, delegating_pk, &cfrags)
-        .map_err(ReencryptionError::OnOpen)?;
-    let dem = DEM::new(&key_seed);
-    dem.decrypt(&ciphertext, &capsule.to_array())
-        .map_err(ReencryptionError::OnDecryption)
+/// The arguments are validated once, before any fragments are created.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_kfrags_batch_with_rng<'a, L, K>(
+    rng: &mut (impl CryptoRng + RngCore),
+    delegations: impl IntoIterator<Item = (L, &'a K)>,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Vec<PolicyKeyFrags>, GenerateKFragsError>
+where
+    L: AsRef<[u8]>,
+    K: AsDelegatingKey + 'a,
+{
+    generate_kfrags_batch_impl(
+        rng,
+        &Parameters::new(),
+        delegations,
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+        &mut Vec::new(),
+    )
 }
 
-#[cfg(test)]
-mod tests {
-
-    use alloc::vec::Vec;
-
-    use crate::{
-        CapsuleFrag, DeserializableFromArray, KeyFrag, SecretKey, SerializableToArray, Signer,
-        VerifiedCapsuleFrag,
-    };
+/// A variant of [`generate_kfrags_batch_with_rng`] creating key fragments
+/// for the given custom [`Parameters`]
+/// (see [`generate_kfrags_with_params_and_rng`]).
+#[allow(clippy::too_many_arguments)]
+pub fn generate_kfrags_batch_with_params_and_rng<'a, L, K>(
+    rng: &mut (impl CryptoRng + RngCore),
+    params: &Parameters,
+    delegations: impl IntoIterator<Item = (L, &'a K)>,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Vec<PolicyKeyFrags>, GenerateKFragsError>
+where
+    L: AsRef<[u8]>,
+    K: AsDelegatingKey + 'a,
+{
+    generate_kfrags_batch_impl(
+        rng,
+        params,
+        delegations,
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+        &mut Vec::new(),
+    )
+}
 
-    use super::{decrypt_original, decrypt_reencrypted, encrypt, generate_kfrags, reencrypt};
+/// Same as [`generate_kfrags_batch_with_params_and_rng`], but uses a buffer from `pool`
+/// for the signed messages instead of allocating one
+/// (pass [`Parameters::new`] as `params` to use the default parameters).
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[allow(clippy::too_many_arguments)]
+pub fn generate_kfrags_batch_with_pool_and_rng<'a, L, K>(
+    rng: &mut (impl CryptoRng + RngCore),
+    pool: &BufferPool,
+    params: &Parameters,
+    delegations: impl IntoIterator<Item = (L, &'a K)>,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Vec<PolicyKeyFrags>, GenerateKFragsError>
+where
+    L: AsRef<[u8]>,
+    K: AsDelegatingKey + 'a,
+{
+    generate_kfrags_batch_impl(
+        rng,
+        params,
+        delegations,
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+        &mut pool.take(),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn generate_kfrags_batch_impl<'a, L, K>(
+    rng: &mut (impl CryptoRng + RngCore),
+    params: &Parameters,
+    delegations: impl IntoIterator<Item = (L, &'a K)>,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+    message: &mut Vec<u8>,
+) -> Result<Vec<PolicyKeyFrags>, GenerateKFragsError>
+where
+    L: AsRef<[u8]>,
+    K: AsDelegatingKey + 'a,
+{
+    check_kfrags_args(threshold, shares)?;
+    Ok(delegations
+        .into_iter()
+        .map(|(label, delegating_sk)| {
+            let delegating_sk = delegating_sk.as_delegating_key();
+            let policy_id =
+                PolicyId::new(&delegating_sk.public_key(), receiving_pk, label.as_ref());
+            let kfrags = generate_kfrags_unchecked(
+                rng,
+                params,
+                delegating_sk,
+                receiving_pk,
+                signer,
+                threshold,
+                shares,
+                sign_delegating_key,
+                sign_receiving_key,
+                Vec::with_capacity(shares),
+                message,
+            );
+            (policy_id, kfrags)
+        })
+        .collect())
+}
+
+/// A synonym for [`generate_kfrags_batch_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+#[allow(clippy::too_many_arguments)]
+pub fn generate_kfrags_batch<'a, L, K>(
+    delegations: impl IntoIterator<Item = (L, &'a K)>,
+    receiving_pk: &PublicKey,
+    signer: &Signer,
+    threshold: usize,
+    shares: usize,
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Result<Vec<PolicyKeyFrags>, GenerateKFragsError>
+where
+    L: AsRef<[u8]>,
+    K: AsDelegatingKey + 'a,
+{
+    generate_kfrags_batch_with_rng(
+        &mut OsRng,
+        delegations,
+        receiving_pk,
+        signer,
+        threshold,
+        shares,
+        sign_delegating_key,
+        sign_receiving_key,
+    )
+}
+
+/// Reencrypts a [`Capsule`] object with a key fragment, creating a capsule fragment.
+///
+/// Having `threshold` (see [`generate_kfrags()`](`crate::generate_kfrags()`))
+/// distinct fragments (along with the original capsule and the corresponding secret key)
+/// allows one to decrypt the original plaintext.
+///
+/// One can call [`KeyFrag::verify()`](`crate::KeyFrag::verify`)
+/// before reencryption to check its integrity.
+pub fn reencrypt_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    capsule: &Capsule,
+    verified_kfrag: VerifiedKeyFrag,
+) -> VerifiedCapsuleFrag {
+    VerifiedCapsuleFrag::reencrypted(rng, capsule, verified_kfrag.unverify())
+}
+
+/// A synonym for [`reencrypt_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn reencrypt(capsule: &Capsule, verified_kfrag: VerifiedKeyFrag) -> VerifiedCapsuleFrag {
+    reencrypt_with_rng(&mut OsRng, capsule, verified_kfrag)
+}
+
+/// Reencrypts a [`Capsule`] object with a key fragment, deriving the randomness
+/// of the correctness proof from `nonce` instead of an RNG.
+///
+/// The same capsule, key fragment and nonce always produce the same capsule fragment,
+/// byte for byte, which makes retries idempotent and allows replaying reencryptions,
+/// e.g. when resolving disputes.
+///
+/// The randomness is derived from the secret of the key fragment along with the capsule
+/// and the nonce, so, unlike with a plain signature nonce, reusing `nonce` does not leak
+/// the key fragment.
+///
+/// **Warning:** requests with the same nonce result in identical fragments,
+/// which makes them linkable by an observer; use a unique nonce per request
+/// (e.g. a request ID) unless this is intended,
+/// and use [`reencrypt`] when reproducibility is not required.
+pub fn reencrypt_deterministic(
+    capsule: &Capsule,
+    verified_kfrag: VerifiedKeyFrag,
+    nonce: &[u8],
+) -> VerifiedCapsuleFrag {
+    VerifiedCapsuleFrag::reencrypted_deterministic(capsule, verified_kfrag.unverify(), nonce)
+}
+
+/// Decrypts the ciphertext using previously reencrypted capsule fragments.
+///
+/// `decrypting_sk` is the secret key whose associated public key was used in
+/// [`generate_kfrags()`](`crate::generate_kfrags()`).
+///
+/// `delegating_pk` is the public key of the encrypting party.
+/// Used to check the validity of decryption.
+///
+/// One can call [`CapsuleFrag::verify()`](`crate::CapsuleFrag::verify`)
+/// before reencryption to check its integrity.
+pub fn decrypt_reencrypted(
+    receiving_sk: &impl AsReceivingKey,
+    delegating_pk: &PublicKey,
+    capsule: &Capsule,
+    verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<Box<[u8]>, ReencryptionError> {
+    let cfrags: Vec<_> = verified_cfrags
+        .into_iter()
+        .map(|vcfrag| vcfrag.unverify())
+        .collect();
+    let key_seed = capsule
+        .open_reencrypted(receiving_sk.as_receiving_key(), delegating_pk, &cfrags)
+        .map_err(ReencryptionError::OnOpen)?;
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.decrypt(&ciphertext, &capsule_bytes)
+        .map_err(ReencryptionError::OnDecryption)
+}
+
+/// Same as [`decrypt_reencrypted`], but writes the plaintext into the given buffer
+/// (replacing its previous contents) instead of allocating a new one.
+/// On error, the buffer is left empty.
+pub fn decrypt_reencrypted_into(
+    receiving_sk: &impl AsReceivingKey,
+    delegating_pk: &PublicKey,
+    capsule: &Capsule,
+    verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+    ciphertext: impl AsRef<[u8]>,
+    plaintext: &mut Vec<u8>,
+) -> Result<(), ReencryptionError> {
+    plaintext.clear();
+    let cfrags: Vec<_> = verified_cfrags
+        .into_iter()
+        .map(|vcfrag| vcfrag.unverify())
+        .collect();
+    let key_seed = capsule
+        .open_reencrypted(receiving_sk.as_receiving_key(), delegating_pk, &cfrags)
+        .map_err(ReencryptionError::OnOpen)?;
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.decrypt_into(ciphertext.as_ref(), &capsule_bytes, plaintext)
+        .map_err(ReencryptionError::OnDecryption)
+}
+
+/// A variant of [`decrypt_reencrypted`] that returns [`DecryptionError::AllocationFailed`]
+/// (wrapped in [`ReencryptionError::OnDecryption`]) instead of aborting
+/// if the memory for the plaintext cannot be allocated.
+pub fn try_decrypt_reencrypted(
+    receiving_sk: &impl AsReceivingKey,
+    delegating_pk: &PublicKey,
+    capsule: &Capsule,
+    verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<Box<[u8]>, ReencryptionError> {
+    let mut plaintext =
+        try_plaintext_buffer(ciphertext.as_ref()).map_err(ReencryptionError::OnDecryption)?;
+    decrypt_reencrypted_into(
+        receiving_sk,
+        delegating_pk,
+        capsule,
+        verified_cfrags,
+        ciphertext,
+        &mut plaintext,
+    )?;
+    Ok(plaintext.into_boxed_slice())
+}
+
+/// Reencrypts a [`Capsule`] object with a key fragment, creating a capsule fragment
+/// without a proof of correct reencryption.
+///
+/// **Warning:** the receiver cannot check the correctness of such a fragment,
+/// so this should only be used in deployments where proxies are trusted.
+/// Use [`reencrypt`] otherwise.
+pub fn reencrypt_unproven(
+    capsule: &Capsule,
+    verified_kfrag: VerifiedKeyFrag,
+) -> UnprovenCapsuleFrag {
+    UnprovenCapsuleFrag::reencrypted(capsule, &CompactKeyFrag::from(&verified_kfrag))
+}
+
+/// Reencrypts a [`Capsule`] object with a compact key fragment,
+/// creating a capsule fragment without a proof of correct reencryption.
+///
+/// **Warning:** the same considerations as for [`reencrypt_unproven`] apply;
+/// additionally, a [`CompactKeyFrag`] cannot be verified,
+/// so it must be obtained from a trusted source.
+pub fn reencrypt_compact(capsule: &Capsule, kfrag: &CompactKeyFrag) -> UnprovenCapsuleFrag {
+    UnprovenCapsuleFrag::reencrypted(capsule, kfrag)
+}
+
+/// Decrypts the ciphertext using capsule fragments created by [`reencrypt_unproven`].
+///
+/// Since the fragments cannot be verified individually,
+/// an invalid fragment results in [`OpenReencryptedError::ValidationFailed`],
+/// without indicating which fragment is at fault.
+pub fn decrypt_reencrypted_unproven(
+    receiving_sk: &impl AsReceivingKey,
+    delegating_pk: &PublicKey,
+    capsule: &Capsule,
+    cfrags: impl IntoIterator<Item = UnprovenCapsuleFrag>,
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<Box<[u8]>, ReencryptionError> {
+    let cfrags: Vec<_> = cfrags.into_iter().collect();
+    let key_seed = capsule
+        .open_reencrypted_unproven(receiving_sk.as_receiving_key(), delegating_pk, &cfrags)
+        .map_err(ReencryptionError::OnOpen)?;
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.decrypt(&ciphertext, &capsule_bytes)
+        .map_err(ReencryptionError::OnDecryption)
+}
+
+#[cfg(test)]
+mod tests {
+
+    extern crate std;
+
+    use alloc::vec::Vec;
+
+    use rand_core::OsRng;
+
+    use crate::dem::{ciphertext_nonce, derived_nonce, DecryptionError, NonceStrategy};
+    use crate::{
+        CapsuleFrag, CompactKeyFrag, DeserializableFromArray, KeyFrag, KeyFragVerificationError,
+        OpenReencryptedError, Parameters, PolicyId, SecretKey, SerializableToArray, Signer,
+        UnprovenCapsuleFrag, VerifiedCapsuleFrag,
+    };
+
+    use super::{
+        decrypt_original, decrypt_original_into, decrypt_reencrypted, decrypt_reencrypted_into,
+        decrypt_reencrypted_unproven, encrypt, encrypt_into, encrypt_with_nonce_strategy,
+        generate_kfrags, generate_kfrags_batch, generate_kfrags_batch_with_params_and_rng,
+        generate_kfrags_with_params, has_derived_nonce, reencrypt, reencrypt_compact,
+        reencrypt_deterministic, reencrypt_unproven, try_decrypt_original, try_decrypt_reencrypted,
+        try_generate_kfrags, try_generate_kfrags_with_params_and_rng, GenerateKFragsError,
+        ReencryptionError, MAX_SHARES,
+    };
 
     #[test]
     fn test_simple_api() {
@@ -244,7 +916,8 @@ mod tests {
             num_frags,
             true,
             true,
-        );
+        )
+        .unwrap();
 
         // Bob requests re-encryption to some set of `threshold` ursulas
 
@@ -296,4 +969,583 @@ mod tests {
         .unwrap();
         assert_eq!(&plaintext_bob as &[u8], plaintext);
     }
+
+    #[test]
+    fn test_custom_params() {
+        check_custom_params(&Parameters::from_seed(b"custom parameters"));
+    }
+
+    fn check_custom_params(params: &Parameters) {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let verifying_pk = signer.verifying_key();
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+
+        let verified_kfrags = generate_kfrags_with_params(
+            params,
+            &delegating_sk,
+            &receiving_pk,
+            &signer,
+            2,
+            3,
+            true,
+            true,
+        )
+        .unwrap();
+
+        // A deserialized kfrag only verifies with the same parameters attached
+        let kfrag = KeyFrag::from_array(&verified_kfrags[0].to_array()).unwrap();
+        assert!(kfrag
+            .clone()
+            .verify(&verifying_pk, Some(&delegating_pk), Some(&receiving_pk))
+            .is_err());
+        assert!(kfrag
+            .with_params(params)
+            .verify(&verifying_pk, Some(&delegating_pk), Some(&receiving_pk))
+            .is_ok());
+
+        // Same for the other ways to create and verify kfrags
+        let try_vkfrags = try_generate_kfrags_with_params_and_rng(
+            &mut OsRng,
+            params,
+            &delegating_sk,
+            &receiving_pk,
+            &signer,
+            2,
+            3,
+            true,
+            true,
+        )
+        .unwrap();
+        let batch = generate_kfrags_batch_with_params_and_rng(
+            &mut OsRng,
+            params,
+            [(b"label", &delegating_sk)],
+            &receiving_pk,
+            &signer,
+            2,
+            3,
+            true,
+            true,
+        )
+        .unwrap();
+        let kfrags: Vec<_> = [&try_vkfrags[0], &batch[0].1[0]]
+            .iter()
+            .map(|vkfrag| KeyFrag::from_array(&vkfrag.to_array()).unwrap())
+            .collect();
+        let items = || {
+            kfrags.iter().cloned().map(|kfrag| {
+                (
+                    kfrag,
+                    &verifying_pk,
+                    Some(&delegating_pk),
+                    Some(&receiving_pk),
+                )
+            })
+        };
+        assert!(KeyFrag::verify_batch_with_rng(&mut OsRng, items())
+            .iter()
+            .all(|result| result.is_err()));
+        assert!(
+            KeyFrag::verify_batch_with_params_and_rng(&mut OsRng, params, items())
+                .iter()
+                .all(|result| result.is_ok())
+        );
+
+        let cfrags: Vec<_> = verified_kfrags[0..2]
+            .iter()
+            .map(|vkfrag| {
+                let vcfrag = reencrypt(&capsule, vkfrag.clone());
+                CapsuleFrag::from_array(&vcfrag.to_array()).unwrap()
+            })
+            .collect();
+
+        // Same for cfrags and the capsule
+        assert!(cfrags[0]
+            .clone()
+            .verify(&capsule, &verifying_pk, &delegating_pk, &receiving_pk)
+            .is_err());
+        let capsule_with_params = capsule.with_params(params);
+        let verified_cfrags: Vec<_> = cfrags
+            .into_iter()
+            .map(|cfrag| {
+                cfrag
+                    .verify(
+                        &capsule_with_params,
+                        &verifying_pk,
+                        &delegating_pk,
+                        &receiving_pk,
+                    )
+                    .unwrap()
+            })
+            .collect();
+
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule_with_params,
+            verified_cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+
+    #[test]
+    fn test_application_context() {
+        let params = Parameters::new()
+            .with_application_context(b"deployment A")
+            .unwrap();
+        check_custom_params(&params);
+
+        // The fragments of one deployment do not verify with the parameters of another one,
+        // even though the point `u` is the same.
+        let other_params = Parameters::new()
+            .with_application_context(b"deployment B")
+            .unwrap();
+
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let verifying_pk = signer.verifying_key();
+        let receiving_pk = SecretKey::random().public_key();
+
+        let (capsule, _ciphertext) = encrypt(&delegating_pk, b"peace at dawn").unwrap();
+        let verified_kfrags = generate_kfrags_with_params(
+            &params,
+            &delegating_sk,
+            &receiving_pk,
+            &signer,
+            2,
+            3,
+            true,
+            true,
+        )
+        .unwrap();
+
+        let kfrag = KeyFrag::from_array(&verified_kfrags[0].to_array()).unwrap();
+        assert_eq!(
+            kfrag
+                .with_params(&other_params)
+                .verify(&verifying_pk, Some(&delegating_pk), Some(&receiving_pk))
+                .map_err(|(err, _)| err),
+            Err(KeyFragVerificationError::IncorrectSignature)
+        );
+
+        let vcfrag = reencrypt(&capsule.with_params(&params), verified_kfrags[0].clone());
+        let cfrag = CapsuleFrag::from_array(&vcfrag.to_array()).unwrap();
+        assert!(cfrag
+            .clone()
+            .verify(
+                &capsule.with_params(&params),
+                &verifying_pk,
+                &delegating_pk,
+                &receiving_pk
+            )
+            .is_ok());
+        assert!(cfrag
+            .verify(
+                &capsule.with_params(&other_params),
+                &verifying_pk,
+                &delegating_pk,
+                &receiving_pk
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_unproven() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+
+        let verified_kfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+
+        // Simulate network transfer
+        let cfrags: Vec<_> = verified_kfrags[0..2]
+            .iter()
+            .map(|vkfrag| reencrypt_unproven(&capsule, vkfrag.clone()))
+            .map(|cfrag| UnprovenCapsuleFrag::from_array(&cfrag.to_array()).unwrap())
+            .collect();
+
+        let plaintext_bob = decrypt_reencrypted_unproven(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            cfrags.clone(),
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+
+        // Verified fragments archived without their proofs
+        let archived: Vec<_> = verified_kfrags[0..2]
+            .iter()
+            .map(|vkfrag| reencrypt(&capsule, vkfrag.clone()).strip_proof().to_array())
+            .collect();
+        let restored = archived
+            .iter()
+            .map(|arr| UnprovenCapsuleFrag::from_array(arr).unwrap());
+        let plaintext_archived = decrypt_reencrypted_unproven(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            restored,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_archived as &[u8], plaintext);
+
+        // A fragment for a different capsule
+        let (capsule2, _ciphertext2) = encrypt(&delegating_pk, plaintext).unwrap();
+        let mut mixed_cfrags = cfrags;
+        mixed_cfrags[1] = reencrypt_unproven(&capsule2, verified_kfrags[1].clone());
+        let result = decrypt_reencrypted_unproven(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            mixed_cfrags,
+            &ciphertext,
+        );
+        assert_eq!(
+            result,
+            Err(ReencryptionError::OnOpen(
+                OpenReencryptedError::ValidationFailed
+            ))
+        );
+    }
+
+    #[test]
+    fn test_reencrypt_deterministic() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let verifying_pk = signer.verifying_key();
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+        let (capsule2, _ciphertext2) = encrypt(&delegating_pk, plaintext).unwrap();
+
+        let verified_kfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+
+        let vcfrag1 = reencrypt_deterministic(&capsule, verified_kfrags[0].clone(), b"request 1");
+        let vcfrag2 = reencrypt_deterministic(&capsule, verified_kfrags[0].clone(), b"request 1");
+        assert_eq!(vcfrag1.to_array(), vcfrag2.to_array());
+
+        // Different nonces, capsules or key fragments produce different proofs
+        let vcfrag3 = reencrypt_deterministic(&capsule, verified_kfrags[0].clone(), b"request 2");
+        assert_ne!(vcfrag1.to_array(), vcfrag3.to_array());
+        let vcfrag4 = reencrypt_deterministic(&capsule2, verified_kfrags[0].clone(), b"request 1");
+        assert_ne!(vcfrag1.to_array(), vcfrag4.to_array());
+
+        // The fragments are still valid
+        let cfrag = CapsuleFrag::from_array(&vcfrag3.to_array()).unwrap();
+        assert!(cfrag
+            .verify(&capsule, &verifying_pk, &delegating_pk, &receiving_pk)
+            .is_ok());
+
+        let vcfrags = verified_kfrags[1..3]
+            .iter()
+            .map(|vkfrag| reencrypt_deterministic(&capsule, vkfrag.clone(), b"request 1"));
+        let plaintext_bob = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            vcfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+
+    #[test]
+    fn test_generate_kfrags_validation() {
+        let delegating_sk = SecretKey::random();
+        let receiving_pk = SecretKey::random().public_key();
+        let signer = Signer::new(SecretKey::random());
+
+        let generate = |threshold, shares| {
+            generate_kfrags(
+                &delegating_sk,
+                &receiving_pk,
+                &signer,
+                threshold,
+                shares,
+                true,
+                true,
+            )
+            .map(|vkfrags| vkfrags.len())
+        };
+
+        assert_eq!(generate(1, 1), Ok(1));
+        assert_eq!(generate(3, 3), Ok(3));
+        assert_eq!(generate(0, 3), Err(GenerateKFragsError::ZeroThreshold));
+        assert_eq!(generate(0, 0), Err(GenerateKFragsError::ZeroThreshold));
+        assert_eq!(generate(1, 0), Err(GenerateKFragsError::ZeroShares));
+        assert_eq!(
+            generate(4, 3),
+            Err(GenerateKFragsError::ThresholdExceedsShares)
+        );
+        assert_eq!(
+            generate(2, MAX_SHARES + 1),
+            Err(GenerateKFragsError::TooManyShares)
+        );
+    }
+
+    #[test]
+    fn test_into_buffers() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+
+        let mut ciphertext = Vec::new();
+        let mut plaintext = Vec::new();
+
+        let messages: [&[u8]; 3] = [b"peace at dawn", b"", b"a somewhat longer message"];
+        for message in messages {
+            let capsule = encrypt_into(&delegating_pk, message, &mut ciphertext).unwrap();
+
+            decrypt_original_into(&delegating_sk, &capsule, &ciphertext, &mut plaintext).unwrap();
+            assert_eq!(plaintext, message);
+            assert_eq!(
+                decrypt_original(&delegating_sk, &capsule, &ciphertext).unwrap(),
+                plaintext.clone().into_boxed_slice()
+            );
+
+            let vcfrags: Vec<_> = vkfrags[0..2]
+                .iter()
+                .map(|vkfrag| reencrypt(&capsule, vkfrag.clone()))
+                .collect();
+            decrypt_reencrypted_into(
+                &receiving_sk,
+                &delegating_pk,
+                &capsule,
+                vcfrags,
+                &ciphertext,
+                &mut plaintext,
+            )
+            .unwrap();
+            assert_eq!(plaintext, message);
+        }
+
+        // The output buffer is emptied on error
+        let capsule = encrypt_into(&delegating_pk, b"peace at dawn", &mut ciphertext).unwrap();
+        ciphertext[30] ^= 1;
+        plaintext.extend_from_slice(b"leftovers");
+        assert!(
+            decrypt_original_into(&delegating_sk, &capsule, &ciphertext, &mut plaintext).is_err()
+        );
+        assert!(plaintext.is_empty());
+    }
+
+    #[test]
+    fn test_nonce_strategy() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let plaintext = b"peace at dawn";
+
+        let (capsule, ciphertext) =
+            encrypt_with_nonce_strategy(&delegating_pk, plaintext, NonceStrategy::Derived).unwrap();
+        assert!(has_derived_nonce(&capsule, &ciphertext));
+        assert_eq!(
+            ciphertext_nonce(&ciphertext).unwrap(),
+            derived_nonce(&capsule.to_array()).as_slice()
+        );
+        let plaintext_back = decrypt_original(&delegating_sk, &capsule, &ciphertext).unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        let (capsule, ciphertext) =
+            encrypt_with_nonce_strategy(&delegating_pk, plaintext, NonceStrategy::Random).unwrap();
+        assert!(!has_derived_nonce(&capsule, &ciphertext));
+        let plaintext_back = decrypt_original(&delegating_sk, &capsule, &ciphertext).unwrap();
+        assert_eq!(&plaintext_back as &[u8], plaintext);
+
+        assert!(!has_derived_nonce(&capsule, b"too short"));
+    }
+
+    #[test]
+    fn test_compact_kfrags() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+
+        let verified_kfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+
+        // Simulate network transfer
+        let compact_kfrags: Vec<_> = verified_kfrags
+            .iter()
+            .map(CompactKeyFrag::from)
+            .map(|kfrag| CompactKeyFrag::from_array(&kfrag.to_array()).unwrap())
+            .collect();
+        assert!(compact_kfrags[0].to_array().len() < verified_kfrags[0].to_array().len() / 2);
+        assert_eq!(compact_kfrags[0].id(), verified_kfrags[0].id());
+
+        // Compact fragments produce the same capsule fragments as the full ones
+        let cfrags: Vec<_> = compact_kfrags[1..3]
+            .iter()
+            .map(|kfrag| reencrypt_compact(&capsule, kfrag))
+            .collect();
+        assert_eq!(
+            cfrags[0],
+            reencrypt_unproven(&capsule, verified_kfrags[1].clone())
+        );
+
+        let plaintext_bob = decrypt_reencrypted_unproven(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            cfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+    }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_serialize_errors() {
+        let error = ReencryptionError::OnOpen(OpenReencryptedError::MismatchedCapsuleFrags);
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            r#"{"OnOpen":"MismatchedCapsuleFrags"}"#
+        );
+
+        let error = ReencryptionError::OnDecryption(DecryptionError::AuthenticationFailed);
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            r#"{"OnDecryption":"AuthenticationFailed"}"#
+        );
+
+        assert_eq!(
+            serde_json::to_string(&GenerateKFragsError::ThresholdExceedsShares).unwrap(),
+            r#""ThresholdExceedsShares""#
+        );
+    }
+
+    #[test]
+    fn test_generate_kfrags_batch() {
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+
+        let sk1 = SecretKey::random();
+        let sk2 = SecretKey::random();
+        let delegations = [(b"dataset 1", &sk1), (b"dataset 2", &sk2)];
+
+        let batch =
+            generate_kfrags_batch(delegations, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+        assert_eq!(batch.len(), 2);
+
+        for ((label, delegating_sk), (policy_id, vkfrags)) in delegations.iter().zip(batch) {
+            let delegating_pk = delegating_sk.public_key();
+            assert_eq!(
+                policy_id,
+                PolicyId::new(&delegating_pk, &receiving_pk, &label[..])
+            );
+            assert_eq!(vkfrags.len(), 3);
+
+            let plaintext = b"peace at dawn";
+            let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+            let vcfrags = vkfrags[0..2]
+                .iter()
+                .map(|vkfrag| reencrypt(&capsule, vkfrag.clone()));
+            let plaintext_bob = decrypt_reencrypted(
+                &receiving_sk,
+                &delegating_pk,
+                &capsule,
+                vcfrags,
+                &ciphertext,
+            )
+            .unwrap();
+            assert_eq!(&plaintext_bob as &[u8], plaintext);
+        }
+
+        // The arguments are validated before any delegation is processed
+        assert_eq!(
+            generate_kfrags_batch(delegations, &receiving_pk, &signer, 4, 3, true, true).err(),
+            Some(GenerateKFragsError::ThresholdExceedsShares)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_error_source() {
+        use std::error::Error;
+        use std::string::ToString;
+
+        let error = ReencryptionError::OnDecryption(DecryptionError::AuthenticationFailed);
+        let source = error.source().unwrap();
+        assert_eq!(
+            source.to_string(),
+            DecryptionError::AuthenticationFailed.to_string()
+        );
+        assert!(source.source().is_none());
+    }
+
+    #[test]
+    fn test_fallible_allocation() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+
+        let plaintext_alice = try_decrypt_original(&delegating_sk, &capsule, &ciphertext).unwrap();
+        assert_eq!(&plaintext_alice as &[u8], plaintext);
+
+        let vkfrags =
+            try_generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+        assert_eq!(vkfrags.len(), 3);
+        assert_eq!(
+            try_generate_kfrags(&delegating_sk, &receiving_pk, &signer, 0, 3, true, true).err(),
+            Some(GenerateKFragsError::ZeroThreshold)
+        );
+
+        let vcfrags = vkfrags[0..2]
+            .iter()
+            .map(|vkfrag| reencrypt(&capsule, vkfrag.clone()));
+        let plaintext_bob = try_decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            vcfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_bob as &[u8], plaintext);
+
+        // Too short ciphertexts are handled as usual
+        assert_eq!(
+            try_decrypt_original(&delegating_sk, &capsule, &ciphertext[..5]),
+            Err(DecryptionError::CiphertextTooShort)
+        );
+    }
 }
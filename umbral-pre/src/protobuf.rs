@@ -0,0 +1,181 @@
+//! Protobuf messages for the Umbral objects (as `prost` types),
+//! and conversions between them and the native types.
+//!
+//! The messages correspond to the schema in `proto/umbral.proto`
+//! (shipped with the crate); each of them contains the byte representation of the object.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::traits::{DeserializableFromArray, DeserializationError, SerializableToArray};
+
+macro_rules! proto_message {
+    ($(#[$attr:meta])* $name:ident, $native:ty) => {
+        $(#[$attr])*
+        #[derive(Clone, PartialEq, prost::Message)]
+        pub struct $name {
+            /// The byte representation of the object.
+            #[prost(bytes = "vec", tag = "1")]
+            pub data: Vec<u8>,
+        }
+
+        impl From<&$native> for $name {
+            fn from(obj: &$native) -> Self {
+                Self {
+                    data: obj.to_array().to_vec(),
+                }
+            }
+        }
+
+        impl From<$native> for $name {
+            fn from(obj: $native) -> Self {
+                Self::from(&obj)
+            }
+        }
+
+        impl TryFrom<$name> for $native {
+            type Error = DeserializationError;
+
+            fn try_from(message: $name) -> Result<Self, Self::Error> {
+                Self::from_bytes(&message.data)
+            }
+        }
+
+        impl TryFrom<&$name> for $native {
+            type Error = DeserializationError;
+
+            fn try_from(message: &$name) -> Result<Self, Self::Error> {
+                Self::from_bytes(&message.data)
+            }
+        }
+    };
+}
+
+proto_message!(
+    /// A protobuf message for [`PublicKey`](`crate::PublicKey`).
+    PublicKey,
+    crate::PublicKey
+);
+
+proto_message!(
+    /// A protobuf message for [`Signature`](`crate::Signature`).
+    Signature,
+    crate::Signature
+);
+
+proto_message!(
+    /// A protobuf message for [`Capsule`](`crate::Capsule`).
+    Capsule,
+    crate::Capsule
+);
+
+proto_message!(
+    /// A protobuf message for [`KeyFragID`](`crate::KeyFragID`).
+    KeyFragID,
+    crate::KeyFragID
+);
+
+proto_message!(
+    /// A protobuf message for [`KeyFrag`](`crate::KeyFrag`).
+    KeyFrag,
+    crate::KeyFrag
+);
+
+proto_message!(
+    /// A protobuf message for [`CapsuleFrag`](`crate::CapsuleFrag`).
+    CapsuleFrag,
+    crate::CapsuleFrag
+);
+
+// Verified objects can be sent, but are received as their unverified counterparts.
+
+impl From<&crate::VerifiedKeyFrag> for KeyFrag {
+    fn from(obj: &crate::VerifiedKeyFrag) -> Self {
+        Self {
+            data: obj.to_array().to_vec(),
+        }
+    }
+}
+
+impl From<&crate::VerifiedCapsuleFrag> for CapsuleFrag {
+    fn from(obj: &crate::VerifiedCapsuleFrag) -> Self {
+        Self {
+            data: obj.to_array().to_vec(),
+        }
+    }
+}
+
+/// A protobuf message for [`Envelope`](`crate::Envelope`).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Envelope {
+    /// The byte representation of the envelope.
+    #[prost(bytes = "vec", tag = "1")]
+    pub data: Vec<u8>,
+}
+
+impl From<&crate::Envelope> for Envelope {
+    fn from(envelope: &crate::Envelope) -> Self {
+        Self {
+            data: envelope.to_bytes().into_vec(),
+        }
+    }
+}
+
+impl TryFrom<&Envelope> for crate::Envelope {
+    type Error = DeserializationError;
+
+    fn try_from(message: &Envelope) -> Result<Self, Self::Error> {
+        Self::from_bytes(&message.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+    use core::convert::TryFrom;
+
+    use prost::Message;
+
+    use crate::test_utils::{prepare_delegation, Delegation};
+
+    #[test]
+    fn test_roundtrip() {
+        let Delegation {
+            delegating_pk,
+            capsule,
+            verified_kfrags: vkfrags,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+        let vcfrag = vcfrags[0].clone();
+
+        let message = super::Capsule::from(&capsule);
+        // `encode_to_vec()` is only available with the `std` feature of `prost`
+        let mut encoded = Vec::new();
+        message.encode(&mut encoded).unwrap();
+        let decoded = super::Capsule::decode(encoded.as_slice()).unwrap();
+        assert_eq!(crate::Capsule::try_from(decoded).unwrap(), capsule);
+
+        let message = super::KeyFrag::from(&vkfrags[0]);
+        assert_eq!(
+            crate::KeyFrag::try_from(&message).unwrap(),
+            vkfrags[0].clone_unverified()
+        );
+
+        let message = super::CapsuleFrag::from(&vcfrag);
+        assert_eq!(
+            crate::CapsuleFrag::try_from(&message).unwrap(),
+            vcfrag.clone_unverified()
+        );
+
+        let message = super::PublicKey::from(delegating_pk);
+        assert_eq!(crate::PublicKey::try_from(message).unwrap(), delegating_pk);
+
+        // Malformed data is rejected
+        let message = super::Capsule {
+            data: [1u8; 3].to_vec(),
+        };
+        assert!(crate::Capsule::try_from(message).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! A dense encoding of the public key and capsule bundles for QR codes.
+//!
+//! The bundle is the concatenation of the byte representations of the [`PublicKey`]
+//! and the [`Capsule`], encoded in Base45 (RFC 9285), which only uses the characters
+//! of the QR code alphanumeric mode (`0-9`, `A-Z`, space, and `$%*+-./:`).
+//! This takes 5.5 bits per character instead of 8 bits in the byte mode,
+//! and the QR code error correction makes a separate checksum unnecessary.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::capsule::Capsule;
+use crate::keys::PublicKey;
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, RepresentableAsArray,
+    SerializableToArray,
+};
+
+/// The prefix of the encoded bundles.
+pub const QR_PREFIX: &str = "UMBRAL:";
+
+const CHARSET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+fn construction_error(message: &str) -> DeserializationError {
+    DeserializationError::ConstructionFailure(ConstructionError::new("QR bundle", message))
+}
+
+fn encode_base45(data: &[u8]) -> String {
+    let mut result = String::with_capacity((data.len() + 1) / 2 * 3);
+    for chunk in data.chunks(2) {
+        let (mut value, digits) = match chunk {
+            [a, b] => (usize::from(*a) * 256 + usize::from(*b), 3),
+            [a] => (usize::from(*a), 2),
+            _ => unreachable!(),
+        };
+        for _ in 0..digits {
+            result.push(CHARSET[value % 45] as char);
+            value /= 45;
+        }
+    }
+    result
+}
+
+fn decode_base45(encoded: &str) -> Option<Vec<u8>> {
+    let digits = encoded
+        .bytes()
+        .map(|c| CHARSET.iter().position(|x| *x == c))
+        .collect::<Option<Vec<usize>>>()?;
+
+    let mut result = Vec::with_capacity(digits.len() / 3 * 2 + 1);
+    for chunk in digits.chunks(3) {
+        match *chunk {
+            [c, d, e] => {
+                let value = c + d * 45 + e * 45 * 45;
+                if value > 0xffff {
+                    return None;
+                }
+                result.extend_from_slice(&(value as u16).to_be_bytes());
+            }
+            [c, d] => {
+                let value = c + d * 45;
+                if value > 0xff {
+                    return None;
+                }
+                result.push(value as u8);
+            }
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
+/// Encodes the public key (usually, the delegating key) and the capsule
+/// as a string suitable for the QR code alphanumeric mode.
+pub fn to_qr_string(public_key: &PublicKey, capsule: &Capsule) -> String {
+    let mut bytes = Vec::with_capacity(PublicKey::serialized_size() + Capsule::serialized_size());
+    bytes.extend_from_slice(&public_key.to_array());
+    bytes.extend_from_slice(&capsule.to_array());
+
+    let mut result = String::from(QR_PREFIX);
+    result.push_str(&encode_base45(&bytes));
+    result
+}
+
+/// Decodes the public key and the capsule encoded by [`to_qr_string`].
+///
+/// QR scanners may return the alphanumeric data in lowercase,
+/// so the string is converted to uppercase before decoding.
+pub fn from_qr_string(encoded: &str) -> Result<(PublicKey, Capsule), DeserializationError> {
+    let encoded = encoded.trim().to_ascii_uppercase();
+    let body = encoded
+        .strip_prefix(QR_PREFIX)
+        .ok_or_else(|| construction_error("Missing the bundle prefix"))?;
+    let bytes = decode_base45(body).ok_or_else(|| construction_error("Invalid Base45 encoding"))?;
+
+    let pk_size = PublicKey::serialized_size();
+    if bytes.len() != pk_size + Capsule::serialized_size() {
+        return Err(construction_error("Invalid bundle length"));
+    }
+    let (pk_bytes, capsule_bytes) = bytes.split_at(pk_size);
+    Ok((
+        PublicKey::from_bytes(pk_bytes)?,
+        Capsule::from_bytes(capsule_bytes)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{decode_base45, encode_base45, from_qr_string, to_qr_string, QR_PREFIX};
+    use crate::{encrypt, SecretKey};
+
+    #[test]
+    fn test_base45_vectors() {
+        // Examples from RFC 9285
+        let vectors: [(&[u8], &str); 3] = [
+            (b"AB", "BB8"),
+            (b"Hello!!", "%69 VD92EX0"),
+            (b"ietf!", "QED8WEX0"),
+        ];
+        for (bytes, encoded) in vectors {
+            assert_eq!(encode_base45(bytes), encoded);
+            assert_eq!(decode_base45(encoded).unwrap(), bytes);
+        }
+
+        // Out of range values, wrong length, characters outside of the alphabet
+        assert!(decode_base45("GGW").is_none());
+        assert!(decode_base45("BB8A").is_none());
+        assert!(decode_base45("bb8").is_none());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+
+        let encoded = to_qr_string(&pk, &capsule);
+        assert!(encoded.starts_with(QR_PREFIX));
+        // 33 + 98 bytes
+        assert_eq!(encoded.len(), QR_PREFIX.len() + 65 * 3 + 2);
+
+        assert_eq!(from_qr_string(&encoded).unwrap(), (pk, capsule));
+        assert_eq!(
+            from_qr_string(&encoded.to_ascii_lowercase()).unwrap(),
+            (pk, capsule)
+        );
+
+        assert!(from_qr_string(&encoded[QR_PREFIX.len()..]).is_err());
+        assert!(from_qr_string(&encoded[..encoded.len() - 3]).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Signed statements bound to a specific capsule.
+
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+use crate::capsule::Capsule;
+use crate::hashing_ds::capsule_statement_message;
+use crate::keys::{PublicKey, Signature, Signer};
+
+/// Signs an arbitrary statement about `capsule`
+/// (e.g. a receipt of a proxy that it has served a reencryption request for it)
+/// using the given RNG.
+///
+/// The signed message is domain-separated and includes the digest of the capsule,
+/// so the resulting signature cannot be confused with a signature of a key fragment,
+/// a signature of the same statement about a different capsule,
+/// or a signature of the raw statement bytes.
+pub fn sign_capsule_statement_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    signer: &Signer,
+    capsule: &Capsule,
+    statement: &[u8],
+) -> Signature {
+    let message = capsule_statement_message(capsule, statement);
+    signer.sign_with_rng(rng, &message)
+}
+
+/// A synonym for [`sign_capsule_statement_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn sign_capsule_statement(signer: &Signer, capsule: &Capsule, statement: &[u8]) -> Signature {
+    sign_capsule_statement_with_rng(&mut OsRng, signer, capsule, statement)
+}
+
+/// Verifies a signature created by [`sign_capsule_statement`]
+/// for the given capsule and statement.
+pub fn verify_capsule_statement(
+    signature: &Signature,
+    verifying_pk: &PublicKey,
+    capsule: &Capsule,
+    statement: &[u8],
+) -> bool {
+    let message = capsule_statement_message(capsule, statement);
+    signature.verify(verifying_pk, &message)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{sign_capsule_statement, verify_capsule_statement};
+    use crate::{encrypt, SecretKey, Signer};
+
+    #[test]
+    fn test_capsule_statement() {
+        let delegating_pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&delegating_pk, b"peace at dawn").unwrap();
+        let (other_capsule, _ciphertext) = encrypt(&delegating_pk, b"peace at dawn").unwrap();
+
+        let signer = Signer::new(SecretKey::random());
+        let verifying_pk = signer.verifying_key();
+        let statement = b"served at 1600000000";
+
+        let signature = sign_capsule_statement(&signer, &capsule, statement);
+        assert!(verify_capsule_statement(
+            &signature,
+            &verifying_pk,
+            &capsule,
+            statement
+        ));
+
+        // Bound to the capsule
+        assert!(!verify_capsule_statement(
+            &signature,
+            &verifying_pk,
+            &other_capsule,
+            statement
+        ));
+
+        // Bound to the statement
+        assert!(!verify_capsule_statement(
+            &signature,
+            &verifying_pk,
+            &capsule,
+            b"served at 1600000001"
+        ));
+
+        // Bound to the signer
+        let other_pk = SecretKey::random().public_key();
+        assert!(!verify_capsule_statement(
+            &signature, &other_pk, &capsule, statement
+        ));
+
+        // Not a signature of the raw statement
+        assert!(!signature.verify(&verifying_pk, statement));
+    }
+}
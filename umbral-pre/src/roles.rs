@@ -0,0 +1,154 @@
+//! Role-specific secret key wrappers.
+//!
+//! A [`SecretKey`] can play one of three roles in the protocol:
+//! the delegating key (the key the data is encrypted to),
+//! the receiving key (the key the access is delegated to),
+//! and the signing key (used to sign the key fragments).
+//! The functions taking a secret key accept either a plain [`SecretKey`]
+//! or the wrapper for the respective role, so that, when the wrappers are used,
+//! passing a key in place of another one is a compile-time error.
+
+use core::fmt;
+
+use crate::keys::{PublicKey, SecretKey};
+use crate::traits::{fmt_secret, HasTypeName};
+
+/// A secret key that can be used as the delegating key.
+pub trait AsDelegatingKey {
+    /// Returns the underlying secret key.
+    fn as_delegating_key(&self) -> &SecretKey;
+}
+
+/// A secret key that can be used as the receiving key.
+pub trait AsReceivingKey {
+    /// Returns the underlying secret key.
+    fn as_receiving_key(&self) -> &SecretKey;
+}
+
+impl AsDelegatingKey for SecretKey {
+    fn as_delegating_key(&self) -> &SecretKey {
+        self
+    }
+}
+
+impl AsReceivingKey for SecretKey {
+    fn as_receiving_key(&self) -> &SecretKey {
+        self
+    }
+}
+
+macro_rules! role_key {
+    ($name:ident, $role:literal) => {
+        #[doc = concat!("A secret key to be used as the ", $role, " key.")]
+        #[derive(Clone)]
+        pub struct $name(SecretKey);
+
+        impl $name {
+            /// Wraps the given secret key.
+            pub fn new(sk: SecretKey) -> Self {
+                Self(sk)
+            }
+
+            /// Returns a public key corresponding to this secret key.
+            pub fn public_key(&self) -> PublicKey {
+                self.0.public_key()
+            }
+
+            /// Returns the wrapped secret key.
+            pub fn into_secret_key(self) -> SecretKey {
+                self.0
+            }
+        }
+
+        impl From<SecretKey> for $name {
+            fn from(sk: SecretKey) -> Self {
+                Self::new(sk)
+            }
+        }
+
+        impl HasTypeName for $name {
+            fn type_name() -> &'static str {
+                stringify!($name)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_secret::<Self>(f)
+            }
+        }
+    };
+}
+
+role_key!(DelegatingSecretKey, "delegating");
+role_key!(ReceivingSecretKey, "receiving");
+role_key!(SigningSecretKey, "signing");
+
+impl AsDelegatingKey for DelegatingSecretKey {
+    fn as_delegating_key(&self) -> &SecretKey {
+        &self.0
+    }
+}
+
+impl AsReceivingKey for ReceivingSecretKey {
+    fn as_receiving_key(&self) -> &SecretKey {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{DelegatingSecretKey, ReceivingSecretKey, SigningSecretKey};
+    use crate::{
+        decrypt_original, decrypt_reencrypted, encrypt, generate_kfrags, reencrypt, SecretKey,
+        Signer,
+    };
+
+    #[test]
+    fn test_role_keys() {
+        let delegating_sk = DelegatingSecretKey::new(SecretKey::random());
+        let delegating_pk = delegating_sk.public_key();
+        let signing_sk = SigningSecretKey::from(SecretKey::random());
+        let signer = Signer::new(signing_sk.clone());
+        assert_eq!(signer.verifying_key(), signing_sk.public_key());
+
+        let receiving_sk = ReceivingSecretKey::new(SecretKey::random());
+        let receiving_pk = receiving_sk.public_key();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+        let plaintext_original = decrypt_original(&delegating_sk, &capsule, &ciphertext).unwrap();
+        assert_eq!(&plaintext_original as &[u8], plaintext);
+
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+        let vcfrags = vkfrags[0..2]
+            .iter()
+            .map(|vkfrag| reencrypt(&capsule, vkfrag.clone()));
+        let plaintext_reencrypted = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            vcfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_reencrypted as &[u8], plaintext);
+
+        // Plain secret keys are still accepted
+        let receiving_sk = receiving_sk.into_secret_key();
+        let vcfrags = vkfrags[1..3]
+            .iter()
+            .map(|vkfrag| reencrypt(&capsule, vkfrag.clone()));
+        let plaintext_reencrypted = decrypt_reencrypted(
+            &receiving_sk,
+            &delegating_pk,
+            &capsule,
+            vcfrags,
+            &ciphertext,
+        )
+        .unwrap();
+        assert_eq!(&plaintext_reencrypted as &[u8], plaintext);
+    }
+}
@@ -24,6 +24,8 @@ See https://github.com/iqlusioninc/crates/issues/757
 */
 
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 
 use generic_array::{ArrayLength, GenericArray};
 use zeroize::Zeroize;
@@ -61,10 +63,31 @@ where
     }
 }
 
+impl<const N: usize> CanBeZeroizedOnDrop for [u8; N] {
+    fn ensure_zeroized_on_drop(&mut self) {
+        self.iter_mut().zeroize()
+    }
+}
+
+impl CanBeZeroizedOnDrop for Vec<u8> {
+    fn ensure_zeroized_on_drop(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl CanBeZeroizedOnDrop for String {
+    fn ensure_zeroized_on_drop(&mut self) {
+        self.zeroize()
+    }
+}
+
 /// A container for secret data.
 /// Makes the usage of secret data explicit and easy to track,
 /// prevents the secret data from being put on stack,
 /// and zeroizes the contents on drop.
+///
+/// Can be used for application secrets as well (e.g. keys derived from Umbral's outputs);
+/// to store a custom type, implement [`CanBeZeroizedOnDrop`] for it.
 #[derive(Clone)] // No Debug derivation, to avoid exposing the secret data accidentally.
 pub struct SecretBox<T>(Box<T>)
 where
@@ -74,10 +97,27 @@ impl<T> SecretBox<T>
 where
     T: CanBeZeroizedOnDrop + Clone,
 {
-    pub(crate) fn new(val: T) -> Self {
+    /// Moves the value into the container.
+    ///
+    /// Note that the original location of `val` is not zeroized;
+    /// if it is a local variable holding the secret itself
+    /// (as opposed to a pointer to the heap, like [`Vec`]), prefer
+    /// [`new_with`](`Self::new_with`).
+    pub fn new(val: T) -> Self {
         Self(Box::new(val))
     }
 
+    /// Creates the container with the default value, and fills it in place with `init`,
+    /// so that the secret never appears outside of the heap.
+    pub fn new_with(init: impl FnOnce(&mut T)) -> Self
+    where
+        T: Default,
+    {
+        let mut result = Self(Box::default());
+        init(result.as_mut_secret());
+        result
+    }
+
     /// Returns an immutable reference to the secret data.
     pub fn as_secret(&self) -> &T {
         self.0.as_ref()
@@ -87,6 +127,17 @@ where
     pub fn as_mut_secret(&mut self) -> &mut T {
         self.0.as_mut()
     }
+
+    /// Calls `f` with an immutable reference to the secret data, returning its result.
+    /// Makes it easier to keep the references from escaping into the surrounding code.
+    pub fn with_secret<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(self.as_secret())
+    }
+
+    /// Calls `f` with a mutable reference to the secret data, returning its result.
+    pub fn with_mut_secret<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(self.as_mut_secret())
+    }
 }
 
 impl<T> Drop for SecretBox<T>
@@ -97,3 +148,35 @@ where
         self.0.ensure_zeroized_on_drop()
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{CanBeZeroizedOnDrop, SecretBox};
+
+    #[test]
+    fn test_access() {
+        let mut secret = SecretBox::new_with(|seed: &mut [u8; 4]| seed.copy_from_slice(b"seed"));
+        assert_eq!(secret.as_secret(), b"seed");
+        assert_eq!(secret.with_secret(|seed| seed[0]), b's');
+
+        secret.with_mut_secret(|seed| seed[0] = b'n');
+        assert_eq!(secret.as_secret(), b"need");
+
+        let vec_secret = SecretBox::new(Vec::from(&b"file key"[..]));
+        assert_eq!(vec_secret.with_secret(|key| key.len()), 8);
+    }
+
+    #[test]
+    fn test_zeroize() {
+        let mut arr = *b"seed";
+        arr.ensure_zeroized_on_drop();
+        assert_eq!(arr, [0u8; 4]);
+
+        let mut vec = Vec::from(&b"file key"[..]);
+        vec.ensure_zeroized_on_drop();
+        assert!(vec.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 //! Utilities to interact with `serde`.
 
+use alloc::boxed::Box;
 use core::fmt;
 use core::marker::PhantomData;
 
@@ -28,15 +29,28 @@ pub(crate) fn serde_serialize<T, S>(
 where
     T: SerializableToArray,
     S: Serializer,
+{
+    serde_serialize_bytes(obj.to_array().as_ref(), serializer, representation)
+}
+
+/// A helper function that will serialize a variable-sized bytestring
+/// in the same way as [`serde_serialize`].
+pub(crate) fn serde_serialize_bytes<S>(
+    bytes: &[u8],
+    serializer: S,
+    representation: Representation,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
 {
     if serializer.is_human_readable() {
         let repr = match representation {
-            Representation::Base64 => base64::encode(obj.to_array().as_ref()),
-            Representation::Hex => hex::encode(obj.to_array().as_ref()),
+            Representation::Base64 => base64::encode(bytes),
+            Representation::Hex => hex::encode(bytes),
         };
         serializer.serialize_str(&repr)
     } else {
-        serializer.serialize_bytes(obj.to_array().as_ref())
+        serializer.serialize_bytes(bytes)
     }
 }
 
@@ -125,6 +139,62 @@ where
     }
 }
 
+struct RawBytesVisitor {
+    representation: Representation,
+    type_name: &'static str,
+}
+
+impl<'de> de::Visitor<'de> for RawBytesVisitor {
+    type Value = Box<[u8]>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.representation {
+            Representation::Base64 => write!(f, "b64-encoded {} bytes", self.type_name),
+            Representation::Hex => write!(f, "hex-encoded {} bytes", self.type_name),
+        }
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let bytes = match self.representation {
+            Representation::Base64 => base64::decode(v).map_err(de::Error::custom)?,
+            Representation::Hex => hex::decode(v).map_err(de::Error::custom)?,
+        };
+        Ok(bytes.into_boxed_slice())
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.into())
+    }
+}
+
+/// A helper function that will deserialize a variable-sized bytestring,
+/// matching the format used by [`serde_serialize_bytes`].
+/// The caller is responsible for parsing the result.
+pub(crate) fn serde_deserialize_bytes<'de, D>(
+    deserializer: D,
+    representation: Representation,
+    type_name: &'static str,
+) -> Result<Box<[u8]>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let visitor = RawBytesVisitor {
+        representation,
+        type_name,
+    };
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(visitor)
+    } else {
+        deserializer.deserialize_bytes(visitor)
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
 
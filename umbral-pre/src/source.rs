@@ -0,0 +1,195 @@
+//! Identification of the delegation a key or capsule fragment originates from.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use digest::Digest;
+use generic_array::GenericArray;
+use typenum::U32;
+
+#[cfg(feature = "serde-support")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::curve::CurvePoint;
+use crate::dst;
+use crate::hashing::Hash;
+use crate::traits::{
+    fmt_public, from_str_public, ConstructionError, DeserializableFromArray, DeserializationError,
+    HasTypeName, RepresentableAsArray, SerializableToArray,
+};
+
+#[cfg(feature = "serde-support")]
+use crate::serde::{serde_deserialize, serde_serialize, Representation};
+
+type SourceIdSize = U32;
+
+/// An opaque identifier of a delegation, that is, of a single call to
+/// [`generate_kfrags`](`crate::generate_kfrags`).
+///
+/// All the key fragments created in that call, and all the capsule fragments
+/// created with them (for any capsule) share the same identifier,
+/// while fragments from different delegations have different ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceId(GenericArray<u8, SourceIdSize>);
+
+impl SourceId {
+    pub(crate) fn from_precursor(precursor: &CurvePoint) -> Self {
+        Self(
+            Hash::new_with_dst(dst::SOURCE_ID)
+                .chain_bytes(precursor.to_array())
+                .digest()
+                .finalize(),
+        )
+    }
+}
+
+impl AsRef<[u8]> for SourceId {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl RepresentableAsArray for SourceId {
+    type Size = SourceIdSize;
+}
+
+impl SerializableToArray for SourceId {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        self.0
+    }
+}
+
+impl DeserializableFromArray for SourceId {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        Ok(Self(*arr))
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for SourceId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize(self, serializer, Representation::Hex)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for SourceId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_deserialize(deserializer, Representation::Hex)
+    }
+}
+
+impl HasTypeName for SourceId {
+    fn type_name() -> &'static str {
+        "SourceId"
+    }
+}
+
+impl fmt::Display for SourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(self, f)
+    }
+}
+
+impl FromStr for SourceId {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
+/// A trait for objects originating from a specific delegation.
+pub trait HasSourceId {
+    /// Returns the identifier of the delegation this object originates from.
+    fn source_id(&self) -> SourceId;
+}
+
+/// Partitions the given objects (e.g. capsule fragments received from many proxies
+/// across many policies) by the delegation they originate from.
+///
+/// The groups are returned in the order of the first appearance of their identifiers,
+/// and the objects in each group preserve their relative order.
+pub fn group_by_source<T: HasSourceId>(
+    items: impl IntoIterator<Item = T>,
+) -> Vec<(SourceId, Vec<T>)> {
+    let mut groups = Vec::<(SourceId, Vec<T>)>::new();
+    for item in items {
+        let source_id = item.source_id();
+        match groups.iter_mut().find(|(id, _)| id == &source_id) {
+            Some((_, group)) => group.push(item),
+            None => groups.push((source_id, Vec::from([item]))),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{group_by_source, HasSourceId};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{encrypt, reencrypt};
+
+    #[cfg(feature = "serde-support")]
+    use crate::serde::tests::{check_deserialization, check_serialization};
+
+    #[cfg(feature = "serde-support")]
+    use crate::serde::Representation;
+
+    #[test]
+    fn test_group_by_source() {
+        let Delegation {
+            delegating_pk,
+            capsule: capsule1,
+            verified_kfrags: vkfrags1,
+            verified_cfrags: vcfrags1,
+            ..
+        } = prepare_delegation(true, true);
+        let vkfrags2 = prepare_delegation(true, true).verified_kfrags;
+
+        let (capsule2, _ciphertext) = encrypt(&delegating_pk, b"peace at dusk").unwrap();
+
+        // Fragments of the same delegation share the source even for different capsules
+        let vcfrag1 = vcfrags1[0].clone();
+        let vcfrag2 = reencrypt(&capsule2, vkfrags1[1].clone());
+        assert_eq!(vcfrag1.source_id(), vkfrags1[0].source_id());
+        assert_eq!(vcfrag1.source_id(), vcfrag2.source_id());
+        assert_eq!(vcfrag1.source_id(), vcfrag1.clone().unverify().source_id());
+
+        let vcfrag3 = reencrypt(&capsule1, vkfrags2[0].clone());
+        assert_ne!(vcfrag1.source_id(), vcfrag3.source_id());
+
+        let vcfrag4 = vcfrags1[2].clone();
+        let groups = group_by_source([
+            vcfrag1.clone(),
+            vcfrag3.clone(),
+            vcfrag2.clone(),
+            vcfrag4.clone(),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, vcfrag1.source_id());
+        assert_eq!(groups[0].1, [vcfrag1, vcfrag2, vcfrag4]);
+        assert_eq!(groups[1].0, vcfrag3.source_id());
+        assert_eq!(groups[1].1, [vcfrag3]);
+    }
+
+    #[cfg(feature = "serde-support")]
+    #[test]
+    fn test_serde_serialization() {
+        let vkfrags = prepare_delegation(true, true).verified_kfrags;
+
+        let source_id = vkfrags[0].source_id();
+        check_serialization(&source_id, Representation::Hex);
+        check_deserialization(&source_id);
+    }
+}
@@ -0,0 +1,161 @@
+//! SSZ (Simple Serialize) encoding of the objects, as used by the Ethereum consensus layer.
+//!
+//! All the supported objects have a fixed size, and are represented
+//! as the SSZ type `Vector[byte, N]`, where `N` is the size of their byte representation.
+//! The encoding is, therefore, the same as the byte representation itself,
+//! and the hash tree root is the Merkle root of the representation
+//! split into 32-byte chunks.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use digest::Digest;
+use sha2::Sha256;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::keys::PublicKey;
+use crate::traits::{
+    DeserializableFromArray, DeserializationError, RepresentableAsArray, SerializableToArray,
+};
+
+const CHUNK_SIZE: usize = 32;
+
+/// A trait for objects that can be encoded as SSZ.
+pub trait SszEncode {
+    /// Returns the size of the SSZ encoding (the same for all objects of the type).
+    fn ssz_fixed_len() -> usize;
+
+    /// Encodes the object as SSZ.
+    fn to_ssz(&self) -> Box<[u8]>;
+
+    /// Returns the SSZ hash tree root of the object.
+    fn hash_tree_root(&self) -> [u8; 32];
+}
+
+/// A trait for objects that can be decoded from SSZ.
+pub trait SszDecode: Sized {
+    /// Decodes the object from SSZ.
+    fn from_ssz(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError>;
+}
+
+fn hash_pair(left: &[u8; CHUNK_SIZE], right: &[u8; CHUNK_SIZE]) -> [u8; CHUNK_SIZE] {
+    let mut result = [0u8; CHUNK_SIZE];
+    result.copy_from_slice(&Sha256::new().chain(left).chain(right).finalize());
+    result
+}
+
+/// Merkleizes the data packed into zero-padded 32-byte chunks,
+/// with the number of leaves padded to the next power of two.
+fn merkleize_bytes(data: &[u8]) -> [u8; CHUNK_SIZE] {
+    let mut layer = data
+        .chunks(CHUNK_SIZE)
+        .map(|chunk| {
+            let mut leaf = [0u8; CHUNK_SIZE];
+            leaf[..chunk.len()].copy_from_slice(chunk);
+            leaf
+        })
+        .collect::<Vec<_>>();
+
+    if layer.is_empty() {
+        return [0u8; CHUNK_SIZE];
+    }
+
+    // The missing leaves are zero chunks, and so are the roots of the subtrees built from them.
+    let mut zero_subtree = [0u8; CHUNK_SIZE];
+    while layer.len() > 1 {
+        if layer.len() % 2 == 1 {
+            layer.push(zero_subtree);
+        }
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+        zero_subtree = hash_pair(&zero_subtree, &zero_subtree);
+    }
+    layer[0]
+}
+
+macro_rules! impl_ssz {
+    ($($type:ty),*) => {
+        $(
+            impl SszEncode for $type {
+                fn ssz_fixed_len() -> usize {
+                    <$type as RepresentableAsArray>::serialized_size()
+                }
+
+                fn to_ssz(&self) -> Box<[u8]> {
+                    self.to_array().as_slice().into()
+                }
+
+                fn hash_tree_root(&self) -> [u8; 32] {
+                    merkleize_bytes(&self.to_array())
+                }
+            }
+
+            impl SszDecode for $type {
+                fn from_ssz(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+                    Self::from_bytes(data)
+                }
+            }
+        )*
+    };
+}
+
+impl_ssz!(Capsule, CapsuleFrag, PublicKey);
+
+#[cfg(test)]
+mod tests {
+
+    use digest::Digest;
+    use sha2::Sha256;
+
+    use super::{hash_pair, merkleize_bytes, SszDecode, SszEncode};
+    use crate::{encrypt, Capsule, PublicKey, SecretKey, SerializableToArray};
+
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        let mut result = [0u8; 32];
+        result.copy_from_slice(&Sha256::digest(data));
+        result
+    }
+
+    #[test]
+    fn test_merkleize() {
+        // A single chunk is its own root
+        assert_eq!(merkleize_bytes(&[1u8; 32]), [1u8; 32]);
+        assert_eq!(merkleize_bytes(&[1u8; 3]), {
+            let mut chunk = [0u8; 32];
+            chunk[..3].copy_from_slice(&[1u8; 3]);
+            chunk
+        });
+
+        // Three chunks are padded to four with a zero chunk
+        let data = [[1u8; 32], [2u8; 32], [3u8; 32]].concat();
+        let expected = hash_pair(
+            &hash_pair(&[1u8; 32], &[2u8; 32]),
+            &hash_pair(&[3u8; 32], &[0u8; 32]),
+        );
+        assert_eq!(merkleize_bytes(&data), expected);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+
+        let pk_ssz = pk.to_ssz();
+        assert_eq!(pk_ssz.len(), PublicKey::ssz_fixed_len());
+        assert_eq!(PublicKey::from_ssz(&pk_ssz).unwrap(), pk);
+
+        let capsule_ssz = capsule.to_ssz();
+        assert_eq!(&capsule_ssz as &[u8], capsule.to_array().as_slice());
+        assert_eq!(Capsule::from_ssz(&capsule_ssz).unwrap(), capsule);
+
+        // A 33-byte key is two chunks
+        let mut chunks = [0u8; 64];
+        chunks[..33].copy_from_slice(&pk.to_array());
+        assert_eq!(pk.hash_tree_root(), sha256(&chunks));
+
+        assert!(Capsule::from_ssz(&capsule_ssz[1..]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Incremental deserialization of object streams.
+
+use generic_array::GenericArray;
+
+use crate::secret_box::SecretBox;
+use crate::traits::{ConstructionError, DeserializableFromArray};
+
+/// An incremental deserializer for a stream of objects of the same type,
+/// accepting the data in chunks of arbitrary size (e.g. partial network reads).
+///
+/// ```
+/// use umbral_pre::*;
+///
+/// let pk = SecretKey::random().public_key();
+/// let (capsule1, _ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+/// let (capsule2, _ciphertext) = encrypt(&pk, b"peace at dusk").unwrap();
+/// let data = [capsule1.to_vec(), capsule2.to_vec()].concat();
+///
+/// let mut deserializer = StreamDeserializer::<Capsule>::new();
+/// let mut capsules = Vec::new();
+/// for mut chunk in data.chunks(60) {
+///     while let Some(result) = deserializer.push(&mut chunk) {
+///         capsules.push(result.unwrap());
+///     }
+/// }
+/// assert_eq!(capsules, [capsule1, capsule2]);
+/// assert!(deserializer.is_empty());
+/// ```
+pub struct StreamDeserializer<T: DeserializableFromArray> {
+    // Kept in a `SecretBox` in case the deserialized type is secret (e.g. `SecretKey`).
+    buffer: SecretBox<GenericArray<u8, T::Size>>,
+    filled: usize,
+}
+
+impl<T: DeserializableFromArray> StreamDeserializer<T> {
+    /// Creates a new deserializer with an empty buffer.
+    pub fn new() -> Self {
+        Self {
+            buffer: SecretBox::new(GenericArray::default()),
+            filled: 0,
+        }
+    }
+
+    /// Consumes the bytes from the beginning of `data`
+    /// until either an object is complete, or `data` is exhausted.
+    ///
+    /// Returns the deserialization result if an object was completed
+    /// (in which case `data` may still contain unconsumed bytes,
+    /// and the method should be called again), or `None` otherwise.
+    /// A failed object does not affect the deserialization of the following ones.
+    pub fn push(&mut self, data: &mut &[u8]) -> Option<Result<T, ConstructionError>> {
+        let size = T::serialized_size();
+        let to_take = core::cmp::min(size - self.filled, data.len());
+        let (taken, rest) = data.split_at(to_take);
+        self.buffer.as_mut_secret()[self.filled..self.filled + to_take].copy_from_slice(taken);
+        self.filled += to_take;
+        *data = rest;
+
+        if self.filled == size {
+            self.filled = 0;
+            Some(T::from_array(self.buffer.as_secret()))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of buffered bytes of an incomplete object.
+    pub fn buffered_len(&self) -> usize {
+        self.filled
+    }
+
+    /// Returns `true` if there is no incomplete object in the buffer
+    /// (that is, the stream can end at this point).
+    pub fn is_empty(&self) -> bool {
+        self.filled == 0
+    }
+}
+
+impl<T: DeserializableFromArray> Default for StreamDeserializer<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::StreamDeserializer;
+    use crate::test_utils::prepare_delegation;
+    use crate::{encrypt, Capsule, CapsuleFrag, SecretKey, SerializableToArray};
+
+    #[test]
+    fn test_chunks() {
+        let vcfrags = prepare_delegation(true, true).verified_cfrags;
+        let cfrags: Vec<CapsuleFrag> = vcfrags
+            .iter()
+            .map(|vcfrag| vcfrag.clone_unverified())
+            .collect();
+
+        let mut data = Vec::new();
+        for cfrag in cfrags.iter() {
+            data.extend_from_slice(&cfrag.to_array());
+        }
+
+        // Chunks both smaller and larger than the object size, not aligned with the objects
+        for chunk_size in [1, 7, 100, 500, data.len()] {
+            let mut deserializer = StreamDeserializer::<CapsuleFrag>::new();
+            let mut cfrags_back = Vec::new();
+            for mut chunk in data.chunks(chunk_size) {
+                while let Some(result) = deserializer.push(&mut chunk) {
+                    cfrags_back.push(result.unwrap());
+                }
+                assert!(chunk.is_empty());
+            }
+            assert_eq!(cfrags_back, cfrags);
+            assert!(deserializer.is_empty());
+        }
+
+        // An incomplete object remains in the buffer
+        let mut deserializer = StreamDeserializer::<CapsuleFrag>::new();
+        assert!(deserializer.push(&mut &data[..10]).is_none());
+        assert_eq!(deserializer.buffered_len(), 10);
+        assert!(!deserializer.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_object() {
+        let pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0u8; 98]);
+        data.extend_from_slice(&capsule.to_array());
+
+        let mut deserializer = StreamDeserializer::<Capsule>::new();
+        let mut chunk = &data[..];
+        assert!(deserializer.push(&mut chunk).unwrap().is_err());
+        assert_eq!(deserializer.push(&mut chunk).unwrap().unwrap(), capsule);
+        assert!(deserializer.push(&mut chunk).is_none());
+    }
+}
@@ -0,0 +1,70 @@
+//! Fixtures shared between the unit tests.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::{
+    encrypt, generate_kfrags, reencrypt, Capsule, PublicKey, SecretKey, Signer,
+    VerifiedCapsuleFrag, VerifiedKeyFrag,
+};
+
+/// A complete and valid delegation with random keys.
+pub(crate) struct Delegation {
+    pub delegating_sk: SecretKey,
+    pub delegating_pk: PublicKey,
+    pub signer: Signer,
+    pub verifying_pk: PublicKey,
+    pub receiving_sk: SecretKey,
+    pub receiving_pk: PublicKey,
+    pub capsule: Capsule,
+    pub ciphertext: Box<[u8]>,
+    pub verified_kfrags: Box<[VerifiedKeyFrag]>,
+    pub verified_cfrags: Box<[VerifiedCapsuleFrag]>,
+}
+
+/// Creates a delegation with 3 key fragments (2 of which are required),
+/// encrypts `b"peace at dawn"`, and reencrypts the capsule with every key fragment.
+pub(crate) fn prepare_delegation(
+    sign_delegating_key: bool,
+    sign_receiving_key: bool,
+) -> Delegation {
+    let delegating_sk = SecretKey::random();
+    let delegating_pk = delegating_sk.public_key();
+
+    let signer = Signer::new(SecretKey::random());
+    let verifying_pk = signer.verifying_key();
+
+    let receiving_sk = SecretKey::random();
+    let receiving_pk = receiving_sk.public_key();
+
+    let (capsule, ciphertext) = encrypt(&delegating_pk, b"peace at dawn").unwrap();
+
+    let verified_kfrags = generate_kfrags(
+        &delegating_sk,
+        &receiving_pk,
+        &signer,
+        2,
+        3,
+        sign_delegating_key,
+        sign_receiving_key,
+    )
+    .unwrap();
+
+    let verified_cfrags: Vec<_> = verified_kfrags
+        .iter()
+        .map(|vkfrag| reencrypt(&capsule, vkfrag.clone()))
+        .collect();
+
+    Delegation {
+        delegating_sk,
+        delegating_pk,
+        signer,
+        verifying_pk,
+        receiving_sk,
+        receiving_pk,
+        capsule,
+        ciphertext,
+        verified_kfrags,
+        verified_cfrags: verified_cfrags.into_boxed_slice(),
+    }
+}
@@ -0,0 +1,164 @@
+//! Checks the published test vectors for the canonical encoding
+//! (`vectors/canonical_encoding.json`).
+
+use alloc::vec::Vec;
+
+use serde_json::Value;
+
+use crate::{
+    Capsule, CapsuleFrag, DeserializableFromArray, KeyFrag, PublicKey, SecretKey,
+    SerializableToArray, SerializableToSecretArray,
+};
+
+const VECTORS: &str = include_str!("../vectors/canonical_encoding.json");
+
+fn load_vectors() -> Value {
+    serde_json::from_str(VECTORS).unwrap()
+}
+
+fn hex_field(entry: &Value, name: &str) -> Vec<u8> {
+    hex::decode(entry[name].as_str().unwrap()).unwrap()
+}
+
+fn public_key_field(entry: &Value, name: &str) -> PublicKey {
+    PublicKey::from_bytes(&hex_field(entry, name)).unwrap()
+}
+
+#[test]
+fn test_secret_keys() {
+    let vectors = load_vectors();
+    for entry in vectors["secret_keys"].as_array().unwrap() {
+        let sk_bytes = hex_field(entry, "secret_key");
+        let pk_bytes = hex_field(entry, "public_key");
+
+        let sk = SecretKey::from_bytes(&sk_bytes).unwrap();
+        assert_eq!(sk.to_secret_array().as_secret().as_slice(), sk_bytes);
+        assert_eq!(sk.public_key().to_array().as_slice(), pk_bytes);
+
+        let pk = PublicKey::from_bytes(&pk_bytes).unwrap();
+        assert_eq!(pk.to_array().as_slice(), pk_bytes);
+    }
+}
+
+#[test]
+fn test_capsules() {
+    let vectors = load_vectors();
+    for entry in vectors["capsules"].as_array().unwrap() {
+        let capsule_bytes = hex_field(entry, "capsule");
+
+        // Fields are serialized in order, without separators or length prefixes
+        let concatenated = [
+            hex_field(entry, "point_e"),
+            hex_field(entry, "point_v"),
+            hex_field(entry, "signature"),
+        ]
+        .concat();
+        assert_eq!(concatenated, capsule_bytes);
+
+        let capsule = Capsule::from_bytes(&capsule_bytes).unwrap();
+        assert_eq!(capsule.to_array().as_slice(), capsule_bytes);
+        assert_eq!(
+            capsule.canonical_digest().as_slice(),
+            hex_field(entry, "canonical_digest")
+        );
+    }
+}
+
+#[test]
+fn test_key_fragments() {
+    let vectors = load_vectors();
+    for entry in vectors["key_fragments"].as_array().unwrap() {
+        let kfrag_bytes = hex_field(entry, "kfrag");
+
+        let concatenated = [
+            hex_field(entry, "id"),
+            hex_field(entry, "key"),
+            hex_field(entry, "precursor"),
+            hex_field(entry, "commitment"),
+            hex_field(entry, "signature_for_proxy"),
+            hex_field(entry, "signature_for_receiver"),
+            hex_field(entry, "delegating_key_signed"),
+            hex_field(entry, "receiving_key_signed"),
+        ]
+        .concat();
+        assert_eq!(concatenated, kfrag_bytes);
+
+        let kfrag = KeyFrag::from_bytes(&kfrag_bytes).unwrap();
+        assert_eq!(kfrag.to_array().as_slice(), kfrag_bytes);
+        assert_eq!(
+            kfrag.canonical_digest().as_slice(),
+            hex_field(entry, "canonical_digest")
+        );
+
+        // The keys are only passed if the signature covers them,
+        // to check that the flags are encoded as expected.
+        let delegating_pk = public_key_field(entry, "delegating_pk");
+        let receiving_pk = public_key_field(entry, "receiving_pk");
+        let delegating_key_signed = hex_field(entry, "delegating_key_signed") == [1];
+        let receiving_key_signed = hex_field(entry, "receiving_key_signed") == [1];
+        assert!(kfrag
+            .verify(
+                &public_key_field(entry, "verifying_pk"),
+                Some(&delegating_pk).filter(|_| delegating_key_signed),
+                Some(&receiving_pk).filter(|_| receiving_key_signed),
+            )
+            .is_ok());
+    }
+}
+
+#[test]
+fn test_capsule_fragments() {
+    let vectors = load_vectors();
+    for entry in vectors["capsule_fragments"].as_array().unwrap() {
+        let cfrag_bytes = hex_field(entry, "cfrag");
+
+        let concatenated = [
+            hex_field(entry, "point_e1"),
+            hex_field(entry, "point_v1"),
+            hex_field(entry, "kfrag_id"),
+            hex_field(entry, "precursor"),
+            hex_field(entry, "point_e2"),
+            hex_field(entry, "point_v2"),
+            hex_field(entry, "kfrag_commitment"),
+            hex_field(entry, "kfrag_pok"),
+            hex_field(entry, "signature"),
+            hex_field(entry, "kfrag_signature"),
+        ]
+        .concat();
+        assert_eq!(concatenated, cfrag_bytes);
+
+        let cfrag = CapsuleFrag::from_bytes(&cfrag_bytes).unwrap();
+        assert_eq!(cfrag.to_array().as_slice(), cfrag_bytes);
+        assert_eq!(
+            cfrag.canonical_digest().as_slice(),
+            hex_field(entry, "canonical_digest")
+        );
+
+        let capsule = Capsule::from_bytes(&hex_field(entry, "capsule")).unwrap();
+        assert!(cfrag
+            .verify(
+                &capsule,
+                &public_key_field(entry, "verifying_pk"),
+                &public_key_field(entry, "delegating_pk"),
+                &public_key_field(entry, "receiving_pk"),
+            )
+            .is_ok());
+    }
+}
+
+#[test]
+fn test_invalid_encodings() {
+    let vectors = load_vectors();
+    for entry in vectors["invalid"].as_array().unwrap() {
+        let bytes = hex_field(entry, "bytes");
+        let rejected = match entry["type"].as_str().unwrap() {
+            "PublicKey" => PublicKey::from_bytes(&bytes).is_err(),
+            "SecretKey" => SecretKey::from_bytes(&bytes).is_err(),
+            "Capsule" => Capsule::from_bytes(&bytes).is_err(),
+            "KeyFrag" => KeyFrag::from_bytes(&bytes).is_err(),
+            "CapsuleFrag" => CapsuleFrag::from_bytes(&bytes).is_err(),
+            type_name => panic!("Unknown type in the test vectors: {}", type_name),
+        };
+        assert!(rejected, "Accepted invalid encoding: {}", entry["reason"]);
+    }
+}
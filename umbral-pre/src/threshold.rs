@@ -0,0 +1,459 @@
+//! Splitting the receiving secret key between several devices,
+//! so that opening a reencrypted capsule requires several of them to cooperate.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use generic_array::sequence::Concat;
+use generic_array::GenericArray;
+use rand_core::{CryptoRng, RngCore};
+use typenum::op;
+
+#[cfg(feature = "default-rng")]
+use rand_core::OsRng;
+
+use crate::capsule::{lambda_coeffs, Capsule, OpenReencryptedError};
+use crate::capsule_frag::{UnprovenCapsuleFrag, VerifiedCapsuleFrag};
+use crate::curve::{CurvePoint, CurveScalar, NonZeroCurveScalar};
+use crate::dem::{DecryptionError, DEM};
+use crate::key_frag::poly_eval;
+use crate::keys::{PublicKey, SecretKey};
+use crate::secret_box::SecretBox;
+use crate::traits::{
+    fmt_public, fmt_secret, from_str_public, ConstructionError, DeserializableFromArray,
+    DeserializationError, HasTypeName, RepresentableAsArray, SerializableToArray,
+    SerializableToSecretArray,
+};
+
+#[cfg(feature = "serde-support")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "serde-support")]
+use crate::serde::{serde_deserialize, serde_serialize, Representation};
+
+type ScalarSize = <CurveScalar as RepresentableAsArray>::Size;
+type PointSize = <CurvePoint as RepresentableAsArray>::Size;
+
+fn nonzero_index(
+    index: CurveScalar,
+    type_name: &str,
+) -> Result<NonZeroCurveScalar, ConstructionError> {
+    NonZeroCurveScalar::from_array_ct(&index.to_array())
+        .ok_or_else(|| ConstructionError::new(type_name, "Invalid share index"))
+}
+
+/// A share of a receiving secret key, held by one of the receiver's devices.
+#[derive(Clone)]
+pub struct ReceivingKeyShare {
+    index: NonZeroCurveScalar,
+    value: SecretBox<CurveScalar>,
+}
+
+impl ReceivingKeyShare {
+    /// Creates a partial decryption for the reencrypted capsule fragments
+    /// of a single capsule (any of them can be passed, since they have the same precursor).
+    ///
+    /// **Warning:** the device should only create partial decryptions
+    /// for the capsule fragments it has verified, or it becomes a Diffie-Hellman oracle
+    /// for the combining party.
+    pub fn partial_decryption(&self, verified_cfrag: &VerifiedCapsuleFrag) -> PartialDecryption {
+        let precursor = verified_cfrag.precursor();
+        PartialDecryption {
+            index: self.index.clone(),
+            precursor,
+            point: &precursor * self.value.as_secret(),
+        }
+    }
+}
+
+impl RepresentableAsArray for ReceivingKeyShare {
+    type Size = op!(ScalarSize + ScalarSize);
+}
+
+impl SerializableToSecretArray for ReceivingKeyShare {
+    fn to_secret_array(&self) -> SecretBox<GenericArray<u8, Self::Size>> {
+        let index = CurveScalar::from(&self.index).to_array();
+        SecretBox::new(index.concat(self.value.as_secret().to_array()))
+    }
+}
+
+impl DeserializableFromArray for ReceivingKeyShare {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let (index, rest) = CurveScalar::take(*arr)?;
+        let index = nonzero_index(index, Self::type_name())?;
+        let value = CurveScalar::take_last(rest)?;
+        Ok(Self {
+            index,
+            value: SecretBox::new(value),
+        })
+    }
+}
+
+impl HasTypeName for ReceivingKeyShare {
+    fn type_name() -> &'static str {
+        "ReceivingKeyShare"
+    }
+}
+
+impl fmt::Display for ReceivingKeyShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_secret::<Self>(f)
+    }
+}
+
+/// A contribution of a single [`ReceivingKeyShare`] towards opening a reencrypted capsule.
+#[derive(Clone)]
+pub struct PartialDecryption {
+    index: NonZeroCurveScalar,
+    precursor: CurvePoint,
+    point: CurvePoint,
+}
+
+impl RepresentableAsArray for PartialDecryption {
+    type Size = op!(ScalarSize + PointSize + PointSize);
+}
+
+impl SerializableToArray for PartialDecryption {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        CurveScalar::from(&self.index)
+            .to_array()
+            .concat(self.precursor.to_array())
+            .concat(self.point.to_array())
+    }
+}
+
+impl DeserializableFromArray for PartialDecryption {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let (index, rest) = CurveScalar::take(*arr)?;
+        let index = nonzero_index(index, Self::type_name())?;
+        let (precursor, rest) = CurvePoint::take(rest)?;
+        let point = CurvePoint::take_last(rest)?;
+        Ok(Self {
+            index,
+            precursor,
+            point,
+        })
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl Serialize for PartialDecryption {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_serialize(self, serializer, Representation::Base64)
+    }
+}
+
+#[cfg(feature = "serde-support")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-support")))]
+impl<'de> Deserialize<'de> for PartialDecryption {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde_deserialize(deserializer, Representation::Base64)
+    }
+}
+
+impl HasTypeName for PartialDecryption {
+    fn type_name() -> &'static str {
+        "PartialDecryption"
+    }
+}
+
+impl fmt::Display for PartialDecryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_public(self, f)
+    }
+}
+
+impl FromStr for PartialDecryption {
+    type Err = DeserializationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_str_public(s)
+    }
+}
+
+/// Errors that can happen when splitting the receiving key.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
+pub enum SplitReceivingKeyError {
+    /// `threshold` is zero.
+    ZeroThreshold,
+    /// `threshold` is greater than `shares` (the resulting shares would be useless).
+    ThresholdExceedsShares,
+}
+
+impl fmt::Display for SplitReceivingKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroThreshold => write!(f, "Threshold must be non-zero"),
+            Self::ThresholdExceedsShares => {
+                write!(f, "Threshold cannot exceed the number of shares")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for SplitReceivingKeyError {}
+
+/// Errors that can happen when decrypting with partial decryptions.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
+pub enum ThresholdDecryptionError {
+    /// No partial decryptions were provided.
+    NoPartialDecryptions,
+    /// The partial decryptions were created for capsule fragments
+    /// different from the given ones.
+    MismatchedPartialDecryptions,
+    /// Some of the partial decryptions were created with the same key share.
+    RepeatingPartialDecryptions,
+    /// An error when opening a capsule. See [`OpenReencryptedError`] for the options.
+    OnOpen(OpenReencryptedError),
+    /// An error when decrypting the ciphertext. See [`DecryptionError`] for the options.
+    OnDecryption(DecryptionError),
+}
+
+impl fmt::Display for ThresholdDecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoPartialDecryptions => write!(f, "Empty partial decryption sequence"),
+            Self::MismatchedPartialDecryptions => write!(
+                f,
+                "Partial decryptions were created for different capsule fragments"
+            ),
+            Self::RepeatingPartialDecryptions => {
+                write!(f, "Some of the partial decryptions are repeated")
+            }
+            Self::OnOpen(err) => write!(f, "Threshold decryption error on open: {}", err),
+            Self::OnDecryption(err) => {
+                write!(f, "Threshold decryption error on decryption: {}", err)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for ThresholdDecryptionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OnOpen(err) => Some(err),
+            Self::OnDecryption(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Splits the receiving secret key into `shares` shares,
+/// `threshold` of which are required to decrypt, using the given RNG.
+///
+/// The public key of `receiving_sk` is still used as the receiving key
+/// in [`generate_kfrags`](`crate::generate_kfrags`);
+/// after splitting, `receiving_sk` itself should be erased.
+pub fn split_receiving_key_with_rng(
+    rng: &mut (impl CryptoRng + RngCore),
+    receiving_sk: &SecretKey,
+    threshold: usize,
+    shares: usize,
+) -> Result<Box<[ReceivingKeyShare]>, SplitReceivingKeyError> {
+    if threshold == 0 {
+        return Err(SplitReceivingKeyError::ZeroThreshold);
+    }
+    if threshold > shares {
+        return Err(SplitReceivingKeyError::ThresholdExceedsShares);
+    }
+
+    let mut coefficients = Vec::<SecretBox<NonZeroCurveScalar>>::with_capacity(threshold);
+    coefficients.push(receiving_sk.to_secret_scalar());
+    for _ in 1..threshold {
+        coefficients.push(SecretBox::new(NonZeroCurveScalar::random(rng)));
+    }
+
+    let mut result = Vec::<ReceivingKeyShare>::with_capacity(shares);
+    for _ in 0..shares {
+        let index = NonZeroCurveScalar::random(rng);
+        let value = SecretBox::new(poly_eval(&coefficients, &index));
+        result.push(ReceivingKeyShare { index, value });
+    }
+    Ok(result.into_boxed_slice())
+}
+
+/// A synonym for [`split_receiving_key_with_rng`] with the default RNG.
+#[cfg(feature = "default-rng")]
+#[cfg_attr(docsrs, doc(cfg(feature = "default-rng")))]
+pub fn split_receiving_key(
+    receiving_sk: &SecretKey,
+    threshold: usize,
+    shares: usize,
+) -> Result<Box<[ReceivingKeyShare]>, SplitReceivingKeyError> {
+    split_receiving_key_with_rng(&mut OsRng, receiving_sk, threshold, shares)
+}
+
+/// Decrypts the ciphertext using previously reencrypted capsule fragments
+/// and at least `threshold` partial decryptions (see [`split_receiving_key`]).
+///
+/// Providing fewer partial decryptions than the threshold leads to
+/// [`OpenReencryptedError::ValidationFailed`].
+pub fn decrypt_reencrypted_threshold(
+    receiving_pk: &PublicKey,
+    delegating_pk: &PublicKey,
+    capsule: &Capsule,
+    verified_cfrags: impl IntoIterator<Item = VerifiedCapsuleFrag>,
+    partial_decryptions: &[PartialDecryption],
+    ciphertext: impl AsRef<[u8]>,
+) -> Result<Box<[u8]>, ThresholdDecryptionError> {
+    let cfrags: Vec<_> = verified_cfrags
+        .into_iter()
+        .map(|vcfrag| UnprovenCapsuleFrag::from(&vcfrag.unverify()))
+        .collect();
+
+    let precursor = partial_decryptions
+        .first()
+        .ok_or(ThresholdDecryptionError::NoPartialDecryptions)?
+        .precursor;
+    if partial_decryptions
+        .iter()
+        .any(|pd| pd.precursor != precursor)
+        || cfrags.iter().any(|cfrag| cfrag.precursor != precursor)
+    {
+        return Err(ThresholdDecryptionError::MismatchedPartialDecryptions);
+    }
+
+    // Lagrange interpolation at zero "in the exponent"
+    let indices: Vec<_> = partial_decryptions
+        .iter()
+        .map(|pd| pd.index.clone())
+        .collect();
+    let lambdas =
+        lambda_coeffs(&indices).ok_or(ThresholdDecryptionError::RepeatingPartialDecryptions)?;
+    let mut dh_point = CurvePoint::identity();
+    for (pd, lambda_i) in partial_decryptions.iter().zip(lambdas.iter()) {
+        dh_point = &dh_point + &(&pd.point * lambda_i);
+    }
+
+    let key_seed = capsule
+        .open_reencrypted_with_dh_point(receiving_pk, delegating_pk, &cfrags, &dh_point)
+        .map_err(ThresholdDecryptionError::OnOpen)?;
+    let capsule_bytes = capsule.to_array();
+    let dem = DEM::new(&key_seed, &capsule_bytes);
+    dem.decrypt(&ciphertext, &capsule_bytes)
+        .map_err(ThresholdDecryptionError::OnDecryption)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use alloc::vec::Vec;
+
+    use super::{
+        decrypt_reencrypted_threshold, split_receiving_key, PartialDecryption, ReceivingKeyShare,
+        SplitReceivingKeyError, ThresholdDecryptionError,
+    };
+    use crate::{
+        encrypt, generate_kfrags, reencrypt, DeserializableFromArray, OpenReencryptedError,
+        SecretKey, SerializableToArray, SerializableToSecretArray, Signer,
+    };
+
+    #[test]
+    fn test_threshold_decryption() {
+        let delegating_sk = SecretKey::random();
+        let delegating_pk = delegating_sk.public_key();
+        let signer = Signer::new(SecretKey::random());
+        let receiving_sk = SecretKey::random();
+        let receiving_pk = receiving_sk.public_key();
+
+        let key_shares = split_receiving_key(&receiving_sk, 2, 3).unwrap();
+
+        let plaintext = b"peace at dawn";
+        let (capsule, ciphertext) = encrypt(&delegating_pk, plaintext).unwrap();
+        let vkfrags =
+            generate_kfrags(&delegating_sk, &receiving_pk, &signer, 2, 3, true, true).unwrap();
+        let vcfrags: Vec<_> = vkfrags[0..2]
+            .iter()
+            .map(|vkfrag| reencrypt(&capsule, vkfrag.clone()))
+            .collect();
+
+        // Simulate network transfer of the key shares to the devices
+        // and of the partial decryptions back to the combiner
+        let partial_decryptions: Vec<_> = key_shares
+            .iter()
+            .map(|share| {
+                ReceivingKeyShare::from_array(share.to_secret_array().as_secret()).unwrap()
+            })
+            .map(|share| share.partial_decryption(&vcfrags[0]))
+            .map(|pd| PartialDecryption::from_array(&pd.to_array()).unwrap())
+            .collect();
+
+        // Any two of the three devices are enough
+        for (i, j) in [(0, 1), (1, 2), (0, 2)] {
+            let plaintext_bob = decrypt_reencrypted_threshold(
+                &receiving_pk,
+                &delegating_pk,
+                &capsule,
+                vcfrags.clone(),
+                &[
+                    partial_decryptions[i].clone(),
+                    partial_decryptions[j].clone(),
+                ],
+                &ciphertext,
+            )
+            .unwrap();
+            assert_eq!(&plaintext_bob as &[u8], plaintext);
+        }
+
+        // One is not
+        let result = decrypt_reencrypted_threshold(
+            &receiving_pk,
+            &delegating_pk,
+            &capsule,
+            vcfrags.clone(),
+            &partial_decryptions[0..1],
+            &ciphertext,
+        );
+        assert_eq!(
+            result,
+            Err(ThresholdDecryptionError::OnOpen(
+                OpenReencryptedError::ValidationFailed
+            ))
+        );
+
+        // Repeating partial decryptions
+        let result = decrypt_reencrypted_threshold(
+            &receiving_pk,
+            &delegating_pk,
+            &capsule,
+            vcfrags,
+            &[
+                partial_decryptions[0].clone(),
+                partial_decryptions[0].clone(),
+            ],
+            &ciphertext,
+        );
+        assert_eq!(
+            result,
+            Err(ThresholdDecryptionError::RepeatingPartialDecryptions)
+        );
+    }
+
+    #[test]
+    fn test_split_receiving_key_errors() {
+        let receiving_sk = SecretKey::random();
+        assert_eq!(
+            split_receiving_key(&receiving_sk, 0, 3).map(|_| ()),
+            Err(SplitReceivingKeyError::ZeroThreshold)
+        );
+        assert_eq!(
+            split_receiving_key(&receiving_sk, 4, 3).map(|_| ()),
+            Err(SplitReceivingKeyError::ThresholdExceedsShares)
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Verified objects with a record of when they were verified.
+
+use crate::capsule_frag::VerifiedCapsuleFrag;
+use crate::key_frag::VerifiedKeyFrag;
+
+/// A verified object along with the time of its verification
+/// and an optional maximum age, after which it should be verified again.
+///
+/// Timestamps are supplied by the caller (as in
+/// [`DelegationCertificate::verify`](`crate::DelegationCertificate::verify`)),
+/// and are expected to be in the same units (e.g. seconds since the Unix epoch).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeBounded<T> {
+    verified: T,
+    verified_at: u64,
+    max_age: Option<u64>,
+}
+
+/// A [`VerifiedKeyFrag`] with the time of its verification.
+pub type TimeBoundedKeyFrag = TimeBounded<VerifiedKeyFrag>;
+
+/// A [`VerifiedCapsuleFrag`] with the time of its verification.
+pub type TimeBoundedCapsuleFrag = TimeBounded<VerifiedCapsuleFrag>;
+
+impl<T> TimeBounded<T> {
+    /// Returns the time the object was verified at.
+    pub fn verified_at(&self) -> u64 {
+        self.verified_at
+    }
+
+    /// Returns the maximum age of the verification, if any.
+    pub fn max_age(&self) -> Option<u64> {
+        self.max_age
+    }
+
+    /// Returns `true` if the verification is older than the maximum age at `timestamp`.
+    /// An object without a maximum age never becomes stale.
+    pub fn is_stale(&self, timestamp: u64) -> bool {
+        match self.max_age {
+            Some(max_age) => timestamp > self.verified_at.saturating_add(max_age),
+            None => false,
+        }
+    }
+
+    /// Returns the verified object if it is not stale at `timestamp`.
+    pub fn get(&self, timestamp: u64) -> Option<&T> {
+        if self.is_stale(timestamp) {
+            None
+        } else {
+            Some(&self.verified)
+        }
+    }
+
+    /// Returns the verified object regardless of its age.
+    pub fn into_inner(self) -> T {
+        self.verified
+    }
+}
+
+impl VerifiedKeyFrag {
+    /// Records the time of verification of this key fragment,
+    /// and an optional maximum age after which it will be considered stale.
+    pub fn time_bounded(self, verified_at: u64, max_age: Option<u64>) -> TimeBoundedKeyFrag {
+        TimeBounded {
+            verified: self,
+            verified_at,
+            max_age,
+        }
+    }
+}
+
+impl VerifiedCapsuleFrag {
+    /// Records the time of verification of this capsule fragment,
+    /// and an optional maximum age after which it will be considered stale.
+    pub fn time_bounded(self, verified_at: u64, max_age: Option<u64>) -> TimeBoundedCapsuleFrag {
+        TimeBounded {
+            verified: self,
+            verified_at,
+            max_age,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::test_utils::{prepare_delegation, Delegation};
+
+    #[test]
+    fn test_staleness() {
+        let Delegation {
+            verified_kfrags: vkfrags,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+        let vcfrag = vcfrags[0].clone();
+
+        let kfrag = vkfrags[0].clone().time_bounded(1000, Some(60));
+        assert_eq!(kfrag.verified_at(), 1000);
+        assert_eq!(kfrag.max_age(), Some(60));
+        assert!(!kfrag.is_stale(1000));
+        assert!(!kfrag.is_stale(1060));
+        assert!(kfrag.is_stale(1061));
+        assert_eq!(kfrag.get(1030), Some(&vkfrags[0]));
+        assert_eq!(kfrag.get(2000), None);
+        assert_eq!(kfrag.into_inner(), vkfrags[0]);
+
+        // No maximum age
+        let cfrag = vcfrag.clone().time_bounded(1000, None);
+        assert!(!cfrag.is_stale(u64::MAX));
+
+        // No overflow for large maximum ages
+        let cfrag = vcfrag.time_bounded(1000, Some(u64::MAX));
+        assert!(!cfrag.is_stale(u64::MAX));
+    }
+}
@@ -1,17 +1,25 @@
 use alloc::format;
 use alloc::string::String;
+use alloc::vec::Vec;
 use core::cmp::Ordering;
 use core::fmt;
 use core::ops::Sub;
 
+use digest::Digest;
 use generic_array::sequence::Split;
 use generic_array::{ArrayLength, GenericArray};
-use typenum::{Diff, Unsigned, U1, U8};
+use typenum::{Diff, Unsigned, U1, U32, U8};
 
+use crate::dst;
+use crate::hashing::Hash;
 use crate::secret_box::SecretBox;
 
+#[cfg(feature = "serde-support")]
+use serde::Serialize;
+
 /// Errors that can happen during deserializing an object from a bytestring of correct length.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
 pub struct ConstructionError {
     /// The name of the type that was being deserialized
     /// (can be one of the nested fields).
@@ -28,6 +36,17 @@ impl ConstructionError {
             message: message.into(),
         }
     }
+
+    /// The name of the type that was being deserialized
+    /// (can be one of the nested fields).
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// The associated error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 
 impl fmt::Display for ConstructionError {
@@ -40,8 +59,13 @@ impl fmt::Display for ConstructionError {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for ConstructionError {}
+
 /// The provided bytestring is of an incorrect size.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
 pub struct SizeMismatchError {
     received_size: usize,
     expected_size: usize,
@@ -55,6 +79,16 @@ impl SizeMismatchError {
             expected_size,
         }
     }
+
+    /// The size of the given bytestring.
+    pub fn received_size(&self) -> usize {
+        self.received_size
+    }
+
+    /// The expected size of the bytestring.
+    pub fn expected_size(&self) -> usize {
+        self.expected_size
+    }
 }
 
 impl fmt::Display for SizeMismatchError {
@@ -67,8 +101,13 @@ impl fmt::Display for SizeMismatchError {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for SizeMismatchError {}
+
 /// Errors that can happen during object deserialization.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde-support", derive(Serialize))]
 pub enum DeserializationError {
     /// Failed to construct the object from a given bytestring (with the correct length).
     ConstructionFailure(ConstructionError),
@@ -85,6 +124,17 @@ impl fmt::Display for DeserializationError {
     }
 }
 
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for DeserializationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ConstructionFailure(err) => Some(err),
+            Self::SizeMismatch(err) => Some(err),
+        }
+    }
+}
+
 /// A trait denoting that the object can be represented as an array of bytes
 /// with size known at compile time.
 pub trait RepresentableAsArray: Sized {
@@ -95,17 +145,73 @@ pub trait RepresentableAsArray: Sized {
     // type Array = GenericArray<u8, Self::Size>;
     // but it's currently an unstable feature or Rust.
 
+    /// Resulting array length as a constant,
+    /// usable for sizing buffers at compile time (e.g. `[0u8; Capsule::SERIALIZED_SIZE]`).
+    const SERIALIZED_SIZE: usize = Self::Size::USIZE;
+
     /// Resulting array length exposed as a runtime method.
     fn serialized_size() -> usize {
-        Self::Size::to_usize()
+        Self::SERIALIZED_SIZE
     }
 }
 
 /// A trait denoting that the object can be serialized to an array of bytes
 /// with size known at compile time.
+///
+/// The encoding is canonical: every object has exactly one byte representation
+/// (curve points are always compressed, scalars are reduced modulo the curve order),
+/// and [`DeserializableFromArray::from_array`] rejects any other representation.
+/// The only exception are ECDSA signatures with a non-normalized `s`
+/// which are never produced by this library, and always fail verification.
+/// With the `p256` feature `s` is not normalized, so both forms of a signature are valid.
 pub trait SerializableToArray: RepresentableAsArray {
     /// Produces a byte array with the object's contents.
     fn to_array(&self) -> GenericArray<u8, Self::Size>;
+
+    /// Produces a byte vector with the object's contents,
+    /// for the callers that do not need the size known at compile time.
+    fn to_vec(&self) -> Vec<u8> {
+        self.to_array().to_vec()
+    }
+
+    /// Writes the object's contents to the beginning of `buf` without allocating,
+    /// returning the number of bytes written.
+    ///
+    /// Fails if `buf` is shorter than
+    /// [`serialized_size()`](`RepresentableAsArray::serialized_size()`).
+    fn write_to(&self, buf: &mut [u8]) -> Result<usize, SizeMismatchError> {
+        let size = Self::serialized_size();
+        if buf.len() < size {
+            return Err(SizeMismatchError::new(buf.len(), size));
+        }
+        buf[..size].copy_from_slice(&self.to_array());
+        Ok(size)
+    }
+
+    /// Writes the object's contents to `writer` (e.g. a file or a socket).
+    ///
+    /// The object is not streamed field by field: it is serialized
+    /// with [`to_array()`](`Self::to_array`) first, and the array is written
+    /// with a single `write_all()` call.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    fn write_into(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        writer.write_all(&self.to_array())
+    }
+
+    /// Returns a SHA-256 digest (or the hash selected by a `hash-*` feature)
+    /// of the canonical encoding of the object,
+    /// intended to be used as a stable identifier of the object
+    /// (e.g. for deduplication, receipts, or anchoring on-chain).
+    ///
+    /// The digest of a verified object (e.g. [`VerifiedKeyFrag`](`crate::VerifiedKeyFrag`))
+    /// is the same as the digest of its unverified counterpart.
+    fn canonical_digest(&self) -> GenericArray<u8, U32> {
+        Hash::new_with_dst(dst::CANONICAL_DIGEST)
+            .chain_bytes(self.to_array())
+            .digest()
+            .finalize()
+    }
 }
 
 /// A trait denoting that the object can be serialized to an array of bytes
@@ -113,6 +219,40 @@ pub trait SerializableToArray: RepresentableAsArray {
 pub trait SerializableToSecretArray: RepresentableAsArray {
     /// Produces a byte array with the object's contents, wrapped in a secret container.
     fn to_secret_array(&self) -> SecretBox<GenericArray<u8, Self::Size>>;
+
+    /// Produces a byte vector with the object's contents, wrapped in a secret container.
+    fn to_secret_vec(&self) -> SecretBox<Vec<u8>> {
+        SecretBox::new(self.to_secret_array().as_secret().to_vec())
+    }
+
+    /// Writes the object's contents to the beginning of `buf`,
+    /// returning the number of bytes written.
+    /// The contents are copied from [`to_secret_array()`](`Self::to_secret_array`),
+    /// so a temporary heap-allocated copy is created (and zeroized on drop).
+    /// The caller is responsible for zeroizing `buf` afterwards.
+    ///
+    /// Fails if `buf` is shorter than
+    /// [`serialized_size()`](`RepresentableAsArray::serialized_size()`).
+    fn write_secret_to(&self, buf: &mut [u8]) -> Result<usize, SizeMismatchError> {
+        let size = Self::serialized_size();
+        if buf.len() < size {
+            return Err(SizeMismatchError::new(buf.len(), size));
+        }
+        buf[..size].copy_from_slice(self.to_secret_array().as_secret());
+        Ok(size)
+    }
+
+    /// Writes the object's contents to `writer` (e.g. a file or a socket).
+    /// The caller is responsible for the protection of the written data.
+    ///
+    /// Same as [`SerializableToArray::write_into`], the object is serialized
+    /// with [`to_secret_array()`](`Self::to_secret_array`) first
+    /// (the intermediate copy is zeroized on drop).
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    fn write_secret_into(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        writer.write_all(self.to_secret_array().as_secret())
+    }
 }
 
 /// A trait denoting that the object can be deserialized from an array of bytes
@@ -121,8 +261,11 @@ pub trait DeserializableFromArray: RepresentableAsArray {
     /// Attempts to produce the object back from the serialized form.
     fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError>;
 
-    /// Attempts to produce the object back from a dynamically sized byte array,
-    /// checking that its length is correct.
+    /// Attempts to produce the object back from a dynamically sized byte array
+    /// (e.g. a slice or a vector), checking that its length is correct.
+    ///
+    /// Returns [`DeserializationError::SizeMismatch`] if the length differs
+    /// from [`serialized_size()`](`RepresentableAsArray::serialized_size()`).
     fn from_bytes(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
         let data_slice = data.as_ref();
         let received_size = data_slice.len();
@@ -138,6 +281,21 @@ pub trait DeserializableFromArray: RepresentableAsArray {
         }
     }
 
+    /// Reads exactly [`serialized_size()`](`RepresentableAsArray::serialized_size()`) bytes
+    /// from `reader` (e.g. a file or a socket) and attempts to produce the object from them.
+    ///
+    /// Construction failures are returned as errors of the kind
+    /// [`InvalidData`](`std::io::ErrorKind::InvalidData`) wrapping the [`ConstructionError`].
+    /// The intermediate buffer is zeroized, so this can be used for secret objects as well.
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    fn read_from(reader: &mut impl std::io::Read) -> std::io::Result<Self> {
+        let mut arr = SecretBox::new(GenericArray::<u8, Self::Size>::default());
+        reader.read_exact(arr.as_mut_secret())?;
+        Self::from_array(arr.as_secret())
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+    }
+
     /// Used to implement [`from_array()`](`Self::from_array()`) for structs whose fields
     /// implement [`SerializableToArray`].
     ///
@@ -188,6 +346,24 @@ impl DeserializableFromArray for bool {
     }
 }
 
+impl RepresentableAsArray for u64 {
+    type Size = U8;
+}
+
+impl SerializableToArray for u64 {
+    fn to_array(&self) -> GenericArray<u8, Self::Size> {
+        GenericArray::<u8, Self::Size>::from(self.to_be_bytes())
+    }
+}
+
+impl DeserializableFromArray for u64 {
+    fn from_array(arr: &GenericArray<u8, Self::Size>) -> Result<Self, ConstructionError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(arr);
+        Ok(u64::from_be_bytes(bytes))
+    }
+}
+
 /// A reflection trait providing access to the type's name.
 pub trait HasTypeName {
     /// Returns a string with the name of the type
@@ -204,6 +380,10 @@ pub(crate) fn fmt_secret<T: HasTypeName>(f: &mut fmt::Formatter<'_>) -> fmt::Res
 }
 
 /// A `fmt` implementation for types with public data.
+///
+/// Shows the first 8 bytes of the serialized object in hex,
+/// or, with the alternate flag (`{:#}`), the whole object,
+/// in which case the result can be parsed back with [`from_str_public`].
 pub(crate) fn fmt_public<T>(obj: &T, f: &mut fmt::Formatter<'_>) -> fmt::Result
 where
     T: HasTypeName + SerializableToArray + RepresentableAsArray,
@@ -211,6 +391,9 @@ where
     Diff<<T as RepresentableAsArray>::Size, U8>: ArrayLength<u8>,
 {
     let bytes = (*obj).to_array();
+    if f.alternate() {
+        return write!(f, "{}:{}", T::type_name(), hex::encode(bytes));
+    }
     let (to_show, _): (GenericArray<u8, U8>, GenericArray<u8, _>) = bytes.split();
     let mut hex_repr = [b'*'; 16]; // exactly 16 bytes long, to fit the encode() result
     hex::encode_to_slice(to_show, &mut hex_repr).map_err(|_| fmt::Error)?;
@@ -222,6 +405,34 @@ where
     )
 }
 
+/// A `FromStr` implementation for types with public data.
+///
+/// Accepts the hex representation of the serialized object, optionally prefixed with `0x`,
+/// or with the type name and a colon (as produced by [`fmt_public`] with the alternate flag).
+pub(crate) fn from_str_public<T>(s: &str) -> Result<T, DeserializationError>
+where
+    T: HasTypeName + DeserializableFromArray,
+{
+    let s = s.trim();
+    let s = match s.split_once(':') {
+        Some((type_name, rest)) if type_name == T::type_name() => rest,
+        Some(_) => {
+            return Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new(T::type_name(), "Unexpected type name"),
+            ))
+        }
+        None => s,
+    };
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    let bytes = hex::decode(s).map_err(|_| {
+        DeserializationError::ConstructionFailure(ConstructionError::new(
+            T::type_name(),
+            "Invalid hex encoding",
+        ))
+    })?;
+    T::from_bytes(bytes)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -322,6 +533,70 @@ mod tests {
 
         let s_from_bytes = SomeStruct::from_bytes(&s_arr_ref).unwrap();
         assert_eq!(s_from_bytes, s);
+
+        let s_vec = s.to_vec();
+        assert_eq!(s_vec, s_arr_ref);
+        assert_eq!(SomeStruct::from_bytes(&s_vec[..]).unwrap(), s);
+
+        let mut buf = [0xffu8; 8];
+        assert_eq!(s.write_to(&mut buf), Ok(6));
+        assert_eq!(buf[..6], s_arr_ref);
+        assert_eq!(buf[6..], [0xff, 0xff]);
+        assert_eq!(s.write_to(&mut buf[..5]), Err(SizeMismatchError::new(5, 6)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_io() {
+        use alloc::vec::Vec;
+        use std::io::ErrorKind;
+
+        let s = SomeStruct {
+            f1: 1,
+            f2: 2,
+            f3: 3,
+            f4: true,
+        };
+
+        let mut written = Vec::new();
+        s.write_into(&mut written).unwrap();
+        s.write_into(&mut written).unwrap();
+        assert_eq!(written.len(), 12);
+
+        let mut reader = &written[..];
+        assert_eq!(SomeStruct::read_from(&mut reader).unwrap(), s);
+        assert_eq!(SomeStruct::read_from(&mut reader).unwrap(), s);
+        assert_eq!(
+            SomeStruct::read_from(&mut reader).unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+
+        // invalid value for `f4`
+        let mut reader = &[0x00u8, 0x01, 0x02, 0x00, 0x03, 0x02][..];
+        assert_eq!(
+            SomeStruct::read_from(&mut reader).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_canonical_digest() {
+        let s = SomeStruct {
+            f1: 1,
+            f2: 2,
+            f3: 3,
+            f4: true,
+        };
+        let s_back = SomeStruct::from_array(&s.to_array()).unwrap();
+        assert_eq!(s.canonical_digest(), s_back.canonical_digest());
+
+        let s_diff = SomeStruct {
+            f1: 1,
+            f2: 2,
+            f3: 3,
+            f4: false,
+        };
+        assert_ne!(s.canonical_digest(), s_diff.canonical_digest());
     }
 
     #[test]
@@ -347,6 +622,15 @@ mod tests {
             Err(DeserializationError::SizeMismatch(SizeMismatchError::new(
                 7, 6
             )))
-        )
+        );
+
+        // A missing byte
+        let s = SomeStruct::from_bytes(&s_arr[..5]);
+        assert_eq!(
+            s,
+            Err(DeserializationError::SizeMismatch(SizeMismatchError::new(
+                5, 6
+            )))
+        );
     }
 }
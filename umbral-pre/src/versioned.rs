@@ -0,0 +1,174 @@
+//! Versioned serialization of the objects.
+//!
+//! A versioned representation is the magic bytes [`VERSIONED_MAGIC`],
+//! followed by a single byte identifying the object type,
+//! a [`CiphertextHeader`] (the same header the DEM ciphertexts start with)
+//! containing the format version of the object and the identifiers of the curve, hash and DEM
+//! of the configuration that produced it, and the object's byte representation
+//! in the layout of that version.
+//! This allows stored objects to be read back even if the layout changes in the future,
+//! and rejects the objects created with a different curve or hash
+//! (the DEM identifier is not checked, since the objects do not depend on it).
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::dem::CiphertextHeader;
+use crate::key_frag::KeyFrag;
+use crate::keys::{PublicKey, Signature};
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, HasTypeName,
+    SerializableToArray,
+};
+
+/// The magic bytes at the start of a versioned representation.
+pub const VERSIONED_MAGIC: [u8; 4] = *b"UMBR";
+
+const HEADER_SIZE: usize = 4 + 1 + CiphertextHeader::SIZE;
+
+/// A trait for objects with a versioned serialization format.
+pub trait VersionedSerialization:
+    SerializableToArray + DeserializableFromArray + HasTypeName
+{
+    /// The byte identifying the object type in the versioned representation.
+    const TYPE_TAG: u8;
+
+    /// The version of the current layout (the one produced by `to_array()`).
+    const FORMAT_VERSION: u8 = 1;
+
+    /// Deserializes the object from the layout with the given version.
+    ///
+    /// The default implementation only supports the current version;
+    /// types whose layout has changed dispatch to the older layouts here.
+    fn from_versioned_payload(version: u8, payload: &[u8]) -> Result<Self, DeserializationError> {
+        if version == Self::FORMAT_VERSION {
+            Self::from_bytes(payload)
+        } else {
+            Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new(
+                    Self::type_name(),
+                    &format!("Unsupported format version {}", version),
+                ),
+            ))
+        }
+    }
+
+    /// Serializes the object with the versioned header.
+    fn to_versioned_bytes(&self) -> Box<[u8]> {
+        let mut result = Vec::with_capacity(HEADER_SIZE + Self::serialized_size());
+        result.extend_from_slice(&VERSIONED_MAGIC);
+        result.push(Self::TYPE_TAG);
+        let header = CiphertextHeader {
+            version: Self::FORMAT_VERSION,
+            ..CiphertextHeader::CURRENT
+        };
+        result.extend_from_slice(&header.to_bytes());
+        result.extend_from_slice(&self.to_array());
+        result.into_boxed_slice()
+    }
+
+    /// Deserializes the object from a versioned representation,
+    /// checking the header and dispatching on the format version.
+    fn from_versioned_bytes(data: impl AsRef<[u8]>) -> Result<Self, DeserializationError> {
+        let data = data.as_ref();
+        let error = |msg: &str| {
+            DeserializationError::ConstructionFailure(ConstructionError::new(
+                Self::type_name(),
+                msg,
+            ))
+        };
+
+        if data.len() < HEADER_SIZE {
+            return Err(error("The data is too short for a versioned header"));
+        }
+        let (header, payload) = data.split_at(HEADER_SIZE);
+        if header[..4] != VERSIONED_MAGIC {
+            return Err(error("Invalid magic bytes"));
+        }
+        if header[4] != Self::TYPE_TAG {
+            return Err(error("The data contains an object of a different type"));
+        }
+        // The length is checked above, so this cannot fail.
+        let object_header = CiphertextHeader::from_ciphertext(&header[5..])
+            .map_err(|_| error("The data is too short for a versioned header"))?;
+        if object_header.curve != CiphertextHeader::CURRENT.curve
+            || object_header.hash != CiphertextHeader::CURRENT.hash
+        {
+            return Err(error("The data was created with a different curve or hash"));
+        }
+        Self::from_versioned_payload(object_header.version, payload)
+    }
+}
+
+impl VersionedSerialization for PublicKey {
+    const TYPE_TAG: u8 = 1;
+}
+
+impl VersionedSerialization for Signature {
+    const TYPE_TAG: u8 = 2;
+}
+
+impl VersionedSerialization for Capsule {
+    const TYPE_TAG: u8 = 3;
+}
+
+impl VersionedSerialization for KeyFrag {
+    const TYPE_TAG: u8 = 4;
+}
+
+impl VersionedSerialization for CapsuleFrag {
+    const TYPE_TAG: u8 = 5;
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{VersionedSerialization, VERSIONED_MAGIC};
+    use crate::{encrypt, Capsule, CiphertextHeader, PublicKey, SecretKey, SerializableToArray};
+
+    #[test]
+    fn test_roundtrip() {
+        let pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+
+        let bytes = capsule.to_versioned_bytes();
+        assert_eq!(bytes[..4], VERSIONED_MAGIC);
+        assert_eq!(bytes[4], Capsule::TYPE_TAG);
+        assert_eq!(bytes[5], 1);
+        assert_eq!(bytes[6..9], CiphertextHeader::CURRENT.to_bytes()[1..]);
+        assert_eq!(bytes[9..], *capsule.to_array());
+        assert_eq!(Capsule::from_versioned_bytes(&bytes).unwrap(), capsule);
+
+        // Wrong type
+        assert!(PublicKey::from_versioned_bytes(&bytes).is_err());
+
+        // Unknown version
+        let mut future = bytes.to_vec();
+        future[5] = 2;
+        assert!(Capsule::from_versioned_bytes(&future).is_err());
+
+        // Another curve or hash
+        for offset in [6, 7] {
+            let mut other_config = bytes.to_vec();
+            other_config[offset] ^= 0x80;
+            assert!(Capsule::from_versioned_bytes(&other_config).is_err());
+        }
+
+        // The DEM is not checked
+        let mut other_dem = bytes.to_vec();
+        other_dem[8] ^= 0x80;
+        assert_eq!(Capsule::from_versioned_bytes(&other_dem).unwrap(), capsule);
+
+        // Wrong magic
+        let mut wrong_magic = bytes.to_vec();
+        wrong_magic[0] = b'X';
+        assert!(Capsule::from_versioned_bytes(&wrong_magic).is_err());
+
+        // Raw bytes without the header are not accepted
+        assert!(Capsule::from_versioned_bytes(capsule.to_array()).is_err());
+        assert!(Capsule::from_versioned_bytes(b"UMB").is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Borrowed views of serialized objects, for the cases where many objects
+//! are received, but only some of their fields are needed right away
+//! (e.g. a proxy routing capsule fragments by their key fragment IDs).
+//!
+//! The views reference the input buffer directly, and only perform the checks
+//! that do not require curve arithmetic on creation;
+//! the points are decompressed (and validated) only when the full object is decoded.
+
+use alloc::format;
+
+use crate::capsule::Capsule;
+use crate::capsule_frag::CapsuleFrag;
+use crate::curve::CurveScalar;
+use crate::format::{FieldEncoding, Layout, CAPSULE, CAPSULE_FRAG};
+use crate::key_frag::KeyFragID;
+use crate::keys::Signature;
+use crate::traits::{
+    ConstructionError, DeserializableFromArray, DeserializationError, SizeMismatchError,
+};
+
+/// Checks the size of the serialized object, and the fields that can be checked cheaply:
+/// the tags of the points, the ranges of the scalars and the signatures, and the flags.
+fn precheck(layout: &Layout, data: &[u8]) -> Result<(), DeserializationError> {
+    if data.len() != layout.size() {
+        return Err(DeserializationError::SizeMismatch(SizeMismatchError::new(
+            data.len(),
+            layout.size(),
+        )));
+    }
+
+    let mut offset = 0;
+    for field in layout.fields {
+        let size = field.encoding.size();
+        let field_bytes = &data[offset..offset + size];
+        offset += size;
+        let valid = match field.encoding {
+            FieldEncoding::Point => field_bytes[0] == 0x02 || field_bytes[0] == 0x03,
+            FieldEncoding::Scalar => CurveScalar::from_bytes(field_bytes).is_ok(),
+            FieldEncoding::Signature => Signature::from_bytes(field_bytes).is_ok(),
+            FieldEncoding::Identifier => true,
+            FieldEncoding::Bool => field_bytes[0] <= 1,
+        };
+        if !valid {
+            return Err(DeserializationError::ConstructionFailure(
+                ConstructionError::new(
+                    layout.type_name,
+                    &format!("Invalid encoding of `{}`", field.name),
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn field<'a>(layout: &Layout, data: &'a [u8], name: &str) -> &'a [u8] {
+    // Only called with the names from the corresponding layout.
+    let (offset, size) = layout.field_range(name).unwrap_or((0, 0));
+    &data[offset..offset + size]
+}
+
+/// A view of a serialized [`Capsule`] inside a buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapsuleRef<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CapsuleRef<'a> {
+    /// Creates a view of a serialized capsule,
+    /// checking everything except that the points are on the curve.
+    pub fn new(data: &'a [u8]) -> Result<Self, DeserializationError> {
+        precheck(&CAPSULE, data)?;
+        Ok(Self { bytes: data })
+    }
+
+    /// Returns the referenced bytes.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Decodes the capsule, decompressing the points.
+    pub fn to_capsule(&self) -> Result<Capsule, DeserializationError> {
+        Capsule::from_bytes(self.bytes)
+    }
+}
+
+/// A view of a serialized [`CapsuleFrag`] inside a buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapsuleFragRef<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> CapsuleFragRef<'a> {
+    /// Creates a view of a serialized capsule fragment,
+    /// checking everything except that the points are on the curve.
+    pub fn new(data: &'a [u8]) -> Result<Self, DeserializationError> {
+        precheck(&CAPSULE_FRAG, data)?;
+        Ok(Self { bytes: data })
+    }
+
+    /// Returns the referenced bytes.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Returns the identifier of the key fragment used to create this capsule fragment.
+    pub fn kfrag_id(&self) -> KeyFragID {
+        // An identifier is valid for any bytes of the correct length.
+        KeyFragID::from_bytes(field(&CAPSULE_FRAG, self.bytes, "kfrag_id")).unwrap()
+    }
+
+    /// Returns the serialized precursor (identical for all the fragments
+    /// created for the same delegation), which can be used for grouping
+    /// the fragments without decoding them.
+    pub fn precursor_bytes(&self) -> &'a [u8] {
+        field(&CAPSULE_FRAG, self.bytes, "precursor")
+    }
+
+    /// Decodes the capsule fragment, decompressing the points.
+    /// The result has to be verified before use, as with any received capsule fragment.
+    pub fn to_capsule_frag(&self) -> Result<CapsuleFrag, DeserializationError> {
+        CapsuleFrag::from_bytes(self.bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{CapsuleFragRef, CapsuleRef};
+    use crate::test_utils::{prepare_delegation, Delegation};
+    use crate::{encrypt, DeserializationError, SecretKey, SerializableToArray};
+
+    #[test]
+    fn test_views() {
+        let Delegation {
+            capsule,
+            verified_kfrags: vkfrags,
+            verified_cfrags: vcfrags,
+            ..
+        } = prepare_delegation(true, true);
+
+        let capsule_bytes = capsule.to_array();
+        let capsule_ref = CapsuleRef::new(&capsule_bytes).unwrap();
+        assert_eq!(capsule_ref.as_bytes(), capsule_bytes.as_slice());
+        assert_eq!(capsule_ref.to_capsule().unwrap(), capsule);
+
+        let cfrag = vcfrags[0].clone().unverify();
+        let cfrag_bytes = cfrag.to_array();
+        let cfrag_ref = CapsuleFragRef::new(&cfrag_bytes).unwrap();
+        assert_eq!(cfrag_ref.kfrag_id(), vkfrags[0].id());
+        assert_eq!(
+            cfrag_ref.precursor_bytes(),
+            cfrag.precursor.to_array().as_slice()
+        );
+        assert_eq!(cfrag_ref.to_capsule_frag().unwrap(), cfrag);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let pk = SecretKey::random().public_key();
+        let (capsule, _ciphertext) = encrypt(&pk, b"peace at dawn").unwrap();
+        let capsule_bytes = capsule.to_array();
+
+        assert!(matches!(
+            CapsuleRef::new(&capsule_bytes[1..]),
+            Err(DeserializationError::SizeMismatch(_))
+        ));
+
+        // Invalid point tag
+        let mut invalid_tag = capsule_bytes.to_vec();
+        invalid_tag[0] = 0x04;
+        assert!(matches!(
+            CapsuleRef::new(&invalid_tag),
+            Err(DeserializationError::ConstructionFailure(_))
+        ));
+
+        // Invalid coordinate: only detected on decoding
+        let mut invalid_point = capsule_bytes.to_vec();
+        for byte in invalid_point[1..33].iter_mut() {
+            *byte = 0xff;
+        }
+        let capsule_ref = CapsuleRef::new(&invalid_point).unwrap();
+        assert!(capsule_ref.to_capsule().is_err());
+    }
+}